@@ -0,0 +1,55 @@
+/*
+ * C interface to the sudoku solver and generator.
+ *
+ * Puzzles are NUL-terminated strings of 81 digits, row by row, with 0 or .
+ * for empty cells. Buffers written to must hold SUDOKU_LINE_BYTES bytes.
+ * Link against the library built by `cargo build --release
+ * --no-default-features` (libsudoku.so, libsudoku.dylib, or sudoku.dll).
+ */
+#ifndef SUDOKU_H
+#define SUDOKU_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The puzzle has exactly one solution, or was generated. */
+#define SUDOKU_OK 0
+/* No grid fills the puzzle. */
+#define SUDOKU_NO_SOLUTION 1
+/* More than one grid fills the puzzle. */
+#define SUDOKU_MULTIPLE_SOLUTIONS 2
+/* An argument was null or not a puzzle, or the engine failed. */
+#define SUDOKU_INVALID (-1)
+
+/* Bytes a buffer written to must hold: 81 cells and a NUL. */
+#define SUDOKU_LINE_BYTES 82
+
+/* Difficulties, as generated and rated. */
+#define SUDOKU_EASY 0
+#define SUDOKU_MEDIUM 1
+#define SUDOKU_HARD 2
+#define SUDOKU_EXPERT 3
+
+/* Reads a puzzle written as digits, in a line or a drawn grid with any
+ * other characters skipped, writing it as a line with . for empty cells. */
+int sudoku_parse(const char *text, char *line);
+
+/* Solves a puzzle, writing its solution when it has exactly one. */
+int sudoku_solve(const char *puzzle, char *solution);
+
+/* Generates a puzzle with a unique solution at a difficulty. The same seed
+ * always gives the same puzzle. */
+int sudoku_generate(int difficulty, uint64_t seed, char *puzzle);
+
+/* Rates a puzzle with exactly one solution by the techniques that solve
+ * it, writing its difficulty. */
+int sudoku_rate(const char *puzzle, int *difficulty);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SUDOKU_H */
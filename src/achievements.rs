@@ -0,0 +1,239 @@
+use crate::database::Database;
+#[cfg(feature = "gui")]
+use crate::text::draw_text;
+#[cfg(feature = "gui")]
+use crate::theme::Theme;
+use crate::{Difficulty, GameboardController};
+#[cfg(feature = "gui")]
+use graphics::character::CharacterCache;
+#[cfg(feature = "gui")]
+use graphics::types::Color;
+#[cfg(feature = "gui")]
+use graphics::{Context, Graphics};
+use std::time::Duration;
+
+/// Milestones unlocked by solving puzzles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Achievement {
+    FirstSolve,
+    ExpertSolve,
+    QuickEasy,
+    NoHintHard,
+    Flawless,
+    DailyStreak,
+    FiftySolved,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 7] = [
+        Achievement::FirstSolve,
+        Achievement::ExpertSolve,
+        Achievement::QuickEasy,
+        Achievement::NoHintHard,
+        Achievement::Flawless,
+        Achievement::DailyStreak,
+        Achievement::FiftySolved,
+    ];
+
+    /// Name stored in the database, which must not change.
+    pub fn key(self) -> &'static str {
+        match self {
+            Achievement::FirstSolve => "first_solve",
+            Achievement::ExpertSolve => "expert_solve",
+            Achievement::QuickEasy => "quick_easy",
+            Achievement::NoHintHard => "no_hint_hard",
+            Achievement::Flawless => "flawless",
+            Achievement::DailyStreak => "daily_streak",
+            Achievement::FiftySolved => "fifty_solved",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::FirstSolve => "First steps",
+            Achievement::ExpertSolve => "Expert",
+            Achievement::QuickEasy => "Speed run",
+            Achievement::NoHintHard => "Unassisted",
+            Achievement::Flawless => "Flawless",
+            Achievement::DailyStreak => "Daily habit",
+            Achievement::FiftySolved => "Dedicated",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Achievement::FirstSolve => "Solve a puzzle",
+            Achievement::ExpertSolve => "Solve an Expert puzzle",
+            Achievement::QuickEasy => "Solve an Easy puzzle in under 5 minutes",
+            Achievement::NoHintHard => "Solve a Hard puzzle without hints",
+            Achievement::Flawless => "Solve a puzzle without mistakes or hints",
+            Achievement::DailyStreak => "Solve a puzzle on 7 days in a row",
+            Achievement::FiftySolved => "Solve 50 puzzles",
+        }
+    }
+
+    /// Whether a game just solved, and the games before it, earn this.
+    fn earned(self, database: &Database, controller: &GameboardController) -> Result<bool, String> {
+        let difficulty = controller.gameboard.difficulty;
+        Ok(match self {
+            Achievement::FirstSolve => true,
+            Achievement::ExpertSolve => difficulty == Difficulty::Expert,
+            Achievement::QuickEasy => {
+                difficulty == Difficulty::Easy && controller.elapsed() < Duration::from_secs(300)
+            }
+            Achievement::NoHintHard => difficulty == Difficulty::Hard && controller.hints == 0,
+            Achievement::Flawless => controller.mistakes == 0 && controller.hints == 0,
+            Achievement::DailyStreak => database.daily_streak()? >= 7,
+            Achievement::FiftySolved => database.games_won()? >= 50,
+        })
+    }
+}
+
+/// Unlocks what a finished game earned, returning the achievements new to
+/// the player. Nothing is earned by games that were not solved.
+pub fn check(
+    database: &Database,
+    controller: &GameboardController,
+) -> Result<Vec<Achievement>, String> {
+    if !controller.gameboard.is_solved() {
+        return Ok(Vec::new());
+    }
+    let unlocked = database.achievements()?;
+    let mut new = Vec::new();
+    for achievement in Achievement::ALL {
+        if unlocked.iter().any(|(key, _)| key == achievement.key()) {
+            continue;
+        }
+        if achievement.earned(database, controller)? {
+            database.unlock(achievement.key())?;
+            new.push(achievement);
+        }
+    }
+    Ok(new)
+}
+
+/// Every achievement, with the date it was unlocked, if it has been.
+pub fn gallery(database: &Database) -> Result<Vec<(Achievement, Option<String>)>, String> {
+    let unlocked = database.achievements()?;
+    Ok(Achievement::ALL
+        .iter()
+        .map(|&achievement| {
+            let date = unlocked
+                .iter()
+                .find(|(key, _)| key == achievement.key())
+                .map(|(_, date)| date.clone());
+            (achievement, date)
+        })
+        .collect())
+}
+
+#[cfg(feature = "gui")]
+pub struct AchievementsViewSettings {
+    pub font_size: u32,
+    pub background_color: Color,
+    pub text_color: Color,
+    /// Achievements not yet unlocked.
+    pub locked_color: Color,
+}
+
+#[cfg(feature = "gui")]
+impl AchievementsViewSettings {
+    pub fn new(theme: &Theme) -> AchievementsViewSettings {
+        let [r, g, b, a] = theme.text;
+        AchievementsViewSettings {
+            font_size: 16,
+            background_color: theme.panel_background,
+            text_color: theme.text,
+            locked_color: [r, g, b, a * 0.4],
+        }
+    }
+
+    /// Scales text.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.font_size = (16.0 * scale).round() as u32;
+    }
+
+    /// Switches to a theme's colors.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        *self = AchievementsViewSettings {
+            font_size: self.font_size,
+            ..AchievementsViewSettings::new(theme)
+        };
+    }
+}
+
+#[cfg(feature = "gui")]
+pub struct AchievementsView {
+    pub settings: AchievementsViewSettings,
+}
+
+#[cfg(feature = "gui")]
+impl AchievementsView {
+    pub fn new(settings: AchievementsViewSettings) -> AchievementsView {
+        AchievementsView { settings }
+    }
+
+    /// Draws every achievement with its description, dimming those still
+    /// locked, over the area at `pos` with side length `size`.
+    pub fn draw<G: Graphics, C>(
+        &self,
+        gallery: &[(Achievement, Option<String>)],
+        pos: [f64; 2],
+        size: f64,
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::Rectangle;
+
+        let settings = &self.settings;
+        Rectangle::new(settings.background_color).draw(
+            [pos[0], pos[1], size, size],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+        let line_height = settings.font_size as f64 * 1.4;
+        let unlocked = gallery.iter().filter(|(_, date)| date.is_some()).count();
+        let mut lines = vec![
+            (
+                format!("Achievements: {} of {} unlocked", unlocked, gallery.len()),
+                settings.text_color,
+            ),
+            (String::new(), settings.text_color),
+        ];
+        for (achievement, date) in gallery {
+            let (heading, color) = match date {
+                Some(date) => (
+                    format!("{} - unlocked {}", achievement.name(), date),
+                    settings.text_color,
+                ),
+                None => (achievement.name().to_string(), settings.locked_color),
+            };
+            lines.push((heading, color));
+            lines.push((format!("  {}", achievement.description()), color));
+        }
+        for (row, (line, color)) in lines.iter().enumerate() {
+            draw_text(
+                line,
+                settings.font_size,
+                *color,
+                [pos[0] + 10.0, pos[1] + (row + 1) as f64 * line_height],
+                glyphs,
+                c,
+                g,
+            );
+        }
+        draw_text(
+            "Press any key to go back",
+            settings.font_size,
+            settings.text_color,
+            [pos[0] + 10.0, pos[1] + size - line_height / 2.0],
+            glyphs,
+            c,
+            g,
+        );
+    }
+}
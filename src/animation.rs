@@ -0,0 +1,147 @@
+use graphics::types::Color;
+
+/// How a tween's progress maps to its value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    /// Starts fast and slows down.
+    EaseOut,
+}
+
+impl Easing {
+    fn apply(self, t: f64) -> f64 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+        }
+    }
+}
+
+/// A value moving from `from` to `to` over `duration` seconds, after `delay`.
+#[derive(Clone, Copy, Debug)]
+pub struct Tween {
+    pub from: f64,
+    pub to: f64,
+    pub delay: f64,
+    pub duration: f64,
+    pub easing: Easing,
+    /// Seconds since the tween was added, including the delay.
+    age: f64,
+}
+
+impl Tween {
+    pub fn new(from: f64, to: f64, duration: f64) -> Tween {
+        Tween {
+            from,
+            to,
+            delay: 0.0,
+            duration,
+            easing: Easing::Linear,
+            age: 0.0,
+        }
+    }
+
+    pub fn delay(self, delay: f64) -> Tween {
+        Tween { delay, ..self }
+    }
+
+    pub fn easing(self, easing: Easing) -> Tween {
+        Tween { easing, ..self }
+    }
+
+    /// Progress from 0 to 1, or `None` while delayed.
+    pub fn progress(&self) -> Option<f64> {
+        let t = (self.age - self.delay) / self.duration;
+        if t < 0.0 {
+            None
+        } else {
+            Some(t.min(1.0))
+        }
+    }
+
+    /// The current value, or `None` while delayed.
+    pub fn value(&self) -> Option<f64> {
+        self.progress()
+            .map(|t| self.from + (self.to - self.from) * self.easing.apply(t))
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.age >= self.delay + self.duration
+    }
+}
+
+/// What an animation changes on the board.
+#[derive(Clone, Copy, Debug)]
+pub enum Effect {
+    /// Fills a cell, with the tween scaling the color's alpha.
+    CellFill { cell: [usize; 2], color: Color },
+    /// Shakes a cell's digit sideways, with the tween as the amplitude in
+    /// cell widths.
+    Shake { cell: [usize; 2] },
+}
+
+/// Shake oscillations per second.
+const SHAKE_FREQUENCY: f64 = 20.0;
+
+struct Animation {
+    effect: Effect,
+    tween: Tween,
+}
+
+/// Running animations, advanced by update events and read by draw code.
+#[derive(Default)]
+pub struct Animations {
+    running: Vec<Animation>,
+}
+
+impl Animations {
+    pub fn new() -> Animations {
+        Animations::default()
+    }
+
+    pub fn add(&mut self, effect: Effect, tween: Tween) {
+        self.running.push(Animation { effect, tween });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.running.is_empty()
+    }
+
+    /// Advances every animation by `dt` seconds, dropping finished ones.
+    pub fn update(&mut self, dt: f64) {
+        for animation in &mut self.running {
+            animation.tween.age += dt;
+        }
+        self.running.retain(|animation| !animation.tween.is_done());
+    }
+
+    /// Cell fills to draw, with their current colors.
+    pub fn cell_fills(&self) -> impl Iterator<Item = ([usize; 2], Color)> + '_ {
+        self.running
+            .iter()
+            .filter_map(|animation| match animation.effect {
+                Effect::CellFill { cell, color } => animation.tween.value().map(|alpha| {
+                    let mut color = color;
+                    color[3] *= alpha as f32;
+                    (cell, color)
+                }),
+                _ => None,
+            })
+    }
+
+    /// Sideways offset of a cell's digit, in cell widths.
+    pub fn shake_offset(&self, ind: [usize; 2]) -> f64 {
+        self.running
+            .iter()
+            .filter_map(|animation| match animation.effect {
+                Effect::Shake { cell } if cell == ind => {
+                    let age = animation.tween.age - animation.tween.delay;
+                    animation.tween.value().map(|amplitude| {
+                        amplitude * (age * SHAKE_FREQUENCY * std::f64::consts::TAU).sin()
+                    })
+                }
+                _ => None,
+            })
+            .sum()
+    }
+}
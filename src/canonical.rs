@@ -0,0 +1,159 @@
+//! The same puzzle can be written many ways: relabeling the digits,
+//! swapping rows within a band, swapping bands, the same for columns and
+//! stacks, and transposing all give puzzles that play identically. These
+//! are reduced to one canonical form, so a puzzle is recognized however it
+//! was written.
+
+use crate::grid::Grid;
+use crate::SIZE;
+
+/// The orders three things can be put in.
+const ORDERS: [[usize; 3]; 6] = [
+    [0, 1, 2],
+    [0, 2, 1],
+    [1, 0, 2],
+    [1, 2, 0],
+    [2, 0, 1],
+    [2, 1, 0],
+];
+
+/// Every column order reachable by reordering stacks and the columns within
+/// each stack.
+fn column_orders() -> Vec<[usize; SIZE]> {
+    let mut orders = Vec::with_capacity(6 * 6 * 6 * 6);
+    for stacks in &ORDERS {
+        for first in &ORDERS {
+            for second in &ORDERS {
+                for third in &ORDERS {
+                    let mut order = [0; SIZE];
+                    for (k, within) in [first, second, third].iter().enumerate() {
+                        for (m, &col) in within.iter().enumerate() {
+                            order[3 * k + m] = 3 * stacks[k] + col;
+                        }
+                    }
+                    orders.push(order);
+                }
+            }
+        }
+    }
+    orders
+}
+
+/// Rows of a classic-size board.
+type Rows = [[u8; SIZE]; SIZE];
+
+/// The search for the smallest form, kept as rows are chosen.
+struct Search<'a> {
+    grid: &'a Rows,
+    columns: [usize; SIZE],
+    /// Smallest rows found so far, of which the first `best_len` are set.
+    best: Rows,
+    best_len: usize,
+}
+
+impl Search<'_> {
+    /// Chooses the row at `depth`, given the rows used so far and the digit
+    /// labels handed out, pruning any choice that would come out larger than
+    /// the best form.
+    ///
+    /// The rows already chosen always equal the first `depth` best rows.
+    fn choose(&mut self, depth: usize, used: [bool; SIZE], labels: [u8; SIZE + 1]) {
+        if depth == SIZE {
+            return;
+        }
+        // A band is finished before the next one starts.
+        let candidates = if depth.is_multiple_of(3) {
+            0..SIZE
+        } else {
+            let band = (0..SIZE)
+                .find(|&row| used[row] && !(0..3).all(|k| used[row / 3 * 3 + k]))
+                .map_or(0, |row| row / 3 * 3);
+            band..band + 3
+        };
+        for row in candidates {
+            if used[row] {
+                continue;
+            }
+            let mut labels = labels;
+            let mut next = labels.iter().filter(|&&label| label != 0).count() as u8 + 1;
+            let mut line = [0; SIZE];
+            for (cell, &col) in line.iter_mut().zip(&self.columns) {
+                let val = self.grid[row][col] as usize;
+                if val == 0 {
+                    continue;
+                }
+                if labels[val] == 0 {
+                    labels[val] = next;
+                    next += 1;
+                }
+                *cell = labels[val];
+            }
+            if depth < self.best_len {
+                if line > self.best[depth] {
+                    continue;
+                }
+                if line < self.best[depth] {
+                    self.best_len = depth;
+                }
+            }
+            if depth >= self.best_len {
+                self.best[depth] = line;
+                self.best_len = depth + 1;
+            }
+            let mut used = used;
+            used[row] = true;
+            self.choose(depth + 1, used, labels);
+        }
+    }
+}
+
+/// The smallest way of writing a puzzle, comparing row by row with empty
+/// cells first and digits relabeled in order of appearance.
+///
+/// Only puzzles of the classic size have a canonical form; others come out
+/// as an empty grid.
+pub fn canonical(grid: &Grid) -> Grid {
+    let mut canonical = Grid::new(SIZE);
+    if grid.size() != SIZE {
+        return canonical;
+    }
+    let mut rows = [[0; SIZE]; SIZE];
+    let mut transposed = [[0; SIZE]; SIZE];
+    for (j, row) in grid.iter().enumerate() {
+        for (i, &val) in row.iter().enumerate() {
+            rows[j][i] = val;
+            transposed[i][j] = val;
+        }
+    }
+    let orders = column_orders();
+    let mut best = [[0; SIZE]; SIZE];
+    let mut best_len = 0;
+    for grid in [&rows, &transposed] {
+        for &columns in &orders {
+            let mut search = Search {
+                grid,
+                columns,
+                best,
+                best_len,
+            };
+            search.choose(0, [false; SIZE], [0; SIZE + 1]);
+            best = search.best;
+            best_len = search.best_len;
+        }
+    }
+    for (row, best) in canonical.iter_mut().zip(&best) {
+        row.copy_from_slice(best);
+    }
+    canonical
+}
+
+/// A short key for a puzzle that is the same however it was written: the
+/// 64-bit FNV-1a hash of its canonical form, in hex.
+pub fn hash(grid: &Grid) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &val in canonical(grid).iter().flatten() {
+        hash ^= u64::from(val);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
@@ -0,0 +1,176 @@
+use crate::coop::{ChatLine, MAX_CHAT_LEN};
+use crate::text::{draw_text, text_width};
+use crate::theme::Theme;
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{Context, Graphics};
+use piston::input::GenericEvent;
+
+/// Lines of chat shown at once, the latest last.
+const SHOWN_LINES: usize = 8;
+
+/// Types chat messages while the chat is open.
+pub struct ChatController {
+    pub visible: bool,
+    /// Message being typed.
+    pub typed: String,
+}
+
+impl Default for ChatController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChatController {
+    pub fn new() -> ChatController {
+        ChatController {
+            visible: false,
+            typed: String::new(),
+        }
+    }
+
+    /// Handles events while the chat is open, returning a message once
+    /// Return sends it. Typing goes to the chat rather than the board.
+    pub fn event<E: GenericEvent>(&mut self, e: &E) -> Option<String> {
+        use piston::input::{Button, Key};
+
+        if let Some(text) = e.text_args() {
+            let room = MAX_CHAT_LEN.saturating_sub(self.typed.chars().count());
+            self.typed
+                .extend(text.chars().filter(|ch| !ch.is_control()).take(room));
+        }
+        match e.press_args() {
+            Some(Button::Keyboard(Key::Backspace)) => {
+                self.typed.pop();
+                None
+            }
+            Some(Button::Keyboard(Key::Return)) if !self.typed.trim().is_empty() => {
+                Some(std::mem::take(&mut self.typed))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub struct ChatViewSettings {
+    pub font_size: u32,
+    /// Width of the panel along the window's bottom left corner.
+    pub width: f64,
+    pub background_color: Color,
+    pub text_color: Color,
+    /// Colors of the players, by number, for their names.
+    pub player_colors: Vec<Color>,
+}
+
+impl ChatViewSettings {
+    pub fn new(theme: &Theme) -> ChatViewSettings {
+        let mut background_color = theme.panel_background;
+        background_color[3] *= 0.9;
+        ChatViewSettings {
+            font_size: 14,
+            width: 360.0,
+            background_color,
+            text_color: theme.text,
+            player_colors: theme.partners.clone(),
+        }
+    }
+
+    /// Scales text and the panel.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.font_size = (14.0 * scale).round() as u32;
+        self.width = 360.0 * scale;
+    }
+
+    /// Switches to a theme's colors.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        *self = ChatViewSettings {
+            font_size: self.font_size,
+            width: self.width,
+            ..ChatViewSettings::new(theme)
+        };
+    }
+}
+
+pub struct ChatView {
+    pub settings: ChatViewSettings,
+}
+
+impl ChatView {
+    pub fn new(settings: ChatViewSettings) -> ChatView {
+        ChatView { settings }
+    }
+
+    /// Draws a panel in the window's bottom left corner with the latest
+    /// chat messages, each after its player's name in their color, and the
+    /// message being typed below.
+    pub fn draw<G: Graphics, C>(
+        &self,
+        controller: &ChatController,
+        chat: &[ChatLine],
+        muted: bool,
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::Rectangle;
+
+        let settings = &self.settings;
+        let [_, height] = c.get_view_size();
+        let line_height = settings.font_size as f64 * 1.4;
+        let panel_height = (SHOWN_LINES + 3) as f64 * line_height;
+        let y = height - panel_height;
+        Rectangle::new(settings.background_color).draw(
+            [0.0, y, settings.width, panel_height],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+        let title = if muted {
+            "Chat (F10) - muted, Shift+F10 to unmute"
+        } else {
+            "Chat (F10) - Shift+F10 to mute"
+        };
+        let line = |row: usize| y + (row + 1) as f64 * line_height;
+        draw_text(
+            title,
+            settings.font_size,
+            settings.text_color,
+            [8.0, line(0)],
+            glyphs,
+            c,
+            g,
+        );
+        let first = chat.len().saturating_sub(SHOWN_LINES);
+        for (row, said) in chat[first..].iter().enumerate() {
+            let name = format!("{}: ", said.name);
+            let color = match settings.player_colors.len() {
+                0 => settings.text_color,
+                len => settings.player_colors[said.id as usize % len],
+            };
+            let pos = [8.0, line(row + 1)];
+            draw_text(&name, settings.font_size, color, pos, glyphs, c, g);
+            let indent = text_width(&name, settings.font_size, glyphs, c);
+            draw_text(
+                &said.text,
+                settings.font_size,
+                settings.text_color,
+                [pos[0] + indent, pos[1]],
+                glyphs,
+                c,
+                g,
+            );
+        }
+        draw_text(
+            &format!("> {}_", controller.typed),
+            settings.font_size,
+            settings.text_color,
+            [8.0, line(SHOWN_LINES + 1) + line_height / 2.0],
+            glyphs,
+            c,
+            g,
+        );
+    }
+}
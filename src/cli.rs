@@ -0,0 +1,1114 @@
+use crate::collection::{Collection, PROGRESS_FILE};
+use crate::config::Config;
+#[cfg(feature = "gui")]
+use crate::coop::{Role, COOP_ADDRESS};
+use crate::database::{Database, DATABASE_FILE};
+use crate::fpuzzles::{self, FPuzzle};
+use crate::grid::{Grid, SIZES};
+use crate::http::{self, Request, Response};
+use crate::pdf::{self, PrintedPuzzle};
+use crate::profile::Profile;
+use crate::puzzle::{self, SaveFormat};
+use crate::rules::RuleSet;
+#[cfg(feature = "gui")]
+use crate::save;
+#[cfg(feature = "sdl")]
+use crate::sdl;
+use crate::solver;
+use crate::stats;
+use crate::technique::{HiddenSingle, Registry, SolveStep, Technique};
+use crate::tui;
+use crate::variant::{Variant, VariantKind};
+use crate::{
+    file_name, format_duration, new_game, start_game, Difficulty, Gameboard, GameboardController,
+    SIZE,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Command-line arguments: a subcommand run without a window, or options
+/// for the game.
+#[derive(Debug, Parser)]
+#[command(
+    name = "sudoku",
+    version,
+    about = "Sudoku: play in a window or the terminal, or generate, solve, and rate puzzles",
+    after_help = "\
+PUZZLE is a puzzle file, or a puzzle written as 81 digits with 0 or . for
+empty cells."
+)]
+#[cfg_attr(
+    feature = "gui",
+    command(after_help = "\
+PUZZLE is a puzzle file, or a puzzle written as 81 digits with 0 or . for
+empty cells.
+
+--host plays a co-op game others can join, by default on 0.0.0.0:7878, and
+--join plays the game hosted at an address, on port 7878 unless it names
+one. Players edit the same board, going by their profile names. With
+--race each player gets the host's puzzle on a board of their own, sees only
+how much of the others' boards are filled, and the first to solve it wins.
+--watch joins only to watch, seeing the board change without changing it,
+or in a race, each player's board in turn with Tab. Hosted games are
+advertised on the local network over multicast DNS, where Multiplayer in the
+menu lists them, and can be joined by their code there.")
+)]
+pub struct Args {
+    /// Log every detail, to the console and the day's log in logs, such as
+    /// logs/sudoku.2026-10-16.log
+    #[arg(long, global = true)]
+    pub verbose: bool,
+    /// Play as a profile
+    #[cfg(feature = "gui")]
+    #[arg(long, value_name = "NAME", value_parser = profile_name)]
+    pub profile: Option<String>,
+    /// Host a co-op game
+    #[cfg(feature = "gui")]
+    #[arg(
+        long,
+        value_name = "HOST:PORT",
+        num_args = 0..=1,
+        default_missing_value = COOP_ADDRESS,
+        conflicts_with_all = ["join", "watch"]
+    )]
+    pub host: Option<String>,
+    /// Race the players who join, each on a board of their own
+    #[cfg(feature = "gui")]
+    #[arg(long, requires = "host")]
+    pub race: bool,
+    /// Join the co-op game hosted at an address
+    #[cfg(feature = "gui")]
+    #[arg(long, value_name = "HOST[:PORT]", conflicts_with = "watch")]
+    pub join: Option<String>,
+    /// Watch the co-op game hosted at an address
+    #[cfg(feature = "gui")]
+    #[arg(long, value_name = "HOST[:PORT]")]
+    pub watch: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[cfg(feature = "gui")]
+impl Args {
+    /// The co-op game to host or join, if any.
+    pub fn role(&self) -> Option<Role> {
+        match (&self.host, &self.join, &self.watch) {
+            (Some(address), _, _) => Some(Role::Host(address.clone())),
+            (_, Some(address), _) => Some(Role::Join(address.clone())),
+            (_, _, Some(address)) => Some(Role::Watch(address.clone())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Generate new puzzles, one per line in the line format
+    Generate(GenerateArgs),
+    /// Write the solution of each puzzle as a line
+    #[command(after_help = "\
+With no PUZZLE, or -, a puzzle is read from each line of standard input.
+With --format json, each puzzle's result is written as a JSON object on a
+line of its own, with its solution, steps, rating, and timings.")]
+    Solve {
+        #[arg(short, long, value_enum, default_value_t = Results::Text)]
+        format: Results,
+        puzzles: Vec<String>,
+    },
+    /// Write each puzzle's difficulty and the techniques solving it
+    Rate {
+        #[arg(short, long, value_enum, default_value_t = Results::Text)]
+        format: Results,
+        #[arg(required = true)]
+        puzzles: Vec<String>,
+    },
+    /// Write whether each puzzle has a unique solution
+    Check {
+        #[arg(required = true)]
+        puzzles: Vec<String>,
+    },
+    /// Write puzzles in another format
+    Convert {
+        /// line, sdk, csv, or json
+        #[arg(short, long, default_value = "line", value_parser = Format::from_name)]
+        format: Format,
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+        #[arg(required = true)]
+        puzzles: Vec<String>,
+    },
+    /// Print puzzle files, or new puzzles, to a PDF
+    Print(PrintArgs),
+    /// Write the statistics of each difficulty
+    Stats {
+        /// Write every game played to a CSV file instead
+        #[arg(long, value_name = "FILE")]
+        csv: Option<String>,
+    },
+    /// Play in the terminal
+    Tui {
+        #[arg(short, long, default_value = "medium", value_parser = difficulty)]
+        difficulty: Difficulty,
+        #[arg(short, long, default_value_t = SIZE, value_parser = size)]
+        size: usize,
+        puzzle: Option<String>,
+    },
+    /// Play on a window drawn with SDL2, where the OpenGL window cannot open
+    #[cfg(feature = "sdl")]
+    Sdl {
+        #[arg(short, long, default_value = "medium", value_parser = difficulty)]
+        difficulty: Difficulty,
+        #[arg(short, long, default_value_t = SIZE, value_parser = size)]
+        size: usize,
+        puzzle: Option<String>,
+    },
+    /// Answer requests for puzzles, solutions, and ratings with JSON over HTTP
+    #[command(after_help = "\
+  GET /puzzle?difficulty=NAME&size=N&variant=NAME,...  a new puzzle
+  POST /solve  the solution of the puzzle in the body
+  POST /rate   the result of the puzzle in the body, as rate --format json
+A body is a puzzle as digits, {\"puzzle\": \"...\"}, or an f-puzzles file.")]
+    Serve {
+        #[arg(short, long, value_name = "HOST:PORT", default_value = SERVE_ADDRESS)]
+        address: String,
+    },
+}
+
+#[derive(Debug, clap::Args)]
+pub struct GenerateArgs {
+    #[arg(short = 'n', long, default_value_t = 1, value_parser = count)]
+    count: usize,
+    #[arg(short, long, default_value = "medium", value_parser = difficulty)]
+    difficulty: Difficulty,
+    #[arg(short, long, default_value_t = SIZE, value_parser = size)]
+    size: usize,
+    /// A variant to play, such as X-Sudoku or Jigsaw
+    #[arg(short, long = "variant", value_name = "NAME", value_parser = variant)]
+    variants: Vec<VariantKind>,
+    #[arg(long)]
+    anti_knight: bool,
+    #[arg(long)]
+    anti_king: bool,
+    #[arg(long)]
+    non_consecutive: bool,
+    /// line, sdk, csv, or json
+    #[arg(short, long, default_value = "line", value_parser = Format::from_name)]
+    format: Format,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct PrintArgs {
+    /// How many puzzles to generate when no files are given
+    #[arg(short = 'n', long, default_value_t = 1, value_parser = count)]
+    count: usize,
+    #[arg(short, long, default_value = "medium", value_parser = difficulty)]
+    difficulty: Difficulty,
+    /// 1, 2, 4, or 6
+    #[arg(short, long, default_value_t = 1, value_parser = per_page)]
+    per_page: usize,
+    /// Add pages of solutions after the puzzles
+    #[arg(short, long)]
+    solutions: bool,
+    #[arg(short, long, value_name = "FILE", default_value = "sudoku.pdf")]
+    output: PathBuf,
+    #[arg(value_name = "PUZZLE_FILE")]
+    files: Vec<PathBuf>,
+}
+
+#[cfg(feature = "gui")]
+fn profile_name(name: &str) -> Result<String, String> {
+    if !name.is_empty() && name.chars().all(save::is_name_char) {
+        Ok(name.to_string())
+    } else {
+        Err("letters, digits, spaces, - and _ only".to_string())
+    }
+}
+
+fn count(count: &str) -> Result<usize, String> {
+    count
+        .parse()
+        .ok()
+        .filter(|&count| count > 0)
+        .ok_or_else(|| "must be a positive number".to_string())
+}
+
+fn difficulty(name: &str) -> Result<Difficulty, String> {
+    Difficulty::from_name(name).ok_or_else(|| format!("Unknown difficulty: {}", name))
+}
+
+fn size(size: &str) -> Result<usize, String> {
+    size.parse()
+        .ok()
+        .filter(|size| SIZES.contains(size))
+        .ok_or_else(|| "must be 4, 6, 9, 16, or 25".to_string())
+}
+
+fn variant(name: &str) -> Result<VariantKind, String> {
+    VariantKind::from_name(name).ok_or_else(|| format!("Unknown variant: {}", name))
+}
+
+fn per_page(per_page: &str) -> Result<usize, String> {
+    per_page
+        .parse()
+        .ok()
+        .filter(|per_page| pdf::PER_PAGE.contains(per_page))
+        .ok_or_else(|| "must be 1, 2, 4, or 6".to_string())
+}
+
+/// How puzzles are written out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// A single line of a character per cell.
+    Line,
+    File(SaveFormat),
+}
+
+impl Format {
+    fn from_name(name: &str) -> Result<Format, String> {
+        match name.to_lowercase().as_str() {
+            "line" => Ok(Format::Line),
+            "sdk" => Ok(Format::File(SaveFormat::Sdk)),
+            "csv" => Ok(Format::File(SaveFormat::Csv)),
+            "json" => Ok(Format::File(SaveFormat::Json)),
+            _ => Err(format!("Unknown format: {}", name)),
+        }
+    }
+
+    /// Writes a puzzle under a set of rules, rated at a difficulty where the
+    /// format keeps one. Lines only hold classic puzzles.
+    fn write(
+        self,
+        cells: &Grid,
+        rules: &RuleSet,
+        difficulty: Difficulty,
+    ) -> Result<String, String> {
+        match self {
+            Format::Line => match rules.names().first() {
+                Some(name) => Err(format!("{} puzzles cannot be written as a line", name)),
+                None => Ok(puzzle::to_line(cells)),
+            },
+            Format::File(format) => puzzle::write(cells, rules, difficulty, format),
+        }
+    }
+}
+
+/// How the results of solving and rating are written out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Results {
+    Text,
+    /// A JSON object a line, for other tools to read.
+    Json,
+}
+
+/// The result of solving and rating a puzzle, as written with `--format
+/// json`: its solution, or why it has none, the steps taken, its rating,
+/// and how long each part took.
+#[derive(Debug, Default, Serialize)]
+struct Report {
+    puzzle: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    givens: Option<usize>,
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    solution: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rating: Option<&'static str>,
+    /// Whether the techniques registered fill the puzzle, without guessing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logical: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    steps: Option<Vec<Step>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    solve_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    steps_ms: Option<f64>,
+    /// Why a line could not be read as a puzzle.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Report {
+    /// Writes the report as a line of JSON.
+    fn to_line(&self) -> String {
+        serde_json::to_string(self).expect("reports are always written")
+    }
+}
+
+/// A step of a report, as a technique found it, counting rows and columns
+/// from 1.
+#[derive(Debug, Serialize)]
+struct Step {
+    technique: &'static str,
+    placed: Vec<Placed>,
+    eliminated: Vec<Eliminated>,
+    description: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Placed {
+    row: usize,
+    column: usize,
+    digit: u8,
+}
+
+#[derive(Debug, Serialize)]
+struct Eliminated {
+    row: usize,
+    column: usize,
+    digits: Vec<u8>,
+}
+
+impl From<&SolveStep> for Step {
+    fn from(step: &SolveStep) -> Step {
+        Step {
+            technique: step.technique,
+            placed: step
+                .placed
+                .iter()
+                .map(|&([x, y], digit)| Placed {
+                    row: y + 1,
+                    column: x + 1,
+                    digit,
+                })
+                .collect(),
+            eliminated: step
+                .eliminated
+                .iter()
+                .map(|&([x, y], mask)| Eliminated {
+                    row: y + 1,
+                    column: x + 1,
+                    digits: (1..32).filter(|&val| mask & 1 << val != 0).collect(),
+                })
+                .collect(),
+            description: step.description.clone(),
+        }
+    }
+}
+
+/// A puzzle named on the command line.
+struct Input {
+    /// Names the puzzle in messages.
+    label: String,
+    cells: Grid,
+    rules: RuleSet,
+    /// Guesses the solver may take before giving up on the puzzle.
+    budget: usize,
+}
+
+/// Why a puzzle has no solution given, when the solver ran out of guesses.
+const GAVE_UP: &str = "too hard to solve";
+
+impl Input {
+    fn givens(&self) -> usize {
+        self.cells.iter().flatten().filter(|&&val| val != 0).count()
+    }
+
+    /// The puzzle's solution, or why it does not have exactly one.
+    fn solve(&self) -> Result<Grid, &'static str> {
+        match solver::unique_solution_within(&self.cells, &self.rules, self.budget) {
+            Some((_, Some(solution))) => Ok(solution),
+            Some((0, _)) => Err("no solution"),
+            Some(_) => Err("multiple solutions"),
+            None => Err(GAVE_UP),
+        }
+    }
+
+    /// Solves and rates the puzzle.
+    fn report(&self) -> Report {
+        let millis = |start: Instant| Some(start.elapsed().as_secs_f64() * 1000.0);
+        let givens = self.givens();
+        let start = Instant::now();
+        let solved = self.solve();
+        let mut report = Report {
+            puzzle: self.label.clone(),
+            givens: Some(givens),
+            solve_ms: millis(start),
+            ..Report::default()
+        };
+        let solution = match solved {
+            Ok(solution) => solution,
+            Err(reason) => {
+                report.status = reason.to_string();
+                return report;
+            }
+        };
+        let start = Instant::now();
+        let registry = Registry::default();
+        let (steps, logical) = registry.solve(&self.cells, &self.rules);
+        report.steps_ms = millis(start);
+        report.status = "solved".to_string();
+        report.solution = Some(puzzle::to_line(&solution));
+        report.rating = Some(registry.rating(&steps, logical).name());
+        report.logical = Some(logical);
+        report.steps = Some(steps.iter().map(Step::from).collect());
+        report
+    }
+}
+
+/// Reads the puzzles given as arguments: puzzle files, collections in
+/// full, or puzzles written out as digits.
+fn inputs(args: &[String]) -> Result<Vec<Input>, String> {
+    let mut inputs = Vec::new();
+    for (k, arg) in args.iter().enumerate() {
+        let path = Path::new(arg);
+        if !path.is_file() {
+            let cells = puzzle::parse(arg).map_err(|err| format!("{}: {}", arg, err))?;
+            inputs.push(Input {
+                label: format!("Puzzle {}", k + 1),
+                cells,
+                rules: RuleSet::default(),
+                budget: usize::MAX,
+            });
+            continue;
+        }
+        let name = file_name(path);
+        if puzzle::is_collection(path) {
+            let collection = Collection::open(path, Profile::last().path(PROGRESS_FILE))
+                .map_err(|err| format!("{}: {}", name, err))?;
+            for (k, entry) in collection.puzzles.into_iter().enumerate() {
+                inputs.push(Input {
+                    label: format!("{} #{}", name, k + 1),
+                    cells: entry.cells,
+                    rules: RuleSet::default(),
+                    budget: usize::MAX,
+                });
+            }
+        } else {
+            let read = puzzle::read(path).map_err(|err| format!("{}: {}", name, err))?;
+            inputs.push(Input {
+                label: name,
+                cells: read.cells,
+                rules: read.rules,
+                budget: usize::MAX,
+            });
+        }
+    }
+    if inputs.is_empty() {
+        return Err("No puzzles given".to_string());
+    }
+    Ok(inputs)
+}
+
+/// Runs a subcommand instead of opening the game's window, returning the
+/// exit code.
+pub fn run(command: Command) -> i32 {
+    let (action, result) = match command {
+        Command::Generate(args) => ("generate", generate(args)),
+        Command::Solve { format, puzzles } => ("solve", solve(format, &puzzles)),
+        Command::Rate { format, puzzles } => ("rate", rate(format, &puzzles)),
+        Command::Check { puzzles } => ("check", check(&puzzles)),
+        Command::Convert {
+            format,
+            output,
+            puzzles,
+        } => ("convert", convert(format, output, &puzzles)),
+        Command::Print(args) => ("print", print(args)),
+        Command::Stats { csv } => ("read statistics", print_stats(csv)),
+        Command::Tui {
+            difficulty,
+            size,
+            puzzle,
+        } => ("play in the terminal", play_tui(difficulty, size, puzzle)),
+        #[cfg(feature = "sdl")]
+        Command::Sdl {
+            difficulty,
+            size,
+            puzzle,
+        } => ("play with SDL2", play_sdl(difficulty, size, puzzle)),
+        Command::Serve { address } => ("serve", serve(&address)),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("Could not {}: {}", action, err);
+            1
+        }
+    }
+}
+
+/// Runs `sudoku generate`, writing new puzzles to standard output, one per
+/// line in the line format.
+fn generate(args: GenerateArgs) -> Result<(), String> {
+    let GenerateArgs {
+        count,
+        difficulty,
+        size,
+        variants,
+        anti_knight,
+        anti_king,
+        non_consecutive,
+        format,
+    } = args;
+    let mut rules = RuleSet::default();
+    for kind in variants {
+        rules = rules.with(Variant::new(kind));
+    }
+    rules.constraints.anti_knight = anti_knight;
+    rules.constraints.anti_king = anti_king;
+    rules.constraints.non_consecutive = non_consecutive;
+    rules.check()?;
+    // Checked on an empty grid first, as generating can take a while.
+    format.write(&Grid::new(size), &rules, difficulty)?;
+    for _ in 0..count {
+        let mut gameboard = Gameboard::new();
+        gameboard.generate_rules(difficulty, rules.clone(), size);
+        // Rules are left out when no grid can be found that obeys them.
+        if gameboard.rules.names() != rules.names() {
+            return Err(format!(
+                "No {}x{} puzzle could be found for these rules",
+                size, size
+            ));
+        }
+        let puzzle = gameboard.puzzle();
+        println!("{}", format.write(&puzzle, &gameboard.rules, difficulty)?);
+    }
+    Ok(())
+}
+
+/// Runs `sudoku solve`, writing the solution of each puzzle as a line.
+///
+/// With no puzzles given, or `-`, puzzles are read from standard input
+/// instead, one per line.
+///
+/// With `--format json`, each puzzle's result object is written instead.
+fn solve(results: Results, puzzles: &[String]) -> Result<(), String> {
+    if puzzles.is_empty() || puzzles == ["-"] {
+        let stdin = io::stdin();
+        let stdout = io::stdout();
+        return solve_lines(stdin.lock(), stdout.lock(), results);
+    }
+    let inputs = inputs(puzzles)?;
+    let mut failed = 0;
+    for input in &inputs {
+        if results == Results::Json {
+            let report = input.report();
+            if report.solution.is_none() {
+                failed += 1;
+            }
+            println!("{}", report.to_line());
+            continue;
+        }
+        match input.solve() {
+            Ok(solution) => println!("{}", puzzle::to_line(&solution)),
+            Err(reason) => {
+                eprintln!("{}: {}", input.label, reason);
+                failed += 1;
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!(
+            "{} of {} puzzles have no unique solution",
+            failed,
+            inputs.len()
+        )),
+    }
+}
+
+/// Solves the puzzle on each line read, skipping blank lines, and writes a
+/// line for each: its solution, or `no solution`, `multiple solutions`, or
+/// `invalid puzzle`, so the output lines up with the puzzles.
+///
+/// As JSON, each line is instead the puzzle's result object, or one with
+/// its `error` for lines that are not puzzles.
+///
+/// Stops quietly once the output is closed, as when piped into `head`.
+fn solve_lines<R: BufRead, W: Write>(input: R, output: W, results: Results) -> Result<(), String> {
+    let mut output = BufWriter::new(output);
+    for (k, line) in input.lines().enumerate() {
+        let line = line.map_err(|err| err.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let label = format!("Line {}", k + 1);
+        let result = match puzzle::parse(&line) {
+            Ok(cells) => {
+                let input = Input {
+                    label,
+                    cells,
+                    rules: RuleSet::default(),
+                    budget: usize::MAX,
+                };
+                match results {
+                    Results::Text => input
+                        .solve()
+                        .map_or_else(str::to_string, |solution| puzzle::to_line(&solution)),
+                    Results::Json => input.report().to_line(),
+                }
+            }
+            Err(err) if results == Results::Json => Report {
+                puzzle: label,
+                status: "invalid puzzle".to_string(),
+                error: Some(err),
+                ..Report::default()
+            }
+            .to_line(),
+            Err(err) => {
+                eprintln!("Line {}: {}", k + 1, err);
+                "invalid puzzle".to_string()
+            }
+        };
+        match writeln!(output, "{}", result) {
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            written => written.map_err(|err| err.to_string())?,
+        }
+    }
+    match output.flush() {
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        flushed => flushed.map_err(|err| err.to_string()),
+    }
+}
+
+/// Runs `sudoku rate`, writing each puzzle's difficulty, its number of
+/// givens, and whether the techniques registered solve it, naming the
+/// hardest taken beyond single steps.
+///
+/// With `--format json`, each puzzle's result object is written instead.
+fn rate(results: Results, puzzles: &[String]) -> Result<(), String> {
+    let inputs = inputs(puzzles)?;
+    let registry = Registry::default();
+    let mut failed = 0;
+    for input in &inputs {
+        if results == Results::Json {
+            let report = input.report();
+            if report.solution.is_none() {
+                failed += 1;
+            }
+            println!("{}", report.to_line());
+            continue;
+        }
+        if let Err(reason) = input.solve() {
+            println!("{}: {}", input.label, reason);
+            failed += 1;
+            continue;
+        }
+        let (steps, solved) = registry.solve(&input.cells, &input.rules);
+        let solved_by = match registry.hardest(&steps) {
+            _ if !solved => "needs more than single steps".to_string(),
+            Some(hardest) if hardest.weight() > HiddenSingle.weight() => {
+                format!("solved by steps up to {}", hardest.name())
+            }
+            _ => "solved by single steps".to_string(),
+        };
+        println!(
+            "{}: {}, {} givens, {}",
+            input.label,
+            registry.rating(&steps, solved).name(),
+            input.givens(),
+            solved_by
+        );
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!(
+            "{} of {} puzzles have no unique solution",
+            failed,
+            inputs.len()
+        )),
+    }
+}
+
+/// Runs `sudoku check`, writing whether each puzzle has a unique solution.
+fn check(puzzles: &[String]) -> Result<(), String> {
+    let inputs = inputs(puzzles)?;
+    let mut failed = 0;
+    for input in &inputs {
+        let result = input.solve().map(|_| "unique solution");
+        if result.is_err() {
+            failed += 1;
+        }
+        println!(
+            "{}: {}",
+            input.label,
+            result.unwrap_or_else(|reason| reason)
+        );
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!(
+            "{} of {} puzzles have no unique solution",
+            failed,
+            inputs.len()
+        )),
+    }
+}
+
+/// Runs `sudoku convert`, writing puzzles in another format to standard
+/// output or a file.
+fn convert(format: Format, output: Option<PathBuf>, puzzles: &[String]) -> Result<(), String> {
+    let inputs = inputs(puzzles)?;
+    let mut written = Vec::new();
+    for input in &inputs {
+        let difficulty = Difficulty::for_clues(input.givens());
+        let text = format
+            .write(&input.cells, &input.rules, difficulty)
+            .map_err(|err| format!("{}: {}", input.label, err))?;
+        written.push(text);
+    }
+    match output {
+        Some(path) => {
+            if written.len() > 1 && format != Format::Line {
+                return Err("Only one puzzle can be written to a file".to_string());
+            }
+            std::fs::write(&path, written.join("\n") + "\n").map_err(|err| err.to_string())?;
+            println!("Wrote {}", path.display());
+        }
+        None => {
+            for text in written {
+                println!("{}", text.trim_end());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs `sudoku print`, writing puzzles to a PDF.
+///
+/// Puzzle files given are printed, collections in full, and otherwise new
+/// puzzles are generated.
+fn print(options: PrintArgs) -> Result<(), String> {
+    let mut puzzles = Vec::new();
+    for path in &options.files {
+        let name = file_name(path);
+        if puzzle::is_collection(path) {
+            let collection = Collection::open(path, Profile::last().path(PROGRESS_FILE))
+                .map_err(|err| format!("{}: {}", name, err))?;
+            for (k, entry) in collection.puzzles.iter().enumerate() {
+                let label = format!("{} #{}", name, k + 1);
+                let (cells, solution) = puzzle::solve(entry.cells.clone())
+                    .map_err(|err| format!("{}: {}", label, err))?;
+                puzzles.push(PrintedPuzzle {
+                    cells,
+                    solution,
+                    label,
+                });
+            }
+        } else {
+            let opened = puzzle::open(path).map_err(|err| format!("{}: {}", name, err))?;
+            if let Some(rule) = opened.rules.names().first() {
+                return Err(format!("{}: {} puzzles cannot be printed", name, rule));
+            }
+            puzzles.push(PrintedPuzzle {
+                cells: opened.cells,
+                solution: opened.solution,
+                label: opened.title.unwrap_or(name),
+            });
+        }
+    }
+    if options.files.is_empty() {
+        for k in 0..options.count {
+            let mut gameboard = Gameboard::new();
+            gameboard.generate_rules(options.difficulty, RuleSet::default(), SIZE);
+            puzzles.push(PrintedPuzzle {
+                cells: gameboard.puzzle(),
+                solution: gameboard.solution,
+                label: format!("Puzzle {} - {}", k + 1, options.difficulty.name()),
+            });
+        }
+    }
+    pdf::save(
+        &options.output,
+        &puzzles,
+        options.per_page,
+        options.solutions,
+    )
+    .map_err(|err| err.to_string())?;
+    println!("Wrote {}", options.output.display());
+    Ok(())
+}
+
+/// Runs `sudoku stats`, writing the statistics of each difficulty, or with
+/// `--csv FILE` every game played to a CSV file.
+fn print_stats(csv: Option<String>) -> Result<(), String> {
+    let database = Database::open(Profile::last().path(DATABASE_FILE))?;
+    if let Some(path) = csv {
+        let games = stats::export_csv(&database, &path)?;
+        println!("Wrote {} games to {}", games, path);
+        return Ok(());
+    }
+    let time = |time: Option<Duration>| time.map_or_else(|| "-".to_string(), format_duration);
+    println!(
+        "{:<8} {:>6} {:>6} {:>8} {:>8} {:>7} {:>7} {:>6} {:>8}",
+        "", "Played", "Won", "Best", "Average", "Streak", "Longest", "Hints", "Mistakes"
+    );
+    for &difficulty in &Difficulty::ALL {
+        let stats = database.stats(difficulty)?;
+        println!(
+            "{:<8} {:>6} {:>6} {:>8} {:>8} {:>7} {:>7} {:>6} {:>8}",
+            difficulty.name(),
+            stats.played,
+            stats.won,
+            time(stats.best_time),
+            time(stats.average_time),
+            stats.current_streak,
+            stats.longest_streak,
+            stats.hints,
+            stats.mistakes
+        );
+    }
+    Ok(())
+}
+
+/// Runs `sudoku tui`, playing the puzzle given in the terminal, or new
+/// puzzles generated as the window would for the last profile.
+fn play_tui(difficulty: Difficulty, size: usize, puzzle: Option<String>) -> Result<(), String> {
+    let (controller, database, config) = open_game(difficulty, size, puzzle)?;
+    tui::play(controller, &database, &config)
+}
+
+/// Runs `sudoku sdl`, playing as `sudoku tui` does on a window drawn with
+/// SDL2.
+#[cfg(feature = "sdl")]
+fn play_sdl(difficulty: Difficulty, size: usize, puzzle: Option<String>) -> Result<(), String> {
+    let (controller, database, config) = open_game(difficulty, size, puzzle)?;
+    sdl::play(controller, &database, &config)
+}
+
+/// A game of the puzzle given, or of a new one, for the last profile, with
+/// that profile's puzzle database and config.
+fn open_game(
+    difficulty: Difficulty,
+    size: usize,
+    puzzle: Option<String>,
+) -> Result<(GameboardController, Option<Database>, Config), String> {
+    let profile = Profile::last();
+    let config = Config::load(profile.config_path());
+    let database = Database::open(profile.path(DATABASE_FILE)).ok();
+    let controller = if let Some(puzzle) = puzzle {
+        // A collection is played from its first puzzle.
+        let input = inputs(&[puzzle])?.remove(0);
+        let solution = input
+            .solve()
+            .map_err(|reason| format!("{}: {}", input.label, reason))?;
+        let mut gameboard = Gameboard::from_puzzle(input.cells, solution);
+        gameboard.rules = input.rules;
+        start_game(gameboard, &config)
+    } else {
+        new_game(difficulty, size, &database, &config)
+    };
+    Ok((controller, database, config))
+}
+
+/// Address `sudoku serve` listens on unless given another.
+const SERVE_ADDRESS: &str = "127.0.0.1:8080";
+
+/// Guesses the solver may take on a puzzle sent to `sudoku serve`, so one
+/// request cannot keep a thread busy for good.
+const SERVE_BUDGET: usize = 100_000;
+
+/// Runs `sudoku serve`, answering requests for new puzzles, solutions, and
+/// ratings until stopped.
+fn serve(address: &str) -> Result<(), String> {
+    http::serve(address, route).map_err(|err| format!("{}: {}", address, err))
+}
+
+/// Answers a request to `sudoku serve`.
+fn route(request: &Request) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/puzzle") => new_puzzle(request),
+        ("POST", "/solve") => match body_input(&request.body) {
+            Ok(input) => match input.solve() {
+                Ok(solution) => {
+                    Response::new(200, &json!({ "solution": puzzle::to_line(&solution) }))
+                }
+                Err(GAVE_UP) => Response::error(503, GAVE_UP),
+                Err(reason) => Response::error(422, reason),
+            },
+            Err(err) => Response::error(400, &err),
+        },
+        ("POST", "/rate") => match body_input(&request.body) {
+            Ok(input) => {
+                let report = input.report();
+                let status = match (&report.solution, report.status.as_str()) {
+                    (Some(_), _) => 200,
+                    (None, GAVE_UP) => 503,
+                    (None, _) => 422,
+                };
+                Response::new(status, &report)
+            }
+            Err(err) => Response::error(400, &err),
+        },
+        (_, "/puzzle" | "/solve" | "/rate") => Response::error(405, "Method not allowed"),
+        _ => Response::error(404, "Not found"),
+    }
+}
+
+/// Reads the puzzle in a request body: digits, a JSON object with the
+/// digits as its `puzzle`, or an f-puzzles file, which keeps its variants.
+fn body_input(body: &str) -> Result<Input, String> {
+    let body = body.trim();
+    let (cells, rules) = if body.starts_with('{') {
+        let root: Value = serde_json::from_str(body).map_err(|err| err.to_string())?;
+        match root.get("puzzle").and_then(Value::as_str) {
+            Some(line) => (puzzle::parse(line)?, RuleSet::default()),
+            None => {
+                let fpuzzle = FPuzzle::parse(body)?;
+                (fpuzzle.cells, fpuzzle.rules)
+            }
+        }
+    } else {
+        (puzzle::parse(body)?, RuleSet::default())
+    };
+    Ok(Input {
+        label: "Puzzle".to_string(),
+        cells,
+        rules,
+        budget: SERVE_BUDGET,
+    })
+}
+
+/// A puzzle generated for `GET /puzzle`.
+#[derive(Serialize)]
+struct NewPuzzle {
+    puzzle: String,
+    difficulty: &'static str,
+    size: usize,
+    rules: Vec<String>,
+    /// The seed, as text, as it may not fit a JavaScript number.
+    seed: String,
+    /// The puzzle as an f-puzzles file, for variants it can hold.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fpuzzles: Option<FPuzzle>,
+}
+
+/// Answers `GET /puzzle`, generating a puzzle at the difficulty, size, and
+/// comma-separated variants asked for, medium and classic by default.
+///
+/// Puzzles are written as a line of digits, and those with variants also
+/// as an f-puzzles file, where it can hold them.
+fn new_puzzle(request: &Request) -> Response {
+    let difficulty = match request.param("difficulty") {
+        Some(name) => match Difficulty::from_name(name) {
+            Some(difficulty) => difficulty,
+            None => return Response::error(400, &format!("Unknown difficulty: {}", name)),
+        },
+        None => Difficulty::Medium,
+    };
+    let size = match request.param("size").map(str::parse) {
+        Some(Ok(size)) if SIZES.contains(&size) => size,
+        Some(_) => return Response::error(400, "size must be 4, 6, 9, 16, or 25"),
+        None => SIZE,
+    };
+    let mut rules = RuleSet::default();
+    let names = request.param("variant").unwrap_or("");
+    for name in names.split(',').filter(|name| !name.is_empty()) {
+        match VariantKind::from_name(name) {
+            Some(kind) => rules = rules.with(Variant::new(kind)),
+            None => return Response::error(400, &format!("Unknown variant: {}", name)),
+        }
+    }
+    if let Err(err) = rules.check() {
+        return Response::error(400, &err);
+    }
+    let mut gameboard = Gameboard::new();
+    gameboard.generate_rules(difficulty, rules.clone(), size);
+    if gameboard.rules.names() != rules.names() {
+        return Response::error(422, "No puzzle could be found for these rules");
+    }
+    let cells = gameboard.puzzle();
+    let mut body = NewPuzzle {
+        puzzle: puzzle::to_line(&cells),
+        difficulty: difficulty.name(),
+        size,
+        rules: gameboard
+            .rules
+            .names()
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        seed: gameboard.seed.to_string(),
+        fpuzzles: None,
+    };
+    if !gameboard.rules.is_classic() && fpuzzles::unwritable(&gameboard.rules).is_none() {
+        let mut fpuzzle = FPuzzle::new(cells);
+        fpuzzle.rules = gameboard.rules.clone();
+        body.fpuzzles = Some(fpuzzle);
+    }
+    Response::new(200, &body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HARD: &str =
+        "100000709040007200800000000070010060300000005060040020000000008005300070702000046";
+
+    fn post(path: &str, body: &str) -> Response {
+        route(&Request {
+            method: "POST".to_string(),
+            path: path.to_string(),
+            query: Vec::new(),
+            body: body.to_string(),
+        })
+    }
+
+    #[test]
+    fn reads_arguments() {
+        use clap::CommandFactory;
+
+        Args::command().debug_assert();
+        #[cfg(feature = "gui")]
+        {
+            let args = Args::try_parse_from(["sudoku", "--profile", "Sam"]).unwrap();
+            assert!(args.command.is_none());
+            assert_eq!(args.profile.as_deref(), Some("Sam"));
+            assert!(Args::try_parse_from(["sudoku", "--profile", "a/b"]).is_err());
+            let args = Args::try_parse_from(["sudoku", "--verbose", "--host"]).unwrap();
+            assert!(args.verbose && args.command.is_none());
+            assert_eq!(args.role(), Some(Role::Host(COOP_ADDRESS.to_string())));
+            assert!(Args::try_parse_from(["sudoku", "--race"]).is_err());
+            assert!(Args::try_parse_from(["sudoku", "--host", "--join", "a"]).is_err());
+            assert!(Args::try_parse_from(["sudoku", "--join", "a", "--watch", "b"]).is_err());
+        }
+        let args = Args::try_parse_from([
+            "sudoku",
+            "generate",
+            "-n",
+            "3",
+            "-v",
+            "jigsaw",
+            "--size",
+            "6",
+            "--verbose",
+        ])
+        .unwrap();
+        match args.command {
+            Some(Command::Generate(generate)) => {
+                assert_eq!(generate.count, 3);
+                assert_eq!(generate.size, 6);
+                assert_eq!(generate.variants, [VariantKind::Jigsaw]);
+            }
+            other => panic!("not generate: {:?}", other),
+        }
+        assert!(args.verbose);
+        assert!(Args::try_parse_from(["sudoku", "generate", "--size", "7"]).is_err());
+        assert!(Args::try_parse_from(["sudoku", "rate"]).is_err());
+    }
+
+    #[test]
+    fn gives_up_on_puzzles_beyond_the_budget() {
+        let mut input = body_input(HARD).unwrap();
+        assert!(input.solve().is_ok());
+        input.budget = 1;
+        assert_eq!(input.solve(), Err(GAVE_UP));
+        assert_eq!(input.report().status, GAVE_UP);
+    }
+
+    #[test]
+    fn answers_solve_requests() {
+        assert_eq!(post("/solve", HARD).status, 200);
+        assert_eq!(post("/solve", &"0".repeat(81)).status, 422);
+        assert_eq!(post("/solve", "{\"puzzle\": 12}").status, 400);
+        let nested = "[".repeat(100_000);
+        assert_eq!(
+            post("/solve", &format!("{{\"puzzle\": {}", nested)).status,
+            400
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Commands that print the clipboard, tried in order.
+const PASTE_COMMANDS: [&[&str]; 5] = [
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-out"],
+    &["xsel", "--clipboard", "--output"],
+    &["pbpaste"],
+    &["powershell", "-NoProfile", "-Command", "Get-Clipboard"],
+];
+
+/// Commands that set the clipboard from their input, tried in order.
+const COPY_COMMANDS: [&[&str]; 5] = [
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard", "-in"],
+    &["xsel", "--clipboard", "--input"],
+    &["pbcopy"],
+    &["clip"],
+];
+
+fn unavailable() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no clipboard tool found")
+}
+
+/// Reads text from the system clipboard through the platform's command line
+/// tools.
+pub fn read() -> io::Result<String> {
+    for command in PASTE_COMMANDS.iter() {
+        let output = match Command::new(command[0])
+            .args(&command[1..])
+            .stderr(Stdio::null())
+            .output()
+        {
+            Ok(output) => output,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        if output.status.success() {
+            return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+        }
+    }
+    Err(unavailable())
+}
+
+/// Puts text on the system clipboard.
+pub fn write(text: &str) -> io::Result<()> {
+    for command in COPY_COMMANDS.iter() {
+        let mut child = match Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+    Err(unavailable())
+}
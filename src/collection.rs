@@ -0,0 +1,402 @@
+use crate::grid::Grid;
+#[cfg(feature = "gui")]
+use crate::text::{draw_centered, draw_text};
+#[cfg(feature = "gui")]
+use crate::theme::Theme;
+#[cfg(feature = "gui")]
+use crate::SIZE;
+use crate::{opensudoku, puzzle, Difficulty};
+#[cfg(feature = "gui")]
+use graphics::character::CharacterCache;
+#[cfg(feature = "gui")]
+use graphics::types::Color;
+#[cfg(feature = "gui")]
+use graphics::{Context, Graphics};
+#[cfg(feature = "gui")]
+use piston::input::GenericEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Where the completed entries of each collection are recorded, within a
+/// profile.
+pub const PROGRESS_FILE: &str = "collections.toml";
+/// Where the puzzle library looks for packs.
+pub const PACKS_DIR: &str = "packs";
+
+/// Completed entries by collection file path.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Progress {
+    completed: BTreeMap<String, BTreeSet<usize>>,
+}
+
+impl Progress {
+    fn load<P: AsRef<Path>>(path: P) -> Progress {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, text)
+    }
+}
+
+/// A puzzle in a collection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub cells: Grid,
+    /// Folder or level the puzzle was filed under, if any.
+    pub group: Option<String>,
+}
+
+impl Entry {
+    /// The difficulty the puzzle was filed under, or else the one its
+    /// number of givens meets.
+    pub fn difficulty(&self) -> Difficulty {
+        self.group
+            .as_deref()
+            .and_then(Difficulty::from_name)
+            .unwrap_or_else(|| {
+                Difficulty::for_clues(self.cells.iter().flatten().filter(|&&val| val != 0).count())
+            })
+    }
+}
+
+/// Reads an `.sdm` file, which holds one puzzle per line.
+///
+/// Lines that are not puzzles are skipped.
+pub fn parse_sdm(text: &str) -> Vec<Entry> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| puzzle::parse(line).ok())
+        .map(|cells| Entry { cells, group: None })
+        .collect()
+}
+
+/// Many puzzles from one file, stepped through one at a time.
+pub struct Collection {
+    pub path: PathBuf,
+    pub puzzles: Vec<Entry>,
+    /// Index of the puzzle shown in the browser.
+    pub current: usize,
+    pub completed: BTreeSet<usize>,
+    /// File the completed entries are recorded in.
+    progress_path: PathBuf,
+    /// Whether each puzzle looked up so far was solved before, in this or
+    /// any other form.
+    pub solved_before: BTreeMap<usize, bool>,
+}
+
+impl Collection {
+    /// Opens a collection, along with which of its puzzles were completed as
+    /// recorded in the progress file.
+    ///
+    /// `.opensudoku` files are read as OpenSudoku exports and `.sdm` files as
+    /// one puzzle per line. Any other puzzle file is a collection of one.
+    pub fn open<P: AsRef<Path>, Q: AsRef<Path>>(
+        path: P,
+        progress_path: Q,
+    ) -> Result<Collection, String> {
+        let path = path.as_ref();
+        let progress_path = progress_path.as_ref();
+        let puzzles = if !puzzle::is_collection(path) {
+            vec![Entry {
+                cells: puzzle::open(path)?.cells,
+                group: None,
+            }]
+        } else {
+            let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+            if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("opensudoku"))
+            {
+                opensudoku::parse(&text)?
+            } else {
+                parse_sdm(&text)
+            }
+        };
+        if puzzles.is_empty() {
+            return Err("No puzzles found".to_string());
+        }
+        let completed = Progress::load(progress_path)
+            .completed
+            .remove(&path.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Collection {
+            path: path.to_path_buf(),
+            puzzles,
+            current: 0,
+            completed,
+            progress_path: progress_path.to_path_buf(),
+            solved_before: BTreeMap::new(),
+        })
+    }
+
+    /// Opens every puzzle file in a directory, skipping any that cannot be
+    /// read.
+    pub fn open_all<P: AsRef<Path>, Q: AsRef<Path>>(dir: P, progress_path: Q) -> Vec<Collection> {
+        puzzle::list_files(dir)
+            .iter()
+            .filter_map(|path| {
+                Collection::open(path, &progress_path)
+                    .map_err(|err| warn!("Skipping {}: {}", path.display(), err))
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// The file name, how many puzzles there are and have been completed,
+    /// and how many there are of each difficulty, as in
+    /// `pack.sdm: 3/20 done, 5 Easy, 15 Medium`.
+    pub fn summary(&self) -> String {
+        let name = self
+            .path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        let mix: Vec<String> = Difficulty::ALL
+            .iter()
+            .filter_map(|&difficulty| {
+                let count = self
+                    .puzzles
+                    .iter()
+                    .filter(|entry| entry.difficulty() == difficulty)
+                    .count();
+                Some(format!("{} {}", count, difficulty.name())).filter(|_| count > 0)
+            })
+            .collect();
+        format!(
+            "{}: {}/{} done, {}",
+            name,
+            self.completed.len(),
+            self.puzzles.len(),
+            mix.join(", ")
+        )
+    }
+
+    pub fn current_entry(&self) -> &Entry {
+        &self.puzzles[self.current]
+    }
+
+    /// Records a puzzle as completed, if it is one of this collection's.
+    pub fn complete(&mut self, puzzle: &Grid) {
+        let index = match self.puzzles.iter().position(|entry| &entry.cells == puzzle) {
+            Some(index) => index,
+            None => return,
+        };
+        if !self.completed.insert(index) {
+            return;
+        }
+        let mut progress = Progress::load(&self.progress_path);
+        progress.completed.insert(
+            self.path.to_string_lossy().into_owned(),
+            self.completed.clone(),
+        );
+        if let Err(err) = progress.save(&self.progress_path) {
+            warn!("Could not save collection progress: {}", err);
+        }
+    }
+}
+
+/// Steps through a collection's puzzles.
+#[cfg(feature = "gui")]
+#[derive(Default)]
+pub struct CollectionController;
+
+#[cfg(feature = "gui")]
+impl CollectionController {
+    pub fn new() -> CollectionController {
+        CollectionController
+    }
+
+    /// Handles events, returning true when the shown puzzle should be played.
+    ///
+    /// Left and Right step by one puzzle, Page Up and Page Down by ten, and
+    /// Home and End jump to the ends.
+    pub fn event<E: GenericEvent>(&mut self, collection: &mut Collection, e: &E) -> bool {
+        use piston::input::{Button, Key};
+
+        let last = collection.puzzles.len() - 1;
+        let current = &mut collection.current;
+        if let Some(Button::Keyboard(key)) = e.press_args() {
+            match key {
+                Key::Left => *current = current.saturating_sub(1),
+                Key::Right => *current = (*current + 1).min(last),
+                Key::PageUp => *current = current.saturating_sub(10),
+                Key::PageDown => *current = (*current + 10).min(last),
+                Key::Home => *current = 0,
+                Key::End => *current = last,
+                Key::Return | Key::Space => return true,
+                _ => {}
+            }
+        }
+        false
+    }
+}
+
+#[cfg(feature = "gui")]
+pub struct CollectionViewSettings {
+    pub font_size: u32,
+    pub background_color: Color,
+    pub text_color: Color,
+    pub line_color: Color,
+}
+
+#[cfg(feature = "gui")]
+impl CollectionViewSettings {
+    pub fn new(theme: &Theme) -> CollectionViewSettings {
+        CollectionViewSettings {
+            font_size: 20,
+            background_color: theme.panel_background,
+            text_color: theme.text,
+            line_color: theme.section_edge,
+        }
+    }
+
+    /// Scales text.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.font_size = (20.0 * scale).round() as u32;
+    }
+
+    /// Switches to a theme's colors.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        *self = CollectionViewSettings {
+            font_size: self.font_size,
+            ..CollectionViewSettings::new(theme)
+        };
+    }
+}
+
+#[cfg(feature = "gui")]
+pub struct CollectionView {
+    pub settings: CollectionViewSettings,
+}
+
+#[cfg(feature = "gui")]
+impl CollectionView {
+    pub fn new(settings: CollectionViewSettings) -> CollectionView {
+        CollectionView { settings }
+    }
+
+    /// Draws the shown puzzle with its place in the collection, over the area
+    /// at `pos` with side length `size`.
+    pub fn draw<G: Graphics, C>(
+        &self,
+        collection: &Collection,
+        pos: [f64; 2],
+        size: f64,
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::{Line, Rectangle};
+
+        let settings = &self.settings;
+        Rectangle::new(settings.background_color).draw(
+            [pos[0], pos[1], size, size],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+        let name = collection
+            .path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        let status = if collection.completed.contains(&collection.current) {
+            "completed"
+        } else if collection.solved_before.get(&collection.current) == Some(&true) {
+            "solved before"
+        } else {
+            "not completed"
+        };
+        let entry = collection.current_entry();
+        let lines = [
+            match &entry.group {
+                Some(group) => format!("{} - {}", name, group),
+                None => name,
+            },
+            format!(
+                "Puzzle {} of {} ({})",
+                collection.current + 1,
+                collection.puzzles.len(),
+                status
+            ),
+            format!(
+                "{} of {} completed",
+                collection.completed.len(),
+                collection.puzzles.len()
+            ),
+        ];
+        let line_height = settings.font_size as f64 * 1.4;
+        for (row, line) in lines.iter().enumerate() {
+            draw_text(
+                line,
+                settings.font_size,
+                settings.text_color,
+                [pos[0] + 10.0, pos[1] + (row + 1) as f64 * line_height],
+                glyphs,
+                c,
+                g,
+            );
+        }
+        draw_text(
+            "Left/Right to browse, Return to play",
+            settings.font_size,
+            settings.text_color,
+            [pos[0] + 10.0, pos[1] + size - line_height / 2.0],
+            glyphs,
+            c,
+            g,
+        );
+
+        // The puzzle itself, between the text above and below.
+        let top = pos[1] + (lines.len() as f64 + 0.5) * line_height;
+        let side = (pos[1] + size - 1.5 * line_height - top).min(size - 20.0);
+        let left = pos[0] + (size - side) / 2.0;
+        let cell = side / SIZE as f64;
+        for k in 0..=SIZE {
+            let radius = if k % 3 == 0 { 1.0 } else { 0.5 };
+            let line = Line::new(settings.line_color, radius);
+            let offset = k as f64 * cell;
+            line.draw(
+                [left + offset, top, left + offset, top + side],
+                &c.draw_state,
+                c.transform,
+                g,
+            );
+            line.draw(
+                [left, top + offset, left + side, top + offset],
+                &c.draw_state,
+                c.transform,
+                g,
+            );
+        }
+        for (j, row) in entry.cells.iter().enumerate() {
+            for (i, &val) in row.iter().enumerate() {
+                if val == 0 {
+                    continue;
+                }
+                draw_centered(
+                    (b'0' + val) as char,
+                    cell * 0.6,
+                    settings.text_color,
+                    [
+                        left + (i as f64 + 0.5) * cell,
+                        top + (j as f64 + 0.5) * cell,
+                    ],
+                    glyphs,
+                    c,
+                    g,
+                );
+            }
+        }
+    }
+}
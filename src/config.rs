@@ -0,0 +1,235 @@
+use crate::constraints::Constraints;
+use crate::variant::VariantKind;
+#[cfg(feature = "gui")]
+use piston::input::Key;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Name of the settings file within the config directory.
+pub const CONFIG_FILE: &str = "sudoku.toml";
+
+/// The platform's directory for per-user settings, if it can be found.
+fn config_dir() -> Option<PathBuf> {
+    let var = |name| {
+        env::var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+    if cfg!(target_os = "windows") {
+        var("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        var("XDG_CONFIG_HOME").or_else(|| var("HOME").map(|home| home.join(".config")))
+    }
+}
+
+/// Where user settings are stored, falling back to the working directory
+/// when the platform has no config directory.
+pub fn config_path() -> PathBuf {
+    match config_dir() {
+        Some(dir) => dir.join("sudoku").join(CONFIG_FILE),
+        None => PathBuf::from(CONFIG_FILE),
+    }
+}
+
+/// How digit keys interact with the selected cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputMode {
+    /// Select a cell, then press a digit to fill it.
+    CellFirst,
+    /// Press a digit, then click the cells to fill with it.
+    DigitFirst,
+}
+
+impl InputMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            InputMode::CellFirst => "Cell first",
+            InputMode::DigitFirst => "Digit first",
+        }
+    }
+}
+
+/// UI scales offered in settings, in percent.
+pub const UI_SCALES: [u32; 6] = [75, 100, 125, 150, 175, 200];
+
+/// Sound volumes offered in settings, in percent.
+pub const VOLUMES: [u32; 4] = [25, 50, 75, 100];
+
+/// Board image sizes offered in settings, in pixels.
+pub const EXPORT_SIZES: [u32; 4] = [512, 1024, 2048, 4096];
+
+/// Keys for each game action. Names are those of piston's `Key`, such as
+/// `"Z"` or `"Delete"`. On boards with letter digits, letter keys take Alt.
+#[cfg(feature = "gui")]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keybindings {
+    pub undo: Vec<Key>,
+    pub redo: Vec<Key>,
+    pub erase: Vec<Key>,
+    pub notes: Vec<Key>,
+    pub hint: Vec<Key>,
+    pub check: Vec<Key>,
+    pub peek: Vec<Key>,
+}
+
+#[cfg(feature = "gui")]
+impl Default for Keybindings {
+    fn default() -> Keybindings {
+        Keybindings {
+            undo: vec![Key::Z],
+            redo: vec![Key::Y],
+            erase: vec![Key::Delete, Key::Backspace],
+            notes: vec![Key::N],
+            hint: vec![Key::H],
+            check: vec![Key::C],
+            peek: vec![Key::P],
+        }
+    }
+}
+
+/// User settings.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Name of the color theme.
+    pub theme: String,
+    /// Theme restored when dark mode is toggled off.
+    pub light_theme: String,
+    /// Overrides the theme with maximal contrast, thick lines, and big digits.
+    pub high_contrast: bool,
+    /// Size of the board and UI chrome, in percent.
+    pub ui_scale_percent: u32,
+    /// Path of a TTF/OTF font to use instead of the default.
+    pub font: Option<String>,
+    pub highlight_same_digit: bool,
+    pub highlight_houses: bool,
+    pub auto_check: bool,
+    pub input_mode: InputMode,
+    /// Ignore digits that are already placed nine times.
+    pub strict_digits: bool,
+    pub show_timer: bool,
+    /// Play sounds as digits go in and games end.
+    pub sound: bool,
+    /// Loudness of sounds, in percent.
+    pub volume_percent: u32,
+    /// Show a faint replay of the last attempt at the same puzzle to race.
+    pub ghost_replay: bool,
+    /// Kind of puzzle new games are.
+    pub variant: VariantKind,
+    /// Rules added to new games.
+    pub constraints: Constraints,
+    /// Script whose rule new games add, by name, from the scripts directory.
+    pub rule_script: Option<String>,
+    /// Script Ctrl+R runs over the board, by name, from the scripts directory.
+    pub tool_script: Option<String>,
+    /// Label columns A-I and rows 1-9 around the board.
+    pub coordinate_labels: bool,
+    /// Mistakes that end the game, if limited.
+    pub mistake_limit: Option<u32>,
+    /// Resume an unfinished game on launch instead of asking first.
+    pub continue_last_game: bool,
+    #[cfg(feature = "gui")]
+    pub keybindings: Keybindings,
+    /// Keys of the window, kept as they are by builds without one.
+    #[cfg(not(feature = "gui"))]
+    pub keybindings: toml::Table,
+    /// Size of the window when it was last closed, outside fullscreen.
+    pub window_size: [u32; 2],
+    /// Position of the window when it was last closed, if known.
+    pub window_position: Option<[i32; 2]>,
+    pub fullscreen: bool,
+    /// Side length of exported board images, in pixels.
+    pub export_size: u32,
+    /// URL of a puzzle feed to play from, if any.
+    pub puzzle_feed: Option<String>,
+    /// Show the game being played as this player's status on Discord.
+    pub discord_presence: bool,
+    /// Discord application the status is shown as, which Discord needs to
+    /// show any.
+    pub discord_application_id: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            theme: "light".to_string(),
+            light_theme: "light".to_string(),
+            high_contrast: false,
+            ui_scale_percent: 100,
+            font: None,
+            highlight_same_digit: true,
+            highlight_houses: false,
+            auto_check: false,
+            input_mode: InputMode::CellFirst,
+            strict_digits: false,
+            show_timer: true,
+            sound: true,
+            volume_percent: 50,
+            ghost_replay: false,
+            variant: VariantKind::Classic,
+            constraints: Constraints::default(),
+            rule_script: None,
+            tool_script: None,
+            coordinate_labels: false,
+            mistake_limit: None,
+            continue_last_game: false,
+            keybindings: Default::default(),
+            window_size: [512; 2],
+            window_position: None,
+            fullscreen: false,
+            export_size: 1024,
+            puzzle_feed: None,
+            discord_presence: false,
+            discord_application_id: None,
+        }
+    }
+}
+
+impl Config {
+    /// Switches between the dark theme and the last theme used before it.
+    pub fn toggle_dark_mode(&mut self, dark_theme: &str) {
+        if self.theme == dark_theme {
+            self.theme = self.light_theme.clone();
+        } else {
+            self.light_theme = std::mem::replace(&mut self.theme, dark_theme.to_string());
+        }
+    }
+
+    /// Loads settings, falling back to the defaults if the file is missing or invalid.
+    ///
+    /// A missing file is created with the defaults, so there is one to edit.
+    pub fn load<P: AsRef<Path>>(path: P) -> Config {
+        match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).unwrap_or_else(|err| {
+                warn!("Ignoring invalid config: {}", err);
+                Config::default()
+            }),
+            Err(err) => {
+                let config = Config::default();
+                if err.kind() == io::ErrorKind::NotFound {
+                    if let Err(err) = config.save(&path) {
+                        warn!("Could not create config: {}", err);
+                    }
+                }
+                config
+            }
+        }
+    }
+
+    /// Writes settings, creating the directory if needed.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, text)
+    }
+}
@@ -0,0 +1,140 @@
+use crate::grid::Grid;
+use serde::{Deserialize, Serialize};
+
+/// Offsets of the cells a chess knight's move away.
+const KNIGHT_MOVES: [[isize; 2]; 8] = [
+    [1, 2],
+    [2, 1],
+    [2, -1],
+    [1, -2],
+    [-1, -2],
+    [-2, -1],
+    [-2, 1],
+    [-1, 2],
+];
+
+/// Offsets of the cells diagonally next to a cell, which a chess king
+/// reaches that its row, column, and box do not already cover.
+const KING_MOVES: [[isize; 2]; 4] = [[1, 1], [1, -1], [-1, -1], [-1, 1]];
+
+/// Offsets of the cells orthogonally next to a cell.
+const NEIGHBOR_MOVES: [[isize; 2]; 4] = [[0, 1], [1, 0], [0, -1], [-1, 0]];
+
+/// Rules that can be added to a puzzle of any variant, each keeping digits
+/// out of some cells around a filled one. Any combination can be turned on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Constraints {
+    /// Cells a chess knight's move apart cannot hold the same digit.
+    pub anti_knight: bool,
+    /// Diagonally neighboring cells cannot hold the same digit.
+    pub anti_king: bool,
+    /// Orthogonally neighboring cells cannot hold consecutive digits.
+    pub non_consecutive: bool,
+}
+
+/// The cells at some offsets from a cell, on a board of a size.
+fn moves(
+    moves: &'static [[isize; 2]],
+    size: usize,
+    [x, y]: [usize; 2],
+) -> impl Iterator<Item = [usize; 2]> {
+    moves.iter().filter_map(move |&[dx, dy]| {
+        let i = x.checked_add_signed(dx).filter(|&i| i < size)?;
+        let j = y.checked_add_signed(dy).filter(|&j| j < size)?;
+        Some([i, j])
+    })
+}
+
+impl Constraints {
+    /// Whether no rules are added.
+    pub fn is_empty(self) -> bool {
+        self == Constraints::default()
+    }
+
+    /// Names of the rules added, for display.
+    pub fn names(self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        if self.anti_knight {
+            names.push("Anti-knight");
+        }
+        if self.anti_king {
+            names.push("Anti-king");
+        }
+        if self.non_consecutive {
+            names.push("Non-consecutive");
+        }
+        names
+    }
+
+    /// Each rule added, on its own, to tell which ones keep a digit out.
+    pub fn split(self) -> Vec<Constraints> {
+        let none = Constraints::default();
+        let mut each = Vec::new();
+        if self.anti_knight {
+            each.push(Constraints {
+                anti_knight: true,
+                ..none
+            });
+        }
+        if self.anti_king {
+            each.push(Constraints {
+                anti_king: true,
+                ..none
+            });
+        }
+        if self.non_consecutive {
+            each.push(Constraints {
+                non_consecutive: true,
+                ..none
+            });
+        }
+        each
+    }
+
+    /// Cells that cannot share a digit with a cell on a board of a size.
+    fn seen(self, size: usize, ind: [usize; 2]) -> impl Iterator<Item = [usize; 2]> {
+        let knight: &[_] = if self.anti_knight { &KNIGHT_MOVES } else { &[] };
+        let king: &[_] = if self.anti_king { &KING_MOVES } else { &[] };
+        moves(knight, size, ind).chain(moves(king, size, ind))
+    }
+
+    /// Cells that cannot hold a digit next to a cell's, on a board of a
+    /// size.
+    fn neighbors(self, size: usize, ind: [usize; 2]) -> impl Iterator<Item = [usize; 2]> {
+        let neighbors: &[_] = if self.non_consecutive {
+            &NEIGHBOR_MOVES
+        } else {
+            &[]
+        };
+        moves(neighbors, size, ind)
+    }
+
+    /// Bit mask of the digits a cell can take, given the cells around it.
+    pub fn allowed(self, cells: &Grid, ind: [usize; 2]) -> u32 {
+        let size = cells.size();
+        let seen = self
+            .seen(size, ind)
+            .fold(0, |used, [i, j]| used | 1 << cells[j][i]);
+        let next = self
+            .neighbors(size, ind)
+            .filter(|&[i, j]| cells[j][i] != 0)
+            .fold(0, |used, [i, j]| {
+                let bit = 1u32 << cells[j][i];
+                used | bit << 1 | bit >> 1
+            });
+        !(seen | next)
+    }
+
+    /// Whether a cell's value is repeated in a cell it sees, or is next to
+    /// a neighbor's.
+    pub fn breaks(self, cells: &Grid, ind: [usize; 2]) -> bool {
+        let val = cells[ind[1]][ind[0]];
+        let size = cells.size();
+        val != 0
+            && (self.seen(size, ind).any(|[i, j]| cells[j][i] == val)
+                || self
+                    .neighbors(size, ind)
+                    .any(|[i, j]| cells[j][i] != 0 && cells[j][i].abs_diff(val) == 1))
+    }
+}
@@ -0,0 +1,1698 @@
+use crate::discovery::Advertiser;
+use crate::grid::{Grid, SIZES};
+use crate::save::SavedGame;
+use crate::{Action, GameboardController};
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, info, instrument, warn};
+
+/// Where a co-op game is hosted when no address is given.
+pub const COOP_ADDRESS: &str = "0.0.0.0:7878";
+
+/// Port joined when an address does not name one.
+const COOP_PORT: u16 = 7878;
+
+/// Version of the messages players trade, so games of different versions
+/// refuse each other rather than drift apart.
+const PROTOCOL: u32 = 4;
+
+/// Highest digit on any board, so values above it are refused on reading.
+const MAX_DIGIT: u32 = SIZES[SIZES.len() - 1] as u32;
+
+/// Largest game sent, which is plenty for a saved game with its replay.
+const MAX_GAME: usize = 1 << 22;
+
+/// How long to wait for the host to answer when connecting.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often, and how many times, a player who lost the host tries to
+/// reach it again.
+const REJOIN_DELAY: Duration = Duration::from_secs(2);
+const REJOIN_ATTEMPTS: usize = 30;
+
+/// Longest chat message, in characters.
+pub const MAX_CHAT_LEN: usize = 200;
+
+/// Chat messages kept, the oldest dropped first.
+const CHAT_HISTORY: usize = 100;
+
+/// Whether this player hosts a co-op game, joins one, or only watches one,
+/// and where.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Role {
+    Host(String),
+    Join(String),
+    Watch(String),
+}
+
+/// Another player in a co-op game.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Partner {
+    /// Number of the player, from 0 for the host, which picks their color.
+    pub id: u8,
+    pub name: String,
+    /// The cell they have selected.
+    pub cursor: Option<[usize; 2]>,
+    /// How much of their board they have filled, as a percentage, in a
+    /// race, where their entries are not shared.
+    pub progress: Option<u8>,
+    /// Whether they only watch.
+    pub watching: bool,
+}
+
+/// A message said in the chat.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChatLine {
+    /// Number of the player who said it, which picks their color.
+    pub id: u8,
+    pub name: String,
+    pub text: String,
+}
+
+/// A change to one cell in a co-op game: its value, or one of its notes,
+/// by bit. Changing a note alone leaves the others, so players noting
+/// the same cell at once keep each other's notes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Change {
+    Value(u8),
+    Note(u32, bool),
+}
+
+impl Change {
+    /// The value and notes of a cell after the change.
+    fn apply(self, (value, notes): (u8, u32)) -> (u8, u32) {
+        match self {
+            Change::Value(value) => (value, notes),
+            Change::Note(bit, true) => (value, notes | 1 << bit),
+            Change::Note(bit, false) => (value, notes & !(1 << bit)),
+        }
+    }
+
+    /// The change as a cell has it, whether or not it was made.
+    fn restate(self, (value, notes): (u8, u32)) -> Change {
+        match self {
+            Change::Value(_) => Change::Value(value),
+            Change::Note(bit, _) => Change::Note(bit, notes & 1 << bit != 0),
+        }
+    }
+
+    /// The changes that turn one state of a cell into another.
+    fn between(before: (u8, u32), after: (u8, u32)) -> Vec<Change> {
+        let mut changes = Vec::new();
+        if before.0 != after.0 {
+            changes.push(Change::Value(after.0));
+        }
+        let flipped = before.1 ^ after.1;
+        changes.extend(
+            (0..32)
+                .filter(|bit| flipped & 1 << bit != 0)
+                .map(|bit| Change::Note(bit, after.1 & 1 << bit != 0)),
+        );
+        changes
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Value(value) => write!(f, "value {}", value),
+            Change::Note(bit, on) => write!(f, "note {} {}", bit, *on as u8),
+        }
+    }
+}
+
+/// A change to a co-op game as the host settled it, numbered in the order
+/// every board applies it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Op {
+    seq: u64,
+    /// The player who made it.
+    id: u8,
+    /// Who made it and their count of changes made, across reconnecting,
+    /// for them to tell which of theirs it settles.
+    origin: u64,
+    n: u64,
+    cell: [usize; 2],
+    change: Change,
+}
+
+/// What players tell each other, one message per line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Message {
+    /// A player asks to join, or only to watch, giving their name, and
+    /// when they lost the connection, the game they had and how many of
+    /// its changes.
+    Hello {
+        version: u32,
+        name: String,
+        watching: bool,
+        resume: Option<(u64, u64)>,
+    },
+    /// The host accepts a player, numbering them, and says whether the
+    /// game is a race.
+    Welcome {
+        id: u8,
+        race: bool,
+    },
+    /// The game being played, as a saved game, sent on joining and whenever
+    /// the host starts another, with its number and how many changes it
+    /// has had.
+    Game {
+        game: u64,
+        seq: u64,
+        save: String,
+    },
+    Join {
+        id: u8,
+        name: String,
+        watching: bool,
+    },
+    Leave {
+        id: u8,
+    },
+    /// A change a player made to a co-op game, sent to the host to
+    /// settle.
+    Edit {
+        origin: u64,
+        n: u64,
+        cell: [usize; 2],
+        change: Change,
+    },
+    /// A change the host settled, sent to everyone.
+    Op(Op),
+    /// A cell's value and notes after a player changed it in a race, sent
+    /// to those watching.
+    Cell {
+        id: u8,
+        cell: [usize; 2],
+        value: u8,
+        notes: u32,
+    },
+    Cursor {
+        id: u8,
+        cell: Option<[usize; 2]>,
+    },
+    /// How much of their board a player racing has filled.
+    Progress {
+        id: u8,
+        percent: u8,
+    },
+    /// A player racing solved their board: sent to the host, which passes
+    /// on only the first, to everyone, as the winner.
+    Won {
+        id: u8,
+    },
+    /// Something a player said in the chat, sent to the host, which passes
+    /// it on to everyone else.
+    Chat {
+        id: u8,
+        text: String,
+    },
+}
+
+fn invalid(line: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("not a co-op message: {}", line.trim()),
+    )
+}
+
+impl Message {
+    /// Reads the next message, or `None` once the connection closes.
+    fn read<R: BufRead>(reader: &mut R) -> io::Result<Option<Message>> {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |k: usize| -> io::Result<u32> {
+            words
+                .get(k)
+                .and_then(|word| word.parse().ok())
+                .ok_or_else(|| invalid(&line))
+        };
+        let big = |k: usize| -> io::Result<u64> {
+            words
+                .get(k)
+                .and_then(|word| word.parse().ok())
+                .ok_or_else(|| invalid(&line))
+        };
+        let id = || number(1).and_then(|id| u8::try_from(id).map_err(|_| invalid(&line)));
+        let value = |k: usize| match number(k)? {
+            value if value <= MAX_DIGIT => u8::try_from(value).map_err(|_| invalid(&line)),
+            _ => Err(invalid(&line)),
+        };
+        let name = |k: usize| words.get(k..).map(|rest| rest.join(" "));
+        let change = |k: usize| match words.get(k).copied() {
+            Some("value") => value(k + 1).map(Change::Value),
+            Some("note") => match number(k + 1)? {
+                bit if bit < 32 => Ok(Change::Note(bit, number(k + 2)? != 0)),
+                _ => Err(invalid(&line)),
+            },
+            _ => Err(invalid(&line)),
+        };
+        let message = match words.first().copied() {
+            Some(word @ ("hello" | "watch")) => Message::Hello {
+                version: number(1)?,
+                name: name(4).ok_or_else(|| invalid(&line))?,
+                watching: word == "watch",
+                resume: Some((big(2)?, big(3)?)).filter(|&(game, _)| game != 0),
+            },
+            Some("welcome") => Message::Welcome {
+                id: id()?,
+                race: words.get(2) == Some(&"race"),
+            },
+            Some("game") => {
+                let (game, seq) = (big(1)?, big(2)?);
+                let len = number(3)? as usize;
+                if len > MAX_GAME {
+                    return Err(invalid(&line));
+                }
+                let mut save = vec![0; len];
+                reader.read_exact(&mut save)?;
+                let save = String::from_utf8(save).map_err(|_| invalid(&line))?;
+                Message::Game { game, seq, save }
+            }
+            Some(word @ ("join" | "watcher")) => Message::Join {
+                id: id()?,
+                name: name(2).ok_or_else(|| invalid(&line))?,
+                watching: word == "watcher",
+            },
+            Some("leave") => Message::Leave { id: id()? },
+            Some("edit") => Message::Edit {
+                origin: big(1)?,
+                n: big(2)?,
+                cell: [number(3)? as usize, number(4)? as usize],
+                change: change(5)?,
+            },
+            Some("op") => Message::Op(Op {
+                seq: big(1)?,
+                id: number(2).and_then(|id| u8::try_from(id).map_err(|_| invalid(&line)))?,
+                origin: big(3)?,
+                n: big(4)?,
+                cell: [number(5)? as usize, number(6)? as usize],
+                change: change(7)?,
+            }),
+            Some("cell") => Message::Cell {
+                id: id()?,
+                cell: [number(2)? as usize, number(3)? as usize],
+                value: value(4)?,
+                notes: number(5)?,
+            },
+            Some("cursor") if words.get(2) == Some(&"-") => Message::Cursor {
+                id: id()?,
+                cell: None,
+            },
+            Some("cursor") => Message::Cursor {
+                id: id()?,
+                cell: Some([number(2)? as usize, number(3)? as usize]),
+            },
+            Some("progress") => Message::Progress {
+                id: id()?,
+                percent: number(2)?.min(100) as u8,
+            },
+            Some("won") => Message::Won { id: id()? },
+            Some("chat") => Message::Chat {
+                id: id()?,
+                text: name(2).ok_or_else(|| invalid(&line))?,
+            },
+            _ => return Err(invalid(&line)),
+        };
+        Ok(Some(message))
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Message::Hello {
+                version,
+                name,
+                watching,
+                resume,
+            } => {
+                let (game, seq) = resume.unwrap_or((0, 0));
+                let word = if *watching { "watch" } else { "hello" };
+                writeln!(writer, "{} {} {} {} {}", word, version, game, seq, name)
+            }
+            Message::Welcome { id, race: false } => writeln!(writer, "welcome {} coop", id),
+            Message::Welcome { id, race: true } => writeln!(writer, "welcome {} race", id),
+            Message::Game { game, seq, save } => {
+                write!(writer, "game {} {} {}\n{}", game, seq, save.len(), save)
+            }
+            Message::Join {
+                id,
+                name,
+                watching: false,
+            } => writeln!(writer, "join {} {}", id, name),
+            Message::Join {
+                id,
+                name,
+                watching: true,
+            } => writeln!(writer, "watcher {} {}", id, name),
+            Message::Leave { id } => writeln!(writer, "leave {}", id),
+            Message::Edit {
+                origin,
+                n,
+                cell: [i, j],
+                change,
+            } => writeln!(writer, "edit {} {} {} {} {}", origin, n, i, j, change),
+            Message::Op(Op {
+                seq,
+                id,
+                origin,
+                n,
+                cell: [i, j],
+                change,
+            }) => writeln!(
+                writer,
+                "op {} {} {} {} {} {} {}",
+                seq, id, origin, n, i, j, change
+            ),
+            Message::Cell {
+                id,
+                cell: [i, j],
+                value,
+                notes,
+            } => writeln!(writer, "cell {} {} {} {} {}", id, i, j, value, notes),
+            Message::Cursor {
+                id,
+                cell: Some([i, j]),
+            } => writeln!(writer, "cursor {} {} {}", id, i, j),
+            Message::Cursor { id, cell: None } => writeln!(writer, "cursor {} -", id),
+            Message::Progress { id, percent } => writeln!(writer, "progress {} {}", id, percent),
+            Message::Won { id } => writeln!(writer, "won {}", id),
+            Message::Chat { id, text } => writeln!(writer, "chat {} {}", id, text),
+        }?;
+        writer.flush()
+    }
+}
+
+/// What the connection threads pass to the game.
+enum Event {
+    /// A player connected to the host, and is yet to say hello, or a
+    /// player who lost the host reached it again.
+    Connected(u8, TcpStream),
+    Message(u8, Message),
+    Closed(u8),
+    /// The host could not be reached again.
+    Lost,
+}
+
+/// Reads messages from a connection until it closes, passing them on.
+fn read_messages(id: u8, stream: TcpStream, events: Sender<Event>) {
+    let mut reader = BufReader::new(stream);
+    loop {
+        match Message::read(&mut reader) {
+            Ok(Some(message)) => {
+                if events.send(Event::Message(id, message)).is_err() {
+                    return;
+                }
+            }
+            Ok(None) => break,
+            Err(err) => {
+                warn!("Co-op connection {} failed: {}", id, err);
+                break;
+            }
+        }
+    }
+    let _ = events.send(Event::Closed(id));
+}
+
+/// Connects to a host, returning a stream to read and one to write.
+fn connect(address: &str) -> io::Result<(TcpStream, TcpStream)> {
+    let addresses: Vec<_> = if address.contains(':') {
+        address.to_socket_addrs()?.collect()
+    } else {
+        (address, COOP_PORT).to_socket_addrs()?.collect()
+    };
+    let mut last = io::Error::new(io::ErrorKind::NotFound, "no address found");
+    for address in addresses {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => {
+                stream.set_nodelay(true)?;
+                return Ok((stream.try_clone()?, stream));
+            }
+            Err(err) => last = err,
+        }
+    }
+    Err(last)
+}
+
+/// Tries to reach the host again every few seconds once the connection
+/// drops, then reads from the new connection, or gives up.
+fn rejoin(address: &str, events: Sender<Event>) {
+    for attempt in 1..=REJOIN_ATTEMPTS {
+        thread::sleep(REJOIN_DELAY);
+        debug!(
+            "Reaching the host at {} again, attempt {}",
+            address, attempt
+        );
+        if let Ok((reader, writer)) = connect(address) {
+            if events.send(Event::Connected(0, writer)).is_ok() {
+                read_messages(0, reader, events);
+            }
+            return;
+        }
+    }
+    warn!("Gave up reaching the host at {}", address);
+    let _ = events.send(Event::Lost);
+}
+
+/// Chat text as sent: on one line, with runs of spaces closed up, and no
+/// longer than a chat message can be.
+fn chat_text(text: &str) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    words
+        .join(" ")
+        .chars()
+        .filter(|ch| !ch.is_control())
+        .take(MAX_CHAT_LEN)
+        .collect()
+}
+
+/// The puzzle in play as it started, with nothing filled in and its timer at
+/// zero, for a race.
+fn fresh(controller: &GameboardController) -> GameboardController {
+    let board = &controller.gameboard;
+    let mut game = SavedGame::new(controller);
+    game.cells = board.puzzle();
+    game.notes = Grid::new(board.size());
+    game.elapsed = 0;
+    game.mistakes = 0;
+    game.hints = 0;
+    game.peeks = 0;
+    game.replay = None;
+    game.move_log = Vec::new();
+    game.restore()
+}
+
+/// A connection to another player: to each joined player for the host, or
+/// to the host for a player who joined.
+struct Peer {
+    id: u8,
+    stream: TcpStream,
+    /// Set once they say hello.
+    name: Option<String>,
+    watching: bool,
+}
+
+/// A co-op game: the players connected, and the board as last shared, to
+/// find the changes made since.
+///
+/// The host's board is the one played. Each change a player makes, to a
+/// value or a single note, is sent to the host, which settles it on its
+/// board and numbers it, then sends it as settled to everyone, the player
+/// who made it too. Every board applies the host's changes in the same
+/// order, and shows the changes of its own player still on their way on
+/// top, so boards agree once those arrive, whoever changed a cell at once.
+///
+/// A player who loses the host keeps playing, and on reaching it again is
+/// sent the changes missed while away, then sends theirs again. The host
+/// settles each change once, however often it is sent.
+///
+/// In a race each player plays the host's puzzle on a board of their own,
+/// sharing only how much of it they have filled, and the host settles who
+/// solved it first.
+///
+/// Those watching change nothing. In a race they are sent every player's
+/// board, and watch one at a time.
+pub struct Session {
+    /// This player's number, 0 for the host.
+    id: u8,
+    name: String,
+    host: bool,
+    race: bool,
+    watching: bool,
+    /// Each player's board in a race, by number, as the host last heard,
+    /// for those watching.
+    boards: HashMap<u8, (Grid, Grid<u32>)>,
+    /// The player whose board is shown, for those watching a race.
+    watched: u8,
+    /// The percentage of this player's board last sent, in a race.
+    progress: Option<u8>,
+    /// Whether this player has solved the race's puzzle.
+    finished: bool,
+    /// Who solved the race's puzzle first.
+    winner: Option<u8>,
+    peers: Vec<Peer>,
+    partners: Vec<Partner>,
+    events: Receiver<Event>,
+    /// Where to reach the host again, and where to pass the connection,
+    /// for a player who joined.
+    rejoin: Option<(String, Sender<Event>)>,
+    /// Tells this player's changes from others' across reconnecting.
+    origin: u64,
+    /// How many changes this player has made.
+    edits: u64,
+    /// This player's changes the host has yet to settle, by count.
+    pending: VecDeque<(u64, [usize; 2], Change)>,
+    /// The number of the game in play, and how many of its changes have
+    /// been settled.
+    game: u64,
+    seq: u64,
+    /// The changes settled in the game in play, for the host to send
+    /// players who reconnect.
+    log: Vec<Op>,
+    /// The last change settled from each player, by origin, so changes
+    /// sent again are settled once.
+    applied: HashMap<u64, u64>,
+    /// The board as the host settled it, for a player who joined.
+    settled_cells: Grid,
+    settled_notes: Grid<u32>,
+    /// What has been said in the chat, oldest first.
+    chat: Vec<ChatLine>,
+    /// Whether others' chat messages go unannounced.
+    muted: bool,
+    /// Whether the host's game has arrived, for a player who joined, or
+    /// has started, for the host.
+    joined: bool,
+    /// The port hosted on.
+    port: u16,
+    /// The game as found on the local network, while hosting.
+    advertiser: Option<Advertiser>,
+    puzzle: Grid,
+    cells: Grid,
+    notes: Grid<u32>,
+    cursor: Option<[usize; 2]>,
+}
+
+impl Session {
+    /// Starts a session over the board in play.
+    fn new(name: &str, host: bool, race: bool, events: Receiver<Event>) -> Session {
+        Session {
+            id: 0,
+            name: name.to_string(),
+            host,
+            race,
+            watching: false,
+            boards: HashMap::new(),
+            watched: 0,
+            progress: None,
+            finished: false,
+            winner: None,
+            peers: Vec::new(),
+            partners: Vec::new(),
+            events,
+            rejoin: None,
+            origin: rand::random(),
+            edits: 0,
+            pending: VecDeque::new(),
+            game: 0,
+            seq: 0,
+            log: Vec::new(),
+            applied: HashMap::new(),
+            settled_cells: Grid::new(0),
+            settled_notes: Grid::new(0),
+            chat: Vec::new(),
+            muted: false,
+            joined: host,
+            port: 0,
+            advertiser: None,
+            puzzle: Grid::new(0),
+            cells: Grid::new(0),
+            notes: Grid::new(0),
+            cursor: None,
+        }
+    }
+
+    /// Hosts a co-op game or a race on an address, taking players as they
+    /// connect.
+    #[instrument]
+    pub fn host(address: &str, name: &str, race: bool) -> io::Result<Session> {
+        let listener = TcpListener::bind(address)?;
+        let port = listener.local_addr()?.port();
+        // Players can still join by address without it.
+        let advertiser = Advertiser::start(name, port, race)
+            .map_err(|err| warn!("Could not advertise on the local network: {}", err))
+            .ok();
+        let game = if race { "race" } else { "co-op game" };
+        match &advertiser {
+            Some(advertiser) => println!(
+                "Hosting {} on {}, code {}",
+                game,
+                listener.local_addr()?,
+                advertiser.code
+            ),
+            None => println!("Hosting {} on {}", game, listener.local_addr()?),
+        }
+        let (sender, events) = mpsc::channel();
+        thread::spawn(move || {
+            let mut next_id: u8 = 1;
+            for stream in listener.incoming() {
+                let stream = match stream.and_then(|stream| {
+                    stream.set_nodelay(true)?;
+                    Ok((stream.try_clone()?, stream))
+                }) {
+                    Ok(streams) => streams,
+                    Err(err) => {
+                        warn!("Could not accept co-op player: {}", err);
+                        continue;
+                    }
+                };
+                let (reader, writer) = stream;
+                let id = next_id;
+                next_id = next_id.wrapping_add(1).max(1);
+                if sender.send(Event::Connected(id, writer)).is_err() {
+                    return;
+                }
+                let sender = sender.clone();
+                thread::spawn(move || read_messages(id, reader, sender));
+            }
+        });
+        let mut session = Session::new(name, true, race, events);
+        session.new_game();
+        session.port = port;
+        session.advertiser = advertiser;
+        Ok(session)
+    }
+
+    /// Joins the co-op game or race hosted at an address, whose board
+    /// replaces the one in play once it arrives, to play or only to watch.
+    #[instrument]
+    pub fn join(address: &str, name: &str, watching: bool) -> io::Result<Session> {
+        let (reader, stream) = connect(address)?;
+        let (sender, events) = mpsc::channel();
+        let rejoin = (address.to_string(), sender.clone());
+        thread::spawn(move || read_messages(0, reader, sender));
+        let mut session = Session::new(name, false, false, events);
+        session.watching = watching;
+        session.rejoin = Some(rejoin);
+        session.hello().write(&mut &stream)?;
+        session.peers.push(Peer {
+            id: 0,
+            stream,
+            name: None,
+            watching: false,
+        });
+        Ok(session)
+    }
+
+    /// Holds the game back until `start`, so players gather first.
+    pub fn hold(mut self) -> Session {
+        self.joined = !self.host;
+        self
+    }
+
+    /// Shares the game in play with the players gathered, once.
+    pub fn start(&mut self, controller: &mut GameboardController) {
+        if self.joined {
+            return;
+        }
+        self.joined = true;
+        if self.race {
+            self.restart(controller);
+        } else {
+            self.share(controller);
+        }
+    }
+
+    /// Whether the game has started, or for a player who joined, has
+    /// arrived.
+    pub fn started(&self) -> bool {
+        self.joined
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.host
+    }
+
+    pub fn is_race(&self) -> bool {
+        self.race
+    }
+
+    /// The port hosted on, or 0 for a player who joined.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// The code players can join by, while the game is found on the local
+    /// network.
+    pub fn code(&self) -> Option<&str> {
+        self.advertiser
+            .as_ref()
+            .map(|advertiser| advertiser.code.as_str())
+    }
+
+    /// The players' numbers and names in order, the host first, and
+    /// whether each only watches.
+    pub fn players(&self) -> Vec<(u8, &str, bool)> {
+        let mut players: Vec<(u8, &str, bool)> = self
+            .partners
+            .iter()
+            .map(|partner| (partner.id, partner.name.as_str(), partner.watching))
+            .collect();
+        players.push((self.id, &self.name, self.watching));
+        players.sort_unstable();
+        players
+    }
+
+    pub fn is_watching(&self) -> bool {
+        self.watching
+    }
+
+    /// Shows the next player's board, watching a race, returning their
+    /// name.
+    pub fn watch_next(&mut self) -> Option<String> {
+        if !self.watching || !self.race {
+            return None;
+        }
+        let mut racers: Vec<u8> = self
+            .partners
+            .iter()
+            .filter(|partner| !partner.watching)
+            .map(|partner| partner.id)
+            .collect();
+        racers.sort_unstable();
+        let next = racers
+            .iter()
+            .find(|&&id| id > self.watched)
+            .or_else(|| racers.first())?;
+        self.watched = *next;
+        Some(self.name_of(self.watched))
+    }
+
+    /// This player's number, 0 for the host.
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Sends a message to every player who has said hello, or to the host,
+    /// but the one it came from. Players who cannot be written to are
+    /// dropped, and their reading thread reports them gone.
+    fn send(&mut self, message: &Message, except: Option<u8>) {
+        self.send_where(message, |peer| Some(peer.id) != except);
+    }
+
+    /// Sends a message to those watching only.
+    fn send_watchers(&mut self, message: &Message) {
+        self.send_where(message, |peer| peer.watching);
+    }
+
+    fn send_where<F: Fn(&Peer) -> bool>(&mut self, message: &Message, to: F) {
+        let host = self.host;
+        for peer in &mut self.peers {
+            if !to(peer) || (host && peer.name.is_none()) {
+                continue;
+            }
+            if let Err(err) = message.write(&mut peer.stream) {
+                warn!("Could not send to co-op player {}: {}", peer.id, err);
+                let _ = peer.stream.shutdown(Shutdown::Both);
+            }
+        }
+    }
+
+    /// How this player says hello, picking up the game they had if they
+    /// lost the host.
+    fn hello(&self) -> Message {
+        Message::Hello {
+            version: PROTOCOL,
+            name: self.name.clone(),
+            watching: self.watching,
+            resume: Some((self.game, self.seq)).filter(|_| self.joined),
+        }
+    }
+
+    /// The game in play, as sent to players joining.
+    fn game(&self, controller: &GameboardController) -> Option<Message> {
+        let save = toml::to_string(&SavedGame::new(controller))
+            .map_err(|err| warn!("Could not send game: {}", err))
+            .ok()?;
+        Some(Message::Game {
+            game: self.game,
+            seq: self.seq,
+            save,
+        })
+    }
+
+    /// Numbers another game, with no changes settled yet.
+    fn new_game(&mut self) {
+        self.game = rand::random::<u64>().max(1);
+        self.seq = 0;
+        self.log.clear();
+    }
+
+    /// Takes the board in play as the one shared.
+    fn take_board(&mut self, controller: &GameboardController) {
+        let board = &controller.gameboard;
+        self.puzzle = board.puzzle();
+        self.cells = board.cells.clone();
+        self.notes = board.notes.clone();
+        self.settled_cells = board.cells.clone();
+        self.settled_notes = board.notes.clone();
+    }
+
+    /// Shares the game in play, as the host, as another game.
+    fn share(&mut self, controller: &GameboardController) {
+        self.new_game();
+        self.take_board(controller);
+        if let Some(game) = self.game(controller) {
+            self.send(&game, None);
+        }
+    }
+
+    /// Starts the race over on the puzzle in play, from an empty board for
+    /// every player at once.
+    fn restart(&mut self, controller: &mut GameboardController) {
+        *controller = fresh(controller);
+        self.reset_race();
+        self.share(controller);
+    }
+
+    /// Forgets how far everyone got in the last race.
+    fn reset_race(&mut self) {
+        self.progress = None;
+        self.finished = false;
+        self.winner = None;
+        self.boards.clear();
+        for partner in self.partners.iter_mut().filter(|partner| !partner.watching) {
+            partner.progress = Some(0);
+        }
+    }
+
+    /// Notes a change to a player's board in a race.
+    fn record_cell(&mut self, id: u8, [i, j]: [usize; 2], value: u8, notes: u32) {
+        let size = self.puzzle.size();
+        if i >= size || j >= size || value as usize > size {
+            return;
+        }
+        let puzzle = &self.puzzle;
+        let board = self
+            .boards
+            .entry(id)
+            .or_insert_with(|| (puzzle.clone(), Grid::new(size)));
+        board.0[j][i] = value;
+        board.1[j][i] = notes;
+    }
+
+    /// The cells of a board that differ from the puzzle, as changes by a
+    /// player, to bring someone who starts watching up to date.
+    fn board_cells(&self, id: u8, cells: &Grid, notes: &Grid<u32>) -> Vec<Message> {
+        cells
+            .positions()
+            .filter(|&[i, j]| cells[j][i] != self.puzzle[j][i] || notes[j][i] != 0)
+            .map(|[i, j]| Message::Cell {
+                id,
+                cell: [i, j],
+                value: cells[j][i],
+                notes: notes[j][i],
+            })
+            .collect()
+    }
+
+    /// Shows the board of the player watched, in a race.
+    fn show_watched(&self, controller: &mut GameboardController) {
+        let board = &mut controller.gameboard;
+        let (cells, notes) = match self.boards.get(&self.watched) {
+            Some((cells, notes)) if cells.size() == board.size() => (cells.clone(), notes.clone()),
+            _ => (self.puzzle.clone(), Grid::new(self.puzzle.size())),
+        };
+        if board.cells != cells || board.notes != notes {
+            board.cells = cells;
+            board.notes = notes;
+            board.update_solved();
+        }
+    }
+
+    /// Whether the board a player racing has sent, cell by cell, matches
+    /// the solution, so a claim to have won can be believed.
+    fn has_solved(&self, controller: &GameboardController, id: u8) -> bool {
+        let board = &controller.gameboard;
+        match self.boards.get(&id) {
+            Some((cells, _)) if cells.size() == board.size() => cells
+                .positions()
+                .filter(|&ind| board.in_play(ind))
+                .all(|[i, j]| cells[j][i] == board.solution[j][i]),
+            _ => false,
+        }
+    }
+
+    /// Settles the race on a player who solved the puzzle, unless someone
+    /// solved it first.
+    fn declare(&mut self, controller: &mut GameboardController, id: u8) {
+        if self.winner.is_none() {
+            self.send(&Message::Won { id }, None);
+            self.announce(controller, id);
+        }
+    }
+
+    fn announce(&mut self, controller: &mut GameboardController, id: u8) {
+        self.winner = Some(id);
+        if id == self.id {
+            controller.notify("You won the race!");
+        } else {
+            controller.notify(&format!("{} won the race", self.name_of(id)));
+        }
+    }
+
+    fn set_progress(&mut self, id: u8, percent: u8) {
+        if let Some(partner) = self.partners.iter_mut().find(|partner| partner.id == id) {
+            partner.progress = Some(percent);
+        }
+    }
+
+    /// What has been said in the chat, oldest first.
+    pub fn chat(&self) -> &[ChatLine] {
+        &self.chat
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Stops announcing others' chat messages, or starts again. They are
+    /// still kept to read.
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    /// Says something in the chat, to everyone in the game.
+    pub fn say(&mut self, text: &str) {
+        let text = chat_text(text);
+        if text.is_empty() {
+            return;
+        }
+        self.record_chat(self.id, text.clone());
+        self.send(&Message::Chat { id: self.id, text }, None);
+    }
+
+    /// Keeps a chat message, dropping the oldest past the history kept.
+    fn record_chat(&mut self, id: u8, text: String) {
+        let name = if id == self.id {
+            self.name.clone()
+        } else {
+            self.name_of(id)
+        };
+        self.chat.push(ChatLine { id, name, text });
+        let excess = self.chat.len().saturating_sub(CHAT_HISTORY);
+        self.chat.drain(..excess);
+    }
+
+    /// Keeps a chat message another player said, announcing it unless
+    /// muted.
+    fn hear(&mut self, controller: &mut GameboardController, id: u8, text: &str) {
+        let text = chat_text(text);
+        if text.is_empty() || id == self.id {
+            return;
+        }
+        if !self.muted {
+            controller.notify(&format!("{}: {}", self.name_of(id), text));
+        }
+        self.record_chat(id, text);
+    }
+
+    /// The name of a player.
+    pub fn name_of(&self, id: u8) -> String {
+        self.partners
+            .iter()
+            .find(|partner| partner.id == id)
+            .map_or_else(
+                || format!("Player {}", id + 1),
+                |partner| partner.name.clone(),
+            )
+    }
+
+    /// Sends the changes this player made since the board was last
+    /// shared. The host settles its own at once, and others hold theirs
+    /// pending until the host settles them.
+    fn send_edits(&mut self, controller: &GameboardController) {
+        let board = &controller.gameboard;
+        let changes: Vec<([usize; 2], Change)> = board
+            .cells
+            .positions()
+            .flat_map(|[i, j]| {
+                let before = (self.cells[j][i], self.notes[j][i]);
+                let after = (board.cells[j][i], board.notes[j][i]);
+                Change::between(before, after)
+                    .into_iter()
+                    .map(move |change| ([i, j], change))
+            })
+            .collect();
+        for (cell, change) in changes {
+            let [i, j] = cell;
+            let (value, notes) = change.apply((self.cells[j][i], self.notes[j][i]));
+            self.cells[j][i] = value;
+            self.notes[j][i] = notes;
+            self.edits += 1;
+            if self.host {
+                self.settle(Op {
+                    seq: 0,
+                    id: self.id,
+                    origin: self.origin,
+                    n: self.edits,
+                    cell,
+                    change,
+                });
+            } else {
+                self.pending.push_back((self.edits, cell, change));
+                let message = Message::Edit {
+                    origin: self.origin,
+                    n: self.edits,
+                    cell,
+                    change,
+                };
+                self.send(&message, None);
+            }
+        }
+    }
+
+    /// Numbers a change the host made or took, and sends it to everyone.
+    fn settle(&mut self, mut op: Op) {
+        self.seq += 1;
+        op.seq = self.seq;
+        self.log.push(op);
+        self.send(&Message::Op(op), None);
+    }
+
+    /// Settles a change a player sent, as the host, unless it was already.
+    /// A change this board refuses is settled as the cell is, so the
+    /// player sees it undone.
+    fn take_edit(
+        &mut self,
+        controller: &mut GameboardController,
+        from: u8,
+        origin: u64,
+        n: u64,
+        [i, j]: [usize; 2],
+        change: Change,
+    ) {
+        let size = self.cells.size();
+        let unsettled = self.applied.get(&origin).is_none_or(|&last| n > last);
+        let in_range = match change {
+            Change::Value(value) => value as usize <= size,
+            Change::Note(..) => true,
+        };
+        if i >= size || j >= size || !unsettled || !in_range {
+            return;
+        }
+        self.applied.insert(origin, n);
+        let board = &controller.gameboard;
+        let before = (board.cells[j][i], board.notes[j][i]);
+        if !controller.is_over() {
+            controller.dispatch(Action::Partner {
+                player: from,
+                cell: [i, j],
+                state: change.apply(before),
+            });
+        }
+        let board = &controller.gameboard;
+        let after = (board.cells[j][i], board.notes[j][i]);
+        self.cells[j][i] = after.0;
+        self.notes[j][i] = after.1;
+        self.settle(Op {
+            seq: 0,
+            id: from,
+            origin,
+            n,
+            cell: [i, j],
+            change: change.restate(after),
+        });
+    }
+
+    /// Applies a change the host settled to the board as settled, and
+    /// shows the cell with this player's changes still pending on top.
+    fn follow(&mut self, controller: &mut GameboardController, op: Op) {
+        let size = self.settled_cells.size();
+        let [i, j] = op.cell;
+        if op.seq <= self.seq || i >= size || j >= size {
+            return;
+        }
+        self.seq = op.seq;
+        let settled = op
+            .change
+            .apply((self.settled_cells[j][i], self.settled_notes[j][i]));
+        self.settled_cells[j][i] = settled.0;
+        self.settled_notes[j][i] = settled.1;
+        if op.origin == self.origin {
+            self.pending.retain(|&(n, _, _)| n > op.n);
+        }
+        let shown = self
+            .pending
+            .iter()
+            .filter(|&&(_, cell, _)| cell == op.cell)
+            .fold(settled, |state, &(_, _, change)| change.apply(state));
+        controller.dispatch(Action::Partner {
+            player: op.id,
+            cell: op.cell,
+            state: shown,
+        });
+        self.cells[j][i] = controller.gameboard.cells[j][i];
+        self.notes[j][i] = controller.gameboard.notes[j][i];
+    }
+
+    /// Says hello again to the host, reached again after the connection
+    /// dropped, and sends again the changes it has yet to settle.
+    fn reconnect(&mut self, controller: &mut GameboardController, mut stream: TcpStream) {
+        let mut messages = vec![self.hello()];
+        messages.extend(self.pending.iter().map(|&(n, cell, change)| Message::Edit {
+            origin: self.origin,
+            n,
+            cell,
+            change,
+        }));
+        if messages
+            .iter()
+            .any(|message| message.write(&mut stream).is_err())
+        {
+            // Its reading thread reports it closed, to try again.
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        self.peers.push(Peer {
+            id: 0,
+            stream,
+            name: None,
+            watching: false,
+        });
+        info!("Reconnected to the host");
+        controller.notify("Reconnected to the host");
+    }
+
+    /// Keeps playing when the host is lost, trying to reach it again, in a
+    /// co-op game joined. Anywhere else the session ends.
+    fn lose_host(&mut self, controller: &mut GameboardController) -> Result<(), String> {
+        match &self.rejoin {
+            Some((address, events)) if self.joined && !self.race => {
+                let (address, events) = (address.clone(), events.clone());
+                info!("Lost connection to the host at {}, reconnecting", address);
+                thread::spawn(move || rejoin(&address, events));
+                self.peers.clear();
+                self.partners.clear();
+                controller.notify("Lost connection to the host, reconnecting");
+                Ok(())
+            }
+            _ => Err("Lost connection to the host".to_string()),
+        }
+    }
+
+    /// Handles a message from the host, returning whether it started
+    /// another game.
+    fn handle_host(&mut self, controller: &mut GameboardController, message: Message) -> bool {
+        match message {
+            Message::Welcome { id, race } => {
+                self.id = id;
+                self.race = race;
+                self.partners.clear();
+            }
+            Message::Game { game, seq, save } => match SavedGame::parse(&save) {
+                Ok(saved) => {
+                    *controller = saved.restore();
+                    self.take_board(controller);
+                    self.game = game;
+                    self.seq = seq;
+                    self.pending.clear();
+                    let host = self.name_of(0);
+                    if self.race {
+                        self.reset_race();
+                    }
+                    if self.watching && !self.joined {
+                        let game = if self.race { "race" } else { "game" };
+                        controller.notify(&format!("Watching {}'s {}", host, game));
+                    } else if self.race && !self.watching {
+                        controller
+                            .notify(&format!("Racing {}: first to solve the puzzle wins", host));
+                    } else if !self.joined {
+                        controller.notify(&format!("Joined {}'s game", host));
+                    }
+                    self.joined = true;
+                    return true;
+                }
+                Err(err) => warn!("Could not read co-op game: {}", err),
+            },
+            Message::Join { id, name, watching } if id != self.id => {
+                if watching {
+                    controller.notify(&format!("{} is watching", name));
+                } else {
+                    controller.notify(&format!("{} is playing", name));
+                }
+                self.partners.retain(|partner| partner.id != id);
+                self.partners.push(Partner {
+                    id,
+                    name,
+                    cursor: None,
+                    progress: Some(0).filter(|_| self.race && !watching),
+                    watching,
+                });
+            }
+            Message::Leave { id } => {
+                controller.notify(&format!("{} left", self.name_of(id)));
+                self.partners.retain(|partner| partner.id != id);
+            }
+            Message::Cell {
+                id,
+                cell,
+                value,
+                notes,
+            } if self.race => self.record_cell(id, cell, value, notes),
+            Message::Op(op) if self.joined && !self.race => self.follow(controller, op),
+            Message::Cursor { id, cell } => {
+                if let Some(partner) = self.partners.iter_mut().find(|partner| partner.id == id) {
+                    partner.cursor = cell;
+                }
+            }
+            Message::Progress { id, percent } => self.set_progress(id, percent),
+            Message::Won { id } => self.announce(controller, id),
+            Message::Chat { id, text } => self.hear(controller, id, &text),
+            _ => {}
+        }
+        false
+    }
+
+    /// Handles a message from a joined player, passing it on to the others.
+    fn handle_player(&mut self, controller: &mut GameboardController, from: u8, message: Message) {
+        let named = self
+            .peers
+            .iter()
+            .any(|peer| peer.id == from && peer.name.is_some());
+        // Those watching are heard only saying hello.
+        let playing = named
+            && self
+                .peers
+                .iter()
+                .any(|peer| peer.id == from && !peer.watching);
+        match message {
+            Message::Hello {
+                version,
+                name,
+                watching,
+                resume,
+            } if !named => {
+                let peer = match self.peers.iter_mut().find(|peer| peer.id == from) {
+                    Some(peer) => peer,
+                    None => return,
+                };
+                if version != PROTOCOL {
+                    warn!("Refusing co-op player {}: protocol {}", name, version);
+                    let _ = peer.stream.shutdown(Shutdown::Both);
+                    return;
+                }
+                // Say hello back with who is playing and the game, which a
+                // race starts over for everyone instead, unless they only
+                // watch it, when they are sent every board. A player back
+                // in the same game is sent only the changes they missed.
+                let resumed = resume
+                    .filter(|&(game, seq)| {
+                        self.joined && !self.race && game == self.game && seq <= self.seq
+                    })
+                    .map(|(_, seq)| seq as usize);
+                let mut greeting = vec![Message::Welcome {
+                    id: from,
+                    race: self.race,
+                }];
+                greeting.push(Message::Join {
+                    id: self.id,
+                    name: self.name.clone(),
+                    watching: false,
+                });
+                for partner in &self.partners {
+                    greeting.push(Message::Join {
+                        id: partner.id,
+                        name: partner.name.clone(),
+                        watching: partner.watching,
+                    });
+                    greeting.push(Message::Cursor {
+                        id: partner.id,
+                        cell: partner.cursor,
+                    });
+                }
+                greeting.push(Message::Cursor {
+                    id: self.id,
+                    cell: self.cursor,
+                });
+                if let Some(seq) = resumed {
+                    greeting.extend(self.log[seq..].iter().map(|&op| Message::Op(op)));
+                } else if self.joined && (!self.race || watching) {
+                    greeting.extend(self.game(controller));
+                }
+                if self.joined && self.race && watching {
+                    greeting.extend(self.board_cells(self.id, &self.cells, &self.notes));
+                    for (&id, (cells, notes)) in &self.boards {
+                        greeting.extend(self.board_cells(id, cells, notes));
+                    }
+                }
+                let peer = match self.peers.iter_mut().find(|peer| peer.id == from) {
+                    Some(peer) => peer,
+                    None => return,
+                };
+                for message in &greeting {
+                    if message.write(&mut peer.stream).is_err() {
+                        let _ = peer.stream.shutdown(Shutdown::Both);
+                        return;
+                    }
+                }
+                peer.name = Some(name.clone());
+                peer.watching = watching;
+                self.send(
+                    &Message::Join {
+                        id: from,
+                        name: name.clone(),
+                        watching,
+                    },
+                    Some(from),
+                );
+                self.partners.push(Partner {
+                    id: from,
+                    name: name.clone(),
+                    cursor: None,
+                    progress: None,
+                    watching,
+                });
+                info!("Player {} ({}) joined, watching: {}", from, name, watching);
+                if resume.is_some() {
+                    controller.notify(&format!("{} is back", name));
+                } else if watching {
+                    controller.notify(&format!("{} is watching", name));
+                } else if self.race && self.joined {
+                    self.restart(controller);
+                    controller.notify(&format!("{} joined: the race is on", name));
+                } else {
+                    controller.notify(&format!("{} joined", name));
+                }
+            }
+            Message::Cell {
+                cell, value, notes, ..
+            } if playing && self.race => {
+                self.record_cell(from, cell, value, notes);
+                let relayed = Message::Cell {
+                    id: from,
+                    cell,
+                    value,
+                    notes,
+                };
+                self.send_watchers(&relayed);
+            }
+            Message::Edit {
+                origin,
+                n,
+                cell,
+                change,
+            } if playing && !self.race && self.joined => {
+                self.take_edit(controller, from, origin, n, cell, change)
+            }
+            Message::Cursor { cell, .. } if playing => {
+                if let Some(partner) = self.partners.iter_mut().find(|partner| partner.id == from) {
+                    partner.cursor = cell;
+                }
+                self.send(&Message::Cursor { id: from, cell }, Some(from));
+            }
+            Message::Progress { percent, .. } if playing => {
+                self.set_progress(from, percent);
+                self.send(&Message::Progress { id: from, percent }, Some(from));
+            }
+            Message::Won { .. } if playing => {
+                if self.has_solved(controller, from) {
+                    self.declare(controller, from);
+                } else {
+                    warn!("Ignoring {}'s claim to have won", self.name_of(from));
+                }
+            }
+            // Those watching can chat too.
+            Message::Chat { text, .. } if named => {
+                let text = chat_text(&text);
+                if !text.is_empty() {
+                    self.hear(controller, from, &text);
+                    self.send(&Message::Chat { id: from, text }, Some(from));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Trades changes with the other players: applies theirs to the board
+    /// in play and sends this player's.
+    ///
+    /// Returns whether the host's game replaced the board in play, or why
+    /// the session ended.
+    pub fn sync(&mut self, controller: &mut GameboardController) -> Result<bool, String> {
+        if self.cells.size() == 0 {
+            self.take_board(controller);
+        }
+        // This player's changes go out before others' are taken in, so
+        // none are lost under theirs.
+        let board = &controller.gameboard;
+        let same = board.size() == self.cells.size() && board.puzzle() == self.puzzle;
+        if self.joined && same && !self.race && !self.watching {
+            self.send_edits(controller);
+        }
+        let mut replaced = false;
+        loop {
+            let event = match self.events.try_recv() {
+                Ok(event) => event,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) if self.host => break,
+                Err(TryRecvError::Disconnected) => {
+                    return Err("Lost connection to the host".to_string())
+                }
+            };
+            match event {
+                Event::Connected(_, stream) if !self.host => self.reconnect(controller, stream),
+                Event::Connected(id, stream) => self.peers.push(Peer {
+                    id,
+                    stream,
+                    name: None,
+                    watching: false,
+                }),
+                Event::Message(_, message) if !self.host => {
+                    replaced |= self.handle_host(controller, message);
+                }
+                Event::Message(from, message) => self.handle_player(controller, from, message),
+                Event::Closed(_) if !self.host => self.lose_host(controller)?,
+                Event::Lost => return Err("Could not reconnect to the host".to_string()),
+                Event::Closed(id) => {
+                    let named = self
+                        .peers
+                        .iter()
+                        .any(|peer| peer.id == id && peer.name.is_some());
+                    self.peers.retain(|peer| peer.id != id);
+                    if named {
+                        info!("Player {} ({}) left", id, self.name_of(id));
+                        controller.notify(&format!("{} left", self.name_of(id)));
+                        self.partners.retain(|partner| partner.id != id);
+                        self.send(&Message::Leave { id }, None);
+                    }
+                }
+            }
+        }
+        controller.partners = self.partners.clone();
+        controller.spectating = self.watching;
+        if !self.joined {
+            return Ok(replaced);
+        }
+
+        // A new puzzle is everyone's when the host starts it, and leaves the
+        // game when anyone else does.
+        let board = &controller.gameboard;
+        if board.size() != self.cells.size() || board.puzzle() != self.puzzle {
+            if self.watching {
+                return Err("Stopped watching".to_string());
+            } else if !self.host && self.race {
+                return Err("Left the race".to_string());
+            } else if !self.host {
+                return Err("Left the co-op game".to_string());
+            } else if self.race {
+                self.restart(controller);
+            } else {
+                self.share(controller);
+            }
+        }
+        if self.watching {
+            if self.race {
+                self.show_watched(controller);
+            }
+            return Ok(replaced);
+        }
+        if self.race {
+            // Changes in a race go only to those watching, through the
+            // host.
+            let board = &controller.gameboard;
+            let changed: Vec<[usize; 2]> = board
+                .cells
+                .positions()
+                .filter(|&[i, j]| {
+                    board.cells[j][i] != self.cells[j][i] || board.notes[j][i] != self.notes[j][i]
+                })
+                .collect();
+            for [i, j] in changed {
+                let (value, notes) = (
+                    controller.gameboard.cells[j][i],
+                    controller.gameboard.notes[j][i],
+                );
+                self.cells[j][i] = value;
+                self.notes[j][i] = notes;
+                let message = Message::Cell {
+                    id: self.id,
+                    cell: [i, j],
+                    value,
+                    notes,
+                };
+                if self.host {
+                    self.send_watchers(&message);
+                } else {
+                    self.send(&message, None);
+                }
+            }
+            let board = &controller.gameboard;
+            let percent = (board.progress() * 100.0).floor() as u8;
+            if self.progress != Some(percent) {
+                self.progress = Some(percent);
+                let message = Message::Progress {
+                    id: self.id,
+                    percent,
+                };
+                self.send(&message, None);
+            }
+            if board.is_solved() && !self.finished {
+                self.finished = true;
+                if self.host {
+                    self.declare(controller, self.id);
+                } else {
+                    self.send(&Message::Won { id: self.id }, None);
+                }
+            }
+            return Ok(replaced);
+        }
+        if controller.selected_cell != self.cursor {
+            self.cursor = controller.selected_cell;
+            let message = Message::Cursor {
+                id: self.id,
+                cell: self.cursor,
+            };
+            self.send(&message, None);
+        }
+        Ok(replaced)
+    }
+}
+
+impl Drop for Session {
+    /// Closes the connections, which the reading threads also hold, so the
+    /// other players see this one leave.
+    fn drop(&mut self) {
+        for peer in &self.peers {
+            let _ = peer.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(line: &str) -> io::Result<Option<Message>> {
+        Message::read(&mut line.as_bytes())
+    }
+
+    #[test]
+    fn reads_what_it_writes() {
+        let messages = [
+            Message::Hello {
+                version: 3,
+                name: "Ada Lovelace".to_string(),
+                watching: false,
+                resume: Some((7, 12)),
+            },
+            Message::Hello {
+                version: 3,
+                name: "Bob".to_string(),
+                watching: true,
+                resume: None,
+            },
+            Message::Welcome { id: 2, race: true },
+            Message::Game {
+                game: 7,
+                seq: 12,
+                save: "two\nlines".to_string(),
+            },
+            Message::Join {
+                id: 2,
+                name: "Bob".to_string(),
+                watching: true,
+            },
+            Message::Leave { id: 2 },
+            Message::Edit {
+                origin: 1,
+                n: 4,
+                cell: [3, 8],
+                change: Change::Note(5, true),
+            },
+            Message::Op(Op {
+                seq: 13,
+                id: 1,
+                origin: 1,
+                n: 4,
+                cell: [3, 8],
+                change: Change::Value(9),
+            }),
+            Message::Cursor { id: 1, cell: None },
+            Message::Cursor {
+                id: 1,
+                cell: Some([0, 4]),
+            },
+            Message::Progress { id: 1, percent: 40 },
+            Message::Won { id: 1 },
+            Message::Chat {
+                id: 1,
+                text: "well played".to_string(),
+            },
+        ];
+        let mut written = Vec::new();
+        for message in &messages {
+            message.write(&mut written).unwrap();
+        }
+        let mut reader = &written[..];
+        for message in messages {
+            assert_eq!(Message::read(&mut reader).unwrap(), Some(message));
+        }
+        assert_eq!(Message::read(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn refuses_malformed_lines() {
+        assert!(read("hello\n").is_err());
+        assert!(read("welcome 300 coop\n").is_err());
+        assert!(read("edit 1 1 0 0 note 32 1\n").is_err());
+        assert!(read(&format!("game 1 1 {}\n", MAX_GAME + 1)).is_err());
+        assert!(read("shout 1 hi\n").is_err());
+        assert_eq!(
+            read("progress 1 250\n").unwrap(),
+            Some(Message::Progress {
+                id: 1,
+                percent: 100
+            })
+        );
+    }
+
+    #[test]
+    fn settles_changes_to_cells() {
+        let notes = 1 << 2 | 1 << 5;
+        assert_eq!(Change::Value(4).apply((0, notes)), (4, notes));
+        assert_eq!(Change::Note(5, false).apply((0, notes)), (0, 1 << 2));
+        let changes = Change::between((0, notes), (7, 1 << 2 | 1 << 3));
+        assert_eq!(
+            changes,
+            [
+                Change::Value(7),
+                Change::Note(3, true),
+                Change::Note(5, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_cell_values_up_to_the_highest_digit() {
+        assert_eq!(
+            read("cell 1 2 3 25 0\n").unwrap(),
+            Some(Message::Cell {
+                id: 1,
+                cell: [2, 3],
+                value: 25,
+                notes: 0,
+            })
+        );
+        assert!(read("cell 1 2 3 26 0\n").is_err());
+        assert!(read("cell 1 2 3 300 0\n").is_err());
+        assert!(read("edit 1 1 0 0 value 300\n").is_err());
+    }
+}
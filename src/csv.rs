@@ -0,0 +1,90 @@
+use crate::grid::Grid;
+use crate::SIZE;
+
+/// Reads a grid written as nine rows of nine comma-separated cells, as
+/// spreadsheets export it.
+///
+/// Empty cells may be left blank or hold `0` or `.`. Semicolons are accepted
+/// as separators too, and quotes around cells are ignored.
+pub fn parse(text: &str) -> Result<Grid, String> {
+    let rows: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    if rows.len() != SIZE {
+        return Err(format!("Expected {} rows but found {}", SIZE, rows.len()));
+    }
+    let mut grid = Grid::new(SIZE);
+    for (j, row) in rows.iter().enumerate() {
+        let fields: Vec<&str> = row.split([',', ';']).collect();
+        if fields.len() != SIZE {
+            return Err(format!(
+                "Row {} has {} cells instead of {}",
+                j + 1,
+                fields.len(),
+                SIZE
+            ));
+        }
+        for (i, field) in fields.iter().enumerate() {
+            grid[j][i] = match field.trim().trim_matches('"').trim() {
+                "" | "0" | "." => 0,
+                cell => match cell.parse() {
+                    Ok(val @ 1..=9) => val,
+                    _ => return Err(format!("Invalid cell '{}' in row {}", cell, j + 1)),
+                },
+            };
+        }
+    }
+    Ok(grid)
+}
+
+/// Writes a grid as comma-separated rows, leaving empty cells blank.
+pub fn write(grid: &Grid) -> String {
+    grid.iter()
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|&val| match val {
+                    0 => String::new(),
+                    _ => val.to_string(),
+                })
+                .collect();
+            cells.join(",") + "\n"
+        })
+        .collect()
+}
+
+/// Quotes a field if it holds a separator, quote, or line break, doubling
+/// any quotes inside.
+pub fn field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_what_spreadsheets_export() {
+        let mut text = "5;\"3\";.;0;7;;;;\n".to_string();
+        text += &",,,,,,,,\n".repeat(SIZE - 1);
+        let grid = parse(&text).unwrap();
+        assert_eq!(grid[0][..], [5, 3, 0, 0, 7, 0, 0, 0, 0]);
+        assert_eq!(parse(&write(&grid)).unwrap(), grid);
+        assert!(write(&grid).starts_with("5,3,,,7,,,,\n"));
+        let wrong = text.replacen('7', "10", 1);
+        assert!(parse(&wrong).unwrap_err().contains("'10' in row 1"));
+        assert!(parse(&text[..text.len() - 9]).is_err());
+    }
+
+    #[test]
+    fn quotes_fields_only_when_needed() {
+        assert_eq!(field("plain"), "plain");
+        assert_eq!(field("one, two"), "\"one, two\"");
+        assert_eq!(field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -0,0 +1,357 @@
+use crate::database::Database;
+#[cfg(feature = "gui")]
+use crate::format_duration;
+use crate::rules::RuleSet;
+#[cfg(feature = "gui")]
+use crate::text::draw_text;
+#[cfg(feature = "gui")]
+use crate::theme::Theme;
+use crate::{Difficulty, Gameboard, SIZE};
+#[cfg(feature = "gui")]
+use graphics::character::CharacterCache;
+#[cfg(feature = "gui")]
+use graphics::types::Color;
+#[cfg(feature = "gui")]
+use graphics::{Context, Graphics};
+#[cfg(feature = "gui")]
+use piston::input::GenericEvent;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Today, as days since the Unix epoch in UTC, so the daily puzzle changes
+/// at the same moment for every player.
+pub fn today() -> i64 {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    (secs / 86_400) as i64
+}
+
+/// The year, month, and day of the month of a day since the epoch.
+pub fn date(day: i64) -> (i64, u32, u32) {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = day + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (yoe + era * 400 + i64::from(m <= 2), m, d)
+}
+
+/// Writes a day as a date, such as `16 October 2026`.
+pub fn date_name(day: i64) -> String {
+    let (year, month, d) = date(day);
+    format!("{} {} {}", d, MONTHS[month as usize - 1], year)
+}
+
+/// The seed of a day's puzzle at a difficulty, mixed so that neighboring
+/// days and difficulties give unrelated puzzles.
+pub fn seed(day: i64, difficulty: Difficulty) -> u64 {
+    let index = Difficulty::ALL
+        .iter()
+        .position(|&each| each == difficulty)
+        .unwrap_or(0);
+    // SplitMix64's finalizer.
+    let mut z = (day as u64).wrapping_mul(8).wrapping_add(index as u64);
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Generates a day's puzzle at a difficulty, the same for every player.
+///
+/// Daily puzzles are always classic, whatever variant the player has set.
+pub fn puzzle(day: i64, difficulty: Difficulty) -> Gameboard {
+    let mut gameboard = Gameboard::with_seed(seed(day, difficulty));
+    gameboard.generate_rules(difficulty, RuleSet::default(), SIZE);
+    gameboard
+}
+
+/// The daily puzzles solved over the month of a day.
+pub struct Calendar {
+    pub today: i64,
+    /// The first day of the month.
+    pub first: i64,
+    /// Days in the month.
+    pub days: i64,
+    /// The daily puzzles solved in the month, by day and difficulty, with
+    /// how long each took.
+    pub solved: Vec<(i64, Difficulty, Duration)>,
+}
+
+impl Calendar {
+    /// The month of a day, with nothing solved yet.
+    pub fn new(today: i64) -> Calendar {
+        let (_, _, d) = date(today);
+        let first = today - i64::from(d) + 1;
+        // A day 31 days on is always in the next month.
+        let later = first + 31;
+        let days = later - i64::from(date(later).2) + 1 - first;
+        Calendar {
+            today,
+            first,
+            days,
+            solved: Vec::new(),
+        }
+    }
+
+    /// Reads the daily puzzles solved in the month of a day.
+    pub fn load(database: &Database, today: i64) -> Result<Calendar, String> {
+        let mut calendar = Calendar::new(today);
+        let last = calendar.first + calendar.days - 1;
+        calendar.solved = database.dailies(calendar.first, last)?;
+        Ok(calendar)
+    }
+
+    /// How long today's puzzle at a difficulty took, if it was solved.
+    pub fn solved_today(&self, difficulty: Difficulty) -> Option<Duration> {
+        self.solved
+            .iter()
+            .find(|&&(day, each, _)| day == self.today && each == difficulty)
+            .map(|&(_, _, time)| time)
+    }
+
+    /// How many of a day's puzzles were solved.
+    fn solved_on(&self, day: i64) -> usize {
+        self.solved
+            .iter()
+            .filter(|&&(each, _, _)| each == day)
+            .count()
+    }
+}
+
+#[cfg(feature = "gui")]
+pub struct DailyController {
+    /// Which difficulty is picked.
+    pub selected: usize,
+}
+
+#[cfg(feature = "gui")]
+impl Default for DailyController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "gui")]
+impl DailyController {
+    pub fn new() -> DailyController {
+        DailyController { selected: 1 }
+    }
+
+    /// Handles events, returning the difficulty to play today's puzzle at,
+    /// or `None` inside to go back.
+    pub fn event<E: GenericEvent>(&mut self, e: &E) -> Option<Option<Difficulty>> {
+        use piston::input::{Button, Key};
+
+        let count = Difficulty::ALL.len();
+        match e.press_args() {
+            Some(Button::Keyboard(Key::Up)) => self.selected = (self.selected + count - 1) % count,
+            Some(Button::Keyboard(Key::Down)) => self.selected = (self.selected + 1) % count,
+            Some(Button::Keyboard(Key::Return | Key::Space)) => {
+                return Some(Some(Difficulty::ALL[self.selected]))
+            }
+            Some(Button::Keyboard(Key::Escape | Key::Backspace)) => return Some(None),
+            _ => {}
+        }
+        None
+    }
+}
+
+#[cfg(feature = "gui")]
+pub struct DailyViewSettings {
+    pub font_size: u32,
+    pub background_color: Color,
+    pub selected_background_color: Color,
+    pub text_color: Color,
+    /// Days with every puzzle solved; days with some are fainter.
+    pub solved_color: Color,
+    pub today_color: Color,
+}
+
+#[cfg(feature = "gui")]
+impl DailyViewSettings {
+    pub fn new(theme: &Theme) -> DailyViewSettings {
+        DailyViewSettings {
+            font_size: 16,
+            background_color: theme.panel_background,
+            selected_background_color: theme.panel_selected_background,
+            text_color: theme.text,
+            solved_color: theme.correct_text,
+            today_color: theme.selected_cell_border,
+        }
+    }
+
+    /// Scales text.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.font_size = (16.0 * scale).round() as u32;
+    }
+
+    /// Switches to a theme's colors.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        *self = DailyViewSettings {
+            font_size: self.font_size,
+            ..DailyViewSettings::new(theme)
+        };
+    }
+}
+
+#[cfg(feature = "gui")]
+pub struct DailyView {
+    pub settings: DailyViewSettings,
+}
+
+#[cfg(feature = "gui")]
+impl DailyView {
+    pub fn new(settings: DailyViewSettings) -> DailyView {
+        DailyView { settings }
+    }
+
+    /// Draws today's puzzles to pick from, and the month's calendar with
+    /// each day shaded by how many of its puzzles were solved, over the
+    /// area at `pos` with side length `size`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw<G: Graphics, C>(
+        &self,
+        controller: &DailyController,
+        calendar: &Calendar,
+        pos: [f64; 2],
+        size: f64,
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::{Rectangle, Transformed};
+
+        let settings = &self.settings;
+        let [x, y] = pos;
+        Rectangle::new(settings.background_color).draw(
+            [x, y, size, size],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+        let line_height = settings.font_size as f64 * 1.6;
+        let text = |line: &str, row: f64, glyphs: &mut C, g: &mut G| {
+            draw_text(
+                line,
+                settings.font_size,
+                settings.text_color,
+                [x + 10.0, y + (row + 1.0) * line_height],
+                glyphs,
+                c,
+                g,
+            );
+        };
+        text(
+            &format!("Daily puzzles - {}", date_name(calendar.today)),
+            0.0,
+            glyphs,
+            g,
+        );
+        for (k, &difficulty) in Difficulty::ALL.iter().enumerate() {
+            let row = k as f64 + 2.0;
+            if k == controller.selected {
+                Rectangle::new(settings.selected_background_color).draw(
+                    [x, y + (row + 0.3) * line_height, size, line_height],
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+            }
+            let line = match calendar.solved_today(difficulty) {
+                Some(time) => format!(
+                    "{} - solved in {}",
+                    difficulty.name(),
+                    format_duration(time)
+                ),
+                None => difficulty.name().to_string(),
+            };
+            text(&line, row, glyphs, g);
+        }
+
+        // The month as a grid of weeks from Monday, under the day names.
+        let top = y + 7.5 * line_height;
+        let cell = (size - 20.0) / 7.0;
+        let cell = cell.min((y + size - top - 2.0 * line_height) / 7.0);
+        for (k, name) in ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"]
+            .iter()
+            .enumerate()
+        {
+            draw_text(
+                name,
+                settings.font_size,
+                settings.text_color,
+                [x + 10.0 + k as f64 * cell + 4.0, top],
+                glyphs,
+                c,
+                g,
+            );
+        }
+        // The epoch fell on a Thursday.
+        let offset = (calendar.first + 3).rem_euclid(7);
+        for day in calendar.first..calendar.first + calendar.days {
+            let slot = offset + day - calendar.first;
+            let cx = x + 10.0 + (slot % 7) as f64 * cell;
+            let cy = top + 0.4 * line_height + (slot / 7) as f64 * cell;
+            let solved = calendar.solved_on(day);
+            if solved > 0 {
+                let [r, gr, b, a] = settings.solved_color;
+                let share = solved as f32 / Difficulty::ALL.len() as f32;
+                Rectangle::new([r, gr, b, a * (0.25 + 0.75 * share)]).draw(
+                    [cx + 1.0, cy + 1.0, cell - 2.0, cell - 2.0],
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+            }
+            if day == calendar.today {
+                Rectangle::new_border(settings.today_color, 1.5).draw(
+                    [cx + 1.0, cy + 1.0, cell - 2.0, cell - 2.0],
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+            }
+            let (_, _, d) = date(day);
+            draw_text(
+                &d.to_string(),
+                settings.font_size,
+                settings.text_color,
+                [4.0, cell / 2.0 + settings.font_size as f64 / 2.0],
+                glyphs,
+                &c.trans(cx, cy),
+                g,
+            );
+        }
+        draw_text(
+            "Enter to play, Escape to go back",
+            settings.font_size,
+            settings.text_color,
+            [x + 10.0, y + size - line_height / 2.0],
+            glyphs,
+            c,
+            g,
+        );
+    }
+}
@@ -0,0 +1,602 @@
+use crate::collection::Collection;
+use crate::grid::Grid;
+use crate::replay::Replay;
+use crate::{canonical, puzzle, Difficulty, GameboardController, SIZE};
+use rusqlite::{params, Connection, Params, Row};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Where puzzles and results are stored, within a profile.
+pub const DATABASE_FILE: &str = "sudoku.db";
+
+/// Source of puzzles first seen when a game of them ended.
+pub const PLAYED_SOURCE: &str = "played";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS puzzles (
+        id INTEGER PRIMARY KEY,
+        givens TEXT NOT NULL UNIQUE,
+        solution TEXT,
+        difficulty TEXT NOT NULL,
+        source TEXT NOT NULL,
+        added INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS results (
+        id INTEGER PRIMARY KEY,
+        puzzle INTEGER NOT NULL REFERENCES puzzles(id),
+        seconds INTEGER NOT NULL,
+        mistakes INTEGER NOT NULL,
+        hints INTEGER NOT NULL DEFAULT 0,
+        solved INTEGER NOT NULL,
+        finished INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS solved (
+        hash TEXT PRIMARY KEY,
+        finished INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS achievements (
+        name TEXT PRIMARY KEY,
+        unlocked INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS replays (
+        result INTEGER PRIMARY KEY REFERENCES results(id),
+        moves TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS daily (
+        day INTEGER NOT NULL,
+        difficulty TEXT NOT NULL,
+        seconds INTEGER NOT NULL,
+        finished INTEGER NOT NULL,
+        PRIMARY KEY (day, difficulty)
+    );
+    CREATE INDEX IF NOT EXISTS results_puzzle ON results(puzzle);
+    CREATE INDEX IF NOT EXISTS puzzles_difficulty ON puzzles(difficulty);
+";
+
+/// Seconds since the Unix epoch.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() as i64)
+}
+
+/// A puzzle read back from the database.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredPuzzle {
+    pub id: i64,
+    pub cells: Grid,
+    /// Missing for imported puzzles that have not been played yet.
+    pub solution: Option<Grid>,
+    pub difficulty: Difficulty,
+    /// File the puzzle was imported from, or how it was first seen.
+    pub source: String,
+}
+
+/// Totals over the games of one difficulty.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Games finished, won, lost, or given up.
+    pub played: u32,
+    pub won: u32,
+    /// Fastest and mean times of the games won.
+    pub best_time: Option<Duration>,
+    pub average_time: Option<Duration>,
+    /// Games won in a row up to the last one, and the most ever.
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    pub hints: u32,
+    pub mistakes: u32,
+}
+
+/// How long a game took and whether it was won.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RecentGame {
+    pub time: Duration,
+    pub solved: bool,
+}
+
+/// One finished game, as exported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    /// When the game ended, as `YYYY-MM-DD HH:MM:SS` in UTC.
+    pub finished: String,
+    pub difficulty: String,
+    pub seconds: i64,
+    pub mistakes: i64,
+    pub hints: i64,
+    pub solved: bool,
+    pub givens: String,
+    pub source: String,
+}
+
+/// Which puzzles to find.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Search {
+    pub difficulty: Option<Difficulty>,
+    /// Only puzzles without a recorded result.
+    pub unplayed: bool,
+    pub limit: usize,
+}
+
+/// Generated and imported puzzles, with the result of each game played.
+///
+/// Puzzles are kept once each, by their givens, so importing a file twice
+/// adds nothing. Solved puzzles are also kept by their canonical hash, which
+/// recognizes them even when relabeled, reordered, or transposed.
+pub struct Database {
+    connection: Connection,
+}
+
+impl Database {
+    /// Opens the database, creating its tables if needed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Database, String> {
+        let connection = Connection::open(path).map_err(|err| err.to_string())?;
+        let database = Database { connection };
+        database.execute_batch(SCHEMA)?;
+        // Databases from before hints were recorded lack the column.
+        let columns = database.query("PRAGMA table_info(results)", [], |row| row.get(1))?;
+        if !columns.iter().any(|column: &String| column == "hints") {
+            database
+                .execute_batch("ALTER TABLE results ADD COLUMN hints INTEGER NOT NULL DEFAULT 0")?;
+        }
+        Ok(database)
+    }
+
+    /// Runs statements separated by semicolons, without parameters.
+    fn execute_batch(&self, sql: &str) -> Result<(), String> {
+        self.connection
+            .execute_batch(sql)
+            .map_err(|err| err.to_string())
+    }
+
+    /// Runs one statement, returning how many rows it changed.
+    fn execute<P: Params>(&self, sql: &str, params: P) -> Result<usize, String> {
+        self.connection
+            .execute(sql, params)
+            .map_err(|err| err.to_string())
+    }
+
+    /// Runs a query, reading each row with `read`.
+    fn query<T, P, F>(&self, sql: &str, params: P, read: F) -> Result<Vec<T>, String>
+    where
+        P: Params,
+        F: FnMut(&Row) -> rusqlite::Result<T>,
+    {
+        let mut statement = self
+            .connection
+            .prepare(sql)
+            .map_err(|err| err.to_string())?;
+        let rows = statement
+            .query_map(params, read)
+            .map_err(|err| err.to_string())?;
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|err| err.to_string())
+    }
+
+    /// Stores a puzzle unless it is already stored, returning its id.
+    ///
+    /// A solution fills in one missing from an earlier import.
+    pub fn add_puzzle(
+        &self,
+        cells: &Grid,
+        solution: Option<&Grid>,
+        difficulty: Difficulty,
+        source: &str,
+    ) -> Result<i64, String> {
+        let givens = puzzle::to_line(cells);
+        let solution = solution.map(puzzle::to_line);
+        self.execute(
+            "INSERT OR IGNORE INTO puzzles (givens, solution, difficulty, source, added) \
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![givens, solution, difficulty.name(), source, now()],
+        )?;
+        if let Some(solution) = solution {
+            self.execute(
+                "UPDATE puzzles SET solution = ?2 WHERE givens = ?1 AND solution IS NULL",
+                params![givens, solution],
+            )?;
+        }
+        self.query(
+            "SELECT id FROM puzzles WHERE givens = ?1",
+            params![givens],
+            |row| row.get::<_, i64>(0),
+        )?
+        .first()
+        .copied()
+        .ok_or_else(|| "Puzzle was not stored".to_string())
+    }
+
+    /// Stores every puzzle of a collection, returning how many were new.
+    pub fn add_collection(&self, collection: &Collection) -> Result<usize, String> {
+        let source = collection.path.to_string_lossy();
+        let before = self.count()?;
+        // One transaction, as committing each insert is slow on disk.
+        self.execute_batch("BEGIN")?;
+        for entry in &collection.puzzles {
+            if let Err(err) = self.add_puzzle(&entry.cells, None, entry.difficulty(), &source) {
+                let _ = self.execute_batch("ROLLBACK");
+                return Err(err);
+            }
+        }
+        self.execute_batch("COMMIT")?;
+        Ok(self.count()? - before)
+    }
+
+    /// Number of puzzles stored.
+    pub fn count(&self) -> Result<usize, String> {
+        let counts = self.query("SELECT COUNT(*) FROM puzzles", [], |row| {
+            row.get::<_, i64>(0)
+        })?;
+        Ok(counts.first().copied().unwrap_or(0) as usize)
+    }
+
+    /// Records how a finished game went, with its replay, storing its puzzle
+    /// if needed.
+    ///
+    /// Puzzles are stored as lines of 81 cells, so games on other sizes of
+    /// board go unrecorded.
+    pub fn record_result(&self, controller: &GameboardController) -> Result<(), String> {
+        let board = &controller.gameboard;
+        if board.size() != SIZE {
+            return Ok(());
+        }
+        let id = self.add_puzzle(
+            &board.puzzle(),
+            Some(&board.solution),
+            board.difficulty,
+            PLAYED_SOURCE,
+        )?;
+        self.execute(
+            "INSERT INTO results (puzzle, seconds, mistakes, hints, solved, finished) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                id,
+                (controller.elapsed().as_secs() as i64),
+                i64::from(controller.mistakes),
+                i64::from(controller.hints),
+                i64::from(board.is_solved()),
+                now(),
+            ],
+        )?;
+        let replay = toml::to_string(&controller.replay).map_err(|err| err.to_string())?;
+        self.execute(
+            "INSERT INTO replays (result, moves) VALUES (last_insert_rowid(), ?1)",
+            params![replay],
+        )?;
+        // The canonical hash only knows the symmetries of classic puzzles.
+        if board.is_solved() && board.rules.is_classic() {
+            self.execute(
+                "INSERT OR IGNORE INTO solved (hash, finished) VALUES (?1, ?2)",
+                params![canonical::hash(&board.puzzle()), now()],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The replay of the last finished attempt at a puzzle, if there was one.
+    pub fn last_replay(&self, cells: &Grid) -> Result<Option<Replay>, String> {
+        let replays = self.query(
+            "SELECT replays.moves FROM replays \
+             JOIN results ON results.id = replays.result \
+             JOIN puzzles ON puzzles.id = results.puzzle \
+             WHERE puzzles.givens = ?1 ORDER BY results.id DESC LIMIT 1",
+            params![puzzle::to_line(cells)],
+            |row| row.get::<_, String>(0),
+        )?;
+        replays
+            .first()
+            .map(|text| toml::from_str(text).map_err(|err| err.to_string()))
+            .transpose()
+    }
+
+    /// Whether a game of a puzzle was finished before, written as it is.
+    pub fn has_played(&self, cells: &Grid) -> Result<bool, String> {
+        let found = self.query(
+            "SELECT 1 FROM results JOIN puzzles ON puzzles.id = results.puzzle \
+             WHERE puzzles.givens = ?1 LIMIT 1",
+            params![puzzle::to_line(cells)],
+            |row| row.get::<_, i64>(0),
+        )?;
+        Ok(!found.is_empty())
+    }
+
+    /// Whether a puzzle, however it is written, was solved before.
+    pub fn has_solved(&self, cells: &Grid) -> Result<bool, String> {
+        let found = self.query(
+            "SELECT 1 FROM solved WHERE hash = ?1",
+            params![canonical::hash(cells)],
+            |row| row.get::<_, i64>(0),
+        )?;
+        Ok(!found.is_empty())
+    }
+
+    /// Totals over the games played at a difficulty.
+    pub fn stats(&self, difficulty: Difficulty) -> Result<Stats, String> {
+        let totals = self.query(
+            "SELECT COUNT(*), SUM(solved), MIN(CASE WHEN solved THEN seconds END), \
+             AVG(CASE WHEN solved THEN seconds END), SUM(hints), SUM(mistakes) \
+             FROM results JOIN puzzles ON puzzles.id = results.puzzle \
+             WHERE puzzles.difficulty = ?1",
+            params![difficulty.name()],
+            |row| {
+                // Sums over no games are NULL.
+                let count = |col| -> rusqlite::Result<u32> {
+                    Ok(row.get::<_, Option<i64>>(col)?.unwrap_or(0) as u32)
+                };
+                Ok(Stats {
+                    played: count(0)?,
+                    won: count(1)?,
+                    best_time: row
+                        .get::<_, Option<i64>>(2)?
+                        .map(|secs| Duration::from_secs(secs as u64)),
+                    average_time: row
+                        .get::<_, Option<f64>>(3)?
+                        .map(|secs| Duration::from_secs(secs as u64)),
+                    current_streak: 0,
+                    longest_streak: 0,
+                    hints: count(4)?,
+                    mistakes: count(5)?,
+                })
+            },
+        )?;
+        let mut stats = totals.into_iter().next().unwrap_or_default();
+
+        // Streaks run over the outcomes in the order the games ended.
+        let outcomes = self.query(
+            "SELECT solved FROM results JOIN puzzles ON puzzles.id = results.puzzle \
+             WHERE puzzles.difficulty = ?1 ORDER BY finished, results.id",
+            params![difficulty.name()],
+            |row| Ok(row.get::<_, i64>(0)? != 0),
+        )?;
+        for won in outcomes {
+            stats.current_streak = if won { stats.current_streak + 1 } else { 0 };
+            stats.longest_streak = stats.longest_streak.max(stats.current_streak);
+        }
+        Ok(stats)
+    }
+
+    /// The last games finished, oldest first.
+    pub fn recent(&self, limit: usize) -> Result<Vec<RecentGame>, String> {
+        let mut games = self.query(
+            "SELECT seconds, solved FROM results ORDER BY finished DESC, id DESC LIMIT ?1",
+            params![(limit as i64)],
+            |row| {
+                Ok(RecentGame {
+                    time: Duration::from_secs(row.get::<_, i64>(0)? as u64),
+                    solved: row.get::<_, i64>(1)? != 0,
+                })
+            },
+        )?;
+        games.reverse();
+        Ok(games)
+    }
+
+    /// Games won at any difficulty.
+    pub fn games_won(&self) -> Result<u32, String> {
+        let counts = self.query("SELECT COUNT(*) FROM results WHERE solved", [], |row| {
+            row.get::<_, i64>(0)
+        })?;
+        Ok(counts.first().copied().unwrap_or(0) as u32)
+    }
+
+    /// Days in a row, up to the latest, on which a puzzle was solved,
+    /// counting days in local time.
+    pub fn daily_streak(&self) -> Result<u32, String> {
+        let days = self.query(
+            "SELECT DISTINCT CAST(julianday(date(finished, 'unixepoch', 'localtime')) AS INTEGER) \
+             AS day FROM results WHERE solved ORDER BY day DESC",
+            [],
+            |row| row.get::<_, i64>(0),
+        )?;
+        let streak = days
+            .iter()
+            .zip(days.iter().skip(1))
+            .take_while(|(later, earlier)| *later - *earlier == 1)
+            .count();
+        Ok(if days.is_empty() {
+            0
+        } else {
+            streak as u32 + 1
+        })
+    }
+
+    /// Records a day's puzzle at a difficulty as solved, keeping the first
+    /// time if it was solved again.
+    pub fn record_daily(
+        &self,
+        day: i64,
+        difficulty: Difficulty,
+        time: Duration,
+    ) -> Result<(), String> {
+        self.execute(
+            "INSERT OR IGNORE INTO daily (day, difficulty, seconds, finished) \
+             VALUES (?1, ?2, ?3, ?4)",
+            params![day, difficulty.name(), (time.as_secs() as i64), now(),],
+        )?;
+        Ok(())
+    }
+
+    /// The daily puzzles solved from one day to another, inclusive, by day
+    /// and difficulty, with how long each took.
+    pub fn dailies(
+        &self,
+        first: i64,
+        last: i64,
+    ) -> Result<Vec<(i64, Difficulty, Duration)>, String> {
+        let rows = self.query(
+            "SELECT day, difficulty, seconds FROM daily \
+             WHERE day BETWEEN ?1 AND ?2 ORDER BY day",
+            params![first, last],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            },
+        )?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(day, name, secs)| {
+                let difficulty = Difficulty::from_name(&name)?;
+                Some((day, difficulty, Duration::from_secs(secs as u64)))
+            })
+            .collect())
+    }
+
+    /// Names of the achievements unlocked, with the local date of each.
+    pub fn achievements(&self) -> Result<Vec<(String, String)>, String> {
+        self.query(
+            "SELECT name, date(unlocked, 'unixepoch', 'localtime') FROM achievements \
+             ORDER BY unlocked",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+    }
+
+    /// Records an achievement as unlocked now, unless it already was.
+    pub fn unlock(&self, name: &str) -> Result<(), String> {
+        self.execute(
+            "INSERT OR IGNORE INTO achievements (name, unlocked) VALUES (?1, ?2)",
+            params![name, now()],
+        )?;
+        Ok(())
+    }
+
+    /// Every game finished, oldest first.
+    pub fn history(&self) -> Result<Vec<HistoryEntry>, String> {
+        self.query(
+            "SELECT datetime(finished, 'unixepoch'), difficulty, seconds, mistakes, hints, \
+             solved, givens, source \
+             FROM results JOIN puzzles ON puzzles.id = results.puzzle \
+             ORDER BY finished, results.id",
+            [],
+            |row| {
+                Ok(HistoryEntry {
+                    finished: row.get(0)?,
+                    difficulty: row.get(1)?,
+                    seconds: row.get(2)?,
+                    mistakes: row.get(3)?,
+                    hints: row.get(4)?,
+                    solved: row.get::<_, i64>(5)? != 0,
+                    givens: row.get(6)?,
+                    source: row.get(7)?,
+                })
+            },
+        )
+    }
+
+    /// Finds puzzles, in random order.
+    pub fn search(&self, search: &Search) -> Result<Vec<StoredPuzzle>, String> {
+        let mut sql = "SELECT id, givens, solution, difficulty, source FROM puzzles \
+                       WHERE (?1 IS NULL OR difficulty = ?1)"
+            .to_string();
+        if search.unplayed {
+            sql += " AND id NOT IN (SELECT puzzle FROM results)";
+        }
+        sql += " ORDER BY RANDOM() LIMIT ?2";
+        let rows = self.query(
+            &sql,
+            params![
+                search.difficulty.map(Difficulty::name),
+                (search.limit as i64),
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            },
+        )?;
+        // Rows edited by hand into something unreadable are skipped.
+        let found = rows
+            .into_iter()
+            .filter_map(|(id, givens, solution, difficulty, source)| {
+                Some(StoredPuzzle {
+                    id,
+                    cells: puzzle::parse(&givens).ok()?,
+                    solution: solution.and_then(|text| puzzle::parse(&text).ok()),
+                    difficulty: Difficulty::from_name(&difficulty)?,
+                    source,
+                })
+            });
+        Ok(found.collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &str =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+    const SOLUTION: &str =
+        "534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+
+    fn database() -> Database {
+        Database::open(":memory:").unwrap()
+    }
+
+    #[test]
+    fn keeps_each_puzzle_once() {
+        let database = database();
+        let cells = puzzle::parse(PUZZLE).unwrap();
+        let solution = puzzle::parse(SOLUTION).unwrap();
+        let id = database
+            .add_puzzle(&cells, None, Difficulty::Hard, "import")
+            .unwrap();
+        let again = database
+            .add_puzzle(&cells, Some(&solution), Difficulty::Hard, "played")
+            .unwrap();
+        assert_eq!(id, again);
+        assert_eq!(database.count().unwrap(), 1);
+        let search = Search {
+            difficulty: Some(Difficulty::Hard),
+            unplayed: true,
+            limit: 10,
+        };
+        let found = database.search(&search).unwrap();
+        assert_eq!(found.len(), 1);
+        // A later solution fills in the one missing from the import.
+        assert_eq!(found[0].solution, Some(solution));
+        assert_eq!(found[0].source, "import");
+        let search = Search {
+            difficulty: Some(Difficulty::Easy),
+            ..search
+        };
+        assert!(database.search(&search).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reads_empty_totals_as_zero() {
+        let stats = database().stats(Difficulty::Medium).unwrap();
+        assert_eq!(stats, Stats::default());
+        assert_eq!(database().games_won().unwrap(), 0);
+        assert_eq!(database().daily_streak().unwrap(), 0);
+    }
+
+    #[test]
+    fn keeps_the_first_time_of_a_daily() {
+        let database = database();
+        database
+            .record_daily(100, Difficulty::Easy, Duration::from_secs(90))
+            .unwrap();
+        database
+            .record_daily(100, Difficulty::Easy, Duration::from_secs(60))
+            .unwrap();
+        database
+            .record_daily(101, Difficulty::Hard, Duration::from_secs(300))
+            .unwrap();
+        let dailies = database.dailies(100, 100).unwrap();
+        assert_eq!(
+            dailies,
+            vec![(100, Difficulty::Easy, Duration::from_secs(90))]
+        );
+        database.unlock("First steps").unwrap();
+        database.unlock("First steps").unwrap();
+        assert_eq!(database.achievements().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::text::draw_text;
+use crate::theme::Theme;
+use crate::Gameboard;
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{Context, Graphics};
+use piston::input::{Event, Loop, PressEvent, ReleaseEvent};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Counts events and frames for the debug overlay.
+pub struct DebugController {
+    /// Whether the overlay is shown.
+    pub visible: bool,
+    pub input_events: u64,
+    pub update_events: u64,
+    pub render_events: u64,
+    /// The most recent button press or release.
+    pub last_input: Option<String>,
+    /// When each frame in the last second was rendered.
+    frames: VecDeque<Instant>,
+}
+
+impl Default for DebugController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugController {
+    pub fn new() -> DebugController {
+        DebugController {
+            visible: false,
+            input_events: 0,
+            update_events: 0,
+            render_events: 0,
+            last_input: None,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Frames rendered in the last second.
+    pub fn fps(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Records an event.
+    pub fn event(&mut self, e: &Event) {
+        match e {
+            Event::Input(..) => self.input_events += 1,
+            Event::Loop(Loop::Update(_)) => self.update_events += 1,
+            Event::Loop(Loop::Render(_)) => {
+                self.render_events += 1;
+                let now = Instant::now();
+                self.frames.push_back(now);
+                while self
+                    .frames
+                    .front()
+                    .is_some_and(|&frame| now - frame > Duration::from_secs(1))
+                {
+                    self.frames.pop_front();
+                }
+            }
+            _ => {}
+        }
+        if let Some(button) = e.press_args() {
+            self.last_input = Some(format!("press {:?}", button));
+        }
+        if let Some(button) = e.release_args() {
+            self.last_input = Some(format!("release {:?}", button));
+        }
+    }
+}
+
+pub struct DebugViewSettings {
+    pub position: [f64; 2],
+    pub font_size: u32,
+    pub line_height: f64,
+    pub background_color: Color,
+    pub text_color: Color,
+}
+
+impl DebugViewSettings {
+    pub fn new(theme: &Theme) -> DebugViewSettings {
+        let mut background_color = theme.panel_background;
+        background_color[3] *= 0.85;
+        DebugViewSettings {
+            position: [4.0; 2],
+            font_size: 12,
+            line_height: 15.0,
+            background_color,
+            text_color: theme.text,
+        }
+    }
+
+    /// Switches to a theme's colors.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        *self = DebugViewSettings {
+            position: self.position,
+            font_size: self.font_size,
+            line_height: self.line_height,
+            ..DebugViewSettings::new(theme)
+        };
+    }
+}
+
+pub struct DebugView {
+    pub settings: DebugViewSettings,
+}
+
+impl DebugView {
+    pub fn new(settings: DebugViewSettings) -> DebugView {
+        DebugView { settings }
+    }
+
+    /// Draws the overlay in the window's upper left corner.
+    pub fn draw<G: Graphics, C>(
+        &self,
+        controller: &DebugController,
+        gameboard: &Gameboard,
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::Rectangle;
+
+        let settings = &self.settings;
+        let lines = [
+            format!("FPS: {}", controller.fps()),
+            format!(
+                "Events: {} input, {} update, {} render",
+                controller.input_events, controller.update_events, controller.render_events
+            ),
+            format!(
+                "Last input: {}",
+                controller.last_input.as_deref().unwrap_or("none")
+            ),
+            format!("Seed: {}", gameboard.seed),
+            format!(
+                "Generated in {:.1} ms",
+                gameboard.generate_time.as_secs_f64() * 1000.0
+            ),
+        ];
+        let [x, y] = settings.position;
+        Rectangle::new(settings.background_color).draw(
+            [
+                x,
+                y,
+                320.0,
+                (lines.len() as f64 + 0.5) * settings.line_height,
+            ],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+        for (row, line) in lines.iter().enumerate() {
+            draw_text(
+                line,
+                settings.font_size,
+                settings.text_color,
+                [x + 4.0, y + (row + 1) as f64 * settings.line_height],
+                glyphs,
+                c,
+                g,
+            );
+        }
+    }
+}
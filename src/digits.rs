@@ -0,0 +1,63 @@
+use crate::grid::{digit_char, Grid};
+use crate::SIZE;
+
+/// Reads a puzzle written as digits, with `0` or `.` for empty cells.
+///
+/// Any other characters, such as line breaks, spaces, and the `|`, `-`, and
+/// `+` of a drawn grid, are skipped, so both a single 81-character line and
+/// a 9-line grid are accepted.
+pub fn parse(text: &str) -> Result<Grid, String> {
+    let vals: Vec<u8> = text
+        .chars()
+        .filter_map(|ch| match ch {
+            '.' => Some(0),
+            _ => ch.to_digit(10).map(|val| val as u8),
+        })
+        .collect();
+    if vals.len() != SIZE * SIZE {
+        return Err(format!(
+            "Expected {} cells but found {}",
+            SIZE * SIZE,
+            vals.len()
+        ));
+    }
+    let mut grid = Grid::new(SIZE);
+    for (k, &val) in vals.iter().enumerate() {
+        grid[k / SIZE][k % SIZE] = val;
+    }
+    Ok(grid)
+}
+
+/// Writes a grid as a single line of a character per cell, 81 for the
+/// classic size, with `.` for empty cells.
+pub fn to_line(grid: &Grid) -> String {
+    grid.iter()
+        .flatten()
+        .map(|&val| match val {
+            0 => '.',
+            _ => digit_char(val),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: &str =
+        "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+
+    #[test]
+    fn reads_lines_and_drawn_grids() {
+        let grid = parse(LINE).unwrap();
+        assert_eq!(grid[0][..], [5, 3, 0, 0, 7, 0, 0, 0, 0]);
+        assert_eq!(to_line(&grid), LINE);
+        let drawn: String = LINE
+            .as_bytes()
+            .chunks(SIZE)
+            .map(|row| format!("| {} |\n", String::from_utf8_lossy(row)))
+            .collect();
+        assert_eq!(parse(&drawn).unwrap(), grid);
+        assert!(parse(&LINE[1..]).unwrap_err().contains("found 80"));
+    }
+}
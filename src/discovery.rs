@@ -0,0 +1,495 @@
+use rand::Rng;
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// The DNS-SD service games are advertised as over multicast DNS.
+const SERVICE: &str = "_sudoku._tcp.local";
+
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Set on the class of records only this host answers for, so caches drop
+/// older copies.
+const CACHE_FLUSH: u16 = 0x8000;
+
+/// Seconds answers may be kept, and kept to ten for queriers that are not
+/// mDNS responders themselves, as RFC 6762 asks.
+const TTL: u32 = 120;
+const LEGACY_TTL: u32 = 10;
+
+/// How often the network is asked again for games.
+const QUERY_INTERVAL: Duration = Duration::from_secs(3);
+
+/// How long a game stays listed once it stops answering.
+const GAME_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Letters of join codes, leaving out those mistaken for digits.
+const CODE_LETTERS: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ";
+pub const CODE_LEN: usize = 4;
+
+/// A game found on the local network.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FoundGame {
+    /// The host's name.
+    pub name: String,
+    pub code: String,
+    pub race: bool,
+    pub address: SocketAddr,
+    seen: Instant,
+}
+
+/// Picks a join code for a game.
+fn new_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..CODE_LEN)
+        .map(|_| CODE_LETTERS[rng.gen_range(0, CODE_LETTERS.len())] as char)
+        .collect()
+}
+
+fn put_u16(packet: &mut Vec<u8>, n: u16) {
+    packet.extend_from_slice(&n.to_be_bytes());
+}
+
+/// Writes a name as labels, uncompressed.
+fn put_name(packet: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        let label = &label.as_bytes()[..label.len().min(63)];
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label);
+    }
+    packet.push(0);
+}
+
+fn put_record(packet: &mut Vec<u8>, name: &str, kind: u16, class: u16, ttl: u32, data: &[u8]) {
+    put_name(packet, name);
+    put_u16(packet, kind);
+    put_u16(packet, class);
+    packet.extend_from_slice(&ttl.to_be_bytes());
+    put_u16(packet, data.len() as u16);
+    packet.extend_from_slice(data);
+}
+
+fn read_u16(packet: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([
+        *packet.get(pos)?,
+        *packet.get(pos + 1)?,
+    ]))
+}
+
+/// Reads a name, following compression pointers, returning it and where
+/// the data after it starts.
+fn read_name(packet: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    let mut jumps = 0;
+    loop {
+        let len = *packet.get(pos)? as usize;
+        if len & 0xc0 == 0xc0 {
+            // Bounded, as pointers may loop in a malformed packet.
+            jumps += 1;
+            if jumps > 16 {
+                return None;
+            }
+            end.get_or_insert(pos + 2);
+            pos = (len & 0x3f) << 8 | *packet.get(pos + 1)? as usize;
+        } else if len == 0 {
+            end.get_or_insert(pos + 1);
+            break;
+        } else {
+            let label = packet.get(pos + 1..pos + 1 + len)?;
+            labels.push(String::from_utf8_lossy(label).into_owned());
+            pos += 1 + len;
+        }
+    }
+    Some((labels.join("."), end?))
+}
+
+/// A record of a DNS message, with its data left in the message, as names
+/// within it may point elsewhere in the message.
+struct Record {
+    name: String,
+    kind: u16,
+    data: usize,
+    len: usize,
+}
+
+/// The parts of a DNS message read here.
+struct Message {
+    id: u16,
+    response: bool,
+    questions: Vec<(String, u16)>,
+    records: Vec<Record>,
+}
+
+impl Message {
+    fn parse(packet: &[u8]) -> Option<Message> {
+        let count = |k: usize| read_u16(packet, 4 + 2 * k).map(usize::from);
+        let mut message = Message {
+            id: read_u16(packet, 0)?,
+            response: read_u16(packet, 2)? & 0x8000 != 0,
+            questions: Vec::new(),
+            records: Vec::new(),
+        };
+        let mut pos = 12;
+        for _ in 0..count(0)? {
+            let (name, next) = read_name(packet, pos)?;
+            message.questions.push((name, read_u16(packet, next)?));
+            pos = next + 4;
+        }
+        // Answers, authorities, and additional records alike.
+        let records = count(1)? + count(2)? + count(3)?;
+        for _ in 0..records {
+            let (name, next) = read_name(packet, pos)?;
+            let kind = read_u16(packet, next)?;
+            let len = usize::from(read_u16(packet, next + 8)?);
+            let data = next + 10;
+            if data + len > packet.len() {
+                return None;
+            }
+            message.records.push(Record {
+                name,
+                kind,
+                data,
+                len,
+            });
+            pos = data + len;
+        }
+        Some(message)
+    }
+}
+
+/// The key and value pairs of a TXT record.
+fn read_txt(packet: &[u8], record: &Record) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+    let mut pos = record.data;
+    while pos < record.data + record.len {
+        let len = packet[pos] as usize;
+        let text = packet.get(pos + 1..(pos + 1 + len).min(record.data + record.len));
+        let text = String::from_utf8_lossy(text.unwrap_or_default());
+        if let Some((key, value)) = text.split_once('=') {
+            fields.insert(key.to_ascii_lowercase(), value.to_string());
+        }
+        pos += 1 + len;
+    }
+    fields
+}
+
+/// Binds the mDNS port alongside the system's own responder, which holds it
+/// too on most desktops.
+#[cfg(unix)]
+fn bind_shared(port: u16) -> io::Result<UdpSocket> {
+    use std::mem;
+    use std::os::unix::io::FromRawFd;
+
+    // Safety: the socket is owned by the UdpSocket as soon as it is made,
+    // which closes it on failure, and the option and address passed are
+    // of the sizes given.
+    unsafe {
+        let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = UdpSocket::from_raw_fd(fd);
+        let on: libc::c_int = 1;
+        for &option in &[libc::SO_REUSEADDR, libc::SO_REUSEPORT] {
+            let set = libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                option,
+                &on as *const libc::c_int as *const libc::c_void,
+                mem::size_of_val(&on) as libc::socklen_t,
+            );
+            if set != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        let mut address: libc::sockaddr_in = mem::zeroed();
+        #[cfg(any(
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "netbsd",
+            target_os = "dragonfly"
+        ))]
+        {
+            address.sin_len = mem::size_of::<libc::sockaddr_in>() as u8;
+        }
+        address.sin_family = libc::AF_INET as libc::sa_family_t;
+        address.sin_port = port.to_be();
+        let bound = libc::bind(
+            fd,
+            &address as *const libc::sockaddr_in as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+        );
+        if bound != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(socket)
+    }
+}
+
+#[cfg(not(unix))]
+fn bind_shared(port: u16) -> io::Result<UdpSocket> {
+    UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))
+}
+
+/// The address other machines reach this one at, as the one the route to
+/// the mDNS group leaves from.
+fn local_address() -> Option<Ipv4Addr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((MDNS_GROUP, MDNS_PORT)).ok()?;
+    match socket.local_addr().ok()?.ip() {
+        std::net::IpAddr::V4(address) if !address.is_unspecified() => Some(address),
+        _ => None,
+    }
+}
+
+/// What a host answers with.
+struct Advert {
+    name: String,
+    code: String,
+    race: bool,
+    port: u16,
+    address: Option<Ipv4Addr>,
+}
+
+impl Advert {
+    /// The records of the game: where to find its instance, its port, and
+    /// what it is. Queriers that are not on the mDNS port get a plain DNS
+    /// answer, with the query's id and question.
+    fn answer(&self, legacy: Option<u16>) -> Vec<u8> {
+        let instance = format!("{} {}.{}", self.name, self.code, SERVICE);
+        let target = format!("sudoku-{}.local", self.code.to_ascii_lowercase());
+        let (ttl, unique) = match legacy {
+            Some(_) => (LEGACY_TTL, CLASS_IN),
+            None => (TTL, CLASS_IN | CACHE_FLUSH),
+        };
+        let mut packet = Vec::new();
+        put_u16(&mut packet, legacy.unwrap_or(0));
+        // A response, with authority.
+        put_u16(&mut packet, 0x8400);
+        put_u16(&mut packet, legacy.map_or(0, |_| 1));
+        put_u16(&mut packet, 3 + self.address.map_or(0, |_| 1));
+        put_u16(&mut packet, 0);
+        put_u16(&mut packet, 0);
+        if legacy.is_some() {
+            put_name(&mut packet, SERVICE);
+            put_u16(&mut packet, TYPE_PTR);
+            put_u16(&mut packet, CLASS_IN);
+        }
+
+        let mut data = Vec::new();
+        put_name(&mut data, &instance);
+        put_record(&mut packet, SERVICE, TYPE_PTR, CLASS_IN, ttl, &data);
+
+        let mut data = vec![0; 4];
+        put_u16(&mut data, self.port);
+        put_name(&mut data, &target);
+        put_record(&mut packet, &instance, TYPE_SRV, unique, ttl, &data);
+
+        let mode = if self.race { "race" } else { "coop" };
+        let mut data = Vec::new();
+        for field in &[
+            format!("name={}", self.name),
+            format!("code={}", self.code),
+            format!("mode={}", mode),
+        ] {
+            let field = &field.as_bytes()[..field.len().min(255)];
+            data.push(field.len() as u8);
+            data.extend_from_slice(field);
+        }
+        put_record(&mut packet, &instance, TYPE_TXT, unique, ttl, &data);
+
+        if let Some(address) = self.address {
+            put_record(&mut packet, &target, TYPE_A, unique, ttl, &address.octets());
+        }
+        packet
+    }
+}
+
+/// Answers queries for the game until told to stop.
+fn answer_queries(socket: UdpSocket, advert: Advert, stop: Arc<AtomicBool>) {
+    let mut buffer = [0; 9000];
+    while !stop.load(Ordering::Relaxed) {
+        let (len, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(err)
+                if err.kind() == io::ErrorKind::WouldBlock
+                    || err.kind() == io::ErrorKind::TimedOut =>
+            {
+                continue
+            }
+            Err(err) => {
+                warn!(
+                    "Stopped answering for the game on the local network: {}",
+                    err
+                );
+                return;
+            }
+        };
+        let query = match Message::parse(&buffer[..len]) {
+            Some(query) if !query.response => query,
+            _ => continue,
+        };
+        let asked = query.questions.iter().any(|(name, kind)| {
+            name.eq_ignore_ascii_case(SERVICE) && (*kind == TYPE_PTR || *kind == TYPE_ANY)
+        });
+        if !asked {
+            continue;
+        }
+        let (answer, to) = if from.port() == MDNS_PORT {
+            (
+                advert.answer(None),
+                SocketAddr::from((MDNS_GROUP, MDNS_PORT)),
+            )
+        } else {
+            (advert.answer(Some(query.id)), from)
+        };
+        if let Err(err) = socket.send_to(&answer, to) {
+            warn!(
+                "Could not answer for the game on the local network: {}",
+                err
+            );
+        }
+    }
+}
+
+/// A hosted game advertised on the local network over multicast DNS,
+/// until dropped.
+pub struct Advertiser {
+    /// The code players can join the game by.
+    pub code: String,
+    stop: Arc<AtomicBool>,
+}
+
+impl Advertiser {
+    pub fn start(name: &str, port: u16, race: bool) -> io::Result<Advertiser> {
+        let socket = bind_shared(MDNS_PORT)?;
+        socket.join_multicast_v4(&MDNS_GROUP, &Ipv4Addr::UNSPECIFIED)?;
+        socket.set_multicast_ttl_v4(255)?;
+        // Wakes now and then to see whether to stop.
+        socket.set_read_timeout(Some(Duration::from_millis(500)))?;
+        let code = new_code();
+        let advert = Advert {
+            name: name.to_string(),
+            code: code.clone(),
+            race,
+            port,
+            address: local_address(),
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        thread::spawn(move || answer_queries(socket, advert, stopped));
+        Ok(Advertiser { code, stop })
+    }
+}
+
+impl Drop for Advertiser {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Looks for games on the local network, asking over multicast DNS from a
+/// port of its own, so hosts answer it directly.
+pub struct Browser {
+    socket: UdpSocket,
+    asked: Option<Instant>,
+    /// Games that answered lately, in the order found.
+    pub games: Vec<FoundGame>,
+}
+
+impl Browser {
+    pub fn new() -> io::Result<Browser> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_nonblocking(true)?;
+        Ok(Browser {
+            socket,
+            asked: None,
+            games: Vec::new(),
+        })
+    }
+
+    /// Asks for games every few seconds and takes in the answers, without
+    /// waiting for any.
+    pub fn poll(&mut self) {
+        if self
+            .asked
+            .is_none_or(|asked| asked.elapsed() >= QUERY_INTERVAL)
+        {
+            self.asked = Some(Instant::now());
+            let mut query = Vec::new();
+            put_u16(&mut query, rand::thread_rng().gen());
+            for &n in &[0, 1, 0, 0, 0] {
+                put_u16(&mut query, n);
+            }
+            put_name(&mut query, SERVICE);
+            put_u16(&mut query, TYPE_PTR);
+            put_u16(&mut query, CLASS_IN);
+            if let Err(err) = self.socket.send_to(&query, (MDNS_GROUP, MDNS_PORT)) {
+                warn!("Could not look for games on the local network: {}", err);
+            }
+        }
+        let mut buffer = [0; 9000];
+        while let Ok((len, from)) = self.socket.recv_from(&mut buffer) {
+            self.take(&buffer[..len], from);
+        }
+        self.games.retain(|game| game.seen.elapsed() < GAME_TIMEOUT);
+    }
+
+    /// Takes in the games an answer names, found at the address it came from.
+    fn take(&mut self, packet: &[u8], from: SocketAddr) {
+        let message = match Message::parse(packet) {
+            Some(message) if message.response => message,
+            _ => return,
+        };
+        let suffix = format!(".{}", SERVICE);
+        let ours = |record: &Record| {
+            record.name.len() > suffix.len()
+                && record.name[record.name.len() - suffix.len()..].eq_ignore_ascii_case(&suffix)
+        };
+        for txt in message
+            .records
+            .iter()
+            .filter(|record| record.kind == TYPE_TXT && ours(record))
+        {
+            let port = message
+                .records
+                .iter()
+                .find(|record| record.kind == TYPE_SRV && record.name == txt.name)
+                .and_then(|srv| read_u16(packet, srv.data + 4));
+            let fields = read_txt(packet, txt);
+            let (port, code) = match (port, fields.get("code")) {
+                (Some(port), Some(code)) => (port, code.to_ascii_uppercase()),
+                _ => continue,
+            };
+            let game = FoundGame {
+                name: fields.get("name").cloned().unwrap_or_default(),
+                code,
+                race: fields.get("mode").is_some_and(|mode| mode == "race"),
+                address: SocketAddr::new(from.ip(), port),
+                seen: Instant::now(),
+            };
+            match self.games.iter_mut().find(|each| each.code == game.code) {
+                Some(found) => *found = game,
+                None => {
+                    debug!("Found game {} at {}", game.code, game.address);
+                    self.games.push(game);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,414 @@
+use crate::grid::Grid;
+use crate::puzzle::SaveFormat;
+use crate::rules::RuleSet;
+use crate::solver;
+use crate::variant::{
+    Cage, Dot, DotKind, Parity, ParityMark, Thermo, Variant, VariantKind, Whisper,
+};
+use crate::{key_digit, Difficulty, Gameboard, SIZE};
+use piston::input::keyboard::ModifierKey;
+use piston::input::GenericEvent;
+
+/// Guesses the live check may take before it stops short of an answer, so
+/// typing stays quick on puzzles far from finished.
+const CHECK_BUDGET: usize = 5_000;
+
+/// What the live check found out about the puzzle being set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Check {
+    NoSolution,
+    /// Exactly one solution, rated by its number of givens.
+    Unique(Difficulty),
+    Several,
+    /// The solver gave up before settling it.
+    Unsettled,
+}
+
+impl Check {
+    pub fn describe(self) -> String {
+        match self {
+            Check::NoSolution => "No solution".to_string(),
+            Check::Unique(difficulty) => format!("Unique solution - {}", difficulty.name()),
+            Check::Several => "More than one solution".to_string(),
+            Check::Unsettled => "Too open to check yet".to_string(),
+        }
+    }
+}
+
+/// What the editor asks of the rest of the app.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditorAction {
+    /// Plays the puzzle, which has a unique solution.
+    Play,
+    /// Writes the puzzle to a file.
+    Save(SaveFormat),
+    /// Copies a link opening the puzzle in f-puzzles.
+    CopyLink,
+}
+
+/// Whether two cells touch, along an edge or at a corner.
+fn touching(a: [usize; 2], b: [usize; 2]) -> bool {
+    a != b && a[0].abs_diff(b[0]) <= 1 && a[1].abs_diff(b[1]) <= 1
+}
+
+/// Whether two cells share an edge.
+fn orthogonal(a: [usize; 2], b: [usize; 2]) -> bool {
+    a[0].abs_diff(b[0]) + a[1].abs_diff(b[1]) == 1
+}
+
+/// Drops the variants left without any clues, which add no rules.
+fn prune(variants: &mut Vec<Variant>) {
+    variants.retain(|variant| match variant {
+        Variant::Killer(cages) => !cages.is_empty(),
+        Variant::Thermo(thermos) => !thermos.is_empty(),
+        Variant::Whispers(whispers) => !whispers.is_empty(),
+        Variant::EvenOdd(marks) => !marks.is_empty(),
+        Variant::Kropki(kropki) => !kropki.dots.is_empty(),
+        _ => true,
+    });
+}
+
+/// Sets a puzzle by hand, such as one from a newspaper: digits typed into
+/// cells become givens, and cages, lines, dots, and marks are drawn through
+/// picked cells. Each change checks again how many solutions it has.
+pub struct EditorController {
+    /// The puzzle being set, with its solution once it has exactly one.
+    pub gameboard: Gameboard,
+    pub selected_cell: Option<[usize; 2]>,
+    /// Cells picked, in order, for the next cage, line, or dot.
+    pub picked: Vec<[usize; 2]>,
+    /// The sum being typed for a cage of the picked cells.
+    pub cage_sum: Option<String>,
+    pub check: Check,
+    /// What was last done, or why it could not be.
+    pub message: Option<String>,
+    cursor_pos: [f64; 2],
+}
+
+impl Default for EditorController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditorController {
+    /// Starts on an empty classic board.
+    pub fn new() -> EditorController {
+        let mut editor = EditorController {
+            gameboard: Gameboard::from_puzzle(Grid::new(SIZE), Grid::new(SIZE)),
+            selected_cell: Some([0, 0]),
+            picked: Vec::new(),
+            cage_sum: None,
+            check: Check::Unsettled,
+            message: None,
+            cursor_pos: [0.0; 2],
+        };
+        editor.recheck();
+        editor
+    }
+
+    /// A fresh board to play the puzzle set on.
+    pub fn play(&self) -> Gameboard {
+        let board = &self.gameboard;
+        let mut gameboard = Gameboard::from_puzzle(board.cells.clone(), board.solution.clone());
+        gameboard.rules = board.rules.clone();
+        gameboard
+    }
+
+    /// Takes in the latest givens and rules, and counts the solutions.
+    fn recheck(&mut self) {
+        let board = &mut self.gameboard;
+        board.givens = board.cells.map(|&val| val != 0);
+        let clues = board
+            .givens
+            .iter()
+            .flatten()
+            .filter(|&&given| given)
+            .count();
+        self.check = match solver::count_solutions(&board.cells, &board.rules, 2, CHECK_BUDGET) {
+            Some(0) => Check::NoSolution,
+            Some(1) => Check::Unique(Difficulty::for_clues(clues)),
+            Some(_) => Check::Several,
+            None => Check::Unsettled,
+        };
+        board.solution = match self.check {
+            Check::Unique(difficulty) => {
+                board.difficulty = difficulty;
+                solver::unique_solution(&board.cells, &board.rules)
+                    .unwrap_or_else(|| Grid::new(SIZE))
+            }
+            _ => Grid::new(SIZE),
+        };
+    }
+
+    /// Handles events on a board drawn at `pos` with side `size`, returning
+    /// anything the app should do.
+    pub fn event<E: GenericEvent>(
+        &mut self,
+        pos: [f64; 2],
+        size: f64,
+        modifiers: ModifierKey,
+        e: &E,
+    ) -> Option<EditorAction> {
+        use piston::input::{Button, Key, MouseButton};
+
+        if let Some(cursor) = e.mouse_cursor_args() {
+            self.cursor_pos = cursor;
+        }
+        let key = match e.press_args() {
+            Some(Button::Mouse(MouseButton::Left)) => {
+                let cell = size / SIZE as f64;
+                let [x, y] = [self.cursor_pos[0] - pos[0], self.cursor_pos[1] - pos[1]];
+                if (0.0..size).contains(&x) && (0.0..size).contains(&y) {
+                    let ind = [(x / cell) as usize, (y / cell) as usize];
+                    self.selected_cell = Some(ind);
+                    if modifiers.contains(ModifierKey::SHIFT) {
+                        self.pick(ind);
+                    }
+                }
+                return None;
+            }
+            Some(Button::Keyboard(key)) => key,
+            _ => return None,
+        };
+        if let Some(sum) = &mut self.cage_sum {
+            match key {
+                Key::Return => self.add_cage(),
+                Key::Backspace => {
+                    sum.pop();
+                }
+                Key::Escape => self.cage_sum = None,
+                _ => {
+                    if let Some(val) = char::from_u32(key.code() as u32)
+                        .and_then(|ch| ch.to_digit(10))
+                        .filter(|_| sum.len() < 2)
+                    {
+                        sum.push_str(&val.to_string());
+                    }
+                }
+            }
+            return None;
+        }
+        if modifiers.contains(ModifierKey::CTRL) {
+            return match key {
+                Key::S if modifiers.contains(ModifierKey::ALT) => {
+                    Some(EditorAction::Save(SaveFormat::Json))
+                }
+                Key::S if modifiers.contains(ModifierKey::SHIFT) => {
+                    Some(EditorAction::Save(SaveFormat::Csv))
+                }
+                Key::S => Some(EditorAction::Save(SaveFormat::Sdk)),
+                Key::U => Some(EditorAction::CopyLink),
+                _ => None,
+            };
+        }
+        let selected = self.selected_cell.unwrap_or([0, 0]);
+        let step = |delta: [usize; 2]| {
+            [
+                (selected[0] + delta[0]) % SIZE,
+                (selected[1] + delta[1]) % SIZE,
+            ]
+        };
+        if let Some(val) = key_digit(key, SIZE) {
+            self.gameboard.cells[selected[1]][selected[0]] = val;
+            self.recheck();
+            return None;
+        }
+        match key {
+            Key::Left => self.selected_cell = Some(step([SIZE - 1, 0])),
+            Key::Right => self.selected_cell = Some(step([1, 0])),
+            Key::Up => self.selected_cell = Some(step([0, SIZE - 1])),
+            Key::Down => self.selected_cell = Some(step([0, 1])),
+            Key::Backspace | Key::Delete | Key::D0 => {
+                self.gameboard.cells[selected[1]][selected[0]] = 0;
+                self.recheck();
+            }
+            Key::Space => self.pick(selected),
+            Key::Escape => self.picked.clear(),
+            Key::K => self.start_cage(),
+            Key::T => self.add_line(VariantKind::Thermo),
+            Key::W => self.add_line(VariantKind::Whispers),
+            Key::B if modifiers.contains(ModifierKey::SHIFT) => self.add_dot(DotKind::White),
+            Key::B => self.add_dot(DotKind::Black),
+            Key::E => self.toggle_mark(selected, Parity::Even),
+            Key::O => self.toggle_mark(selected, Parity::Odd),
+            Key::R => self.remove_at(selected),
+            Key::X => self.toggle_variant(VariantKind::Diagonal),
+            Key::I => self.toggle_variant(VariantKind::Windoku),
+            Key::G => self.generate(),
+            Key::N | Key::A | Key::C => {
+                let constraints = &mut self.gameboard.rules.constraints;
+                let rule = match key {
+                    Key::N => &mut constraints.anti_knight,
+                    Key::A => &mut constraints.anti_king,
+                    _ => &mut constraints.non_consecutive,
+                };
+                *rule = !*rule;
+                self.recheck();
+            }
+            Key::Return => match self.check {
+                Check::Unique(_) => return Some(EditorAction::Play),
+                check => self.message = Some(format!("Cannot play: {}", check.describe())),
+            },
+            _ => {}
+        }
+        None
+    }
+
+    /// Adds or removes a variant without clues, such as X-Sudoku.
+    fn toggle_variant(&mut self, kind: VariantKind) {
+        let rules = &mut self.gameboard.rules;
+        if rules.get(kind).is_some() {
+            rules.variants.retain(|variant| variant.kind() != kind);
+        } else {
+            rules.entry(kind);
+        }
+        self.recheck();
+    }
+
+    /// Replaces the puzzle with one generated under the same kinds of
+    /// rules, with new cages, lines, dots, and marks to fit it.
+    fn generate(&mut self) {
+        let rules = &self.gameboard.rules;
+        let rules = rules
+            .variants
+            .iter()
+            .map(|variant| Variant::new(variant.kind()))
+            .fold(RuleSet::default(), RuleSet::with)
+            .with_constraints(rules.constraints);
+        let mut generated = Gameboard::new();
+        generated.generate_rules(Difficulty::Medium, rules.clone(), SIZE);
+        if generated.rules.is_classic() && !rules.is_classic() {
+            self.message = Some("No puzzle could be found for these rules".to_string());
+            return;
+        }
+        self.gameboard = Gameboard::from_puzzle(generated.puzzle(), Grid::new(SIZE));
+        self.gameboard.rules = generated.rules;
+        self.picked.clear();
+        self.message = None;
+        self.recheck();
+    }
+
+    /// Adds a cell to the picked ones, or takes it back out.
+    fn pick(&mut self, ind: [usize; 2]) {
+        match self.picked.iter().position(|&cell| cell == ind) {
+            Some(k) => {
+                self.picked.remove(k);
+            }
+            None => self.picked.push(ind),
+        }
+    }
+
+    /// Asks for the sum of a cage of the picked cells, if they can make one.
+    fn start_cage(&mut self) {
+        let overlaps = match self.gameboard.rules.get(VariantKind::Killer) {
+            Some(Variant::Killer(cages)) => cages
+                .iter()
+                .any(|cage| cage.cells.iter().any(|cell| self.picked.contains(cell))),
+            _ => false,
+        };
+        self.message = Some(
+            if self.picked.is_empty() {
+                "Pick the cage's cells first"
+            } else if self.picked.len() > SIZE {
+                "Cages hold at most 9 cells"
+            } else if overlaps {
+                "Cages cannot overlap"
+            } else {
+                self.cage_sum = Some(String::new());
+                "Type the cage's sum, then press Enter"
+            }
+            .to_string(),
+        );
+    }
+
+    /// Adds a cage of the picked cells with the sum typed.
+    fn add_cage(&mut self) {
+        let sum = self.cage_sum.take().and_then(|sum| sum.parse().ok());
+        let sum = match sum {
+            Some(sum) if sum > 0 => sum,
+            _ => {
+                self.message = Some("Cages need a sum".to_string());
+                return;
+            }
+        };
+        if let Variant::Killer(cages) = self.gameboard.rules.entry(VariantKind::Killer) {
+            cages.push(Cage {
+                sum,
+                cells: std::mem::take(&mut self.picked),
+            });
+        }
+        self.message = None;
+        self.recheck();
+    }
+
+    /// Adds a thermometer, from its bulb, or a whispers line through the
+    /// picked cells, in the order they were picked.
+    fn add_line(&mut self, kind: VariantKind) {
+        if self.picked.len() < 2 || !self.picked.windows(2).all(|w| touching(w[0], w[1])) {
+            self.message = Some("Pick touching cells along the line first".to_string());
+            return;
+        }
+        let cells = std::mem::take(&mut self.picked);
+        match self.gameboard.rules.entry(kind) {
+            Variant::Thermo(thermos) => thermos.push(Thermo { cells }),
+            Variant::Whispers(whispers) => whispers.push(Whisper { cells }),
+            _ => {}
+        }
+        self.message = None;
+        self.recheck();
+    }
+
+    /// Adds a dot between the two picked cells, replacing any already there.
+    fn add_dot(&mut self, kind: DotKind) {
+        let cells = match self.picked[..] {
+            [a, b] if orthogonal(a, b) => [a, b],
+            _ => {
+                self.message = Some("Pick two neighboring cells first".to_string());
+                return;
+            }
+        };
+        if let Variant::Kropki(kropki) = self.gameboard.rules.entry(VariantKind::Kropki) {
+            kropki
+                .dots
+                .retain(|dot| !dot.cells.contains(&cells[0]) || !dot.cells.contains(&cells[1]));
+            kropki.dots.push(Dot { kind, cells });
+        }
+        self.picked.clear();
+        self.message = None;
+        self.recheck();
+    }
+
+    /// Marks a cell even or odd, or clears a mark of the same parity.
+    fn toggle_mark(&mut self, cell: [usize; 2], parity: Parity) {
+        if let Variant::EvenOdd(marks) = self.gameboard.rules.entry(VariantKind::EvenOdd) {
+            let had = marks
+                .iter()
+                .any(|mark| mark == &ParityMark { parity, cell });
+            marks.retain(|mark| mark.cell != cell);
+            if !had {
+                marks.push(ParityMark { parity, cell });
+            }
+        }
+        prune(&mut self.gameboard.rules.variants);
+        self.recheck();
+    }
+
+    /// Removes every cage, line, dot, and mark through a cell.
+    fn remove_at(&mut self, cell: [usize; 2]) {
+        for variant in &mut self.gameboard.rules.variants {
+            match variant {
+                Variant::Killer(cages) => cages.retain(|cage| !cage.cells.contains(&cell)),
+                Variant::Thermo(thermos) => thermos.retain(|thermo| !thermo.cells.contains(&cell)),
+                Variant::Whispers(whispers) => {
+                    whispers.retain(|whisper| !whisper.cells.contains(&cell))
+                }
+                Variant::EvenOdd(marks) => marks.retain(|mark| mark.cell != cell),
+                Variant::Kropki(kropki) => kropki.dots.retain(|dot| !dot.cells.contains(&cell)),
+                _ => {}
+            }
+        }
+        prune(&mut self.gameboard.rules.variants);
+        self.recheck();
+    }
+}
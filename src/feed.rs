@@ -0,0 +1,258 @@
+use crate::fpuzzles::FPuzzle;
+use crate::grid::Grid;
+use crate::puzzle;
+use crate::rules::RuleSet;
+use crate::variant::{Variant, VariantKind};
+use crate::Difficulty;
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tracing::warn;
+
+/// Where the last puzzle feed fetched is kept for offline play, within a
+/// profile.
+pub const FEED_CACHE_FILE: &str = "feed.txt";
+
+/// Seconds to wait for a feed before falling back to the cached one.
+const FETCH_TIMEOUT: &str = "10";
+
+/// Commands that print a URL's body, tried in order.
+const FETCH_COMMANDS: [&[&str]; 2] = [
+    &[
+        "curl",
+        "--fail",
+        "--silent",
+        "--show-error",
+        "--location",
+        "--max-time",
+        FETCH_TIMEOUT,
+    ],
+    &[
+        "wget",
+        "--quiet",
+        "--timeout",
+        FETCH_TIMEOUT,
+        "--output-document",
+        "-",
+    ],
+];
+
+/// Variants a feed can name for a puzzle line, having no clues to give.
+/// Others need the puzzle as an f-puzzles file.
+const NAMED_VARIANTS: [VariantKind; 2] = [VariantKind::Diagonal, VariantKind::Windoku];
+
+/// A puzzle from a feed, with what the feed says about it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeedPuzzle {
+    pub cells: Grid,
+    /// The feed's rating, if it gives one.
+    pub difficulty: Option<Difficulty>,
+    pub rules: RuleSet,
+    pub title: Option<String>,
+}
+
+/// Fetches a feed through the platform's command line tools, so no HTTP or
+/// TLS library is needed.
+pub fn fetch(url: &str) -> io::Result<String> {
+    for command in FETCH_COMMANDS.iter() {
+        let output = match Command::new(command[0])
+            .args(&command[1..])
+            .arg(url)
+            .stdin(Stdio::null())
+            .output()
+        {
+            Ok(output) => output,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        if !output.status.success() {
+            let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Err(io::Error::other(message));
+        }
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "neither curl nor wget was found",
+    ))
+}
+
+/// Fetches a feed and caches it, or reads the cached copy when the feed
+/// cannot be reached. Returns the feed's text and whether it came from the
+/// cache.
+pub fn fetch_cached<P: AsRef<Path>>(url: &str, cache: P) -> Result<(String, bool), String> {
+    match fetch(url) {
+        Ok(text) => {
+            // A feed that cannot be read is not cached over a good one.
+            if parse(&text).is_ok() {
+                if let Err(err) = fs::write(&cache, &text) {
+                    warn!("Could not cache puzzle feed: {}", err);
+                }
+            }
+            Ok((text, false))
+        }
+        Err(err) => {
+            warn!("Could not fetch puzzle feed {}: {}", url, err);
+            match fs::read_to_string(cache) {
+                Ok(text) => Ok((text, true)),
+                Err(_) => Err(err.to_string()),
+            }
+        }
+    }
+}
+
+/// Reads the puzzles of a feed, in order, skipping any that cannot be read.
+///
+/// A feed is either text with a puzzle of digits on each line, followed by
+/// a difficulty or variant names, or JSON. JSON feeds are a list of
+/// puzzles, or an object with a `puzzles` list, or a single puzzle; each
+/// puzzle is a string of digits, or an object with a `puzzle` string and
+/// optional `difficulty`, `rules`, and `title`, or with an `fpuzzles`
+/// object instead of `puzzle` for variants with clues.
+pub fn parse(text: &str) -> Result<Vec<FeedPuzzle>, String> {
+    let trimmed = text.trim_start();
+    let entries: Vec<Result<FeedPuzzle, String>> =
+        if trimmed.starts_with('{') || trimmed.starts_with('[') {
+            let mut root: Value = serde_json::from_str(trimmed).map_err(|err| err.to_string())?;
+            let list = match root.get_mut("puzzles").map(Value::take).unwrap_or(root) {
+                Value::Array(list) => list,
+                single => vec![single],
+            };
+            list.into_iter().map(from_json).collect()
+        } else {
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(from_line)
+                .collect()
+        };
+    let mut puzzles = Vec::new();
+    for (k, entry) in entries.into_iter().enumerate() {
+        match entry {
+            Ok(found) => puzzles.push(found),
+            Err(err) => warn!("Skipping puzzle {} of feed: {}", k + 1, err),
+        }
+    }
+    if puzzles.is_empty() {
+        return Err("The feed has no puzzles".to_string());
+    }
+    Ok(puzzles)
+}
+
+/// Reads a puzzle line: digits, then a difficulty or variant names, in any
+/// order, separated by spaces.
+fn from_line(line: &str) -> Result<FeedPuzzle, String> {
+    let mut words = line.split_whitespace();
+    let cells = puzzle::parse(words.next().unwrap_or(""))?;
+    let mut found = FeedPuzzle {
+        cells,
+        difficulty: None,
+        rules: RuleSet::default(),
+        title: None,
+    };
+    for word in words {
+        match Difficulty::from_name(word) {
+            Some(difficulty) => found.difficulty = Some(difficulty),
+            None => found.rules = with_variants(found.rules, word.split(','))?,
+        }
+    }
+    Ok(found)
+}
+
+/// A puzzle of a JSON feed.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Line(String),
+    Puzzle {
+        puzzle: Option<String>,
+        /// An f-puzzles file, for variants with clues.
+        fpuzzles: Option<Value>,
+        difficulty: Option<String>,
+        rules: Option<Names>,
+        title: Option<String>,
+    },
+}
+
+/// Variant names, as a list or separated by commas.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Names {
+    List(Vec<String>),
+    Text(String),
+}
+
+/// Reads a puzzle of a JSON feed.
+fn from_json(value: Value) -> Result<FeedPuzzle, String> {
+    let entry = serde_json::from_value(value).map_err(|err| err.to_string())?;
+    let (puzzle, fpuzzles, difficulty, rules, title) = match entry {
+        Entry::Line(text) => return from_line(&text),
+        Entry::Puzzle {
+            puzzle,
+            fpuzzles,
+            difficulty,
+            rules,
+            title,
+        } => (puzzle, fpuzzles, difficulty, rules, title),
+    };
+    let mut found = match (puzzle, fpuzzles) {
+        (_, Some(fpuzzles)) => {
+            let fpuzzle = FPuzzle::from_value(fpuzzles)?;
+            FeedPuzzle {
+                cells: fpuzzle.cells,
+                difficulty: None,
+                rules: fpuzzle.rules,
+                title: fpuzzle.title,
+            }
+        }
+        (Some(text), None) => FeedPuzzle {
+            cells: puzzle::parse(&text)?,
+            difficulty: None,
+            rules: RuleSet::default(),
+            title: None,
+        },
+        (None, None) => return Err("no puzzle or fpuzzles".to_string()),
+    };
+    if let Some(text) = difficulty {
+        let difficulty = Difficulty::from_name(&text);
+        found.difficulty = Some(difficulty.ok_or_else(|| format!("Unknown difficulty: {}", text))?);
+    }
+    match rules {
+        Some(Names::List(names)) => {
+            found.rules = with_variants(found.rules, names.iter().map(String::as_str))?
+        }
+        Some(Names::Text(names)) => found.rules = with_variants(found.rules, names.split(','))?,
+        None => {}
+    }
+    if title.is_some() {
+        found.title = title;
+    }
+    Ok(found)
+}
+
+/// Adds variants by name to a set of rules. Classic is allowed and adds
+/// nothing, so feeds can name it.
+fn with_variants<'a, I: Iterator<Item = &'a str>>(
+    mut rules: RuleSet,
+    names: I,
+) -> Result<RuleSet, String> {
+    for name in names.map(str::trim).filter(|name| !name.is_empty()) {
+        match VariantKind::from_name(name) {
+            Some(VariantKind::Classic) => {}
+            Some(kind) if rules.get(kind).is_some() => {}
+            Some(kind) if NAMED_VARIANTS.contains(&kind) => rules = rules.with(Variant::new(kind)),
+            Some(kind) => {
+                return Err(format!(
+                    "{} puzzles need their clues, as an f-puzzles file",
+                    kind.name()
+                ))
+            }
+            None => return Err(format!("Unknown variant or difficulty: {}", name)),
+        }
+    }
+    rules.check()?;
+    Ok(rules)
+}
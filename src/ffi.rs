@@ -0,0 +1,253 @@
+//! A C interface to the solver and generator, declared in
+//! `include/sudoku.h`.
+//!
+//! Puzzles cross it as NUL-terminated strings of 81 digits with `0` or `.`
+//! for empty cells, the same lines `sudoku solve` reads and writes.
+
+use crate::digits::{parse, to_line};
+use crate::grid::Grid;
+use crate::rules::RuleSet;
+use crate::technique::Registry;
+use crate::{solver, Difficulty, Gameboard, SIZE};
+use std::convert::TryFrom;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, UnwindSafe};
+use std::ptr;
+
+/// The puzzle has exactly one solution, or was generated.
+pub const SUDOKU_OK: c_int = 0;
+/// No grid fills the puzzle.
+pub const SUDOKU_NO_SOLUTION: c_int = 1;
+/// More than one grid fills the puzzle.
+pub const SUDOKU_MULTIPLE_SOLUTIONS: c_int = 2;
+/// An argument was null or not a puzzle, or the engine failed.
+pub const SUDOKU_INVALID: c_int = -1;
+
+/// Bytes a buffer written to must hold: a line of 81 cells and its NUL.
+pub const SUDOKU_LINE_BYTES: usize = SIZE * SIZE + 1;
+
+/// Runs a call, turning a panic into `SUDOKU_INVALID` rather than letting
+/// it unwind into the caller.
+fn guard<F: FnOnce() -> c_int + UnwindSafe>(f: F) -> c_int {
+    panic::catch_unwind(f).unwrap_or(SUDOKU_INVALID)
+}
+
+/// Reads the puzzle a C string holds.
+///
+/// # Safety
+///
+/// `puzzle` must be null or point to a NUL-terminated string.
+unsafe fn read(puzzle: *const c_char) -> Option<Grid> {
+    if puzzle.is_null() {
+        return None;
+    }
+    parse(CStr::from_ptr(puzzle).to_str().ok()?).ok()
+}
+
+/// Copies a grid as a line, with its NUL, into a caller's buffer.
+///
+/// # Safety
+///
+/// `out` must point to at least `SUDOKU_LINE_BYTES` writable bytes.
+unsafe fn write(grid: &Grid, out: *mut c_char) {
+    let line = to_line(grid);
+    ptr::copy_nonoverlapping(line.as_ptr() as *const c_char, out, line.len());
+    *out.add(line.len()) = 0;
+}
+
+/// The puzzle's solution, or the status saying why it has none.
+fn solve(cells: &Grid) -> Result<Grid, c_int> {
+    let rules = RuleSet::default();
+    match solver::unique_solution(cells, &rules) {
+        Some(solution) => Ok(solution),
+        None => match solver::count_solutions(cells, &rules, 2, usize::MAX) {
+            Some(0) => Err(SUDOKU_NO_SOLUTION),
+            _ => Err(SUDOKU_MULTIPLE_SOLUTIONS),
+        },
+    }
+}
+
+/// Reads a puzzle written as digits, in a line or a drawn grid, writing it
+/// to `line` as a line of 81 cells with `.` for empty cells.
+///
+/// Returns `SUDOKU_OK`, or `SUDOKU_INVALID` when the text does not hold 81
+/// cells.
+///
+/// # Safety
+///
+/// `text` must be a NUL-terminated string, and `line` must point to at
+/// least `SUDOKU_LINE_BYTES` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sudoku_parse(text: *const c_char, line: *mut c_char) -> c_int {
+    guard(|| match read(text) {
+        Some(cells) if !line.is_null() => {
+            write(&cells, line);
+            SUDOKU_OK
+        }
+        _ => SUDOKU_INVALID,
+    })
+}
+
+/// Solves a classic puzzle, writing its solution to `solution` when it has
+/// exactly one.
+///
+/// Returns `SUDOKU_OK`, `SUDOKU_NO_SOLUTION`, `SUDOKU_MULTIPLE_SOLUTIONS`,
+/// or `SUDOKU_INVALID`.
+///
+/// # Safety
+///
+/// `puzzle` must be a NUL-terminated string, and `solution` must point to
+/// at least `SUDOKU_LINE_BYTES` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sudoku_solve(puzzle: *const c_char, solution: *mut c_char) -> c_int {
+    guard(|| {
+        let cells = match read(puzzle) {
+            Some(cells) if !solution.is_null() => cells,
+            _ => return SUDOKU_INVALID,
+        };
+        match solve(&cells) {
+            Ok(found) => {
+                write(&found, solution);
+                SUDOKU_OK
+            }
+            Err(status) => status,
+        }
+    })
+}
+
+/// Generates a classic puzzle with a unique solution into `puzzle`, at a
+/// difficulty from 0 for easy to 3 for expert. The same seed always gives
+/// the same puzzle.
+///
+/// Returns `SUDOKU_OK`, or `SUDOKU_INVALID` for an unknown difficulty or a
+/// null buffer.
+///
+/// # Safety
+///
+/// `puzzle` must point to at least `SUDOKU_LINE_BYTES` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sudoku_generate(
+    difficulty: c_int,
+    seed: u64,
+    puzzle: *mut c_char,
+) -> c_int {
+    guard(|| {
+        let difficulty = usize::try_from(difficulty)
+            .ok()
+            .and_then(|k| Difficulty::ALL.get(k));
+        let difficulty = match difficulty {
+            Some(&difficulty) if !puzzle.is_null() => difficulty,
+            _ => return SUDOKU_INVALID,
+        };
+        let mut gameboard = Gameboard::with_seed(seed);
+        gameboard.generate_rules(difficulty, RuleSet::default(), SIZE);
+        write(&gameboard.puzzle(), puzzle);
+        SUDOKU_OK
+    })
+}
+
+/// Rates a classic puzzle by the techniques that solve it, as `sudoku rate`
+/// does, writing its difficulty to `difficulty`, from 0 for easy to 3 for
+/// expert, when it has exactly one solution.
+///
+/// Returns `SUDOKU_OK`, `SUDOKU_NO_SOLUTION`, `SUDOKU_MULTIPLE_SOLUTIONS`,
+/// or `SUDOKU_INVALID`.
+///
+/// # Safety
+///
+/// `puzzle` must be a NUL-terminated string, and `difficulty` must point to
+/// a writable `int`.
+#[no_mangle]
+pub unsafe extern "C" fn sudoku_rate(puzzle: *const c_char, difficulty: *mut c_int) -> c_int {
+    guard(|| {
+        let cells = match read(puzzle) {
+            Some(cells) if !difficulty.is_null() => cells,
+            _ => return SUDOKU_INVALID,
+        };
+        if let Err(status) = solve(&cells) {
+            return status;
+        }
+        let rated = Registry::default().rate(&cells, &RuleSet::default());
+        let k = Difficulty::ALL.iter().position(|&each| each == rated);
+        *difficulty = k.map_or(SUDOKU_INVALID, |k| k as c_int);
+        SUDOKU_OK
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    fn rate(line: &str) -> (c_int, c_int) {
+        let puzzle = CString::new(line).unwrap();
+        let mut difficulty = -1;
+        let status = unsafe { sudoku_rate(puzzle.as_ptr(), &mut difficulty) };
+        (status, difficulty)
+    }
+
+    #[test]
+    fn rates_by_the_techniques_solving_a_puzzle() {
+        let naked =
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+        assert_eq!(rate(naked), (SUDOKU_OK, 0));
+        let hidden =
+            "000000010400000000020000000000050407008000300001090000300400200050100000000806000";
+        assert_eq!(rate(hidden), (SUDOKU_OK, 1));
+        let beyond =
+            "100000709040007200800000000070010060300000005060040020000000008005300070702000046";
+        assert_eq!(rate(beyond), (SUDOKU_OK, 3));
+    }
+
+    #[test]
+    fn parses_solves_and_generates_lines() {
+        let mut out = [0 as c_char; SUDOKU_LINE_BYTES];
+        let text = CString::new("53..7....\n6..195...\n.98....6.\n8...6...3\n4..8.3..1\n7...2...6\n.6....28.\n...419..5\n....8..79").unwrap();
+        assert_eq!(
+            unsafe { sudoku_parse(text.as_ptr(), out.as_mut_ptr()) },
+            SUDOKU_OK
+        );
+        let line = unsafe { CStr::from_ptr(out.as_ptr()) }.to_owned();
+        assert!(line.to_str().unwrap().starts_with("53..7....6.."));
+        assert_eq!(
+            unsafe { sudoku_solve(line.as_ptr(), out.as_mut_ptr()) },
+            SUDOKU_OK
+        );
+        let solution = unsafe { CStr::from_ptr(out.as_ptr()) }.to_str().unwrap();
+        assert!(solution.starts_with("534678912"));
+
+        let generate =
+            |seed, out: &mut [c_char]| unsafe { sudoku_generate(2, seed, out.as_mut_ptr()) };
+        let mut again = [0 as c_char; SUDOKU_LINE_BYTES];
+        assert_eq!(generate(9, &mut out), SUDOKU_OK);
+        assert_eq!(generate(9, &mut again), SUDOKU_OK);
+        assert_eq!(out, again);
+        assert_eq!(
+            unsafe { sudoku_solve(out.as_ptr(), again.as_mut_ptr()) },
+            SUDOKU_OK
+        );
+        assert_eq!(
+            unsafe { sudoku_generate(4, 9, out.as_mut_ptr()) },
+            SUDOKU_INVALID
+        );
+        assert_eq!(
+            unsafe { sudoku_generate(0, 9, ptr::null_mut()) },
+            SUDOKU_INVALID
+        );
+    }
+
+    #[test]
+    fn refuses_puzzles_without_one_solution() {
+        assert_eq!(rate(&"0".repeat(81)).0, SUDOKU_MULTIPLE_SOLUTIONS);
+        assert_eq!(rate("12").0, SUDOKU_INVALID);
+        let clash = CString::new(format!("55{}", "0".repeat(79))).unwrap();
+        let mut out = [0 as c_char; SUDOKU_LINE_BYTES];
+        let status = unsafe { sudoku_solve(clash.as_ptr(), out.as_mut_ptr()) };
+        assert_eq!(status, SUDOKU_NO_SOLUTION);
+        assert_eq!(
+            unsafe { sudoku_solve(ptr::null(), out.as_mut_ptr()) },
+            SUDOKU_INVALID
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use crate::text::draw_text;
+use crate::theme::Theme;
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{Context, Graphics};
+use piston::input::GenericEvent;
+
+/// First row shown when `rows` rows fit, keeping the selection in view.
+fn first_row(selected: usize, rows: usize) -> usize {
+    (selected + 1).saturating_sub(rows)
+}
+
+/// Rows of entries that fit below the title.
+fn visible_rows(height: f64, row_height: f64) -> usize {
+    ((height / row_height) as usize).saturating_sub(1).max(1)
+}
+
+/// Picks a file from a list.
+pub struct FilesController {
+    pub selected: usize,
+    cursor_pos: [f64; 2],
+}
+
+impl Default for FilesController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FilesController {
+    pub fn new() -> FilesController {
+        FilesController {
+            selected: 0,
+            cursor_pos: [0.0; 2],
+        }
+    }
+
+    /// Handles events, returning the index of the chosen file.
+    pub fn event<E: GenericEvent>(
+        &mut self,
+        pos: [f64; 2],
+        size: [f64; 2],
+        row_height: f64,
+        count: usize,
+        e: &E,
+    ) -> Option<usize> {
+        use piston::input::{Button, Key, MouseButton};
+
+        if count == 0 {
+            return None;
+        }
+        self.selected = self.selected.min(count - 1);
+        if let Some(pos) = e.mouse_cursor_args() {
+            self.cursor_pos = pos;
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
+            let rows = visible_rows(size[1], row_height);
+            let x = self.cursor_pos[0] - pos[0];
+            // The first row holds the title.
+            let y = self.cursor_pos[1] - pos[1] - row_height;
+            if x >= 0.0 && x <= size[0] && y >= 0.0 && y <= size[1] - row_height {
+                let row = first_row(self.selected, rows) + (y / row_height) as usize;
+                if row < count {
+                    self.selected = row;
+                    return Some(row);
+                }
+            }
+        }
+        if let Some(Button::Keyboard(key)) = e.press_args() {
+            match key {
+                Key::Up => self.selected = (self.selected + count - 1) % count,
+                Key::Down => self.selected = (self.selected + 1) % count,
+                Key::Return | Key::Space => return Some(self.selected),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+pub struct FilesViewSettings {
+    pub position: [f64; 2],
+    pub size: [f64; 2],
+    pub row_height: f64,
+    pub font_size: u32,
+    pub background_color: Color,
+    pub selected_background_color: Color,
+    pub text_color: Color,
+}
+
+impl FilesViewSettings {
+    pub fn new(theme: &Theme) -> FilesViewSettings {
+        FilesViewSettings {
+            position: [10.0; 2],
+            size: [400.0; 2],
+            row_height: 40.0,
+            font_size: 20,
+            background_color: theme.panel_background,
+            selected_background_color: theme.panel_selected_background,
+            text_color: theme.text,
+        }
+    }
+
+    /// Scales rows and text.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.row_height = 40.0 * scale;
+        self.font_size = (20.0 * scale).round() as u32;
+    }
+
+    /// Switches to a theme's colors, keeping the layout.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.background_color = theme.panel_background;
+        self.selected_background_color = theme.panel_selected_background;
+        self.text_color = theme.text;
+    }
+}
+
+pub struct FilesView {
+    pub settings: FilesViewSettings,
+}
+
+impl FilesView {
+    pub fn new(settings: FilesViewSettings) -> FilesView {
+        FilesView { settings }
+    }
+
+    /// Draws the list under a title, scrolled to keep the selection shown.
+    pub fn draw<G: Graphics, C>(
+        &self,
+        controller: &FilesController,
+        title: &str,
+        names: &[String],
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::Rectangle;
+
+        let settings = &self.settings;
+        let [x, y] = settings.position;
+        Rectangle::new(settings.background_color).draw(
+            [x, y, settings.size[0], settings.size[1]],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+
+        let baseline = (settings.row_height + settings.font_size as f64) / 2.0 - 2.0;
+        draw_text(
+            title,
+            settings.font_size,
+            settings.text_color,
+            [x + 10.0, y + baseline],
+            glyphs,
+            c,
+            g,
+        );
+        if names.is_empty() {
+            draw_text(
+                "No files found",
+                settings.font_size,
+                settings.text_color,
+                [x + 10.0, y + settings.row_height + baseline],
+                glyphs,
+                c,
+                g,
+            );
+            return;
+        }
+        let rows = visible_rows(settings.size[1], settings.row_height);
+        let first = first_row(controller.selected, rows);
+        for (row, name) in names.iter().enumerate().skip(first).take(rows) {
+            let row_y = y + (row - first + 1) as f64 * settings.row_height;
+            if row == controller.selected {
+                Rectangle::new(settings.selected_background_color).draw(
+                    [x, row_y, settings.size[0], settings.row_height],
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+            }
+            draw_text(
+                name,
+                settings.font_size,
+                settings.text_color,
+                [x + 10.0, row_y + baseline],
+                glyphs,
+                c,
+                g,
+            );
+        }
+    }
+}
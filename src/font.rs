@@ -0,0 +1,88 @@
+use opengl_graphics::{Filter, GlyphCache, TextureSettings};
+#[cfg(feature = "sdl")]
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+
+/// Font used when no other font is configured or loads.
+const EMBEDDED_FONT: &[u8] = include_bytes!("../assets/FiraSans-Regular.ttf");
+/// Font file that overrides the embedded font when present.
+const DEFAULT_FONT_PATH: &str = "assets/FiraSans-Regular.ttf";
+/// Where fonts offered in settings are looked up.
+pub const FONTS_DIR: &str = "assets/fonts";
+
+/// Characters the board and UI draw, which a font must provide.
+const REQUIRED_CHARS: &str =
+    "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz:/%!-()";
+
+fn texture_settings() -> TextureSettings {
+    TextureSettings::new().filter(Filter::Nearest)
+}
+
+/// Loads a font file, rejecting fonts missing any required glyph.
+fn load_file<P: AsRef<Path>>(path: P) -> Result<GlyphCache<'static>, String> {
+    let glyphs = GlyphCache::new(path, (), texture_settings()).map_err(|err| err.to_string())?;
+    let missing: String = REQUIRED_CHARS
+        .chars()
+        .filter(|&ch| glyphs.font.glyph(ch).id().0 == 0)
+        .collect();
+    if missing.is_empty() {
+        Ok(glyphs)
+    } else {
+        Err(format!("missing glyphs for {:?}", missing))
+    }
+}
+
+/// Loads the configured font, falling back to the default font.
+///
+/// Returns why the configured font was rejected, if it was.
+pub fn load_glyphs(path: Option<&str>) -> (GlyphCache<'static>, Option<String>) {
+    let mut error = None;
+    if let Some(path) = path {
+        match load_file(path) {
+            Ok(glyphs) => return (glyphs, None),
+            Err(err) => error = Some(format!("Could not use font {}: {}", path, err)),
+        }
+    }
+    let glyphs = load_file(DEFAULT_FONT_PATH).unwrap_or_else(|_| {
+        GlyphCache::from_bytes(EMBEDDED_FONT, (), texture_settings())
+            .expect("Embedded font is valid")
+    });
+    (glyphs, error)
+}
+
+/// The configured font file, falling back to the default font, for drawing
+/// without a glyph cache.
+#[cfg(feature = "sdl")]
+pub fn load_bytes(path: Option<&str>) -> Cow<'static, [u8]> {
+    path.into_iter()
+        .chain(Some(DEFAULT_FONT_PATH))
+        .find_map(|path| fs::read(path).ok())
+        .map_or(Cow::Borrowed(EMBEDDED_FONT), Cow::Owned)
+}
+
+/// Lists the `.ttf` and `.otf` files in a directory, sorted by path.
+pub fn list_fonts<P: AsRef<Path>>(dir: P) -> Vec<String> {
+    let mut fonts: Vec<String> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|ext| ext == "ttf" || ext == "otf")
+                })
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    fonts.sort();
+    fonts
+}
+
+/// Short name of a font path for display.
+pub fn font_name(path: &str) -> String {
+    Path::new(path).file_stem().map_or_else(
+        || path.to_string(),
+        |stem| stem.to_string_lossy().into_owned(),
+    )
+}
@@ -0,0 +1,670 @@
+use crate::constraints::Constraints;
+use crate::grid::{Grid, SIZES};
+use crate::lzstring;
+use crate::rules::RuleSet;
+use crate::variant::{
+    window_cells, windows, Cage, Dot, DotKind, Kropki, Parity, ParityMark, Sandwiches, Thermo,
+    Variant, Whisper,
+};
+use crate::SIZE;
+use serde::{Deserialize, Serialize};
+
+/// Where f-puzzles opens a puzzle passed in its URL.
+const URL_PREFIX: &str = "https://f-puzzles.com/?load=";
+
+/// A puzzle in the JSON format of the f-puzzles editor, which SudokuPad
+/// also reads.
+///
+/// Only the givens and the supported variants and constraints are kept,
+/// in any combination; others, such as arrows, are ignored.
+pub struct FPuzzle {
+    pub cells: Grid,
+    pub rules: RuleSet,
+    pub title: Option<String>,
+    pub author: Option<String>,
+}
+
+/// Writes the puzzle as an f-puzzles file.
+impl Serialize for FPuzzle {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.document().serialize(serializer)
+    }
+}
+
+/// An f-puzzles file, as much of one as is read and written. Members this
+/// leaves out, such as arrows, are skipped when read.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Document {
+    size: Option<usize>,
+    grid: Vec<Vec<CellEntry>>,
+    #[serde(rename = "diagonal+", skip_serializing_if = "is_false")]
+    diagonal_positive: bool,
+    #[serde(rename = "diagonal-", skip_serializing_if = "is_false")]
+    diagonal_negative: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    antiknight: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    antiking: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    nonconsecutive: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    extraregion: Vec<CellsEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    killercage: Vec<CellsEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    sandwichsum: Vec<CellEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    thermometer: Vec<LineEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    line: Vec<LineEntry>,
+    /// White kropki dots.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    difference: Vec<CellsEntry>,
+    /// Black kropki dots.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    ratio: Vec<CellsEntry>,
+    /// Constraints whose absence also says something, such as no dot
+    /// between cells that differ by one.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    negative: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    even: Vec<CellEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    odd: Vec<CellEntry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// A cell of the grid, or a clue or mark in a single cell, which f-puzzles
+/// names as `R1C1`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct CellEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cell: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<Number>,
+    #[serde(skip_serializing_if = "is_false")]
+    given: bool,
+    /// The region of a grid cell outside its usual box.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<usize>,
+}
+
+/// A constraint over several cells, such as a cage or a dot.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct CellsEntry {
+    cells: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<Number>,
+}
+
+/// A constraint drawn as lines of cells, such as a thermometer.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct LineEntry {
+    lines: Vec<Vec<String>>,
+    #[serde(rename = "outlineC", skip_serializing_if = "Option::is_none")]
+    outline_color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<f64>,
+}
+
+/// A value f-puzzles writes as a number in some places and as text in
+/// others, such as cage sums.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Number {
+    Integer(u32),
+    Float(f64),
+    Text(String),
+}
+
+impl Number {
+    fn get(&self) -> Option<u32> {
+        match self {
+            Number::Integer(value) => Some(*value),
+            Number::Float(value) if *value >= 0.0 && value.fract() == 0.0 => Some(*value as u32),
+            Number::Float(_) => None,
+            Number::Text(text) => text.trim().parse().ok(),
+        }
+    }
+}
+
+/// Writes a cell as f-puzzles names it.
+fn cell_name([i, j]: [usize; 2]) -> String {
+    format!("R{}C{}", j + 1, i + 1)
+}
+
+/// Reads the row and column numbers of a cell written as `R1C1`.
+fn parse_row_col(text: &str) -> Option<(usize, usize)> {
+    let text = text.to_ascii_uppercase();
+    let (row, col) = text.strip_prefix('R')?.split_once('C')?;
+    Some((row.parse().ok()?, col.parse().ok()?))
+}
+
+/// The first of a set of rules that f-puzzles cannot show, if any. It has
+/// no samurai boards and no greater-than signs.
+pub fn unwritable(rules: &RuleSet) -> Option<&Variant> {
+    rules
+        .variants
+        .iter()
+        .find(|variant| matches!(variant, Variant::Samurai | Variant::GreaterThan(_)))
+}
+
+/// Reads cells written as `R1C1` on a board of a size, or `None` if any is
+/// off the board.
+fn parse_cells(cells: &[String], size: usize) -> Option<Vec<[usize; 2]>> {
+    cells.iter().map(|cell| parse_cell(cell, size)).collect()
+}
+
+/// Whether the extra regions, each a list of cells, are the windoku windows
+/// on a board of a size. Other extra regions cannot be played.
+fn parse_windows(regions: &[CellsEntry], size: usize) -> Result<bool, String> {
+    if regions.is_empty() {
+        return Ok(false);
+    }
+    let mut found = Vec::new();
+    for region in regions {
+        let mut cells =
+            parse_cells(&region.cells, size).ok_or("An extra region has an invalid cell")?;
+        cells.sort_by_key(|&[i, j]| [j, i]);
+        found.push(cells);
+    }
+    let count = windows(size);
+    if found.len() == count && (0..count).all(|w| found.contains(&window_cells(size, w))) {
+        Ok(true)
+    } else {
+        Err("Extra regions other than windoku windows are not supported".to_string())
+    }
+}
+
+/// Reads a cell written as `R1C1` on a board of a size, counting rows and
+/// columns from 1.
+fn parse_cell(text: &str, size: usize) -> Option<[usize; 2]> {
+    let (row, col) = parse_row_col(text)?;
+    if (1..=size).contains(&row) && (1..=size).contains(&col) {
+        Some([col - 1, row - 1])
+    } else {
+        None
+    }
+}
+
+/// Reads killer cages, whose sums f-puzzles writes as text.
+fn parse_cages(cages: &[CellsEntry], size: usize) -> Result<Vec<Cage>, String> {
+    cages
+        .iter()
+        .enumerate()
+        .map(|(k, cage)| {
+            if cage.cells.is_empty() {
+                return Err(format!("Cage {} has no cells", k + 1));
+            }
+            let cells = parse_cells(&cage.cells, size)
+                .ok_or_else(|| format!("Cage {} has an invalid cell", k + 1))?;
+            let sum = cage.value.as_ref().and_then(Number::get);
+            let sum = sum.ok_or_else(|| format!("Cage {} has no sum", k + 1))?;
+            Ok(Cage { sum, cells })
+        })
+        .collect()
+}
+
+/// Reads sandwich sums, which f-puzzles writes in the cells just outside
+/// the grid: `R0C1` above the first column and `R1C0` left of the first row.
+fn parse_sandwiches(clues: &[CellEntry], size: usize) -> Result<Sandwiches, String> {
+    let mut sandwiches = Sandwiches {
+        rows: vec![None; size],
+        columns: vec![None; size],
+    };
+    for (k, clue) in clues.iter().enumerate() {
+        let cell = clue.cell.as_deref().and_then(parse_row_col);
+        let sum = clue.value.as_ref().and_then(Number::get);
+        let sum = sum.ok_or_else(|| format!("Sandwich sum {} has no value", k + 1))?;
+        match cell {
+            Some((0, col)) if (1..=size).contains(&col) => sandwiches.columns[col - 1] = Some(sum),
+            Some((row, 0)) if (1..=size).contains(&row) => sandwiches.rows[row - 1] = Some(sum),
+            _ => return Err(format!("Sandwich sum {} is not beside the grid", k + 1)),
+        }
+    }
+    Ok(sandwiches)
+}
+
+/// Colour f-puzzles gives lines drawn as German whispers.
+const WHISPER_COLOR: &str = "#67F067";
+
+/// Reads the cells of constraints that f-puzzles writes as one or more
+/// lines each, naming them in errors as `name`.
+fn parse_lines<'a>(
+    entries: impl IntoIterator<Item = &'a LineEntry>,
+    size: usize,
+    name: &str,
+) -> Result<Vec<Vec<[usize; 2]>>, String> {
+    let mut parsed = Vec::new();
+    for (k, entry) in entries.into_iter().enumerate() {
+        if entry.lines.is_empty() {
+            return Err(format!("{} {} has no lines", name, k + 1));
+        }
+        for line in &entry.lines {
+            let cells = parse_cells(line, size)
+                .ok_or_else(|| format!("{} {} has an invalid cell", name, k + 1))?;
+            parsed.push(cells);
+        }
+    }
+    Ok(parsed)
+}
+
+/// Reads thermometers, each of which f-puzzles writes as one or more lines
+/// of cells from the bulb.
+fn parse_thermos(thermos: &[LineEntry], size: usize) -> Result<Vec<Thermo>, String> {
+    let lines = parse_lines(thermos, size, "Thermometer")?;
+    Ok(lines.into_iter().map(|cells| Thermo { cells }).collect())
+}
+
+/// Reads German whispers, which f-puzzles keeps among its plain lines,
+/// telling them apart by their green colour.
+fn parse_whispers(lines: &[LineEntry], size: usize) -> Result<Vec<Whisper>, String> {
+    let whispers = lines.iter().filter(|line| {
+        line.outline_color
+            .as_deref()
+            .is_some_and(|color| color.eq_ignore_ascii_case(WHISPER_COLOR))
+    });
+    let lines = parse_lines(whispers, size, "Whispers line")?;
+    Ok(lines.into_iter().map(|cells| Whisper { cells }).collect())
+}
+
+/// Reads kropki dots, which f-puzzles keeps as `difference` constraints
+/// for white dots and `ratio` constraints for black ones.
+fn parse_dots(document: &Document, size: usize) -> Result<Kropki, String> {
+    let mut kropki = Kropki::default();
+    for (key, dots, kind, value) in [
+        ("difference", &document.difference, DotKind::White, 1),
+        ("ratio", &document.ratio, DotKind::Black, 2),
+    ] {
+        for (k, dot) in dots.iter().enumerate() {
+            let cells: Vec<[usize; 2]> = dot
+                .cells
+                .iter()
+                .filter_map(|cell| parse_cell(cell, size))
+                .collect();
+            let [a, b] = match cells[..] {
+                [a, b] if a[0].abs_diff(b[0]) + a[1].abs_diff(b[1]) == 1 => [a, b],
+                _ => return Err(format!("Dot {} of {} is not between neighbors", k + 1, key)),
+            };
+            let other = dot.value.as_ref().map_or(Some(value), Number::get);
+            if other != Some(value) {
+                return Err(format!("Only {} dots of {} are supported", key, value));
+            }
+            kropki.dots.push(Dot {
+                kind,
+                cells: [a, b],
+            });
+        }
+    }
+    let negative = |key| document.negative.iter().any(|k| k == key);
+    kropki.negative = match (negative("difference"), negative("ratio")) {
+        (false, false) => false,
+        (true, true) => true,
+        _ => return Err("Puzzles missing only one kind of dot are not supported".to_string()),
+    };
+    Ok(kropki)
+}
+
+/// Reads even and odd marks, which f-puzzles keeps in separate lists.
+fn parse_parity_marks(document: &Document, size: usize) -> Result<Vec<ParityMark>, String> {
+    let mut marks = Vec::new();
+    for (key, list, parity) in [
+        ("even", &document.even, Parity::Even),
+        ("odd", &document.odd, Parity::Odd),
+    ] {
+        for (k, mark) in list.iter().enumerate() {
+            let cell = mark
+                .cell
+                .as_deref()
+                .and_then(|cell| parse_cell(cell, size))
+                .ok_or_else(|| format!("Mark {} of {} has an invalid cell", k + 1, key))?;
+            marks.push(ParityMark { parity, cell });
+        }
+    }
+    Ok(marks)
+}
+
+impl FPuzzle {
+    pub fn new(cells: Grid) -> FPuzzle {
+        FPuzzle {
+            cells,
+            rules: RuleSet::default(),
+            title: None,
+            author: None,
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<FPuzzle, String> {
+        let document = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        FPuzzle::from_document(document)
+    }
+
+    /// Reads a puzzle already parsed as JSON, such as one within another
+    /// document.
+    pub fn from_value(value: serde_json::Value) -> Result<FPuzzle, String> {
+        let document = serde_json::from_value(value).map_err(|err| err.to_string())?;
+        FPuzzle::from_document(document)
+    }
+
+    fn from_document(document: Document) -> Result<FPuzzle, String> {
+        let size = document.size.unwrap_or(SIZE);
+        if !SIZES.contains(&size) {
+            return Err(format!("{}x{} puzzles are not supported", size, size));
+        }
+        let box_of = |i, j| Variant::Classic.box_of(size, [i, j]);
+        let mut cells = Grid::new(size);
+        // Cells outside the usual box say which region they are in instead.
+        let mut regions = Grid::new(size);
+        let mut jigsaw = false;
+        match document.grid.len() {
+            0 => return Err("Missing grid".to_string()),
+            rows if rows != size => {
+                return Err(format!("Expected {} rows but found {}", size, rows))
+            }
+            _ => {}
+        }
+        for (j, row) in document.grid.iter().enumerate() {
+            if row.len() != size {
+                return Err(format!("Row {} does not have {} cells", j + 1, size));
+            }
+            for (i, cell) in row.iter().enumerate() {
+                let value = cell.value.as_ref().and_then(Number::get).unwrap_or(0);
+                if cell.given && (1..=size as u32).contains(&value) {
+                    cells[j][i] = value as u8;
+                }
+                let region = match cell.region {
+                    Some(region) if region < size => region,
+                    Some(_) => {
+                        return Err(format!("Cell R{}C{} has an invalid region", j + 1, i + 1))
+                    }
+                    None => box_of(i, j),
+                };
+                jigsaw |= region != box_of(i, j);
+                regions[j][i] = region as u8;
+            }
+        }
+        for region in 0..size as u8 {
+            let count = regions.iter().flatten().filter(|&&r| r == region).count();
+            if count != size {
+                return Err(format!(
+                    "Region {} has {} cells instead of {}",
+                    region + 1,
+                    count,
+                    size
+                ));
+            }
+        }
+        let cages = parse_cages(&document.killercage, size)?;
+        let sandwiches = match &document.sandwichsum[..] {
+            [] => None,
+            clues => Some(parse_sandwiches(clues, size)?),
+        };
+        let thermos = parse_thermos(&document.thermometer, size)?;
+        let whispers = parse_whispers(&document.line, size)?;
+        let kropki = parse_dots(&document, size)?;
+        let marks = parse_parity_marks(&document, size)?;
+        let windoku = parse_windows(&document.extraregion, size)?;
+        let (killer, thermo) = (!cages.is_empty(), !thermos.is_empty());
+        let dots = !kropki.dots.is_empty() || kropki.negative;
+        let diagonal = match (document.diagonal_positive, document.diagonal_negative) {
+            (false, false) => false,
+            (true, true) => true,
+            _ => return Err("Puzzles with only one diagonal are not supported".to_string()),
+        };
+        // Each kind of constraint found adds a variant.
+        let found = IntoIterator::into_iter([
+            Some(Variant::Diagonal).filter(|_| diagonal),
+            Some(Variant::Killer(cages)).filter(|_| killer),
+            Some(Variant::Jigsaw(regions)).filter(|_| jigsaw),
+            sandwiches.map(Variant::Sandwich),
+            Some(Variant::Thermo(thermos)).filter(|_| thermo),
+            Some(Variant::Kropki(kropki)).filter(|_| dots),
+            Some(marks)
+                .filter(|marks| !marks.is_empty())
+                .map(Variant::EvenOdd),
+            Some(whispers)
+                .filter(|whispers| !whispers.is_empty())
+                .map(Variant::Whispers),
+            Some(Variant::Windoku).filter(|_| windoku),
+        ])
+        .flatten();
+        let constraints = Constraints {
+            anti_knight: document.antiknight,
+            anti_king: document.antiking,
+            non_consecutive: document.nonconsecutive,
+        };
+        let rules = found
+            .fold(RuleSet::default(), RuleSet::with)
+            .with_constraints(constraints);
+        rules.check()?;
+        Ok(FPuzzle {
+            cells,
+            rules,
+            title: document.title,
+            author: document.author,
+        })
+    }
+
+    fn document(&self) -> Document {
+        let size = self.cells.size();
+        let jigsaw = match self.rules.layout() {
+            Variant::Jigsaw(regions) => Some(regions),
+            _ => None,
+        };
+        let grid = self
+            .cells
+            .iter()
+            .enumerate()
+            .map(|(j, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(i, &val)| CellEntry {
+                        value: Some(Number::Integer(u32::from(val))).filter(|_| val != 0),
+                        given: val != 0,
+                        region: jigsaw.map(|regions| regions[j][i] as usize),
+                        ..CellEntry::default()
+                    })
+                    .collect()
+            })
+            .collect();
+        let cells = |cells: &[[usize; 2]]| cells.iter().copied().map(cell_name).collect();
+        let mut document = Document {
+            size: Some(size),
+            grid,
+            antiknight: self.rules.constraints.anti_knight,
+            antiking: self.rules.constraints.anti_king,
+            nonconsecutive: self.rules.constraints.non_consecutive,
+            title: self.title.clone(),
+            author: self.author.clone(),
+            ..Document::default()
+        };
+        for variant in &self.rules.variants {
+            match variant {
+                Variant::Classic
+                | Variant::Jigsaw(_)
+                | Variant::Samurai
+                | Variant::GreaterThan(_) => {}
+                Variant::Diagonal => {
+                    document.diagonal_positive = true;
+                    document.diagonal_negative = true;
+                }
+                Variant::Windoku => {
+                    document.extraregion = (0..windows(size))
+                        .map(|w| CellsEntry {
+                            cells: cells(&window_cells(size, w)),
+                            value: None,
+                        })
+                        .collect();
+                }
+                Variant::Killer(cages) => {
+                    document.killercage = cages
+                        .iter()
+                        .map(|cage| CellsEntry {
+                            cells: cells(&cage.cells),
+                            value: Some(Number::Text(cage.sum.to_string())),
+                        })
+                        .collect();
+                }
+                Variant::Sandwich(sandwiches) => {
+                    let columns = sandwiches
+                        .columns
+                        .iter()
+                        .enumerate()
+                        .map(|(i, sum)| sum.map(|sum| (format!("R0C{}", i + 1), sum)));
+                    let rows = sandwiches
+                        .rows
+                        .iter()
+                        .enumerate()
+                        .map(|(j, sum)| sum.map(|sum| (format!("R{}C0", j + 1), sum)));
+                    document.sandwichsum = columns
+                        .chain(rows)
+                        .flatten()
+                        .map(|(cell, sum)| CellEntry {
+                            cell: Some(cell),
+                            value: Some(Number::Text(sum.to_string())),
+                            ..CellEntry::default()
+                        })
+                        .collect();
+                }
+                Variant::Thermo(thermos) => {
+                    document.thermometer = thermos
+                        .iter()
+                        .map(|thermo| LineEntry {
+                            lines: vec![cells(&thermo.cells)],
+                            ..LineEntry::default()
+                        })
+                        .collect();
+                }
+                Variant::Whispers(whispers) => {
+                    document.line = whispers
+                        .iter()
+                        .map(|whisper| LineEntry {
+                            lines: vec![cells(&whisper.cells)],
+                            outline_color: Some(WHISPER_COLOR.to_string()),
+                            width: Some(0.3),
+                        })
+                        .collect();
+                }
+                Variant::EvenOdd(marks) => {
+                    for mark in marks {
+                        let entry = CellEntry {
+                            cell: Some(cell_name(mark.cell)),
+                            ..CellEntry::default()
+                        };
+                        match mark.parity {
+                            Parity::Even => document.even.push(entry),
+                            Parity::Odd => document.odd.push(entry),
+                        }
+                    }
+                }
+                Variant::Kropki(kropki) => {
+                    for dot in &kropki.dots {
+                        let entry = CellsEntry {
+                            cells: cells(&dot.cells),
+                            value: None,
+                        };
+                        match dot.kind {
+                            DotKind::White => document.difference.push(entry),
+                            DotKind::Black => document.ratio.push(entry),
+                        }
+                    }
+                    if kropki.negative {
+                        document.negative = vec!["ratio".to_string(), "difference".to_string()];
+                    }
+                }
+            }
+        }
+        document
+    }
+
+    /// The puzzle as an f-puzzles file.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("f-puzzles files are always written")
+    }
+
+    /// A link that opens the puzzle in f-puzzles, with the JSON compressed
+    /// into the query as f-puzzles expects.
+    pub fn url(&self) -> String {
+        format!(
+            "{}{}",
+            URL_PREFIX,
+            lzstring::compress_to_base64(&self.to_json())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_givens_and_sums_written_either_way() {
+        let mut grid = vec![vec!["{}"; 9]; 9];
+        grid[0][0] = r#"{"value": 5, "given": true}"#;
+        grid[0][1] = r#"{"value": 3}"#;
+        let rows: Vec<String> = grid
+            .iter()
+            .map(|row| format!("[{}]", row.join(",")))
+            .collect();
+        let text = format!(
+            r#"{{"size": 9, "grid": [{}], "arrow": [{{"lines": []}}],
+                "killercage": [{{"cells": ["R1C1", "R1C2"], "value": "8"}},
+                               {{"cells": ["R2C1"], "value": 4}}]}}"#,
+            rows.join(",")
+        );
+        let fpuzzle = FPuzzle::parse(&text).unwrap();
+        assert_eq!(fpuzzle.cells[0][0], 5);
+        // Values not given are the solver's pencil work, not clues.
+        assert_eq!(fpuzzle.cells[0][1], 0);
+        match &fpuzzle.rules.variants[..] {
+            [Variant::Killer(cages)] => {
+                assert_eq!(cages[0].sum, 8);
+                assert_eq!(cages[0].cells, vec![[0, 0], [1, 0]]);
+                assert_eq!(cages[1].sum, 4);
+            }
+            variants => panic!("unexpected variants {:?}", variants),
+        }
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let mut cells = Grid::new(9);
+        cells[4][2] = 7;
+        let mut fpuzzle = FPuzzle::new(cells);
+        fpuzzle.rules = RuleSet::default()
+            .with(Variant::Diagonal)
+            .with(Variant::Thermo(vec![Thermo {
+                cells: vec![[0, 0], [1, 1], [2, 2]],
+            }]))
+            .with(Variant::Whispers(vec![Whisper {
+                cells: vec![[8, 0], [8, 1]],
+            }]));
+        fpuzzle.rules.constraints.anti_knight = true;
+        fpuzzle.title = Some("Round trip".to_string());
+        let read = FPuzzle::parse(&fpuzzle.to_json()).unwrap();
+        assert_eq!(read.cells, fpuzzle.cells);
+        assert_eq!(read.rules, fpuzzle.rules);
+        assert_eq!(read.title, fpuzzle.title);
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        assert!(FPuzzle::parse("{}").is_err());
+        assert!(FPuzzle::parse(r#"{"size": 7, "grid": []}"#).is_err());
+        assert!(FPuzzle::parse(r#"{"grid": [[{}]]}"#).is_err());
+        // Deep nesting is refused rather than overflowing the stack.
+        let deep = format!(r#"{{"grid": {}"#, "[".repeat(200_000));
+        assert!(FPuzzle::parse(&deep).is_err());
+    }
+}
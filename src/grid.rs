@@ -0,0 +1,134 @@
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use std::ops::{Index, IndexMut};
+use std::slice::{Chunks, ChunksMut};
+
+/// Board sizes that can be played, each a number of digits with boxes that
+/// fit it.
+pub const SIZES: [usize; 5] = [4, 6, 9, 16, 25];
+
+/// Width and height of the boxes of a board: square for square sizes, and
+/// otherwise wider than tall, like the 3x2 boxes of a 6x6 board.
+pub fn box_shape(size: usize) -> [usize; 2] {
+    let mut width = 1;
+    while width * width < size {
+        width += 1;
+    }
+    while !size.is_multiple_of(width) {
+        width += 1;
+    }
+    [width, size / width]
+}
+
+/// The character shown for a digit: 1 to 9, then letters from A for 10 up.
+pub fn digit_char(val: u8) -> char {
+    match val {
+        1..=9 => (b'0' + val) as char,
+        _ => (b'A' + val - 10) as char,
+    }
+}
+
+/// The digit a character stands for, reading letters in either case.
+pub fn char_digit(ch: char) -> Option<u8> {
+    match ch.to_ascii_uppercase() {
+        ch @ '1'..='9' => Some(ch as u8 - b'0'),
+        ch @ 'A'..='Z' => Some(ch as u8 - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// A square of cells, indexed by row and then column like nested arrays.
+///
+/// Cell values default to 0, or an empty cell, and the size is fixed when
+/// the grid is made.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Grid<T = u8> {
+    size: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Clone + Default> Grid<T> {
+    /// A grid of `size` rows and columns, each cell holding the default.
+    pub fn new(size: usize) -> Grid<T> {
+        Grid {
+            size,
+            cells: vec![T::default(); size * size],
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    /// Rows and columns in the grid.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The rows, top to bottom.
+    pub fn iter(&self) -> Chunks<'_, T> {
+        self.cells.chunks(self.size.max(1))
+    }
+
+    /// The rows, top to bottom, to change.
+    pub fn iter_mut(&mut self) -> ChunksMut<'_, T> {
+        self.cells.chunks_mut(self.size.max(1))
+    }
+
+    /// Every cell location, in reading order.
+    pub fn positions(&self) -> impl Iterator<Item = [usize; 2]> {
+        let size = self.size;
+        (0..size * size).map(move |k| [k % size, k / size])
+    }
+
+    /// A grid of the same size with a function applied to each cell.
+    pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> Grid<U> {
+        Grid {
+            size: self.size,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+}
+
+impl<T> Index<usize> for Grid<T> {
+    type Output = [T];
+
+    fn index(&self, row: usize) -> &[T] {
+        &self.cells[row * self.size..(row + 1) * self.size]
+    }
+}
+
+impl<T> IndexMut<usize> for Grid<T> {
+    fn index_mut(&mut self, row: usize) -> &mut [T] {
+        &mut self.cells[row * self.size..(row + 1) * self.size]
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Grid<T> {
+    type Item = &'a [T];
+    type IntoIter = Chunks<'a, T>;
+
+    fn into_iter(self) -> Chunks<'a, T> {
+        self.iter()
+    }
+}
+
+/// Written as a list of rows, as boards were when they were fixed arrays.
+impl<T: Serialize> Serialize for Grid<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Grid<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Grid<T>, D::Error> {
+        let rows = Vec::<Vec<T>>::deserialize(deserializer)?;
+        let size = rows.len();
+        if rows.iter().any(|row| row.len() != size) {
+            return Err(de::Error::custom("grid rows and columns differ in number"));
+        }
+        Ok(Grid {
+            size,
+            cells: rows.into_iter().flatten().collect(),
+        })
+    }
+}
@@ -0,0 +1,250 @@
+use serde::Serialize;
+use serde_json::json;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, instrument, warn};
+
+/// Largest request body read, which is plenty for a puzzle file.
+const MAX_BODY: usize = 1 << 20;
+
+/// Longest request or header line read, in bytes.
+const MAX_LINE: usize = 8 << 10;
+
+/// Most headers read before a request is refused.
+const MAX_HEADERS: usize = 100;
+
+/// How long a connection may take to send its request or read the
+/// response before it is dropped.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An HTTP request, enough of one for a small JSON API.
+pub struct Request {
+    pub method: String,
+    /// The path without its query string.
+    pub path: String,
+    /// Query parameters, decoded, in order.
+    pub query: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    /// The first query parameter with a name.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Reads a request from a connection, returning `None` for one that is
+    /// not HTTP or that has lines, headers, or a body too large.
+    fn read<R: BufRead>(reader: &mut R) -> io::Result<Option<Request>> {
+        let mut line = String::new();
+        if !read_line(reader, &mut line)? {
+            return Ok(None);
+        }
+        let mut parts = line.split_whitespace();
+        let (method, target) = match (parts.next(), parts.next()) {
+            (Some(method), Some(target)) => (method.to_string(), target.to_string()),
+            _ => return Ok(None),
+        };
+        let mut length = 0;
+        for headers in 0.. {
+            line.clear();
+            if !read_line(reader, &mut line)? {
+                return Ok(None);
+            }
+            if line.trim().is_empty() {
+                break;
+            }
+            if headers == MAX_HEADERS {
+                return Ok(None);
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+        if length > MAX_BODY {
+            return Ok(None);
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+        let query = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode(key), decode(value))
+            })
+            .collect();
+        Ok(Some(Request {
+            method,
+            path: decode(path),
+            query,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        }))
+    }
+}
+
+/// Reads a line of at most `MAX_LINE` bytes, returning whether a whole
+/// line was read.
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String) -> io::Result<bool> {
+    reader.take(MAX_LINE as u64).read_line(line)?;
+    Ok(line.ends_with('\n'))
+}
+
+/// Decodes a percent-encoded URL part, with `+` for a space.
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut k = 0;
+    while k < bytes.len() {
+        let hex = bytes
+            .get(k + 1..k + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[k], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                k += 3;
+                continue;
+            }
+            (b'+', _) => out.push(b' '),
+            (byte, _) => out.push(byte),
+        }
+        k += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// A JSON response.
+pub struct Response {
+    pub status: u16,
+    /// The body, written as JSON.
+    pub body: String,
+}
+
+impl Response {
+    pub fn new<T: Serialize>(status: u16, body: &T) -> Response {
+        let body = serde_json::to_string(body).expect("responses are always written");
+        Response { status, body }
+    }
+
+    /// A response with an `error` message.
+    pub fn error(status: u16, message: &str) -> Response {
+        Response::new(status, &json!({ "error": message }))
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            422 => "Unprocessable Entity",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        }
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let body = format!("{}\n", self.body);
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\n\
+             Content-Type: application/json\r\n\
+             Content-Length: {}\r\n\
+             Access-Control-Allow-Origin: *\r\n\
+             Connection: close\r\n\r\n{}",
+            self.status,
+            self.reason(),
+            body.len(),
+            body
+        )?;
+        writer.flush()
+    }
+}
+
+/// Answers one request on a connection, then closes it.
+#[instrument(level = "debug", skip_all, fields(peer = ?stream.peer_addr().ok()))]
+fn respond<F: Fn(&Request) -> Response>(stream: TcpStream, handle: &F) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let response = match Request::read(&mut reader)? {
+        Some(request) => {
+            let response = handle(&request);
+            debug!("{} {} {}", request.method, request.path, response.status);
+            response
+        }
+        None => Response::error(400, "Not a request this server reads"),
+    };
+    response.write(&mut &stream)
+}
+
+/// Serves requests on an address until the process ends, each on a thread
+/// of its own so a slow puzzle does not hold up the others.
+pub fn serve<F: Fn(&Request) -> Response + Sync>(address: &str, handle: F) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    println!("Serving on http://{}", listener.local_addr()?);
+    let handle = &handle;
+    thread::scope(|scope| {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    scope.spawn(move || {
+                        if let Err(err) = respond(stream, handle) {
+                            warn!("Could not answer request: {}", err);
+                        }
+                    });
+                }
+                Err(err) => warn!("Could not accept connection: {}", err),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(text: &str) -> Option<Request> {
+        Request::read(&mut text.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn reads_a_request_with_its_query_and_body() {
+        let request = read(
+            "POST /solve?size=9&variant=anti+knight%2Cdiagonal HTTP/1.1\r\n\
+             Content-Length: 3\r\n\r\n123",
+        )
+        .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/solve");
+        assert_eq!(request.param("size"), Some("9"));
+        assert_eq!(request.param("variant"), Some("anti knight,diagonal"));
+        assert_eq!(request.body, "123");
+    }
+
+    #[test]
+    fn refuses_requests_too_large() {
+        let long = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE));
+        assert!(read(&long).is_none());
+        let headers = "X-Header: 1\r\n".repeat(MAX_HEADERS + 1);
+        assert!(read(&format!("GET / HTTP/1.1\r\n{}\r\n", headers)).is_none());
+        let headers = "X-Header: 1\r\n".repeat(MAX_HEADERS);
+        assert!(read(&format!("GET / HTTP/1.1\r\n{}\r\n", headers)).is_some());
+        let body = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        );
+        assert!(read(&body).is_none());
+        assert!(read("GET / HTTP/1.1\r\nHost: unfinished").is_none());
+    }
+}
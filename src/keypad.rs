@@ -0,0 +1,185 @@
+use crate::grid::digit_char;
+use crate::text::{draw_centered, draw_text, text_width};
+use crate::theme::Theme;
+use crate::Gameboard;
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{Context, Graphics};
+use piston::input::GenericEvent;
+
+/// Key rectangles for the digits from 1 up to `digits`, stacked from the
+/// top of the keypad.
+fn key_rects(pos: [f64; 2], size: [f64; 2], digits: usize) -> Vec<[f64; 4]> {
+    let height = size[1] / digits as f64;
+    (0..digits)
+        .map(|k| [pos[0], pos[1] + k as f64 * height, size[0], height])
+        .collect()
+}
+
+pub struct KeypadController {
+    cursor_pos: [f64; 2],
+}
+
+impl Default for KeypadController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeypadController {
+    pub fn new() -> KeypadController {
+        KeypadController {
+            cursor_pos: [0.0; 2],
+        }
+    }
+
+    /// Handles events on a keypad of `digits` keys, returning the digit of a
+    /// clicked key.
+    pub fn event<E: GenericEvent>(
+        &mut self,
+        pos: [f64; 2],
+        size: [f64; 2],
+        digits: usize,
+        e: &E,
+    ) -> Option<u8> {
+        use piston::input::{Button, MouseButton};
+
+        if let Some(pos) = e.mouse_cursor_args() {
+            self.cursor_pos = pos;
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
+            let x = self.cursor_pos[0] - pos[0];
+            let y = self.cursor_pos[1] - pos[1];
+            if x >= 0.0 && x < size[0] && y >= 0.0 && y < size[1] {
+                return Some((y / size[1] * digits as f64) as u8 + 1);
+            }
+        }
+        None
+    }
+}
+
+pub struct KeypadViewSettings {
+    pub position: [f64; 2],
+    pub size: [f64; 2],
+    pub ui_scale: f64,
+    pub background_color: Color,
+    /// Fill of the active digit in digit-first mode.
+    pub active_background_color: Color,
+    pub border_color: Color,
+    pub text_color: Color,
+    /// Color of digits that have all been placed.
+    pub done_text_color: Color,
+}
+
+impl KeypadViewSettings {
+    pub fn new(theme: &Theme) -> KeypadViewSettings {
+        let mut done_text_color = theme.text;
+        done_text_color[3] *= 0.3;
+        KeypadViewSettings {
+            position: [10.0; 2],
+            size: [36.0, 400.0],
+            ui_scale: 1.0,
+            background_color: theme.panel_background,
+            active_background_color: theme.panel_selected_background,
+            border_color: theme.cell_edge,
+            text_color: theme.text,
+            done_text_color,
+        }
+    }
+
+    /// Scales the keypad's width.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.ui_scale = scale;
+    }
+
+    /// Places the keypad to the right of a board at `pos` with side length `size`.
+    pub fn layout(&mut self, pos: [f64; 2], size: f64) {
+        self.position = [pos[0] + size + 8.0 * self.ui_scale, pos[1]];
+        self.size = [36.0 * self.ui_scale, size];
+    }
+
+    /// Switches to a theme's colors, keeping the layout.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        *self = KeypadViewSettings {
+            position: self.position,
+            size: self.size,
+            ui_scale: self.ui_scale,
+            ..KeypadViewSettings::new(theme)
+        };
+    }
+}
+
+pub struct KeypadView {
+    pub settings: KeypadViewSettings,
+}
+
+impl KeypadView {
+    pub fn new(settings: KeypadViewSettings) -> KeypadView {
+        KeypadView { settings }
+    }
+
+    /// Draws a key per digit of the board with how many are left to place,
+    /// greying out finished digits.
+    pub fn draw<G: Graphics, C>(
+        &self,
+        gameboard: &Gameboard,
+        active_digit: Option<u8>,
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::Rectangle;
+
+        let settings = &self.settings;
+        let digits = gameboard.digits();
+        for (k, rect) in key_rects(settings.position, settings.size, digits)
+            .into_iter()
+            .enumerate()
+        {
+            let val = k as u8 + 1;
+            let fill = if active_digit == Some(val) {
+                settings.active_background_color
+            } else {
+                settings.background_color
+            };
+            Rectangle::new(fill)
+                .border(graphics::rectangle::Border {
+                    color: settings.border_color,
+                    radius: 0.5,
+                })
+                .draw(rect, &c.draw_state, c.transform, g);
+
+            let remaining = gameboard.digit_remaining(val);
+            let color = if remaining == 0 {
+                settings.done_text_color
+            } else {
+                settings.text_color
+            };
+            let center_x = rect[0] + rect[2] / 2.0;
+            draw_centered(
+                digit_char(val),
+                rect[3] * 0.5,
+                color,
+                [center_x, rect[1] + rect[3] * 0.4],
+                glyphs,
+                c,
+                g,
+            );
+            // Counts past 9 take two digits, so are drawn as text.
+            let count = remaining.to_string();
+            let font_size = (rect[3] * 0.22).round() as u32;
+            let width = text_width(&count, font_size, glyphs, c);
+            draw_text(
+                &count,
+                font_size,
+                color,
+                [center_x - width / 2.0, rect[1] + rect[3] * 0.9],
+                glyphs,
+                c,
+                g,
+            );
+        }
+    }
+}
@@ -0,0 +1,616 @@
+//! The game without its window: boards, rules, variants, the solver and
+//! generator, and the puzzle formats. The `gui` feature, on by default,
+//! builds the game itself on top, and the `web` feature a smaller game for
+//! browsers.
+
+pub mod canonical;
+pub mod constraints;
+pub mod csv;
+pub mod digits;
+pub mod ffi;
+pub mod fpuzzles;
+pub mod grid;
+pub mod lzstring;
+#[cfg(feature = "python")]
+mod python;
+pub mod render;
+pub mod rules;
+pub mod script;
+pub mod sdk;
+pub mod solver;
+pub mod technique;
+pub mod variant;
+#[cfg(feature = "web")]
+mod web;
+
+use constraints::Constraints;
+use grid::{box_shape, digit_char, Grid};
+use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
+use rules::RuleSet;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+use tracing::{debug, instrument, warn};
+use variant::{Variant, VariantKind};
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+/// Rows and columns of a classic board, the only size most puzzle files and
+/// the database hold.
+pub const SIZE: usize = 9;
+
+/// Guesses the first try at filling a variant grid may take. Each try
+/// after takes a new layout, such as jigsaw regions, and twice the guesses,
+/// up to `MAX_FILL_BUDGET`: rules such as X-Sudoku with anti-knight leave
+/// few grids to find, and the randomized search often needs many guesses.
+const FILL_BUDGET: usize = 2_000;
+
+/// The most guesses a single try at filling a variant grid may take.
+const MAX_FILL_BUDGET: usize = 256_000;
+
+/// Guesses tried across every try at filling a variant grid before
+/// settling for a classic one, as some rules cannot all hold at once.
+const FILL_LIMIT: usize = 500_000;
+
+/// Guesses the solver may take to show a clue can be dug out of a puzzle.
+/// Clues that take longer stay, so large boards generate quickly; the
+/// guesses on a giant board cost a lot, and more rarely free another clue.
+const DIG_BUDGET: usize = 20;
+
+/// How many givens a generated puzzle keeps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+    /// A 4x4 board for children learning the rules, where wrong entries
+    /// are pointed out kindly instead of counted as mistakes.
+    Kids,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Expert,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+            Difficulty::Expert => "Expert",
+            Difficulty::Kids => "Kids",
+        }
+    }
+
+    /// The difficulty with a name, ignoring case.
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        Difficulty::ALL
+            .iter()
+            .copied()
+            .find(|difficulty| difficulty.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The hardest difficulty a puzzle with this many givens meets.
+    pub fn for_clues(clues: usize) -> Difficulty {
+        Difficulty::ALL
+            .iter()
+            .copied()
+            .find(|difficulty| clues >= difficulty.clues())
+            .unwrap_or(Difficulty::Expert)
+    }
+
+    /// Number of givens to dig down to.
+    fn clues(self) -> usize {
+        match self {
+            Difficulty::Easy => 40,
+            Difficulty::Medium => 32,
+            Difficulty::Hard => 27,
+            Difficulty::Expert => 23,
+            Difficulty::Kids => 40,
+        }
+    }
+
+    /// Rows and columns of the boards generated at this difficulty.
+    pub fn board_size(self) -> usize {
+        match self {
+            Difficulty::Kids => 4,
+            _ => SIZE,
+        }
+    }
+}
+
+/// A row, column, box, or a group of cells a variant adds, each of which
+/// must hold every digit once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum House {
+    Row(usize),
+    Column(usize),
+    Box(usize),
+    /// The main diagonal from the top left, or with 1 from the top right.
+    Diagonal(usize),
+    /// A row of one grid of a samurai board, by grid and row within it.
+    GridRow(usize, usize),
+    /// A column of one grid of a samurai board, by grid and column within
+    /// it.
+    GridColumn(usize, usize),
+    /// A windoku window, numbered across then down.
+    Window(usize),
+}
+
+impl House {
+    /// The rows, columns, and box containing a cell on a board of a size,
+    /// with boxes shaped as the variant has them. A cell where samurai
+    /// grids overlap is in the rows and columns of both.
+    pub fn containing(ind: [usize; 2], size: usize, variant: &Variant) -> Vec<House> {
+        let [x, y] = ind;
+        if !variant.in_play(ind) {
+            return Vec::new();
+        }
+        let mut houses = match variant {
+            Variant::Samurai => variant::samurai_grids(ind)
+                .flat_map(|(g, [i, j])| [House::GridRow(g, j), House::GridColumn(g, i)])
+                .collect(),
+            _ => vec![House::Row(y), House::Column(x)],
+        };
+        houses.push(House::Box(variant.box_of(size, ind)));
+        houses
+    }
+
+    /// The kind of variant that adds the house, if it is not a row,
+    /// column, or box.
+    pub fn variant(self) -> Option<VariantKind> {
+        match self {
+            House::Diagonal(_) => Some(VariantKind::Diagonal),
+            House::Window(_) => Some(VariantKind::Windoku),
+            _ => None,
+        }
+    }
+
+    /// The house for display, with boxes named as the variant has them.
+    pub fn name(self, variant: &Variant) -> String {
+        match self {
+            House::Row(j) => format!("row {}", j + 1),
+            House::Column(i) => format!("column {}", i + 1),
+            House::Box(b) if matches!(variant, Variant::Jigsaw(_)) => {
+                format!("region {}", b + 1)
+            }
+            House::Box(b) => format!("box {}", b + 1),
+            House::Diagonal(0) => "the falling diagonal".to_string(),
+            House::Diagonal(_) => "the rising diagonal".to_string(),
+            House::GridRow(g, j) => format!("row {} of grid {}", j + 1, g + 1),
+            House::GridColumn(g, i) => format!("column {} of grid {}", i + 1, g + 1),
+            House::Window(w) => format!("window {}", w + 1),
+        }
+    }
+
+    /// Cell locations in the house on a board of a size, in reading order.
+    pub fn cells(self, size: usize, variant: &Variant) -> Vec<[usize; 2]> {
+        match self {
+            House::Box(b) => return variant.box_cells(size, b),
+            House::Window(w) => return variant::window_cells(size, w),
+            _ => {}
+        }
+        let digits = variant.digits(size);
+        (0..digits)
+            .map(|k| match self {
+                House::Row(j) => [k, j],
+                House::Column(i) => [i, k],
+                House::Diagonal(0) => [k, k],
+                House::GridRow(g, j) => variant::samurai_cell(g, [k, j]),
+                House::GridColumn(g, i) => variant::samurai_cell(g, [i, k]),
+                _ => [size - 1 - k, k],
+            })
+            .collect()
+    }
+}
+
+pub struct Gameboard {
+    pub cells: Grid,
+    pub solution: Grid,
+    pub givens: Grid<bool>,
+    /// Pencil marks, with bit `val` set for each noted digit.
+    pub notes: Grid<u32>,
+    pub difficulty: Difficulty,
+    /// Variants and constraints played beyond the classic rules.
+    pub rules: RuleSet,
+    solved: bool,
+    /// Seed of the generator, so a puzzle can be reproduced.
+    pub seed: u64,
+    /// How long generating the puzzle took, mostly spent in the solver.
+    pub generate_time: Duration,
+    rng: StdRng,
+}
+
+impl Default for Gameboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gameboard {
+    pub fn new() -> Gameboard {
+        Gameboard::with_seed(thread_rng().gen())
+    }
+
+    /// Creates a board whose puzzles are generated from the given seed.
+    pub fn with_seed(seed: u64) -> Gameboard {
+        Gameboard {
+            cells: Grid::new(SIZE),
+            solution: Grid::new(SIZE),
+            givens: Grid::new(SIZE),
+            notes: Grid::new(SIZE),
+            difficulty: Difficulty::Medium,
+            rules: RuleSet::default(),
+            solved: false,
+            seed,
+            generate_time: Duration::default(),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Creates a board for a puzzle from elsewhere, with its filled cells as
+    /// givens.
+    pub fn from_puzzle(cells: Grid, solution: Grid) -> Gameboard {
+        let mut gameboard = Gameboard::new();
+        gameboard.givens = cells.map(|&val| val != 0);
+        gameboard.notes = Grid::new(cells.size());
+        gameboard.cells = cells;
+        gameboard.solution = solution;
+        let clues = gameboard
+            .givens
+            .iter()
+            .flatten()
+            .filter(|&&given| given)
+            .count();
+        gameboard.difficulty = Difficulty::for_clues(clues);
+        gameboard
+    }
+
+    /// The puzzle as it was given, without the player's entries.
+    pub fn puzzle(&self) -> Grid {
+        let mut grid = self.cells.clone();
+        for (row, givens) in grid.iter_mut().zip(self.givens.iter()) {
+            for (val, &given) in row.iter_mut().zip(givens.iter()) {
+                if !given {
+                    *val = 0;
+                }
+            }
+        }
+        grid
+    }
+
+    /// Whether the last change completed the puzzle.
+    pub fn is_solved(&self) -> bool {
+        self.solved
+    }
+
+    /// Rows and columns on the board.
+    pub fn size(&self) -> usize {
+        self.cells.size()
+    }
+
+    /// The highest digit, which is the board's size except on boards of
+    /// several grids.
+    pub fn digits(&self) -> usize {
+        self.rules.layout().digits(self.size())
+    }
+
+    /// Whether a cell is played, rather than in a gap between grids.
+    pub fn in_play(&self, ind: [usize; 2]) -> bool {
+        self.rules.layout().in_play(ind)
+    }
+
+    /// Share of the non-given cells that have been filled, from 0 to 1.
+    pub fn progress(&self) -> f64 {
+        let mut open = 0;
+        let mut filled = 0;
+        for [i, j] in self.cells.positions() {
+            if !self.givens[j][i] && self.in_play([i, j]) {
+                open += 1;
+                if self.cells[j][i] != 0 {
+                    filled += 1;
+                }
+            }
+        }
+        if open == 0 {
+            1.0
+        } else {
+            filled as f64 / open as f64
+        }
+    }
+
+    /// How many cells hold the digit.
+    pub fn digit_count(&self, val: u8) -> usize {
+        self.cells
+            .iter()
+            .flatten()
+            .filter(|&&cell| cell == val)
+            .count()
+    }
+
+    /// How many more of the digit there are to place.
+    pub fn digit_remaining(&self, val: u8) -> usize {
+        let cells = self.cells.positions().filter(|&ind| self.in_play(ind));
+        (cells.count() / self.digits()).saturating_sub(self.digit_count(val))
+    }
+
+    /// Whether every one of the digit has been placed.
+    pub fn digit_complete(&self, val: u8) -> bool {
+        self.digit_remaining(val) == 0
+    }
+
+    /// Whether the cell is part of the original puzzle.
+    pub fn is_given(&self, ind: [usize; 2]) -> bool {
+        self.givens[ind[1]][ind[0]]
+    }
+
+    /// Gets the character at cell location.
+    pub fn char(&self, ind: [usize; 2]) -> Option<char> {
+        match self.cells[ind[1]][ind[0]] {
+            0 => None,
+            val => Some(digit_char(val)),
+        }
+    }
+
+    /// Whether the digit is pencilled into the cell.
+    pub fn has_note(&self, ind: [usize; 2], val: u8) -> bool {
+        self.notes[ind[1]][ind[0]] & (1 << val) != 0
+    }
+
+    /// Set cell value.
+    pub fn set(&mut self, ind: [usize; 2], val: u8) {
+        if self.is_given(ind) {
+            return;
+        }
+        self.cells[ind[1]][ind[0]] = val;
+        self.solved = self.solved();
+    }
+
+    /// Fills a board of a size with a full grid: a shifted pattern of
+    /// digits in a random order.
+    pub fn populate(&mut self, size: usize) {
+        let mut digits: Vec<u8> = (1..=size as u8).collect();
+        let mut seed = Vec::new();
+        for _ in 0..size {
+            let index = self.rng.gen_range(0, digits.len());
+            seed.push(digits.remove(index));
+        }
+        // https://gamedev.stackexchange.com/questions/56149/how-can-i-generate-sudoku-puzzles
+        // Each row shifts by a box width from the one above, and each band
+        // of boxes by one more, so no row, column, or box repeats a digit.
+        let [width, height] = box_shape(size);
+        self.cells = Grid::new(size);
+        for (j, row) in self.cells.iter_mut().enumerate() {
+            let (band, k) = (j / height, j % height);
+            let shift = band + width * ((k + height - band % height) % height);
+            for (i, cell) in row.iter_mut().enumerate() {
+                *cell = seed[(shift + i) % size];
+            }
+        }
+    }
+
+    /// Generates a puzzle of a kind and size, with constraints added, that
+    /// has a unique solution.
+    pub fn generate(
+        &mut self,
+        difficulty: Difficulty,
+        kind: VariantKind,
+        constraints: Constraints,
+        size: usize,
+    ) {
+        let rules = RuleSet::new(Variant::new(kind), constraints);
+        self.generate_rules(difficulty, rules, size);
+    }
+
+    /// Generates a puzzle of a size under any set of rules that has a
+    /// unique solution, making the clues its variants need, such as cages,
+    /// to fit.
+    ///
+    /// Classic puzzles shift a pattern of digits. Other rules are filled in
+    /// by the solver trying digits in a random order, starting over with
+    /// more guesses whenever it gets stuck. Jigsaw regions and samurai
+    /// boards are only made for the classic size, and are left out at other
+    /// sizes. If no grid can be found that obeys every rule, the puzzle is
+    /// classic.
+    #[instrument(level = "debug", skip(self, rules))]
+    pub fn generate_rules(&mut self, difficulty: Difficulty, rules: RuleSet, size: usize) {
+        let start = Instant::now();
+        self.rules = rules;
+        if size != SIZE {
+            self.rules
+                .variants
+                .retain(|variant| !matches!(variant, Variant::Jigsaw(_) | Variant::Samurai));
+        }
+        // The shifted pattern only obeys the classic rules.
+        if self.rules.is_classic() {
+            self.populate(size);
+        } else {
+            let board_size = self.rules.layout().board_size(size);
+            let (mut budget, mut spent) = (FILL_BUDGET, 0);
+            let solution = loop {
+                for variant in &mut self.rules.variants {
+                    variant.make_layout(&mut self.rng);
+                }
+                let found = solver::random_solution(board_size, &self.rules, budget, &mut self.rng);
+                spent += budget;
+                if found.is_none() {
+                    debug!(
+                        "No grid filled within {} guesses, trying another layout",
+                        budget
+                    );
+                }
+                if found.is_some() || spent >= FILL_LIMIT {
+                    break found;
+                }
+                budget = (budget * 2).min(MAX_FILL_BUDGET);
+            };
+            match solution {
+                Some(solution) => {
+                    for variant in &mut self.rules.variants {
+                        variant.make_clues(&solution, &mut self.rng);
+                    }
+                    self.cells = solution;
+                }
+                None => {
+                    warn!(
+                        "Could not fill a grid under {} rules, making a classic puzzle",
+                        self.rules.names().join(", ")
+                    );
+                    self.rules = RuleSet::default();
+                    self.populate(size);
+                }
+            }
+        }
+        let size = self.size();
+        self.solution = self.cells.clone();
+        self.notes = Grid::new(size);
+        self.difficulty = difficulty;
+
+        let layout = self.rules.layout();
+        let mut order: Vec<[usize; 2]> = self
+            .cells
+            .positions()
+            .filter(|&ind| layout.in_play(ind))
+            .collect();
+        for k in (1..order.len()).rev() {
+            order.swap(k, self.rng.gen_range(0, k + 1));
+        }
+        // Smaller and larger boards keep the same share of givens, and
+        // cages, dots, signs, sandwich sums, thermometers, whispers lines,
+        // and even and odd marks carry many of a puzzle's clues. Combined
+        // variants keep the fewest givens any of them would.
+        let target = difficulty.clues() * order.len() / (SIZE * SIZE);
+        let target = self
+            .rules
+            .variants
+            .iter()
+            .map(|variant| match variant {
+                Variant::Killer(_) | Variant::Kropki(_) | Variant::GreaterThan(_) => target / 4,
+                Variant::Sandwich(_) | Variant::Thermo(_) | Variant::Whispers(_) => target / 2,
+                Variant::EvenOdd(_) => target * 3 / 4,
+                _ => target,
+            })
+            .min()
+            .unwrap_or(target);
+        let mut clues = order.len();
+        for [i, j] in order {
+            if clues <= target {
+                break;
+            }
+            if solver::is_forced_within(&self.cells, &self.rules, [i, j], DIG_BUDGET) {
+                self.cells[j][i] = 0;
+                clues -= 1;
+            }
+        }
+        self.givens = self.cells.map(|&val| val != 0);
+        self.solved = false;
+        self.generate_time = start.elapsed();
+        debug!(
+            "Generated a {} {}x{} puzzle with {} clues in {:?}",
+            difficulty.name(),
+            size,
+            size,
+            clues,
+            self.generate_time
+        );
+    }
+
+    /// Rechecks whether the board is solved, after its cells are set
+    /// directly, such as when a saved game is loaded.
+    pub fn update_solved(&mut self) {
+        self.solved = self.solved();
+    }
+
+    /// Whether every cell is filled without breaking a rule.
+    pub fn solved(&self) -> bool {
+        self.cells
+            .positions()
+            .all(|[i, j]| self.cells[j][i] != 0 || !self.in_play([i, j]))
+            && self.cells.positions().all(|ind| !self.conflicts(ind))
+    }
+
+    /// The houses containing a cell, including any the variants add.
+    pub fn houses(&self, ind: [usize; 2]) -> impl Iterator<Item = House> + '_ {
+        House::containing(ind, self.size(), self.rules.layout())
+            .into_iter()
+            .chain(self.rules.extra_houses(self.size(), ind))
+    }
+
+    /// Whether every cell in the house holds its solution value.
+    pub fn house_complete(&self, house: House) -> bool {
+        house
+            .cells(self.size(), self.rules.layout())
+            .iter()
+            .all(|&[i, j]| self.cells[j][i] == self.solution[j][i])
+    }
+
+    /// Checks whether the cell's value is repeated in any of its houses, or
+    /// breaks another of the puzzle's rules.
+    pub fn conflicts(&self, ind: [usize; 2]) -> bool {
+        let [x, y] = ind;
+        let val = self.cells[y][x];
+        if val == 0 {
+            return false;
+        }
+        self.houses(ind).any(|house| {
+            house
+                .cells(self.size(), self.rules.layout())
+                .iter()
+                .any(|&[i, j]| [i, j] != ind && self.cells[j][i] == val)
+        }) || self.rules.breaks(&self.cells, ind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_puzzles_with_one_solution() {
+        for difficulty in Difficulty::ALL {
+            let mut board = Gameboard::with_seed(11);
+            board.generate_rules(difficulty, RuleSet::default(), SIZE);
+            let puzzle = board.puzzle();
+            let clues = puzzle.iter().flatten().filter(|&&val| val != 0).count();
+            assert!(clues >= difficulty.clues(), "{} clues", clues);
+            assert_eq!(
+                solver::unique_solution(&puzzle, &board.rules),
+                Some(board.solution.clone())
+            );
+            assert!(!board.is_solved());
+        }
+    }
+
+    #[test]
+    fn generates_the_same_puzzle_from_a_seed() {
+        let generate = |seed| {
+            let mut board = Gameboard::with_seed(seed);
+            board.generate(
+                Difficulty::Hard,
+                VariantKind::Killer,
+                Constraints::default(),
+                SIZE,
+            );
+            (board.puzzle(), board.rules)
+        };
+        assert_eq!(generate(3), generate(3));
+        assert_ne!(generate(3).0, generate(4).0);
+    }
+
+    #[test]
+    fn generates_other_sizes() {
+        let mut board = Gameboard::with_seed(5);
+        board.generate_rules(Difficulty::Kids, RuleSet::default(), 4);
+        assert_eq!((board.size(), board.digits()), (4, 4));
+        let mut filled = Gameboard::from_puzzle(board.solution.clone(), board.solution.clone());
+        filled.update_solved();
+        assert!(filled.is_solved());
+    }
+}
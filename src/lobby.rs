@@ -0,0 +1,331 @@
+use crate::coop::Session;
+use crate::discovery::{FoundGame, CODE_LEN};
+use crate::text::draw_text;
+use crate::theme::Theme;
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{Context, Graphics};
+use piston::input::GenericEvent;
+
+/// Longest code or address that can be typed.
+const MAX_ADDRESS_LEN: usize = 64;
+
+/// What the lobby asks for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LobbyAction {
+    /// Host a co-op game, or a race, for others to join.
+    Host {
+        race: bool,
+    },
+    /// Join the game at an address, or with a code, to play or only to
+    /// watch.
+    Join {
+        address: String,
+        watching: bool,
+    },
+    /// Start the game for the players gathered, or go back to it once
+    /// started.
+    Start,
+    Leave,
+    Back,
+}
+
+/// The address of a game typed as a code or an address. Codes are looked
+/// up among the games found on the local network.
+pub fn address(text: &str, games: &[FoundGame]) -> Result<String, String> {
+    let text = text.trim();
+    let is_code = text.len() == CODE_LEN && text.chars().all(|ch| ch.is_ascii_alphabetic());
+    if !is_code {
+        return Ok(text.to_string());
+    }
+    games
+        .iter()
+        .find(|game| game.code.eq_ignore_ascii_case(text))
+        .map(|game| game.address.to_string())
+        .ok_or_else(|| {
+            format!(
+                "No game with code {} found on the network",
+                text.to_ascii_uppercase()
+            )
+        })
+}
+
+/// Hosts or joins a game, then shows who is in it until it starts.
+///
+/// Before a game the rows host a co-op game, host a race, pick whether to
+/// join games to play or only to watch, join each game found on the local
+/// network, and last, join by a code or address typed.
+pub struct LobbyController {
+    pub selected: usize,
+    /// Code or address being typed.
+    pub typed: String,
+    /// Whether games are joined only to watch.
+    pub watching: bool,
+}
+
+impl Default for LobbyController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LobbyController {
+    pub fn new() -> LobbyController {
+        LobbyController {
+            selected: 0,
+            typed: String::new(),
+            watching: false,
+        }
+    }
+
+    /// Handles events, returning the chosen action. In a game, Return
+    /// starts it and Backspace leaves it.
+    pub fn event<E: GenericEvent>(
+        &mut self,
+        in_game: bool,
+        games: &[FoundGame],
+        e: &E,
+    ) -> Option<LobbyAction> {
+        use piston::input::{Button, Key};
+
+        if in_game {
+            return match e.press_args() {
+                Some(Button::Keyboard(Key::Return)) => Some(LobbyAction::Start),
+                Some(Button::Keyboard(Key::Backspace)) => Some(LobbyAction::Leave),
+                _ => None,
+            };
+        }
+        let rows = games.len() + 4;
+        // Typing always goes to the code or address, on the last row.
+        if let Some(text) = e.text_args() {
+            let typed = text
+                .chars()
+                .filter(|&ch| ch.is_ascii_alphanumeric() || ".:-[]".contains(ch));
+            self.typed.extend(typed);
+            self.typed.truncate(MAX_ADDRESS_LEN);
+            self.selected = rows - 1;
+        }
+        self.selected = self.selected.min(rows - 1);
+        match e.press_args() {
+            Some(Button::Keyboard(Key::Up)) => self.selected = (self.selected + rows - 1) % rows,
+            Some(Button::Keyboard(Key::Down)) => self.selected = (self.selected + 1) % rows,
+            Some(Button::Keyboard(Key::Backspace)) if !self.typed.is_empty() => {
+                self.typed.pop();
+            }
+            Some(Button::Keyboard(Key::Backspace)) => return Some(LobbyAction::Back),
+            Some(Button::Keyboard(Key::Return)) => {
+                let address = match self.selected {
+                    0 => return Some(LobbyAction::Host { race: false }),
+                    1 => return Some(LobbyAction::Host { race: true }),
+                    2 => {
+                        self.watching = !self.watching;
+                        return None;
+                    }
+                    row if row < rows - 1 => games[row - 3].address.to_string(),
+                    _ if self.typed.trim().is_empty() => return None,
+                    _ => self.typed.clone(),
+                };
+                return Some(LobbyAction::Join {
+                    address,
+                    watching: self.watching,
+                });
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+pub struct LobbyViewSettings {
+    pub font_size: u32,
+    pub background_color: Color,
+    pub selected_background_color: Color,
+    pub text_color: Color,
+}
+
+impl LobbyViewSettings {
+    pub fn new(theme: &Theme) -> LobbyViewSettings {
+        LobbyViewSettings {
+            font_size: 16,
+            background_color: theme.panel_background,
+            selected_background_color: theme.panel_selected_background,
+            text_color: theme.text,
+        }
+    }
+
+    /// Scales text.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.font_size = (16.0 * scale).round() as u32;
+    }
+
+    /// Switches to a theme's colors.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        *self = LobbyViewSettings {
+            font_size: self.font_size,
+            ..LobbyViewSettings::new(theme)
+        };
+    }
+}
+
+pub struct LobbyView {
+    pub settings: LobbyViewSettings,
+}
+
+impl LobbyView {
+    pub fn new(settings: LobbyViewSettings) -> LobbyView {
+        LobbyView { settings }
+    }
+
+    /// Draws the games to host or join, or the players of the game joined,
+    /// over the area at `pos` with side length `size`, with a message at
+    /// the bottom if there is one.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw<G: Graphics, C>(
+        &self,
+        controller: &LobbyController,
+        session: Option<&Session>,
+        games: &[FoundGame],
+        message: Option<&str>,
+        pos: [f64; 2],
+        size: f64,
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::Rectangle;
+
+        let settings = &self.settings;
+        let [x, y] = pos;
+        Rectangle::new(settings.background_color).draw(
+            [x, y, size, size],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+        let line_height = settings.font_size as f64 * 1.6;
+        let text = |line: &str, row: f64, glyphs: &mut C, g: &mut G| {
+            draw_text(
+                line,
+                settings.font_size,
+                settings.text_color,
+                [x + 10.0, y + (row + 1.0) * line_height],
+                glyphs,
+                c,
+                g,
+            );
+        };
+
+        let footer = match session {
+            Some(session) => {
+                let game = if session.is_race() {
+                    "race"
+                } else {
+                    "co-op game"
+                };
+                let title = match (session.is_host(), session.code()) {
+                    (true, Some(code)) => {
+                        format!(
+                            "Hosting a {} - code {}, port {}",
+                            game,
+                            code,
+                            session.port()
+                        )
+                    }
+                    (true, None) => format!("Hosting a {} on port {}", game, session.port()),
+                    (false, _) if session.is_watching() => {
+                        format!("Watching {}'s {}", session.name_of(0), game)
+                    }
+                    (false, _) => format!("Joined {}'s {}", session.name_of(0), game),
+                };
+                text(&title, 0.0, glyphs, g);
+                text("Players:", 2.0, glyphs, g);
+                for (k, (id, name, watching)) in session.players().into_iter().enumerate() {
+                    let marks: Vec<&str> = [
+                        (id == session.id(), "you"),
+                        (id == 0, "host"),
+                        (watching, "watching"),
+                    ]
+                    .iter()
+                    .filter(|&&(marked, _)| marked)
+                    .map(|&(_, mark)| mark)
+                    .collect();
+                    let line = if marks.is_empty() {
+                        name.to_string()
+                    } else {
+                        format!("{} ({})", name, marks.join(", "))
+                    };
+                    text(&line, k as f64 + 3.0, glyphs, g);
+                }
+                match (session.started(), session.is_host()) {
+                    (true, _) => "Enter to return to the game, Backspace to leave",
+                    (false, true) => "Enter to start, Backspace to leave",
+                    (false, false) => "Waiting for the host to start - Backspace to leave",
+                }
+            }
+            None => {
+                text("Multiplayer", 0.0, glyphs, g);
+                let (join, joining) = if controller.watching {
+                    ("Watch", "only to watch")
+                } else {
+                    ("Join", "to play")
+                };
+                let mut rows = vec![
+                    "Host a co-op game".to_string(),
+                    "Host a race".to_string(),
+                    format!("Join games: {}", joining),
+                ];
+                rows.extend(games.iter().map(|game| {
+                    let kind = if game.race { "race" } else { "co-op game" };
+                    format!("{} {}'s {} ({})", join, game.name, kind, game.code)
+                }));
+                rows.push(format!(
+                    "{} by code or address: {}_",
+                    join, controller.typed
+                ));
+                for (k, row) in rows.iter().enumerate() {
+                    let line = k as f64 + 2.0;
+                    if k == controller.selected {
+                        Rectangle::new(settings.selected_background_color).draw(
+                            [x, y + (line + 0.3) * line_height, size, line_height],
+                            &c.draw_state,
+                            c.transform,
+                            g,
+                        );
+                    }
+                    text(row, line, glyphs, g);
+                }
+                if games.is_empty() {
+                    text(
+                        "Looking for games on the local network...",
+                        rows.len() as f64 + 3.0,
+                        glyphs,
+                        g,
+                    );
+                }
+                "Enter to pick, Backspace to go back"
+            }
+        };
+        if let Some(message) = message {
+            draw_text(
+                message,
+                settings.font_size,
+                settings.text_color,
+                [x + 10.0, y + size - 1.5 * line_height],
+                glyphs,
+                c,
+                g,
+            );
+        }
+        draw_text(
+            footer,
+            settings.font_size,
+            settings.text_color,
+            [x + 10.0, y + size - line_height / 2.0],
+            glyphs,
+            c,
+            g,
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use tracing::level_filters::LevelFilter;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt;
+use tracing_subscriber::prelude::*;
+
+/// Where the log is written, next to the saves.
+pub const LOGS_DIR: &str = "logs";
+
+/// Logs kept, one a day as `sudoku.2026-10-16.log`, before the oldest is
+/// removed.
+const KEPT_LOGS: usize = 7;
+
+/// Starts logging to the console and to a file in a directory, with every
+/// detail when verbose. A new file is started each day.
+pub fn init<P: AsRef<Path>>(dir: P, verbose: bool) {
+    let (console_level, file_level) = if verbose {
+        (LevelFilter::DEBUG, LevelFilter::DEBUG)
+    } else {
+        (LevelFilter::WARN, LevelFilter::INFO)
+    };
+    let console = fmt::layer().with_writer(io::stderr);
+    // Warnings read on the console as they did before there was a log.
+    let console = if verbose {
+        console.with_filter(console_level).boxed()
+    } else {
+        console
+            .without_time()
+            .with_target(false)
+            .with_level(false)
+            .with_filter(console_level)
+            .boxed()
+    };
+    // The appender looks for old logs to remove before making the directory.
+    let _ = fs::create_dir_all(&dir);
+    let file = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("sudoku")
+        .filename_suffix("log")
+        .max_log_files(KEPT_LOGS)
+        .build(dir.as_ref())
+        .map_err(|err| eprintln!("Could not open log: {}", err))
+        .ok()
+        .map(|file| {
+            fmt::layer()
+                .with_writer(file)
+                .with_ansi(false)
+                .with_filter(file_level)
+        });
+    // Logging started twice, as by tests, keeps the first.
+    let _ = tracing_subscriber::registry()
+        .with(console)
+        .with(file)
+        .try_init();
+}
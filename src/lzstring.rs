@@ -0,0 +1,150 @@
+use std::collections::{HashMap, HashSet};
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
+
+/// Packs codes of varying widths into characters of `bits_per_char` bits,
+/// least significant bit first, as lz-string does.
+struct BitWriter {
+    bits_per_char: usize,
+    out: Vec<u8>,
+    value: u8,
+    position: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, bits: usize, mut code: u32) {
+        for _ in 0..bits {
+            self.value = (self.value << 1) | (code & 1) as u8;
+            if self.position == self.bits_per_char - 1 {
+                self.out.push(self.value);
+                self.value = 0;
+                self.position = 0;
+            } else {
+                self.position += 1;
+            }
+            code >>= 1;
+        }
+    }
+
+    /// Pads the last character with zero bits.
+    fn finish(mut self) -> Vec<u8> {
+        loop {
+            self.value <<= 1;
+            if self.position == self.bits_per_char - 1 {
+                self.out.push(self.value);
+                return self.out;
+            }
+            self.position += 1;
+        }
+    }
+}
+
+/// State of lz-string's LZW variant.
+struct Compressor {
+    dictionary: HashMap<Vec<u16>, u32>,
+    /// Single characters added to the dictionary but not yet written out.
+    to_create: HashSet<Vec<u16>>,
+    writer: BitWriter,
+    num_bits: usize,
+    /// Codes left before they need another bit.
+    enlarge_in: u32,
+}
+
+impl Compressor {
+    fn count_code(&mut self) {
+        self.enlarge_in -= 1;
+        if self.enlarge_in == 0 {
+            self.enlarge_in = 1 << self.num_bits;
+            self.num_bits += 1;
+        }
+    }
+
+    fn add(&mut self, phrase: Vec<u16>) {
+        let code = self.dictionary.len() as u32 + 3;
+        self.dictionary.insert(phrase, code);
+    }
+
+    /// Writes the code for a phrase, spelling out a new character first.
+    fn emit(&mut self, phrase: &[u16]) {
+        if self.to_create.remove(phrase) {
+            let ch = phrase[0];
+            if ch < 256 {
+                self.writer.write(self.num_bits, 0);
+                self.writer.write(8, ch as u32);
+            } else {
+                self.writer.write(self.num_bits, 1);
+                self.writer.write(16, ch as u32);
+            }
+            self.count_code();
+        } else {
+            self.writer.write(self.num_bits, self.dictionary[phrase]);
+        }
+        self.count_code();
+    }
+}
+
+/// Compresses to `bits_per_char`-bit values.
+fn compress(input: &str, bits_per_char: usize) -> Vec<u8> {
+    let mut compressor = Compressor {
+        dictionary: HashMap::new(),
+        to_create: HashSet::new(),
+        writer: BitWriter {
+            bits_per_char,
+            out: Vec::new(),
+            value: 0,
+            position: 0,
+        },
+        num_bits: 2,
+        enlarge_in: 2,
+    };
+    let mut w: Vec<u16> = Vec::new();
+    for c in input.encode_utf16() {
+        if !compressor.dictionary.contains_key(&[c][..]) {
+            compressor.add(vec![c]);
+            compressor.to_create.insert(vec![c]);
+        }
+        let mut wc = w.clone();
+        wc.push(c);
+        if compressor.dictionary.contains_key(&wc) {
+            w = wc;
+        } else {
+            compressor.emit(&w);
+            compressor.add(wc);
+            w = vec![c];
+        }
+    }
+    if !w.is_empty() {
+        compressor.emit(&w);
+    }
+    // End of stream.
+    compressor.writer.write(compressor.num_bits, 2);
+    compressor.writer.finish()
+}
+
+/// Compresses text like lz-string's `compressToBase64`.
+pub fn compress_to_base64(input: &str) -> String {
+    let mut text: String = compress(input, 6)
+        .into_iter()
+        .map(|value| BASE64[value as usize] as char)
+        .collect();
+    while !text.len().is_multiple_of(4) {
+        text.push('=');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compresses_like_lz_string() {
+        assert_eq!(compress_to_base64("hello"), "BYUwNmD2Q===");
+        assert_eq!(compress_to_base64("aaaaaaaaaaaaaaaaaaaaab"), "IY18QRkA");
+        assert_eq!(
+            compress_to_base64("{\"size\":9,\"grid\":[]}"),
+            "N4IgzglgXgpiBcBOANCA5gJwgEwQbQF0BfIA"
+        );
+        assert_eq!(compress_to_base64("héllo ☃"), "BYS4NmD2AEjAZEA=");
+    }
+}
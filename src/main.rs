@@ -1,192 +1,921 @@
+// Builds without the window share its game, saves, and co-op code, much
+// of which only the window reaches.
+#![cfg_attr(not(feature = "gui"), allow(dead_code))]
+
+use clap::Parser;
+#[cfg(feature = "gui")]
 use glutin_window::GlutinWindow;
-use graphics::character::CharacterCache;
+#[cfg(feature = "gui")]
 use graphics::types::Color;
-use graphics::{Context, Graphics};
-use opengl_graphics::{Filter, GlGraphics, GlyphCache, OpenGL, TextureSettings};
+#[cfg(feature = "gui")]
+use graphics::Viewport;
+#[cfg(feature = "gui")]
+use opengl_graphics::{GlGraphics, GlyphCache, OpenGL};
+#[cfg(feature = "gui")]
 use piston::event_loop::{EventLoop, EventSettings, Events};
+#[cfg(feature = "gui")]
+use piston::input::keyboard::ModifierKey;
+#[cfg(feature = "gui")]
 use piston::input::GenericEvent;
-use piston::input::RenderEvent;
-use piston::window::WindowSettings;
-use rand::{rngs::ThreadRng, thread_rng, Rng};
-use std::collections::HashSet;
-
-const SIZE: usize = 9;
-
-pub struct Gameboard {
-    pub cells: [[u8; SIZE]; SIZE],
-    solved: bool,
-    rng: ThreadRng,
-}
-
-impl Gameboard {
-    pub fn new() -> Gameboard {
-        Gameboard {
-            cells: [[0; SIZE]; SIZE],
-            solved: false,
-            rng: thread_rng(),
-        }
-    }
-
-    /// Gets the character at cell location.
-    pub fn char(&self, ind: [usize; 2]) -> Option<char> {
-        Some(match self.cells[ind[1]][ind[0]] {
-            1 => '1',
-            2 => '2',
-            3 => '3',
-            4 => '4',
-            5 => '5',
-            6 => '6',
-            7 => '7',
-            8 => '8',
-            9 => '9',
-            _ => return None,
-        })
+#[cfg(feature = "gui")]
+use piston::input::{RenderEvent, ResizeEvent, UpdateEvent};
+#[cfg(feature = "gui")]
+use piston::window::{AdvancedWindow, Window, WindowSettings};
+#[cfg(feature = "gui")]
+use rand::{thread_rng, Rng};
+use std::time::{Duration, Instant};
+#[cfg(feature = "gui")]
+use tracing::error;
+use tracing::warn;
+
+mod achievements;
+#[cfg(feature = "gui")]
+mod animation;
+#[cfg(feature = "gui")]
+mod chat;
+mod cli;
+#[cfg(feature = "gui")]
+mod clipboard;
+mod collection;
+mod config;
+mod coop;
+mod daily;
+mod database;
+#[cfg(feature = "gui")]
+mod debug;
+mod discovery;
+#[cfg(feature = "gui")]
+mod editor;
+#[cfg(feature = "gui")]
+mod feed;
+#[cfg(feature = "gui")]
+mod files;
+#[cfg(feature = "gui")]
+mod font;
+mod http;
+#[cfg(feature = "gui")]
+mod keypad;
+#[cfg(feature = "gui")]
+mod lobby;
+mod logging;
+#[cfg(feature = "gui")]
+mod menu;
+mod movelog;
+#[cfg(feature = "gui")]
+mod opengl;
+mod opensudoku;
+mod pdf;
+#[cfg(feature = "gui")]
+mod presence;
+mod profile;
+#[cfg(feature = "gui")]
+mod prompt;
+mod puzzle;
+mod qr;
+mod replay;
+mod save;
+#[cfg(feature = "gui")]
+mod screenshot;
+#[cfg(feature = "sdl")]
+mod sdl;
+#[cfg(feature = "gui")]
+mod settings;
+#[cfg(feature = "gui")]
+mod share;
+#[cfg(feature = "gui")]
+mod slots;
+#[cfg(feature = "gui")]
+mod sound;
+mod stats;
+#[cfg(feature = "gui")]
+mod svg;
+#[cfg(feature = "gui")]
+mod text;
+#[cfg(feature = "gui")]
+mod theme;
+#[cfg(feature = "gui")]
+mod toolbar;
+mod tui;
+#[cfg(feature = "gui")]
+mod win;
+
+#[cfg(feature = "gui")]
+use sudoku::render::Renderer;
+use sudoku::{
+    canonical, constraints, csv, digits, fpuzzles, grid, rules, script, sdk, solver, technique,
+    variant,
+};
+use sudoku::{Difficulty, Gameboard, House, SIZE};
+
+#[cfg(feature = "gui")]
+use achievements::{Achievement, AchievementsView, AchievementsViewSettings};
+#[cfg(feature = "gui")]
+use animation::{Animations, Easing, Effect, Tween};
+#[cfg(feature = "gui")]
+use chat::{ChatController, ChatView, ChatViewSettings};
+#[cfg(feature = "gui")]
+use collection::{
+    Collection, CollectionController, CollectionView, CollectionViewSettings, PACKS_DIR,
+    PROGRESS_FILE,
+};
+#[cfg(feature = "gui")]
+use config::Keybindings;
+use config::{Config, InputMode};
+use coop::Partner;
+#[cfg(feature = "gui")]
+use coop::{Role, Session, COOP_ADDRESS};
+#[cfg(feature = "gui")]
+use daily::{Calendar, DailyController, DailyView, DailyViewSettings};
+use database::Database;
+#[cfg(feature = "gui")]
+use database::{Search, DATABASE_FILE};
+#[cfg(feature = "gui")]
+use debug::{DebugController, DebugView, DebugViewSettings};
+#[cfg(feature = "gui")]
+use discovery::Browser;
+#[cfg(feature = "gui")]
+use editor::{EditorAction, EditorController};
+#[cfg(feature = "gui")]
+use feed::FEED_CACHE_FILE;
+#[cfg(feature = "gui")]
+use files::{FilesController, FilesView, FilesViewSettings};
+#[cfg(feature = "gui")]
+use font::FONTS_DIR;
+#[cfg(feature = "gui")]
+use fpuzzles::FPuzzle;
+#[cfg(feature = "gui")]
+use grid::{box_shape, char_digit, digit_char, Grid};
+#[cfg(feature = "gui")]
+use keypad::{KeypadController, KeypadView, KeypadViewSettings};
+#[cfg(feature = "gui")]
+use lobby::{LobbyAction, LobbyController, LobbyView, LobbyViewSettings};
+use logging::LOGS_DIR;
+#[cfg(feature = "gui")]
+use menu::{MenuAction, MenuController, MenuView, MenuViewSettings};
+use movelog::LogEntry;
+#[cfg(feature = "gui")]
+use movelog::{MoveLogView, MoveLogViewSettings};
+#[cfg(feature = "gui")]
+use opengl::OpenGlRenderer;
+#[cfg(feature = "gui")]
+use pdf::PrintedPuzzle;
+#[cfg(feature = "gui")]
+use presence::Presence;
+#[cfg(feature = "gui")]
+use profile::{Profile, ProfileAction, ProfilesController};
+#[cfg(feature = "gui")]
+use prompt::{PromptController, PromptView, PromptViewSettings};
+#[cfg(feature = "gui")]
+use puzzle::{SaveFormat, PUZZLES_DIR};
+#[cfg(feature = "gui")]
+use replay::Playback;
+use replay::Replay;
+use rules::RuleSet;
+#[cfg(feature = "gui")]
+use save::{SavedGame, AUTOSAVE_FILE, AUTOSAVE_MOVES, SAVES_DIR};
+#[cfg(feature = "gui")]
+use screenshot::{EXPORTS_DIR, SCREENSHOTS_DIR};
+use script::{Script, SCRIPTS_DIR};
+#[cfg(feature = "gui")]
+use settings::{Choices, SettingsController, SettingsView, SettingsViewSettings};
+#[cfg(feature = "gui")]
+use share::{ShareView, ShareViewSettings, Shared};
+#[cfg(feature = "gui")]
+use slots::{SlotAction, SlotsController, SlotsView, SlotsViewSettings};
+#[cfg(feature = "gui")]
+use sound::{Cue, Sounds};
+#[cfg(feature = "gui")]
+use stats::{Dashboard, StatsView, StatsViewSettings};
+#[cfg(feature = "gui")]
+use theme::{Theme, DARK_THEME, THEMES_DIR};
+#[cfg(feature = "gui")]
+use toolbar::{ToolbarController, ToolbarView, ToolbarViewSettings};
+use variant::Variant;
+#[cfg(feature = "gui")]
+use variant::{sign_strokes, DotKind, Parity, VariantKind};
+#[cfg(feature = "gui")]
+use win::{WinAction, WinController, WinView, WinViewSettings};
+
+/// Something the player can do to the board, from a key or a toolbar button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameAction {
+    Undo,
+    Redo,
+    Erase,
+    /// Switches digit entry between values and pencil marks.
+    Notes,
+    Hint,
+    /// Marks entries that disagree with the solution.
+    Check,
+    /// Shows the solution faintly for a few seconds.
+    Peek,
+}
+
+impl GameAction {
+    pub fn label(self) -> &'static str {
+        match self {
+            GameAction::Undo => "Undo",
+            GameAction::Redo => "Redo",
+            GameAction::Erase => "Erase",
+            GameAction::Notes => "Notes",
+            GameAction::Hint => "Hint",
+            GameAction::Check => "Check",
+            GameAction::Peek => "Peek",
+        }
+    }
+}
+
+/// A change to a game, as `GameboardController::dispatch` carries it out.
+///
+/// Keys, clicks, the toolbar and keypad, co-op partners, and replays all
+/// come down to these, so each change to the board is made in one place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Selects a cell, moving the keyboard focus there too.
+    Select([usize; 2]),
+    /// Moves the keyboard focus, leaving the selection.
+    Focus([usize; 2]),
+    /// Enters a digit in a cell, as a note in notes mode.
+    Enter([usize; 2], u8),
+    /// Picks the digit entered in the cells clicked next.
+    ActiveDigit(u8),
+    /// A shortcut key or toolbar button.
+    Command(GameAction),
+    /// Sets a cell as a co-op partner changed it.
+    Partner {
+        player: u8,
+        cell: [usize; 2],
+        state: (u8, u32),
+    },
+    /// Sets a cell as a replayed move left it, selecting it.
+    Replay { cell: [usize; 2], state: (u8, u32) },
+}
+
+/// A change to one cell's value and notes, kept for undo and redo.
+#[derive(Clone, Copy, Debug)]
+struct Edit {
+    ind: [usize; 2],
+    before: (u8, u32),
+    after: (u8, u32),
+}
+
+pub struct GameboardController {
+    pub gameboard: Gameboard,
+    pub selected_cell: Option<[usize; 2]>,
+    /// Cell moved to with the arrow keys.
+    pub focused_cell: Option<[usize; 2]>,
+    /// Digit placed by clicking cells in digit-first input mode.
+    pub active_digit: Option<u8>,
+    /// Whether digits toggle pencil marks instead of filling cells.
+    pub notes_mode: bool,
+    /// Entries found wrong by the last check, until the board changes.
+    pub wrong_entries: Vec<[usize; 2]>,
+    pub start_time: Instant,
+    /// How long the puzzle took, once solved.
+    pub solve_time: Option<Duration>,
+    /// Entries that disagreed with the solution.
+    pub mistakes: u32,
+    /// Mistakes that end the game, if limited.
+    pub mistake_limit: Option<u32>,
+    pub hints: u32,
+    /// Whether the player ended the game without solving it.
+    pub gave_up: bool,
+    /// Times the solution was peeked at.
+    pub peeks: u32,
+    /// When the current peek at the solution ends.
+    peek_until: Option<Instant>,
+    /// Houses completed since the view last took them.
+    pub completed_houses: Vec<House>,
+    /// Entries repeating a digit in their row, column, or box, since the
+    /// view last took them.
+    pub clashing_entries: Vec<[usize; 2]>,
+    undo_stack: Vec<Edit>,
+    redo_stack: Vec<Edit>,
+    /// Board changes since the game was last saved.
+    pub moves_since_save: u32,
+    /// Transient status bar message and when it was posted.
+    message: Option<(String, Instant)>,
+    cursor_pos: [f64; 2],
+    /// Cell under the mouse pointer.
+    hovered_cell: Option<[usize; 2]>,
+    /// Whether a key was pressed since the mouse last moved.
+    keyboard_driven: bool,
+    /// Every change to the board, to play back later.
+    pub replay: Replay,
+    /// The last attempt at the same puzzle, to race against.
+    pub ghost: Option<Replay>,
+    /// Whether the last attempt has been looked for.
+    pub ghost_loaded: bool,
+    /// What the player did, in order, including undos.
+    pub move_log: Vec<LogEntry>,
+    /// The day of the daily puzzle being played, as days since the epoch.
+    pub daily: Option<i64>,
+    /// The other players of a co-op game, with the cells they have selected.
+    pub partners: Vec<Partner>,
+    /// Whether the board is another player's, only watched.
+    pub spectating: bool,
+}
+
+impl GameboardController {
+    pub fn new(gameboard: Gameboard) -> GameboardController {
+        let replay = Replay::new(&gameboard, Duration::ZERO, 0);
+        GameboardController {
+            gameboard,
+            selected_cell: None,
+            focused_cell: None,
+            active_digit: None,
+            notes_mode: false,
+            wrong_entries: Vec::new(),
+            start_time: Instant::now(),
+            solve_time: None,
+            mistakes: 0,
+            mistake_limit: None,
+            hints: 0,
+            gave_up: false,
+            peeks: 0,
+            peek_until: None,
+            completed_houses: Vec::new(),
+            clashing_entries: Vec::new(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            moves_since_save: 0,
+            message: None,
+            cursor_pos: [0.0; 2],
+            hovered_cell: None,
+            keyboard_driven: false,
+            replay,
+            ghost: None,
+            ghost_loaded: false,
+            move_log: Vec::new(),
+            daily: None,
+            partners: Vec::new(),
+            spectating: false,
+        }
+    }
+
+    /// Time spent on the current game.
+    pub fn elapsed(&self) -> Duration {
+        self.solve_time.unwrap_or_else(|| self.start_time.elapsed())
+    }
+
+    /// Whether the mistake limit has been reached.
+    pub fn is_lost(&self) -> bool {
+        self.mistake_limit
+            .is_some_and(|limit| self.mistakes >= limit)
+    }
+
+    /// Whether the game has ended, won or lost.
+    pub fn is_over(&self) -> bool {
+        self.gameboard.is_solved() || self.is_lost() || self.gave_up
+    }
+
+    /// Ends the game unsolved.
+    pub fn give_up(&mut self) {
+        if !self.is_over() {
+            self.gave_up = true;
+            self.solve_time = Some(self.start_time.elapsed());
+        }
+    }
+
+    /// Shows a message in the status bar for a few seconds.
+    pub fn notify(&mut self, text: &str) {
+        self.message = Some((text.to_string(), Instant::now()));
     }
 
-    /// Set cell value.
-    pub fn set(&mut self, ind: [usize; 2], val: u8) {
-        self.cells[ind[1]][ind[0]] = val;
-        self.solved = self.solved();
+    /// The status bar message, if it has not expired.
+    pub fn message(&self) -> Option<&str> {
+        match &self.message {
+            Some((text, posted)) if posted.elapsed() < MESSAGE_DURATION => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Whether the solution is being shown.
+    pub fn is_peeking(&self) -> bool {
+        self.peek_until.is_some_and(|until| Instant::now() < until)
     }
 
-    pub fn leftright(&self, x: usize, y: usize) -> HashSet<u8> {
-        let mut leftright = HashSet::new();
-        for i in 0..x {
-            leftright.insert(self.cells[y][i]);
+    /// Cell to highlight under the mouse pointer, hidden while using the keyboard.
+    pub fn hovered_cell(&self) -> Option<[usize; 2]> {
+        if self.keyboard_driven {
+            None
+        } else {
+            self.hovered_cell
         }
-        for i in x..9 {
-            leftright.insert(self.cells[y][i]);
+    }
+
+    /// Cell to ring as the keyboard focus, shown only while using the keyboard.
+    pub fn focus_ring_cell(&self) -> Option<[usize; 2]> {
+        if self.keyboard_driven {
+            self.focused_cell
+        } else {
+            None
         }
-        leftright
     }
 
-    pub fn updown(&self, x: usize, y: usize) -> HashSet<u8> {
-        let mut updown = HashSet::new();
-        for j in 0..y {
-            updown.insert(self.cells[j][x]);
+    /// Moves the keyboard focus by a step, wrapping around the board edges.
+    ///
+    /// In cell-first mode the selection follows the focus.
+    pub fn move_action(&self, step: [usize; 2], config: &Config) -> Action {
+        let size = self.gameboard.size();
+        let mut focus = match self.focused_cell.or(self.selected_cell) {
+            Some([x, y]) => [(x + step[0]) % size, (y + step[1]) % size],
+            None => [size / 2; 2],
+        };
+        // Skip over the gaps between the grids of a samurai board.
+        while !self.gameboard.in_play(focus) {
+            focus = [(focus[0] + step[0]) % size, (focus[1] + step[1]) % size];
+        }
+        match config.input_mode {
+            InputMode::CellFirst => Action::Select(focus),
+            InputMode::DigitFirst => Action::Focus(focus),
         }
-        for j in y..9 {
-            updown.insert(self.cells[j][x]);
+    }
+
+    /// Selects a clicked cell, entering the active digit there in
+    /// digit-first mode.
+    pub fn click_actions(&self, ind: [usize; 2], config: &Config) -> Vec<Action> {
+        let mut actions = vec![Action::Select(ind)];
+        if config.input_mode == InputMode::DigitFirst {
+            actions.extend(self.active_digit.map(|val| Action::Enter(ind, val)));
         }
-        updown
+        actions
     }
 
-    pub fn inbox(&self, x: usize, y: usize) -> HashSet<u8> {
-        let mut inbox = HashSet::new();
-        let grid_x = x / 3;
-        let grid_y = y / 3;
-        for j in grid_y * 3..(grid_y + 1) * 3 {
-            for i in grid_x * 3..(grid_x + 1) * 3 {
-                inbox.insert(self.cells[j][i]);
+    /// Enters the active digit in the focused cell, in digit-first mode.
+    pub fn confirm_actions(&self, config: &Config) -> Vec<Action> {
+        match (self.focused_cell, self.active_digit) {
+            (Some(ind), Some(val)) if config.input_mode == InputMode::DigitFirst => {
+                vec![Action::Select(ind), Action::Enter(ind, val)]
             }
+            _ => Vec::new(),
         }
-        inbox
     }
 
-    fn fullset(&self) -> HashSet<u8> {
-        (0..9).collect()
+    /// Finds the cell under the mouse pointer on a board zoomed by `zoom`
+    /// and scrolled by `pan`.
+    fn cell_at_cursor(
+        &self,
+        pos: [f64; 2],
+        size: f64,
+        zoom: f64,
+        pan: [f64; 2],
+    ) -> Option<[usize; 2]> {
+        // Find coordinates relative to upper left corner.
+        let x = self.cursor_pos[0] - pos[0];
+        let y = self.cursor_pos[1] - pos[1];
+        // Check that coordinates are inside board boundaries.
+        if x >= 0.0 && x < size && y >= 0.0 && y < size {
+            // Undo the zoom, then compute the cell position.
+            let x = (x + pan[0]) / zoom;
+            let y = (y + pan[1]) / zoom;
+            let cells = self.gameboard.size() as f64;
+            Some([(x / size * cells) as usize, (y / size * cells) as usize])
+                .filter(|&ind| self.gameboard.in_play(ind))
+        } else {
+            None
+        }
+    }
+
+    /// A cell's value and notes.
+    fn cell_state(&self, ind: [usize; 2]) -> (u8, u32) {
+        let [i, j] = ind;
+        (self.gameboard.cells[j][i], self.gameboard.notes[j][i])
     }
 
-    pub fn populate(&mut self) {
-        let mut seed = Vec::new();
-        let set = self.fullset();
-        let mut possibilities: Vec<u8> = set.into_iter().collect();
-        for _ in 0..9 {
-            let index = self.rng.gen_range(0, possibilities.len());
-            seed.push(possibilities.remove(index) + 1);
+    /// Sets a cell's value and notes without recording the change.
+    fn apply(&mut self, ind: [usize; 2], (val, notes): (u8, u32)) {
+        self.gameboard.notes[ind[1]][ind[0]] = notes;
+        self.gameboard.set(ind, val);
+        self.wrong_entries.clear();
+        self.moves_since_save += 1;
+        self.replay
+            .record(self.elapsed(), ind, (val, notes), self.mistakes);
+    }
+
+    /// Changes a cell, recording the change for undo.
+    fn edit(&mut self, ind: [usize; 2], after: (u8, u32)) {
+        let before = self.cell_state(ind);
+        if before == after {
+            return;
         }
-        // https://gamedev.stackexchange.com/questions/56149/how-can-i-generate-sudoku-puzzles
-        let indexes = vec![
-            vec![0, 1, 2, 3, 4, 5, 6, 7, 8],
-            vec![3, 4, 5, 6, 7, 8, 0, 1, 2], // shift 3
-            vec![6, 7, 8, 0, 1, 2, 3, 4, 5], // shift 3
-            vec![7, 8, 0, 1, 2, 3, 4, 5, 6], // shift 1
-            vec![1, 2, 3, 4, 5, 6, 7, 8, 0], // shift 3
-            vec![4, 5, 6, 7, 8, 0, 1, 2, 3], // shift 3
-            vec![5, 6, 7, 8, 0, 1, 2, 3, 4], // shift 1
-            vec![8, 0, 1, 2, 3, 4, 5, 6, 7], // shift 3
-            vec![2, 3, 4, 5, 6, 7, 8, 0, 1], // shift 3
-        ];
-        for j in 0..9 {
-            for i in 0..9 {
-                self.cells[j][i] = seed[indexes[j][i]];
+        self.apply(ind, after);
+        self.undo_stack.push(Edit { ind, before, after });
+        self.redo_stack.clear();
+    }
+
+    /// Places a value, tracking mistakes and completion, and returns whether
+    /// the cell changed.
+    fn place(&mut self, ind: [usize; 2], val: u8) -> bool {
+        if self.is_over()
+            || self.gameboard.is_given(ind)
+            || !self.gameboard.in_play(ind)
+            || self.gameboard.cells[ind[1]][ind[0]] == val
+        {
+            return false;
+        }
+        let gentle = self.gameboard.difficulty == Difficulty::Kids;
+        if val != self.gameboard.solution[ind[1]][ind[0]] {
+            if gentle {
+                self.notify("Not quite - try another number");
+            } else {
+                self.mistakes += 1;
+            }
+        }
+        let notes = self.gameboard.notes[ind[1]][ind[0]];
+        self.edit(ind, (val, notes));
+        if self.gameboard.conflicts(ind) && !gentle {
+            self.clashing_entries.push(ind);
+        }
+        for house in self.gameboard.houses(ind) {
+            if self.gameboard.house_complete(house) {
+                self.completed_houses.push(house);
             }
         }
+        if self.is_over() && self.solve_time.is_none() {
+            self.solve_time = Some(self.start_time.elapsed());
+        }
+        true
     }
 
-    pub fn solved(&self) -> bool {
-        let solution = 1 + 2 + 3 + 4 + 5 + 6 + 7 + 8 + 9;
-        for j in 0..9 {
-            for i in 0..9 {
-                if self.updown(i, j).iter().map(|v| *v as u64).sum::<u64>() != solution {
-                    return false;
-                }
-                if self.leftright(i, j).iter().map(|v| *v as u64).sum::<u64>() != solution {
-                    return false;
+    /// Toggles a pencil mark in an empty cell.
+    fn toggle_note(&mut self, ind: [usize; 2], val: u8) {
+        let (cell, notes) = self.cell_state(ind);
+        if self.is_over()
+            || self.gameboard.is_given(ind)
+            || !self.gameboard.in_play(ind)
+            || cell != 0
+        {
+            return;
+        }
+        self.edit(ind, (0, notes ^ (1 << val)));
+        self.move_log.push(LogEntry::Note {
+            cell: ind,
+            value: val,
+            added: notes & (1 << val) == 0,
+        });
+    }
+
+    /// Places a value or toggles a note, depending on the notes mode.
+    fn enter(&mut self, ind: [usize; 2], val: u8) {
+        if self.notes_mode {
+            self.toggle_note(ind, val);
+        } else if self.place(ind, val) {
+            self.move_log.push(LogEntry::Place {
+                cell: ind,
+                value: val,
+            });
+        }
+    }
+
+    /// Clears the selected cell's value and notes.
+    fn erase(&mut self) {
+        if let Some(ind) = self.selected_cell {
+            if !self.is_over() && !self.gameboard.is_given(ind) && self.cell_state(ind) != (0, 0) {
+                self.edit(ind, (0, 0));
+                self.move_log.push(LogEntry::Erase { cell: ind });
+            }
+        }
+    }
+
+    /// Runs a script tool over the board, taking the cells and notes it
+    /// changes as moves that can be undone, and returns how many cells it
+    /// changed. A tool that fails changes nothing.
+    fn run_tool(&mut self, tool: &Script) -> Result<usize, String> {
+        if self.is_over() || self.spectating {
+            return Err("the board cannot be changed now".to_string());
+        }
+        let (cells, notes) = (self.gameboard.cells.clone(), self.gameboard.notes.clone());
+        let result = tool.run(&mut self.gameboard);
+        let changes: Vec<([usize; 2], (u8, u32))> = cells
+            .positions()
+            .map(|ind| (ind, self.cell_state(ind)))
+            .filter(|&([i, j], after)| after != (cells[j][i], notes[j][i]))
+            .collect();
+        self.gameboard.cells = cells;
+        self.gameboard.notes = notes;
+        self.gameboard.update_solved();
+        result?;
+        for &(ind, (val, after)) in &changes {
+            let [i, j] = ind;
+            if val == 0 && self.gameboard.cells[j][i] != 0 {
+                self.edit(ind, (0, self.gameboard.notes[j][i]));
+                self.move_log.push(LogEntry::Erase { cell: ind });
+            } else if val != 0 && self.place(ind, val) {
+                self.move_log.push(LogEntry::Place {
+                    cell: ind,
+                    value: val,
+                });
+            }
+            let (val, before) = self.cell_state(ind);
+            if before == after || self.is_over() {
+                continue;
+            }
+            self.edit(ind, (val, after));
+            for value in
+                (1..=self.gameboard.digits() as u8).filter(|v| (before ^ after) & (1 << v) != 0)
+            {
+                self.move_log.push(LogEntry::Note {
+                    cell: ind,
+                    value,
+                    added: after & (1 << value) != 0,
+                });
+            }
+        }
+        Ok(changes.len())
+    }
+
+    fn undo(&mut self) {
+        if self.is_over() {
+            return;
+        }
+        match self.undo_stack.pop() {
+            Some(edit) => {
+                self.apply(edit.ind, edit.before);
+                self.selected_cell = Some(edit.ind);
+                self.redo_stack.push(edit);
+                self.move_log.push(LogEntry::Undo);
+            }
+            None => self.notify("Nothing to undo"),
+        }
+    }
+
+    fn redo(&mut self) {
+        if self.is_over() {
+            return;
+        }
+        match self.redo_stack.pop() {
+            Some(edit) => {
+                self.apply(edit.ind, edit.after);
+                self.selected_cell = Some(edit.ind);
+                self.undo_stack.push(edit);
+                self.move_log.push(LogEntry::Redo);
+            }
+            None => self.notify("Nothing to redo"),
+        }
+    }
+
+    /// Fills the selected cell with its solution, or with nothing to fill
+    /// there, a cell whose digit follows from the correct entries in one
+    /// step, saying why when it does.
+    fn hint(&mut self) {
+        let board = &self.gameboard;
+        let mut known = board.cells.clone();
+        for [i, j] in board.cells.positions() {
+            if known[j][i] != board.solution[j][i] {
+                known[j][i] = 0;
+            }
+        }
+        let deductions = solver::deductions(&known, &board.rules);
+        let wanted = self
+            .selected_cell
+            .filter(|&[i, j]| !board.is_given([i, j]) && board.cells[j][i] != board.solution[j][i]);
+        let (ind, deduction) = match wanted {
+            Some(ind) => (
+                ind,
+                deductions.iter().find(|deduction| deduction.cell == ind),
+            ),
+            None => match deductions.first() {
+                Some(deduction) => (deduction.cell, Some(deduction)),
+                None => {
+                    self.notify("No hint available");
+                    return;
                 }
-                if self.inbox(i, j).iter().map(|v| *v as u64).sum::<u64>() != solution {
-                    return false;
+            },
+        };
+        let reason = deduction.map(|deduction| deduction.describe(board.rules.layout()));
+        let val = board.solution[ind[1]][ind[0]];
+        self.hints += 1;
+        self.selected_cell = Some(ind);
+        if self.place(ind, val) {
+            self.move_log.push(LogEntry::Hint {
+                cell: ind,
+                value: val,
+            });
+        }
+        if let Some(reason) = reason {
+            self.notify(&reason);
+        }
+    }
+
+    /// Marks filled cells that disagree with the solution.
+    fn check(&mut self) {
+        let board = &self.gameboard;
+        self.wrong_entries = board
+            .cells
+            .positions()
+            .filter(|&[i, j]| board.cells[j][i] != 0 && board.cells[j][i] != board.solution[j][i])
+            .collect();
+        match self.wrong_entries.len() {
+            0 => self.notify("No mistakes found"),
+            1 => self.notify("1 wrong entry"),
+            n => self.notify(&format!("{} wrong entries", n)),
+        }
+    }
+
+    /// Shows the solution for a while, counting it as assistance.
+    fn peek(&mut self) {
+        if self.is_over() {
+            return;
+        }
+        if !self.is_peeking() {
+            self.peeks += 1;
+        }
+        self.peek_until = Some(Instant::now() + PEEK_DURATION);
+        self.notify("Peeking at the solution");
+    }
+
+    /// Sets a cell as a co-op partner changed it, logging their entries and
+    /// erasures as theirs. Their changes are not undone by this player's
+    /// undo, and their mistakes are not counted against this player.
+    ///
+    /// Changes to givens and to cells out of play are refused, but not
+    /// changes once this player's game is over: the host settles which
+    /// changes are made, so every board ends alike.
+    fn apply_partner(&mut self, player: u8, ind: [usize; 2], (val, notes): (u8, u32)) {
+        let size = self.gameboard.size();
+        if ind[0] >= size
+            || ind[1] >= size
+            || val as usize > self.gameboard.digits()
+            || self.gameboard.is_given(ind)
+            || !self.gameboard.in_play(ind)
+        {
+            return;
+        }
+        let before = self.cell_state(ind);
+        if before == (val, notes) {
+            return;
+        }
+        self.apply(ind, (val, notes));
+        if val != before.0 {
+            self.move_log.push(LogEntry::Partner {
+                player,
+                cell: ind,
+                value: val,
+            });
+        }
+        if val != 0 {
+            for house in self.gameboard.houses(ind) {
+                if self.gameboard.house_complete(house) {
+                    self.completed_houses.push(house);
                 }
             }
         }
-        return true;
+        if self.is_over() && self.solve_time.is_none() {
+            self.solve_time = Some(self.start_time.elapsed());
+        }
     }
-}
-
-pub struct GameboardController {
-    pub gameboard: Gameboard,
-    pub selected_cell: Option<[usize; 2]>,
-    cursor_pos: [f64; 2],
-}
 
-impl GameboardController {
-    pub fn new(gameboard: Gameboard) -> GameboardController {
-        GameboardController {
-            gameboard,
-            selected_cell: None,
-            cursor_pos: [0.0; 2],
+    /// Carries out an action, the one way the game changes.
+    pub fn dispatch(&mut self, action: Action) {
+        match action {
+            Action::Select(ind) => {
+                self.selected_cell = Some(ind);
+                self.focused_cell = Some(ind);
+            }
+            Action::Focus(ind) => self.focused_cell = Some(ind),
+            Action::Enter(ind, val) => self.enter(ind, val),
+            Action::ActiveDigit(val) => self.active_digit = Some(val),
+            Action::Command(GameAction::Undo) => self.undo(),
+            Action::Command(GameAction::Redo) => self.redo(),
+            Action::Command(GameAction::Erase) => self.erase(),
+            Action::Command(GameAction::Notes) => self.notes_mode = !self.notes_mode,
+            Action::Command(GameAction::Hint) => self.hint(),
+            Action::Command(GameAction::Check) => self.check(),
+            Action::Command(GameAction::Peek) => self.peek(),
+            Action::Partner {
+                player,
+                cell,
+                state,
+            } => self.apply_partner(player, cell, state),
+            Action::Replay { cell, state } => {
+                self.apply(cell, state);
+                self.selected_cell = Some(cell);
+            }
         }
     }
 
-    /// Handles events.
-    pub fn event<E: GenericEvent>(&mut self, pos: [f64; 2], size: f64, e: &E) {
-        use piston::input::{Button, Key, MouseButton};
+    /// Follows the cursor, and maps clicks and keys to actions for
+    /// `dispatch` to carry out.
+    #[cfg(feature = "gui")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn event<E: GenericEvent>(
+        &mut self,
+        pos: [f64; 2],
+        size: f64,
+        zoom: f64,
+        pan: [f64; 2],
+        modifiers: ModifierKey,
+        config: &Config,
+        e: &E,
+    ) -> Vec<Action> {
+        use piston::input::{Button, MouseButton};
 
-        if let Some(pos) = e.mouse_cursor_args() {
-            self.cursor_pos = pos;
+        if let Some(cursor) = e.mouse_cursor_args() {
+            self.cursor_pos = cursor;
+            self.hovered_cell = self.cell_at_cursor(pos, size, zoom, pan);
+            self.keyboard_driven = false;
         }
-        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
-            // Find coordinates relative to upper left corner.
-            let x = self.cursor_pos[0] - pos[0];
-            let y = self.cursor_pos[1] - pos[1];
-            // Check that coordinates are inside board boundaries.
-            if x >= 0.0 && x <= size && y >= 0.0 && y <= size {
-                // Compute the cell position.
-                let cell_x = (x / size * 9.0) as usize;
-                let cell_y = (y / size * 9.0) as usize;
-                self.selected_cell = Some([cell_x, cell_y]);
+        if e.cursor_args() == Some(false) {
+            self.hovered_cell = None;
+        }
+        match e.press_args() {
+            Some(Button::Mouse(MouseButton::Left)) => self
+                .cell_at_cursor(pos, size, zoom, pan)
+                .map(|ind| self.click_actions(ind, config))
+                .unwrap_or_default(),
+            Some(Button::Keyboard(key)) => {
+                self.keyboard_driven = true;
+                self.key_actions(key, modifiers, config)
             }
+            _ => Vec::new(),
         }
-        if let Some(Button::Keyboard(key)) = e.press_args() {
-            if let Some(ind) = self.selected_cell {
-                // Set cell value.
-                match key {
-                    Key::D1 => self.gameboard.set(ind, 1),
-                    Key::D2 => self.gameboard.set(ind, 2),
-                    Key::D3 => self.gameboard.set(ind, 3),
-                    Key::D4 => self.gameboard.set(ind, 4),
-                    Key::D5 => self.gameboard.set(ind, 5),
-                    Key::D6 => self.gameboard.set(ind, 6),
-                    Key::D7 => self.gameboard.set(ind, 7),
-                    Key::D8 => self.gameboard.set(ind, 8),
-                    Key::D9 => self.gameboard.set(ind, 9),
-                    _ => {}
-                }
+    }
+
+    /// Maps a key to what it does: enter a digit, take a shortcut bound to
+    /// it, or move around the board.
+    ///
+    /// Letters that are digits on this board enter them, so shortcuts bound
+    /// to those letters are taken with Alt held instead.
+    #[cfg(feature = "gui")]
+    fn key_actions(
+        &self,
+        key: piston::input::Key,
+        modifiers: ModifierKey,
+        config: &Config,
+    ) -> Vec<Action> {
+        use piston::input::Key;
+
+        let size = self.gameboard.size();
+        if !modifiers.contains(ModifierKey::ALT) {
+            if let Some(val) = key_digit(key, self.gameboard.digits()) {
+                return self.digit_action(val, config).into_iter().collect();
             }
         }
+        if let Some(action) = key_action(key, &config.keybindings) {
+            return vec![Action::Command(action)];
+        }
+        match key {
+            Key::Left => vec![self.move_action([size - 1, 0], config)],
+            Key::Right => vec![self.move_action([1, 0], config)],
+            Key::Up => vec![self.move_action([0, size - 1], config)],
+            Key::Down => vec![self.move_action([0, 1], config)],
+            Key::Return | Key::Space => self.confirm_actions(config),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Maps a digit from the keyboard or keypad to its entry.
+    ///
+    /// Digits above the board's highest are ignored, and in strict mode so
+    /// are digits already placed everywhere they go, outside notes mode.
+    pub fn digit_action(&self, val: u8, config: &Config) -> Option<Action> {
+        if val as usize > self.gameboard.digits() {
+            return None;
+        }
+        if config.strict_digits && !self.notes_mode && self.gameboard.digit_complete(val) {
+            return None;
+        }
+        match config.input_mode {
+            InputMode::CellFirst => self.selected_cell.map(|ind| Action::Enter(ind, val)),
+            InputMode::DigitFirst => Some(Action::ActiveDigit(val)),
+        }
     }
 }
 
+/// How long a status bar message stays up.
+const MESSAGE_DURATION: Duration = Duration::from_secs(3);
+
+/// How long a peek at the solution lasts.
+const PEEK_DURATION: Duration = Duration::from_secs(3);
+
+/// Maps a shortcut key to its action.
+#[cfg(feature = "gui")]
+fn key_action(key: piston::input::Key, keys: &Keybindings) -> Option<GameAction> {
+    [
+        (&keys.undo, GameAction::Undo),
+        (&keys.redo, GameAction::Redo),
+        (&keys.erase, GameAction::Erase),
+        (&keys.notes, GameAction::Notes),
+        (&keys.hint, GameAction::Hint),
+        (&keys.check, GameAction::Check),
+        (&keys.peek, GameAction::Peek),
+    ]
+    .iter()
+    .find(|(bound, _)| bound.contains(&key))
+    .map(|&(_, action)| action)
+}
+
+/// Maps a digit key to its value on a board with digits up to `digits`,
+/// with letter keys standing for the digits past 9 on boards that have them.
+#[cfg(feature = "gui")]
+fn key_digit(key: piston::input::Key, digits: usize) -> Option<u8> {
+    let ch = char::from_u32(key.code() as u32)?;
+    char_digit(ch).filter(|&val| val as usize <= digits)
+}
+
+#[cfg(feature = "gui")]
+#[derive(Clone)]
 pub struct GameboardViewSettings {
     pub position: [f64; 2],
     pub size: f64,
@@ -198,50 +927,385 @@ pub struct GameboardViewSettings {
     pub board_edge_radius: f64,
     pub section_edge_radius: f64,
     pub cell_edge_radius: f64,
+    /// Digit glyph size as a fraction of the cell size.
+    pub digit_size: f64,
+    /// Size of the status bar and coordinate label text.
+    pub font_size: u32,
+    /// User-chosen multiplier for the board and UI chrome.
+    pub ui_scale: f64,
+    /// Magnification of the board within its area, from 1 up.
+    pub zoom: f64,
+    /// Offset of the visible area into the zoomed board.
+    pub pan: [f64; 2],
     pub selected_cell_background_color: Color,
+    pub selected_cell_border_color: Color,
+    pub hover_cell_background_color: Color,
+    pub focus_ring_color: Color,
+    pub focus_ring_radius: f64,
+    pub house_background_color: Color,
+    /// Fill of the gaps between the grids of a samurai board.
+    pub void_color: Color,
+    /// Shading of the cells in houses a variant adds, such as diagonals.
+    pub variant_shade_color: Color,
+    /// Dashed outlines of killer cages and their sums, and kropki dots.
+    pub cage_color: Color,
+    /// Grey shapes under the digits: thermometers, and even and odd marks.
+    pub shape_color: Color,
+    /// German whispers lines, in the green setters draw them in.
+    pub whisper_color: Color,
     pub text_color: Color,
+    pub entry_text_color: Color,
+    pub conflict_text_color: Color,
+    pub correct_text_color: Color,
+    pub flash_color: Color,
+    /// Color of solution digits shown while peeking.
+    pub peek_text_color: Color,
+    /// Color of the last attempt's entries when racing it.
+    pub ghost_text_color: Color,
+    pub status_background_color: Color,
+    /// Fill of the progress bar along the top of the status bar.
+    pub progress_color: Color,
+    /// Fill of the last attempt's progress bar, below the player's.
+    pub ghost_progress_color: Color,
+    /// Seconds a completed house stays lit.
+    pub flash_duration: f64,
+    pub confetti_colors: Vec<Color>,
+    /// Cursors of co-op players, by player number.
+    pub partner_colors: Vec<Color>,
+    /// Seconds the win celebration plays before the win screen.
+    pub celebration_duration: f64,
 }
 
+#[cfg(feature = "gui")]
 impl GameboardViewSettings {
-    pub fn new() -> GameboardViewSettings {
+    pub fn new(theme: &Theme) -> GameboardViewSettings {
         GameboardViewSettings {
             position: [10.0; 2],
             size: 400.0,
-            background_color: [0.8, 0.8, 1.0, 1.0],
-            border_color: [0.0, 0.0, 0.2, 1.0],
-            board_edge_color: [0.0, 0.0, 0.2, 1.0],
-            section_edge_color: [0.0, 0.0, 0.2, 1.0],
-            cell_edge_color: [0.0, 0.0, 0.2, 1.0],
+            background_color: theme.board_background,
+            border_color: theme.board_edge,
+            board_edge_color: theme.board_edge,
+            section_edge_color: theme.section_edge,
+            cell_edge_color: theme.cell_edge,
             board_edge_radius: 3.0,
             section_edge_radius: 2.0,
             cell_edge_radius: 1.0,
-            selected_cell_background_color: [0.9, 0.9, 1.0, 1.0],
-            text_color: [0.0, 0.0, 0.1, 1.0],
+            digit_size: 0.765,
+            font_size: 20,
+            ui_scale: 1.0,
+            zoom: 1.0,
+            pan: [0.0; 2],
+            selected_cell_background_color: theme.selected_cell_background,
+            selected_cell_border_color: theme.selected_cell_border,
+            hover_cell_background_color: theme.hover_cell_background,
+            focus_ring_color: theme.focus_ring,
+            focus_ring_radius: 2.0,
+            house_background_color: theme.house_background,
+            void_color: theme.background,
+            variant_shade_color: [
+                theme.section_edge[0],
+                theme.section_edge[1],
+                theme.section_edge[2],
+                0.12,
+            ],
+            cage_color: theme.text,
+            // Opaque, so the shafts do not darken where they overlap.
+            shape_color: [
+                theme.board_background[0] * 0.7 + theme.section_edge[0] * 0.3,
+                theme.board_background[1] * 0.7 + theme.section_edge[1] * 0.3,
+                theme.board_background[2] * 0.7 + theme.section_edge[2] * 0.3,
+                1.0,
+            ],
+            whisper_color: [0.404, 0.941, 0.404, 1.0],
+            text_color: theme.text,
+            entry_text_color: theme.entry_text,
+            conflict_text_color: theme.conflict_text,
+            correct_text_color: theme.correct_text,
+            flash_color: theme.flash,
+            peek_text_color: [theme.text[0], theme.text[1], theme.text[2], 0.25],
+            ghost_text_color: [
+                theme.entry_text[0],
+                theme.entry_text[1],
+                theme.entry_text[2],
+                0.25,
+            ],
+            status_background_color: theme.panel_background,
+            progress_color: theme.entry_text,
+            ghost_progress_color: [
+                theme.entry_text[0],
+                theme.entry_text[1],
+                theme.entry_text[2],
+                0.4,
+            ],
+            flash_duration: 0.6,
+            confetti_colors: theme.confetti.clone(),
+            partner_colors: theme.partners.clone(),
+            celebration_duration: 2.0,
+        }
+    }
+
+    /// Switches to a theme's colors, keeping the layout and sizes.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        *self = GameboardViewSettings {
+            position: self.position,
+            size: self.size,
+            digit_size: self.digit_size,
+            board_edge_radius: self.board_edge_radius,
+            section_edge_radius: self.section_edge_radius,
+            cell_edge_radius: self.cell_edge_radius,
+            focus_ring_radius: self.focus_ring_radius,
+            font_size: self.font_size,
+            ui_scale: self.ui_scale,
+            zoom: self.zoom,
+            pan: self.pan,
+            ..GameboardViewSettings::new(theme)
+        };
+    }
+
+    /// Thickens grid lines and enlarges digits for low-vision players.
+    pub fn set_high_contrast(&mut self, on: bool) {
+        let scale = if on { 2.0 } else { 1.0 };
+        self.board_edge_radius = 3.0 * scale;
+        self.section_edge_radius = 2.0 * scale;
+        self.cell_edge_radius = 1.0 * scale;
+        self.focus_ring_radius = 2.0 * scale;
+        self.digit_size = if on { 0.9 } else { 0.765 };
+    }
+
+    /// Scales text and margins, and shrinks the board below 100%.
+    ///
+    /// Call `layout` afterwards to resize the board.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.ui_scale = scale;
+        self.font_size = (20.0 * scale).round() as u32;
+    }
+
+    /// Sizes the board to fit the window and centers it.
+    pub fn layout(&mut self, window_size: [f64; 2]) {
+        let [width, height] = window_size;
+        let available = width.min(height) - 2.0 * BOARD_MARGIN * self.ui_scale;
+        let size = (available * self.ui_scale.min(1.0)).max(SIZE as f64 * MIN_CELL_SIZE);
+        // Keep looking at the same part of a zoomed board.
+        self.pan = [
+            self.pan[0] * size / self.size,
+            self.pan[1] * size / self.size,
+        ];
+        self.size = size;
+        self.position = [(width - self.size) / 2.0, (height - self.size) / 2.0];
+    }
+
+    /// Sets the zoom, keeping the board point under `anchor` in place.
+    pub fn zoom_at(&mut self, zoom: f64, anchor: [f64; 2]) {
+        let zoom = zoom.clamp(1.0, MAX_ZOOM);
+        for (k, &anchor) in anchor.iter().enumerate() {
+            let offset = anchor - self.position[k];
+            let point = (offset + self.pan[k]) / self.zoom;
+            self.pan[k] = point * zoom - offset;
+        }
+        self.zoom = zoom;
+        self.pan_by([0.0; 2]);
+    }
+
+    /// Scrolls a zoomed board, stopping at its edges.
+    pub fn pan_by(&mut self, delta: [f64; 2]) {
+        let max = self.size * (self.zoom - 1.0);
+        for (pan, delta) in self.pan.iter_mut().zip(delta.iter()) {
+            *pan = (*pan + delta).clamp(0.0, max);
+        }
+    }
+
+    /// The settings to draw the zoomed board with, which may extend past the
+    /// board's area.
+    fn zoomed(&self) -> GameboardViewSettings {
+        GameboardViewSettings {
+            position: [
+                self.position[0] - self.pan[0],
+                self.position[1] - self.pan[1],
+            ],
+            size: self.size * self.zoom,
+            ..self.clone()
         }
     }
 }
 
+/// Space kept around the board for the status bar and coordinate labels.
+#[cfg(feature = "gui")]
+const BOARD_MARGIN: f64 = 56.0;
+/// Smallest cell size the board shrinks to.
+#[cfg(feature = "gui")]
+const MIN_CELL_SIZE: f64 = 10.0;
+/// Largest board zoom.
+#[cfg(feature = "gui")]
+const MAX_ZOOM: f64 = 4.0;
+/// Zoom change per step of the scroll wheel.
+#[cfg(feature = "gui")]
+const ZOOM_STEP: f64 = 1.25;
+
+/// Delay in seconds between neighboring cells lighting up in a flash.
+#[cfg(feature = "gui")]
+const FLASH_RIPPLE: f64 = 0.03;
+/// Seconds a rejected entry shakes.
+#[cfg(feature = "gui")]
+const SHAKE_DURATION: f64 = 0.4;
+/// Widest swing of a shaking digit, in cell widths.
+#[cfg(feature = "gui")]
+const SHAKE_AMPLITUDE: f64 = 0.08;
+
+/// A piece of confetti, positioned relative to the board's upper left corner.
+#[cfg(feature = "gui")]
+struct Particle {
+    pos: [f64; 2],
+    vel: [f64; 2],
+    color: Color,
+}
+
+/// Confetti pieces thrown when the puzzle is solved.
+#[cfg(feature = "gui")]
+const CONFETTI_COUNT: usize = 150;
+/// Downward acceleration of confetti, in board sizes per second squared.
+#[cfg(feature = "gui")]
+const CONFETTI_GRAVITY: f64 = 0.8;
+
+#[cfg(feature = "gui")]
 pub struct GameboardView {
     pub settings: GameboardViewSettings,
+    animations: Animations,
+    particles: Vec<Particle>,
+    /// Seconds left in the win celebration.
+    celebration: f64,
+    cursor_pos: [f64; 2],
+    /// Whether the board is being dragged to pan it.
+    dragging: bool,
 }
 
+#[cfg(feature = "gui")]
 impl GameboardView {
     pub fn new(settings: GameboardViewSettings) -> GameboardView {
-        GameboardView { settings }
+        GameboardView {
+            settings,
+            animations: Animations::new(),
+            particles: Vec::new(),
+            celebration: 0.0,
+            cursor_pos: [0.0; 2],
+            dragging: false,
+        }
     }
 
-    pub fn draw<G: Graphics, C>(
-        &self,
-        controller: &GameboardController,
-        glyphs: &mut C,
-        c: &Context,
-        g: &mut G,
-    ) where
-        C: CharacterCache<Texture = G::Texture>,
-    {
-        use graphics::{Image, Line, Rectangle, Transformed};
+    /// Zooms with the scroll wheel over the board, and pans while the right
+    /// mouse button is dragged. The 0 key resets the zoom.
+    pub fn event<E: GenericEvent>(&mut self, e: &E) {
+        use piston::input::{Button, Key, MouseButton};
+
+        let settings = &mut self.settings;
+        if let Some(pos) = e.mouse_cursor_args() {
+            if self.dragging {
+                settings.pan_by([self.cursor_pos[0] - pos[0], self.cursor_pos[1] - pos[1]]);
+            }
+            self.cursor_pos = pos;
+        }
+        if let Some([_, scroll]) = e.mouse_scroll_args() {
+            let [x, y] = self.cursor_pos;
+            let [left, top] = settings.position;
+            if x >= left && x < left + settings.size && y >= top && y < top + settings.size {
+                settings.zoom_at(settings.zoom * ZOOM_STEP.powf(scroll), self.cursor_pos);
+            }
+        }
+        match e.press_args() {
+            Some(Button::Mouse(MouseButton::Right)) => self.dragging = true,
+            Some(Button::Keyboard(Key::D0)) => {
+                settings.zoom = 1.0;
+                settings.pan = [0.0; 2];
+            }
+            _ => {}
+        }
+        if let Some(Button::Mouse(MouseButton::Right)) = e.release_args() {
+            self.dragging = false;
+        }
+    }
+
+    /// Throws confetti over the board.
+    pub fn celebrate(&mut self) {
+        let mut rng = thread_rng();
+        let size = self.settings.size;
+        self.particles = (0..CONFETTI_COUNT)
+            .map(|_| Particle {
+                pos: [rng.gen_range(0.0, size), rng.gen_range(-0.3, 0.0) * size],
+                vel: [
+                    rng.gen_range(-0.15, 0.15) * size,
+                    rng.gen_range(0.0, 0.3) * size,
+                ],
+                color: self.settings.confetti_colors
+                    [rng.gen_range(0, self.settings.confetti_colors.len())],
+            })
+            .collect();
+        self.celebration = self.settings.celebration_duration;
+    }
+
+    /// Whether the win celebration is still playing.
+    pub fn is_celebrating(&self) -> bool {
+        self.celebration > 0.0
+    }
+
+    /// Cuts the win celebration short.
+    pub fn skip_celebration(&mut self) {
+        self.particles.clear();
+        self.celebration = 0.0;
+    }
+
+    /// Starts flashing the cells of a completed house, rippling from the
+    /// first.
+    pub fn flash(&mut self, cells: &[[usize; 2]]) {
+        for (k, &cell) in cells.iter().enumerate() {
+            self.animations.add(
+                Effect::CellFill {
+                    cell,
+                    color: self.settings.flash_color,
+                },
+                Tween::new(1.0, 0.0, self.settings.flash_duration).delay(k as f64 * FLASH_RIPPLE),
+            );
+        }
+    }
+
+    /// Shakes a cell's digit to show it was rejected.
+    pub fn shake(&mut self, cell: [usize; 2]) {
+        self.animations.add(
+            Effect::Shake { cell },
+            Tween::new(SHAKE_AMPLITUDE, 0.0, SHAKE_DURATION).easing(Easing::EaseOut),
+        );
+    }
+
+    /// Whether anything is animating and the board needs redrawing every frame.
+    pub fn is_animating(&self) -> bool {
+        !self.animations.is_empty() || self.is_celebrating()
+    }
+
+    /// Advances animations by `dt` seconds.
+    pub fn update(&mut self, dt: f64) {
+        self.animations.update(dt);
+
+        let gravity = CONFETTI_GRAVITY * self.settings.size;
+        for particle in &mut self.particles {
+            particle.vel[1] += gravity * dt;
+            particle.pos[0] += particle.vel[0] * dt;
+            particle.pos[1] += particle.vel[1] * dt;
+        }
+        self.celebration -= dt;
+        if !self.is_celebrating() {
+            self.skip_celebration();
+        }
+    }
 
-        let ref settings = self.settings;
+    pub fn draw<R: Renderer>(&self, controller: &GameboardController, config: &Config, r: &mut R) {
+        // Draw the zoomed board clipped to the board's area.
+        r.clip(Some([
+            self.settings.position[0],
+            self.settings.position[1],
+            self.settings.size,
+            self.settings.size,
+        ]));
+        let size = controller.gameboard.size();
+        let zoomed = self.settings.zoomed();
+        let settings = &zoomed;
         let board_rect = [
             settings.position[0],
             settings.position[1],
@@ -249,16 +1313,47 @@ impl GameboardView {
             settings.size,
         ];
 
-        Rectangle::new(settings.background_color).draw(board_rect, &c.draw_state, c.transform, g);
+        r.rect(board_rect, settings.background_color);
+        Self::draw_variant(settings, &controller.gameboard, r);
 
-        if let Some(ind) = controller.selected_cell {
-            let target_value = controller.gameboard.char(ind);
-            for j in 0..9 {
-                for i in 0..9 {
+        if config.highlight_houses {
+            if let Some(selected) = controller.selected_cell {
+                let cell_size = settings.size / size as f64;
+                for j in 0..size {
+                    for i in 0..size {
+                        let shares_house = controller.gameboard.houses(selected).any(|house| {
+                            house
+                                .cells(size, controller.gameboard.rules.layout())
+                                .contains(&[i, j])
+                        });
+                        if !shares_house {
+                            continue;
+                        }
+                        let cell_rect = [
+                            settings.position[0] + i as f64 * cell_size,
+                            settings.position[1] + j as f64 * cell_size,
+                            cell_size,
+                            cell_size,
+                        ];
+                        r.rect(cell_rect, settings.house_background_color);
+                    }
+                }
+            }
+        }
+
+        let target_value = match config.input_mode {
+            InputMode::CellFirst => controller
+                .selected_cell
+                .map(|ind| controller.gameboard.char(ind)),
+            InputMode::DigitFirst => controller.active_digit.map(|val| Some(digit_char(val))),
+        };
+        if let (true, Some(target_value)) = (config.highlight_same_digit, target_value) {
+            for j in 0..size {
+                for i in 0..size {
                     if controller.gameboard.char([i, j]) != target_value {
                         continue;
                     }
-                    let cell_size = settings.size / 9.0;
+                    let cell_size = settings.size / size as f64;
                     let pos = [i as f64 * cell_size, j as f64 * cell_size];
                     let cell_rect = [
                         settings.position[0] + pos[0],
@@ -266,143 +1361,3345 @@ impl GameboardView {
                         cell_size,
                         cell_size,
                     ];
-                    Rectangle::new(settings.selected_cell_background_color).draw(
-                        cell_rect,
-                        &c.draw_state,
-                        c.transform,
-                        g,
+                    r.rect(cell_rect, settings.selected_cell_background_color);
+                }
+            }
+        }
+
+        if let Some([i, j]) = controller.hovered_cell() {
+            let cell_size = settings.size / size as f64;
+            let cell_rect = [
+                settings.position[0] + i as f64 * cell_size,
+                settings.position[1] + j as f64 * cell_size,
+                cell_size,
+                cell_size,
+            ];
+            r.rect(cell_rect, settings.hover_cell_background_color);
+        }
+
+        // Animated fills, such as completed houses flashing.
+        let cell_size = settings.size / size as f64;
+        for ([i, j], color) in self.animations.cell_fills() {
+            let cell_rect = [
+                settings.position[0] + i as f64 * cell_size,
+                settings.position[1] + j as f64 * cell_size,
+                cell_size,
+                cell_size,
+            ];
+            r.rect(cell_rect, color);
+        }
+
+        // Show the solution faintly in cells the player has not got right.
+        if controller.is_peeking() {
+            for j in 0..size {
+                for i in 0..size {
+                    let val = controller.gameboard.solution[j][i];
+                    if controller.gameboard.cells[j][i] == val {
+                        continue;
+                    }
+                    r.centered(
+                        digit_char(val),
+                        cell_size * settings.digit_size,
+                        settings.peek_text_color,
+                        [
+                            settings.position[0] + (i as f64 + 0.5) * cell_size,
+                            settings.position[1] + (j as f64 + 0.5) * cell_size,
+                        ],
+                    );
+                }
+            }
+        }
+
+        // Show the last attempt's entries faintly in cells still empty, as far
+        // as it had got by now.
+        if let (true, false, Some(ghost)) = (
+            config.ghost_replay,
+            controller.is_peeking(),
+            &controller.ghost,
+        ) {
+            let cells = ghost.cells_at(controller.elapsed());
+            for (j, row) in cells.iter().enumerate() {
+                for (i, &val) in row.iter().enumerate() {
+                    if val == 0 || controller.gameboard.cells[j][i] != 0 {
+                        continue;
+                    }
+                    r.centered(
+                        digit_char(val),
+                        cell_size * settings.digit_size,
+                        settings.ghost_text_color,
+                        [
+                            settings.position[0] + (i as f64 + 0.5) * cell_size,
+                            settings.position[1] + (j as f64 + 0.5) * cell_size,
+                        ],
                     );
                 }
             }
         }
 
         // Draw characters.
-        let text_image = Image::new_color(settings.text_color);
-        let cell_size = settings.size / 9.0;
-        for j in 0..9 {
-            for i in 0..9 {
+        for j in 0..size {
+            for i in 0..size {
                 if let Some(ch) = controller.gameboard.char([i, j]) {
-                    let pos = [
-                        settings.position[0] + i as f64 * cell_size + 15.0,
-                        settings.position[1] + j as f64 * cell_size + 34.0,
-                    ];
-                    if let Ok(character) = glyphs.character(34, ch) {
-                        let ch_x = pos[0] + character.left();
-                        let ch_y = pos[1] - character.top();
-                        let text_image = text_image.src_rect([
-                            character.atlas_offset[0],
-                            character.atlas_offset[1],
-                            character.atlas_size[0],
-                            character.atlas_size[1],
-                        ]);
-                        text_image.draw(
-                            character.texture,
-                            &c.draw_state,
-                            c.transform.trans(ch_x, ch_y),
-                            g,
+                    let wrong = controller.wrong_entries.contains(&[i, j]);
+                    let auto_check =
+                        config.auto_check && controller.gameboard.difficulty != Difficulty::Kids;
+                    let text_color =
+                        if wrong || auto_check && controller.gameboard.conflicts([i, j]) {
+                            settings.conflict_text_color
+                        } else if controller.gameboard.is_given([i, j]) {
+                            settings.text_color
+                        } else {
+                            settings.entry_text_color
+                        };
+                    r.centered(
+                        ch,
+                        cell_size * settings.digit_size,
+                        text_color,
+                        [
+                            settings.position[0]
+                                + (i as f64 + 0.5 + self.animations.shake_offset([i, j]))
+                                    * cell_size,
+                            settings.position[1] + (j as f64 + 0.5) * cell_size,
+                        ],
+                    );
+                } else {
+                    // Pencil marks sit in a grid shaped like a box inside the
+                    // cell.
+                    let digits = controller.gameboard.digits();
+                    let [width, height] = box_shape(digits);
+                    for val in 1..=digits as u8 {
+                        if !controller.gameboard.has_note([i, j], val) {
+                            continue;
+                        }
+                        let k = (val - 1) as usize;
+                        r.centered(
+                            digit_char(val),
+                            cell_size * settings.digit_size / width.max(height) as f64,
+                            settings.entry_text_color,
+                            [
+                                settings.position[0]
+                                    + (i as f64 + ((k % width) as f64 + 0.5) / width as f64)
+                                        * cell_size,
+                                settings.position[1]
+                                    + (j as f64 + ((k / width) as f64 + 0.5) / height as f64)
+                                        * cell_size,
+                            ],
                         );
                     }
                 }
             }
         }
 
-        let cell_edge = Line::new(settings.cell_edge_color, settings.cell_edge_radius);
-        for i in 0..9 {
-            if (i % 3) == 0 {
-                continue;
+        Self::draw_cages(settings, &controller.gameboard, r);
+        Self::draw_grid(settings, &controller.gameboard, r);
+        Self::draw_dots(settings, &controller.gameboard, r);
+        Self::draw_signs(settings, &controller.gameboard, r);
+        if let Some(ind) = controller.selected_cell {
+            let cell_size = settings.size / size as f64;
+            let pos = [ind[0] as f64 * cell_size, ind[1] as f64 * cell_size];
+            let cell_rect = [
+                settings.position[0] + pos[0],
+                settings.position[1] + pos[1],
+                cell_size,
+                cell_size,
+            ];
+            r.border(cell_rect, settings.selected_cell_border_color, 1.0)
+        }
+        // Co-op players' cursors, each in their color with their initial.
+        for partner in &controller.partners {
+            let ([i, j], colors) = match (partner.cursor, &settings.partner_colors[..]) {
+                (Some(ind), colors) if !colors.is_empty() => (ind, colors),
+                _ => continue,
+            };
+            let color = colors[partner.id as usize % colors.len()];
+            let cell_size = settings.size / size as f64;
+            let [x, y] = [
+                settings.position[0] + i as f64 * cell_size,
+                settings.position[1] + j as f64 * cell_size,
+            ];
+            r.border(
+                [x + 1.5, y + 1.5, cell_size - 3.0, cell_size - 3.0],
+                color,
+                1.5,
+            );
+            if let Some(initial) = partner.name.chars().next() {
+                r.centered(
+                    initial.to_ascii_uppercase(),
+                    cell_size * 0.22,
+                    color,
+                    [x + cell_size * 0.2, y + cell_size * 0.2],
+                );
             }
+        }
+        if let Some([i, j]) = controller.focus_ring_cell() {
+            // Inset so the ring stays clear of the selection border.
+            let inset = 2.0 * settings.focus_ring_radius;
+            let cell_rect = [
+                settings.position[0] + i as f64 * cell_size + inset,
+                settings.position[1] + j as f64 * cell_size + inset,
+                cell_size - 2.0 * inset,
+                cell_size - 2.0 * inset,
+            ];
+            r.border(
+                cell_rect,
+                settings.focus_ring_color,
+                settings.focus_ring_radius,
+            );
+        }
+
+        r.clip(None);
+        let settings = &self.settings;
+        if settings.zoom > 1.0 {
+            // The zoomed board's own edge is clipped away.
+            r.border(
+                [
+                    settings.position[0],
+                    settings.position[1],
+                    settings.size,
+                    settings.size,
+                ],
+                settings.board_edge_color,
+                settings.board_edge_radius,
+            );
+        }
+
+        for particle in &self.particles {
+            let [x, y] = particle.pos;
+            if x < 0.0 || x > settings.size || y < 0.0 || y > settings.size {
+                continue;
+            }
+            r.rect(
+                [settings.position[0] + x, settings.position[1] + y, 6.0, 4.0],
+                particle.color,
+            );
+        }
+
+        // Labels outside the board follow the zoomed cells that are in view.
+        let offset = 14.0 * settings.ui_scale;
+        let cell_size = zoomed.size / size as f64;
+        let in_view = |k: usize, axis: usize| {
+            let center = zoomed.position[axis] + (k as f64 + 0.5) * cell_size;
+            let start = settings.position[axis];
+            Some(center).filter(|&center| center >= start && center <= start + settings.size)
+        };
+        if config.coordinate_labels {
+            // Columns from A below the board, rows from 1 to its left.
+            let label_size = settings.font_size as f64 * 0.8;
+            for k in 0..size {
+                if let Some(center) = in_view(k, 0) {
+                    r.centered(
+                        (b'A' + k as u8) as char,
+                        label_size,
+                        settings.text_color,
+                        [center, settings.position[1] + settings.size + offset],
+                    );
+                }
+                if let Some(center) = in_view(k, 1) {
+                    // Rows past 9 take two digits, so are drawn as text.
+                    let label = (k + 1).to_string();
+                    let font_size = label_size.round() as u32;
+                    let width = r.text_width(&label, font_size);
+                    r.text(
+                        &label,
+                        font_size,
+                        settings.text_color,
+                        [
+                            settings.position[0] - offset - width / 2.0,
+                            center + label_size * 0.35,
+                        ],
+                    );
+                }
+            }
+        }
+
+        if let Some(Variant::Sandwich(sandwiches)) =
+            controller.gameboard.rules.get(VariantKind::Sandwich)
+        {
+            // Column sums above the board and row sums to its right, on the
+            // side away from the coordinate labels.
+            let font_size = settings.font_size;
+            for (k, sum) in sandwiches.columns.iter().enumerate() {
+                if let (Some(center), Some(sum)) = (in_view(k, 0), sum) {
+                    let label = sum.to_string();
+                    let width = r.text_width(&label, font_size);
+                    r.text(
+                        &label,
+                        font_size,
+                        settings.text_color,
+                        [center - width / 2.0, settings.position[1] - offset],
+                    );
+                }
+            }
+            for (k, sum) in sandwiches.rows.iter().enumerate() {
+                if let (Some(center), Some(sum)) = (in_view(k, 1), sum) {
+                    let label = sum.to_string();
+                    let width = r.text_width(&label, font_size);
+                    r.text(
+                        &label,
+                        font_size,
+                        settings.text_color,
+                        [
+                            settings.position[0] + settings.size + offset + 8.0 * settings.ui_scale
+                                - width / 2.0,
+                            center + font_size as f64 * 0.35,
+                        ],
+                    );
+                }
+            }
+        }
+
+        self.draw_status_bar(controller, config, r);
+    }
+
+    /// Shades the cells of the houses a variant adds, and draws any
+    /// thermometers or even and odd marks under the digits.
+    fn draw_variant<R: Renderer>(
+        settings: &GameboardViewSettings,
+        gameboard: &Gameboard,
+        r: &mut R,
+    ) {
+        let cell_size = settings.size / gameboard.size() as f64;
+        let center = |[i, j]: [usize; 2]| {
+            [
+                settings.position[0] + (i as f64 + 0.5) * cell_size,
+                settings.position[1] + (j as f64 + 0.5) * cell_size,
+            ]
+        };
+        if let Some(Variant::EvenOdd(marks)) = gameboard.rules.get(VariantKind::EvenOdd) {
+            let radius = cell_size * 0.38;
+            for mark in marks {
+                let [x, y] = center(mark.cell);
+                let rect = [x - radius, y - radius, 2.0 * radius, 2.0 * radius];
+                match mark.parity {
+                    Parity::Even => r.rect(rect, settings.shape_color),
+                    Parity::Odd => r.ellipse(rect, settings.shape_color),
+                }
+            }
+        }
+        if let Some(Variant::Thermo(thermos)) = gameboard.rules.get(VariantKind::Thermo) {
+            for thermo in thermos {
+                for pair in thermo.cells.windows(2) {
+                    let ([x1, y1], [x2, y2]) = (center(pair[0]), center(pair[1]));
+                    r.round_line([x1, y1, x2, y2], settings.shape_color, cell_size * 0.15);
+                }
+                if let Some(&start) = thermo.cells.first() {
+                    let [x, y] = center(start);
+                    let radius = cell_size * 0.35;
+                    let rect = [x - radius, y - radius, 2.0 * radius, 2.0 * radius];
+                    r.ellipse(rect, settings.shape_color);
+                }
+            }
+        }
+        if let Some(Variant::Whispers(whispers)) = gameboard.rules.get(VariantKind::Whispers) {
+            for whisper in whispers {
+                for pair in whisper.cells.windows(2) {
+                    let ([x1, y1], [x2, y2]) = (center(pair[0]), center(pair[1]));
+                    r.round_line([x1, y1, x2, y2], settings.whisper_color, cell_size * 0.1);
+                }
+            }
+        }
+
+        let size = gameboard.size();
+        for j in 0..size {
+            for i in 0..size {
+                if gameboard.rules.extra_houses(size, [i, j]).next().is_none() {
+                    continue;
+                }
+                let cell_rect = [
+                    settings.position[0] + i as f64 * cell_size,
+                    settings.position[1] + j as f64 * cell_size,
+                    cell_size,
+                    cell_size,
+                ];
+                r.rect(cell_rect, settings.variant_shade_color);
+            }
+        }
+    }
+
+    /// Outlines each killer cage with a dashed line just inside its cells,
+    /// with its sum in the corner of its top left cell.
+    fn draw_cages<R: Renderer>(settings: &GameboardViewSettings, gameboard: &Gameboard, r: &mut R) {
+        let cages = match gameboard.rules.get(VariantKind::Killer) {
+            Some(Variant::Killer(cages)) => cages,
+            _ => return,
+        };
+        let size = gameboard.size();
+        let cell_size = settings.size / size as f64;
+        let inset = 0.08;
+        let [left, top] = settings.position;
+        for cage in cages {
+            for [x1, y1, x2, y2] in cage.outline(inset) {
+                Self::draw_dashed(
+                    settings,
+                    [
+                        left + x1 * cell_size,
+                        top + y1 * cell_size,
+                        left + x2 * cell_size,
+                        top + y2 * cell_size,
+                    ],
+                    cell_size / 10.0,
+                    r,
+                );
+            }
+            let [i, j] = cage.label_cell();
+            let font_size = (cell_size * 0.22).round() as u32;
+            r.text(
+                &cage.sum.to_string(),
+                font_size,
+                settings.cage_color,
+                [
+                    left + (i as f64 + inset * 1.5) * cell_size,
+                    top + (j as f64 + inset * 1.5) * cell_size + font_size as f64 * 0.8,
+                ],
+            );
+        }
+    }
 
-            let x = settings.position[0] + i as f64 / 9.0 * settings.size;
-            let y = settings.position[1] + i as f64 / 9.0 * settings.size;
+    /// Draws kropki dots over the edges between their cells: white dots
+    /// outlined, black dots filled.
+    fn draw_dots<R: Renderer>(settings: &GameboardViewSettings, gameboard: &Gameboard, r: &mut R) {
+        let kropki = match gameboard.rules.get(VariantKind::Kropki) {
+            Some(Variant::Kropki(kropki)) => kropki,
+            _ => return,
+        };
+        let cell_size = settings.size / gameboard.size() as f64;
+        let radius = cell_size * 0.12;
+        for dot in &kropki.dots {
+            let [[i1, j1], [i2, j2]] = dot.cells;
+            let x = settings.position[0] + ((i1 + i2) as f64 / 2.0 + 0.5) * cell_size;
+            let y = settings.position[1] + ((j1 + j2) as f64 / 2.0 + 0.5) * cell_size;
+            let fill = match dot.kind {
+                DotKind::White => settings.background_color,
+                DotKind::Black => settings.cage_color,
+            };
+            // The outline is a dot of the cage color under the fill.
+            let outer = radius + settings.cell_edge_radius / 2.0;
+            let inner = radius - settings.cell_edge_radius / 2.0;
+            r.ellipse(
+                [x - outer, y - outer, 2.0 * outer, 2.0 * outer],
+                settings.cage_color,
+            );
+            r.ellipse([x - inner, y - inner, 2.0 * inner, 2.0 * inner], fill);
+        }
+    }
+
+    /// Draws greater-than signs over the edges between their cells, each
+    /// opening towards the greater digit.
+    fn draw_signs<R: Renderer>(settings: &GameboardViewSettings, gameboard: &Gameboard, r: &mut R) {
+        let signs = match gameboard.rules.get(VariantKind::GreaterThan) {
+            Some(Variant::GreaterThan(signs)) => signs,
+            _ => return,
+        };
+        let cell_size = settings.size / gameboard.size() as f64;
+        for sign in signs {
+            for [x1, y1, x2, y2] in sign_strokes(sign) {
+                let at = |x: f64, y: f64| {
+                    [
+                        settings.position[0] + x * cell_size,
+                        settings.position[1] + y * cell_size,
+                    ]
+                };
+                let ([x1, y1], [x2, y2]) = (at(x1, y1), at(x2, y2));
+                r.round_line(
+                    [x1, y1, x2, y2],
+                    settings.cage_color,
+                    settings.cell_edge_radius,
+                );
+            }
+        }
+    }
+
+    /// Draws a line as dashes of a length with equal gaps.
+    fn draw_dashed<R: Renderer>(
+        settings: &GameboardViewSettings,
+        [x1, y1, x2, y2]: [f64; 4],
+        dash: f64,
+        r: &mut R,
+    ) {
+        let length = (x2 - x1).hypot(y2 - y1);
+        let steps = (length / dash).ceil() as usize;
+        for k in (0..steps).step_by(2) {
+            let start = k as f64 * dash / length;
+            let end = ((k + 1) as f64 * dash / length).min(1.0);
+            r.line(
+                [
+                    x1 + (x2 - x1) * start,
+                    y1 + (y2 - y1) * start,
+                    x1 + (x2 - x1) * end,
+                    y1 + (y2 - y1) * end,
+                ],
+                settings.cage_color,
+                settings.cell_edge_radius / 2.0,
+            );
+        }
+    }
+
+    /// Draws cell, box, and board edges, tracing the edges of any jigsaw
+    /// regions in place of the boxes.
+    fn draw_grid<R: Renderer>(settings: &GameboardViewSettings, gameboard: &Gameboard, r: &mut R) {
+        let variant = gameboard.rules.layout();
+        let size = gameboard.size();
+        let [width, height] = box_shape(gameboard.digits());
+        let jigsaw = matches!(variant, Variant::Jigsaw(_));
+        let cell_edge =
+            |r: &mut R, line| r.line(line, settings.cell_edge_color, settings.cell_edge_radius);
+        for i in 0..size {
+            let x = settings.position[0] + i as f64 / size as f64 * settings.size;
+            let y = settings.position[1] + i as f64 / size as f64 * settings.size;
             let x2 = settings.position[0] + settings.size;
             let y2 = settings.position[1] + settings.size;
 
-            let vline = [x, settings.position[1], x, y2];
-            cell_edge.draw(vline, &c.draw_state, c.transform, g);
-
-            let hline = [settings.position[0], y, x2, y];
-            cell_edge.draw(hline, &c.draw_state, c.transform, g);
+            if i % width != 0 || jigsaw {
+                let vline = [x, settings.position[1], x, y2];
+                cell_edge(r, vline);
+            }
+            if i % height != 0 || jigsaw {
+                let hline = [settings.position[0], y, x2, y];
+                cell_edge(r, hline);
+            }
         }
 
-        let section_edge = Line::new(settings.section_edge_color, settings.section_edge_radius);
-        for i in 0..3 {
-            let x = settings.position[0] + i as f64 / 3.0 * settings.size;
-            let y = settings.position[1] + i as f64 / 3.0 * settings.size;
+        let section_edge = |r: &mut R, line| {
+            r.line(
+                line,
+                settings.section_edge_color,
+                settings.section_edge_radius,
+            )
+        };
+        if jigsaw {
+            let cell_size = settings.size / size as f64;
+            let [left, top] = settings.position;
+            let box_of = |ind| variant.box_of(size, ind);
+            for j in 0..size {
+                for i in 0..size {
+                    let x = left + (i + 1) as f64 * cell_size;
+                    let y = top + (j + 1) as f64 * cell_size;
+                    if i + 1 < size && box_of([i, j]) != box_of([i + 1, j]) {
+                        let vline = [x, y - cell_size, x, y];
+                        section_edge(r, vline);
+                    }
+                    if j + 1 < size && box_of([i, j]) != box_of([i, j + 1]) {
+                        let hline = [x - cell_size, y, x, y];
+                        section_edge(r, hline);
+                    }
+                }
+            }
+        } else {
             let x2 = settings.position[0] + settings.size;
             let y2 = settings.position[1] + settings.size;
+            for i in (0..size).step_by(width) {
+                let x = settings.position[0] + i as f64 / size as f64 * settings.size;
+                let vline = [x, settings.position[1], x, y2];
+                section_edge(r, vline);
+            }
+            for j in (0..size).step_by(height) {
+                let y = settings.position[1] + j as f64 / size as f64 * settings.size;
+                let hline = [settings.position[0], y, x2, y];
+                section_edge(r, hline);
+            }
+        }
 
-            let vline = [x, settings.position[1], x, y2];
-            section_edge.draw(vline, &c.draw_state, c.transform, g);
+        if *variant != Variant::Samurai {
+            r.border(
+                [
+                    settings.position[0],
+                    settings.position[1],
+                    settings.size,
+                    settings.size,
+                ],
+                settings.board_edge_color,
+                settings.board_edge_radius,
+            );
+            return;
+        }
 
-            let hline = [settings.position[0], y, x2, y];
-            section_edge.draw(hline, &c.draw_state, c.transform, g);
+        // The gaps between samurai grids are covered up, and each grid gets
+        // its own edge.
+        let cell_size = settings.size / size as f64;
+        for [i, j] in gameboard.cells.positions() {
+            if !gameboard.in_play([i, j]) {
+                let rect = [
+                    settings.position[0] + i as f64 * cell_size,
+                    settings.position[1] + j as f64 * cell_size,
+                    cell_size,
+                    cell_size,
+                ];
+                r.rect(rect, settings.void_color);
+            }
+        }
+        for grid in 0..variant::SAMURAI_GRIDS {
+            let [i, j] = variant::samurai_cell(grid, [0, 0]);
+            let rect = [
+                settings.position[0] + i as f64 * cell_size,
+                settings.position[1] + j as f64 * cell_size,
+                SIZE as f64 * cell_size,
+                SIZE as f64 * cell_size,
+            ];
+            r.border(rect, settings.board_edge_color, settings.board_edge_radius);
         }
+    }
 
-        Rectangle::new_border(settings.board_edge_color, settings.board_edge_radius).draw(
-            board_rect,
-            &c.draw_state,
-            c.transform,
-            g,
+    /// Draws the finished board marked against the solution: right entries
+    /// in green, wrong or missing ones in red beside the correct digit, and
+    /// givens as usual.
+    pub fn draw_review<R: Renderer>(&self, controller: &GameboardController, r: &mut R) {
+        let settings = &self.settings;
+        r.rect(
+            [
+                settings.position[0],
+                settings.position[1],
+                settings.size,
+                settings.size,
+            ],
+            settings.background_color,
         );
-        if let Some(ind) = controller.selected_cell {
-            let cell_size = settings.size / 9.0;
-            let pos = [ind[0] as f64 * cell_size, ind[1] as f64 * cell_size];
-            let cell_rect = [
-                settings.position[0] + pos[0],
-                settings.position[1] + pos[1],
+        Self::draw_variant(settings, &controller.gameboard, r);
+
+        let board = &controller.gameboard;
+        let size = board.size();
+        let cell_size = settings.size / size as f64;
+        for j in 0..size {
+            for i in 0..size {
+                let val = board.cells[j][i];
+                let answer = board.solution[j][i];
+                let center = [
+                    settings.position[0] + (i as f64 + 0.5) * cell_size,
+                    settings.position[1] + (j as f64 + 0.5) * cell_size,
+                ];
+                let color = if board.is_given([i, j]) {
+                    settings.text_color
+                } else if val == answer {
+                    settings.correct_text_color
+                } else {
+                    // The correct digit goes in the top right corner.
+                    r.centered(
+                        digit_char(answer),
+                        cell_size * settings.digit_size / 3.0,
+                        settings.correct_text_color,
+                        [center[0] + cell_size / 3.0, center[1] - cell_size / 3.0],
+                    );
+                    settings.conflict_text_color
+                };
+                if let Some(ch) = board.char([i, j]) {
+                    r.centered(ch, cell_size * settings.digit_size, color, center);
+                }
+            }
+        }
+        Self::draw_cages(settings, &controller.gameboard, r);
+        Self::draw_grid(settings, &controller.gameboard, r);
+        Self::draw_dots(settings, &controller.gameboard, r);
+        Self::draw_signs(settings, &controller.gameboard, r);
+
+        let font_size = (settings.font_size as f64 * 0.8).round() as u32;
+        r.text(
+            "Review - press any key to go back",
+            font_size,
+            settings.text_color,
+            [
+                settings.position[0],
+                settings.position[1] + settings.size + font_size as f64 + 8.0 * settings.ui_scale,
+            ],
+        );
+    }
+
+    /// Draws the puzzle being set in the editor: its givens and variant
+    /// elements, the cells picked for the next one, and below the board
+    /// what the live check found and the keys to press.
+    pub fn draw_editor<R: Renderer>(&self, editor: &EditorController, r: &mut R) {
+        let settings = &self.settings;
+        let board = &editor.gameboard;
+        let size = board.size();
+        let cell_size = settings.size / size as f64;
+        r.rect(
+            [
+                settings.position[0],
+                settings.position[1],
+                settings.size,
+                settings.size,
+            ],
+            settings.background_color,
+        );
+        let cell_rect = |[i, j]: [usize; 2]| {
+            [
+                settings.position[0] + i as f64 * cell_size,
+                settings.position[1] + j as f64 * cell_size,
                 cell_size,
                 cell_size,
-            ];
-            Rectangle::new_border([1.0, 0.0, 0.0, 1.0], 1.0).draw(
-                cell_rect,
-                &c.draw_state,
-                c.transform,
-                g,
-            )
+            ]
+        };
+        for &ind in &editor.picked {
+            r.rect(cell_rect(ind), settings.house_background_color);
+        }
+        if let Some(ind) = editor.selected_cell {
+            r.rect(cell_rect(ind), settings.selected_cell_background_color);
+        }
+        Self::draw_variant(settings, board, r);
+        for j in 0..size {
+            for i in 0..size {
+                if let Some(ch) = board.char([i, j]) {
+                    r.centered(
+                        ch,
+                        cell_size * settings.digit_size,
+                        settings.text_color,
+                        [
+                            settings.position[0] + (i as f64 + 0.5) * cell_size,
+                            settings.position[1] + (j as f64 + 0.5) * cell_size,
+                        ],
+                    );
+                }
+            }
+        }
+        Self::draw_cages(settings, board, r);
+        Self::draw_grid(settings, board, r);
+        Self::draw_dots(settings, board, r);
+        Self::draw_signs(settings, board, r);
+
+        let status = if let Some(sum) = &editor.cage_sum {
+            format!("Cage sum: {}_", sum)
+        } else if let Some(message) = &editor.message {
+            message.clone()
+        } else {
+            let names = board.rules.names();
+            if names.is_empty() {
+                editor.check.describe()
+            } else {
+                format!("{} ({})", editor.check.describe(), names.join(", "))
+            }
+        };
+        let lines = [
+            status.as_str(),
+            "Space picks cells: K cage, T thermo, W whispers, B/Shift+B dot",
+            "E/O parity, X diagonals, I windows, N/A/C anti-knight/king/consecutive",
+            "R removes, G generates, Enter plays, Ctrl+S saves, Ctrl+U copies a link",
+        ];
+        let font_size = (settings.font_size as f64 * 0.8).round() as u32;
+        let line_height = font_size as f64 + 8.0 * settings.ui_scale;
+        for (k, line) in lines.iter().enumerate() {
+            r.text(
+                line,
+                font_size,
+                settings.text_color,
+                [
+                    settings.position[0],
+                    settings.position[1] + settings.size + (k + 1) as f64 * line_height,
+                ],
+            );
+        }
+    }
+
+    /// Draws just the board, filling a square of side `size` at the origin,
+    /// for exporting as an image. Highlights, the selection, zoom, and the
+    /// status bar are left out, as are pencil marks unless `notes` is set.
+    pub fn draw_export<R: Renderer>(
+        &self,
+        gameboard: &Gameboard,
+        size: f64,
+        notes: bool,
+        r: &mut R,
+    ) {
+        // Lines keep their on-screen weight relative to the board.
+        let margin = size / 40.0;
+        let board_size = size - 2.0 * margin;
+        let weight = board_size / self.settings.size;
+        let settings = &GameboardViewSettings {
+            position: [margin; 2],
+            size: board_size,
+            zoom: 1.0,
+            pan: [0.0; 2],
+            board_edge_radius: self.settings.board_edge_radius * weight,
+            section_edge_radius: self.settings.section_edge_radius * weight,
+            cell_edge_radius: self.settings.cell_edge_radius * weight,
+            ..self.settings.clone()
+        };
+        r.rect([0.0, 0.0, size, size], settings.background_color);
+        Self::draw_variant(settings, gameboard, r);
+
+        let cells = gameboard.size();
+        let cell_size = settings.size / cells as f64;
+        for j in 0..cells {
+            for i in 0..cells {
+                let cell = [
+                    settings.position[0] + i as f64 * cell_size,
+                    settings.position[1] + j as f64 * cell_size,
+                ];
+                if let Some(ch) = gameboard.char([i, j]) {
+                    let color = if gameboard.is_given([i, j]) {
+                        settings.text_color
+                    } else {
+                        settings.entry_text_color
+                    };
+                    r.centered(
+                        ch,
+                        cell_size * settings.digit_size,
+                        color,
+                        [cell[0] + cell_size / 2.0, cell[1] + cell_size / 2.0],
+                    );
+                } else if notes {
+                    let digits = gameboard.digits();
+                    let [width, height] = box_shape(digits);
+                    for val in 1..=digits as u8 {
+                        if !gameboard.has_note([i, j], val) {
+                            continue;
+                        }
+                        let k = (val - 1) as usize;
+                        r.centered(
+                            digit_char(val),
+                            cell_size * settings.digit_size / width.max(height) as f64,
+                            settings.entry_text_color,
+                            [
+                                cell[0] + ((k % width) as f64 + 0.5) / width as f64 * cell_size,
+                                cell[1] + ((k / width) as f64 + 0.5) / height as f64 * cell_size,
+                            ],
+                        );
+                    }
+                }
+            }
+        }
+        Self::draw_cages(settings, gameboard, r);
+        Self::draw_grid(settings, gameboard, r);
+        Self::draw_dots(settings, gameboard, r);
+        Self::draw_signs(settings, gameboard, r);
+    }
+
+    /// Draws the strip below the board showing the input mode, difficulty,
+    /// mistakes, time, and how much of the board is filled, with how much the
+    /// last attempt had filled by now when racing it. A transient message
+    /// replaces the mode and difficulty.
+    fn draw_status_bar<R: Renderer>(
+        &self,
+        controller: &GameboardController,
+        config: &Config,
+        r: &mut R,
+    ) {
+        let settings = &self.settings;
+        // Leave room for the column labels between the board and the bar.
+        let gap = if config.coordinate_labels { 28.0 } else { 6.0 } * settings.ui_scale;
+        let font_size = (settings.font_size as f64 * 0.8).round() as u32;
+        let height = font_size as f64 + 10.0 * settings.ui_scale;
+        let [x, y] = [
+            settings.position[0],
+            settings.position[1] + settings.size + gap,
+        ];
+        r.rect(
+            [x, y, settings.size, height],
+            settings.status_background_color,
+        );
+        r.rect(
+            [
+                x,
+                y,
+                settings.size * controller.gameboard.progress(),
+                3.0 * settings.ui_scale,
+            ],
+            settings.progress_color,
+        );
+        if let (true, Some(ghost)) = (config.ghost_replay, &controller.ghost) {
+            r.rect(
+                [
+                    x,
+                    y + 3.0 * settings.ui_scale,
+                    settings.size * ghost.progress_at(controller.elapsed()),
+                    2.0 * settings.ui_scale,
+                ],
+                settings.ghost_progress_color,
+            );
+        }
+        // Racing players' progress, each in their color, along the bottom.
+        let racing = controller
+            .partners
+            .iter()
+            .filter_map(|partner| Some((partner, partner.progress?)));
+        for (k, (partner, percent)) in racing.enumerate() {
+            let colors = &settings.partner_colors;
+            let color = match colors.len() {
+                0 => settings.ghost_progress_color,
+                len => colors[partner.id as usize % len],
+            };
+            r.rect(
+                [
+                    x,
+                    y + height - (k as f64 + 1.0) * 2.0 * settings.ui_scale,
+                    settings.size * f64::from(percent) / 100.0,
+                    2.0 * settings.ui_scale,
+                ],
+                color,
+            );
+        }
+
+        let padding = 6.0 * settings.ui_scale;
+        let baseline = y + (height + font_size as f64) / 2.0 - 2.0;
+        let left = match controller.message() {
+            Some(message) => message.to_string(),
+            None => {
+                let mut mode = match config.input_mode {
+                    _ if controller.notes_mode => "Notes".to_string(),
+                    InputMode::CellFirst => "Normal".to_string(),
+                    InputMode::DigitFirst => "Digit first".to_string(),
+                };
+                if let (InputMode::DigitFirst, Some(val)) =
+                    (config.input_mode, controller.active_digit)
+                {
+                    mode = format!("{} ({})", mode, val);
+                }
+                let gameboard = &controller.gameboard;
+                let rules = gameboard.rules.names();
+                if rules.is_empty() {
+                    format!("{} - {}", mode, gameboard.difficulty.name())
+                } else {
+                    format!(
+                        "{} - {} {}",
+                        mode,
+                        rules.join(", "),
+                        gameboard.difficulty.name()
+                    )
+                }
+            }
+        };
+        r.text(
+            &left,
+            font_size,
+            settings.text_color,
+            [x + padding, baseline],
+        );
+
+        let mut right = match controller.mistake_limit {
+            Some(limit) => format!("Mistakes: {}/{}", controller.mistakes, limit),
+            None => format!("Mistakes: {}", controller.mistakes),
+        };
+        if config.show_timer {
+            right = format!("{}  {}", right, format_duration(controller.elapsed()));
         }
+        for partner in &controller.partners {
+            if let Some(percent) = partner.progress {
+                right = format!("{} {}%  {}", partner.name, percent, right);
+            }
+        }
+        let right_width = r.text_width(&right, font_size);
+        r.text(
+            &right,
+            font_size,
+            settings.text_color,
+            [x + settings.size - padding - right_width, baseline],
+        );
     }
 }
 
-fn main() {
-    let mut settings = EventSettings::new();
-    settings.set_lazy(true);
-    settings.swap_buffers(true);
-    settings.max_fps(1);
-    settings.ups(1);
-    let mut events = Events::new(settings);
-    let opengl = OpenGL::V3_2;
-    let settings = WindowSettings::new("Sudoku", [512; 2])
-        .exit_on_esc(true)
-        .graphics_api(opengl);
-    let mut window: GlutinWindow = settings.build().expect("Could not create window");
-    let mut gl = GlGraphics::new(opengl);
+/// Formats a duration as `m:ss`, or `h:mm:ss` past the hour.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Which screen receives input and is drawn.
+#[cfg(feature = "gui")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Screen {
+    Menu,
+    Game,
+    Settings,
+    GameOver,
+    /// The finished board marked against the solution.
+    Review,
+    /// Hosts or joins a game on the network, and gathers its players.
+    Lobby,
+    /// Asks whether to resume the game saved when the app last closed.
+    Restore,
+    /// Named saved games to load or save over.
+    Slots,
+    /// Puzzle files to open.
+    Open,
+    /// The puzzles of an opened collection, one at a time.
+    Collection,
+    /// The current puzzle as a QR code.
+    Share,
+    /// Puzzle packs to pick a collection from.
+    Library,
+    /// Statistics of the games played.
+    Stats,
+    /// Achievements unlocked and still to unlock.
+    Achievements,
+    /// Today's puzzles, and those solved this month.
+    Daily,
+    /// Who is playing.
+    Profiles,
+    /// The finished game played back move by move.
+    Replay,
+    /// A puzzle being set by hand.
+    Editor,
+}
+
+/// What the player did on a screen, for `App::reduce` to carry out.
+///
+/// Each screen maps its input to one of these, the way the board maps keys
+/// and clicks to `Action`s, so the screens change the game and move between
+/// each other in one place.
+#[cfg(feature = "gui")]
+enum ScreenAction {
+    /// Moves made on the board.
+    Board(Vec<Action>),
+    /// Moves on to the next player's board in a race being watched.
+    WatchNext,
+    /// Goes to a screen, such as back to the one a screen was opened from.
+    Show(Screen),
+    Menu(MenuAction),
+    /// The settings changed, with whether the sound did.
+    Configured {
+        sound: bool,
+    },
+    SkipCelebration,
+    Win(WinAction),
+    Editor(EditorAction),
+    /// Closes the replay, back to the finished game.
+    CloseReplay,
+    ExportHistory,
+    /// Starts today's puzzle of a difficulty.
+    Daily(Difficulty),
+    Lobby(LobbyAction),
+    /// Whether to restore the game left unfinished last time.
+    Restore(bool),
+    Slot(SlotAction),
+    /// Opens one of the puzzle files listed.
+    OpenFile(usize),
+    Profile(ProfileAction),
+    /// Opens one of the puzzle packs listed.
+    OpenPack(usize),
+    /// Plays the collection's current puzzle.
+    PlayCollection,
+}
+
+/// The view of each screen and overlay, styled together.
+#[cfg(feature = "gui")]
+struct Views {
+    gameboard: GameboardView,
+    settings: SettingsView,
+    menu: MenuView,
+    win: WinView,
+    toolbar: ToolbarView,
+    keypad: KeypadView,
+    debug: DebugView,
+    prompt: PromptView,
+    slots: SlotsView,
+    files: FilesView,
+    collection: CollectionView,
+    share: ShareView,
+    stats: StatsView,
+    achievements: AchievementsView,
+    daily: DailyView,
+    lobby: LobbyView,
+    move_log: MoveLogView,
+    chat: ChatView,
+}
+
+#[cfg(feature = "gui")]
+impl Views {
+    fn new(theme: &Theme) -> Views {
+        Views {
+            gameboard: GameboardView::new(GameboardViewSettings::new(theme)),
+            settings: SettingsView::new(SettingsViewSettings::new(theme)),
+            menu: MenuView::new(MenuViewSettings::new(theme)),
+            win: WinView::new(WinViewSettings::new(theme)),
+            toolbar: ToolbarView::new(ToolbarViewSettings::new(theme)),
+            keypad: KeypadView::new(KeypadViewSettings::new(theme)),
+            debug: DebugView::new(DebugViewSettings::new(theme)),
+            prompt: PromptView::new(PromptViewSettings::new(theme)),
+            slots: SlotsView::new(SlotsViewSettings::new(theme)),
+            files: FilesView::new(FilesViewSettings::new(theme)),
+            collection: CollectionView::new(CollectionViewSettings::new(theme)),
+            share: ShareView::new(ShareViewSettings::new(theme)),
+            stats: StatsView::new(StatsViewSettings::new(theme)),
+            achievements: AchievementsView::new(AchievementsViewSettings::new(theme)),
+            daily: DailyView::new(DailyViewSettings::new(theme)),
+            lobby: LobbyView::new(LobbyViewSettings::new(theme)),
+            move_log: MoveLogView::new(MoveLogViewSettings::new(theme)),
+            chat: ChatView::new(ChatViewSettings::new(theme)),
+        }
+    }
+
+    /// Applies the configured theme and contrast mode to every view.
+    fn apply_appearance(&mut self, theme: &Theme, config: &Config, window_size: [f64; 2]) {
+        let scale = config.ui_scale_percent as f64 / 100.0;
+        self.gameboard.settings.set_theme(theme);
+        self.gameboard
+            .settings
+            .set_high_contrast(config.high_contrast);
+        self.gameboard.settings.set_scale(scale);
+        self.gameboard.settings.layout(window_size);
+        self.settings.settings.set_theme(theme);
+        self.settings.settings.set_scale(scale);
+        self.menu.settings.set_theme(theme);
+        self.menu.settings.set_scale(scale);
+        self.win.settings.set_theme(theme);
+        self.win.settings.set_scale(scale);
+        self.toolbar.settings.set_theme(theme);
+        self.toolbar.settings.set_scale(scale);
+        self.keypad.settings.set_theme(theme);
+        self.keypad.settings.set_scale(scale);
+        self.debug.settings.set_theme(theme);
+        self.prompt.settings.set_theme(theme);
+        self.prompt.settings.set_scale(scale);
+        self.slots.settings.set_theme(theme);
+        self.slots.settings.set_scale(scale);
+        self.files.settings.set_theme(theme);
+        self.files.settings.set_scale(scale);
+        self.collection.settings.set_theme(theme);
+        self.collection.settings.set_scale(scale);
+        self.share.settings.set_theme(theme);
+        self.share.settings.set_scale(scale);
+        self.stats.settings.set_theme(theme);
+        self.stats.settings.set_scale(scale);
+        self.achievements.settings.set_theme(theme);
+        self.achievements.settings.set_scale(scale);
+        self.daily.settings.set_theme(theme);
+        self.daily.settings.set_scale(scale);
+        self.lobby.settings.set_theme(theme);
+        self.lobby.settings.set_scale(scale);
+        self.move_log.settings.set_theme(theme);
+        self.move_log.settings.set_scale(scale);
+        self.chat.settings.set_theme(theme);
+        self.chat.settings.set_scale(scale);
+    }
+}
+
+/// Starts a game on a board.
+fn start_game(gameboard: Gameboard, config: &Config) -> GameboardController {
+    let mut controller = GameboardController::new(gameboard);
+    controller.mistake_limit = config.mistake_limit;
+    controller
+}
+
+/// Starts a game on a puzzle file, announcing its title.
+#[cfg(feature = "gui")]
+fn open_game(path: &std::path::Path, config: &Config) -> Result<GameboardController, String> {
+    let opened = puzzle::open(path)?;
+    let mut gameboard = Gameboard::from_puzzle(opened.cells, opened.solution);
+    gameboard.rules = opened.rules;
+    let mut controller = start_game(gameboard, config);
+    let title = opened.title.unwrap_or_else(|| file_name(path));
+    controller.notify(&format!("Opened {}", title));
+    Ok(controller)
+}
+
+/// Starts a game on a collection's current puzzle.
+#[cfg(feature = "gui")]
+fn collection_game(
+    collection: &Collection,
+    config: &Config,
+) -> Result<GameboardController, String> {
+    let place = format!(
+        "puzzle {} of {}",
+        collection.current + 1,
+        collection.puzzles.len()
+    );
+    let entry = collection.current_entry();
+    let (cells, solution) = puzzle::solve(entry.cells.clone())
+        .map_err(|err| format!("Cannot play {}: {}", place, err))?;
+    let mut gameboard = Gameboard::from_puzzle(cells, solution);
+    gameboard.difficulty = entry.difficulty();
+    let mut controller = start_game(gameboard, config);
+    controller.notify(&format!("Playing {}", place));
+    Ok(controller)
+}
 
+/// Puzzles generated before settling for one that was solved before.
+const GENERATE_ATTEMPTS: usize = 10;
+
+/// Starts a fresh game on a board of a size, generating again if the puzzle
+/// was solved before.
+///
+/// Boards other than the classic size are always classic puzzles, without
+/// added constraints.
+fn new_game(
+    difficulty: Difficulty,
+    size: usize,
+    database: &Option<Database>,
+    config: &Config,
+) -> GameboardController {
+    let rules = if size == SIZE {
+        let mut rules = RuleSet::new(Variant::new(config.variant), config.constraints);
+        rules.scripts.extend(rule_script(config));
+        rules
+    } else {
+        RuleSet::default()
+    };
     let mut gameboard = Gameboard::new();
-    gameboard.populate();
-    let mut gameboard_controller = GameboardController::new(gameboard);
-    let gameboard_view_settings = GameboardViewSettings::new();
-    let mut gameboard_view = GameboardView::new(gameboard_view_settings);
-
-    let texture_settings = TextureSettings::new().filter(Filter::Nearest);
-    let ref mut glyphs = GlyphCache::new("assets/FiraSans-Regular.ttf", (), texture_settings)
-        .expect("Could not load font");
-    while let Some(e) = events.next(&mut window) {
-        gameboard_controller.event(
-            gameboard_view.settings.position,
-            gameboard_view.settings.size,
-            &e,
-        );
-        if let Some(args) = e.render_args() {
-            gl.draw(args.viewport(), |c, g| {
-                use graphics::clear;
-                clear([1.0; 4], g);
-                let window_size = args.window_size;
-                gameboard_view.settings.position[0] =
-                    (window_size[0] - gameboard_view.settings.size) / 2.0;
-                gameboard_view.settings.position[1] =
-                    (window_size[1] - gameboard_view.settings.size) / 2.0;
-                gameboard_view.draw(&gameboard_controller, glyphs, &c, g);
-            });
+    gameboard.generate_rules(difficulty, rules.clone(), size);
+    for _ in 1..GENERATE_ATTEMPTS {
+        let repeat = gameboard.rules.is_classic()
+            && size == SIZE
+            && database
+                .as_ref()
+                .is_some_and(|database| database.has_solved(&gameboard.puzzle()).unwrap_or(false));
+        if !repeat {
+            break;
+        }
+        gameboard = Gameboard::new();
+        gameboard.generate_rules(difficulty, rules.clone(), size);
+    }
+    start_game(gameboard, config)
+}
+
+/// Where a script is kept, by name.
+fn script_path(name: &str) -> std::path::PathBuf {
+    std::path::Path::new(SCRIPTS_DIR).join(format!("{}.rhai", name))
+}
+
+/// The script whose rule new games add, read from its file again so
+/// changes to it take effect at once.
+fn rule_script(config: &Config) -> Option<Script> {
+    let name = config.rule_script.as_deref()?;
+    match Script::load(script_path(name)) {
+        Ok(script) if script.is_rule() => Some(script),
+        Ok(_) => {
+            warn!("Script {} has no rule", name);
+            None
+        }
+        Err(err) => {
+            warn!("Could not load script {}: {}", name, err);
+            None
+        }
+    }
+}
+
+/// Runs the script tool chosen in the settings over the board, read from
+/// its file again so changes to it take effect at once.
+#[cfg(feature = "gui")]
+fn run_script_tool(config: &Config, controller: &mut GameboardController) {
+    let name = match &config.tool_script {
+        Some(name) => name,
+        None => {
+            controller.notify("Choose a script tool in the settings");
+            return;
+        }
+    };
+    let result = Script::load(script_path(name)).and_then(|tool| controller.run_tool(&tool));
+    match result {
+        Ok(1) => controller.notify(&format!("{} changed 1 cell", name)),
+        Ok(changed) => controller.notify(&format!("{} changed {} cells", name, changed)),
+        Err(err) => {
+            warn!("Could not run script {}: {}", name, err);
+            controller.notify(&format!("{} failed: {}", name, err));
+        }
+    }
+}
+
+/// File name of a path for display.
+fn file_name<P: AsRef<std::path::Path>>(path: P) -> String {
+    path.as_ref()
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+}
+
+/// Writes the current puzzle to the puzzles directory.
+#[cfg(feature = "gui")]
+fn save_puzzle(controller: &mut GameboardController, format: SaveFormat) {
+    match puzzle::save(PUZZLES_DIR, &controller.gameboard, format) {
+        Ok(path) => controller.notify(&format!("Saved {}", file_name(path))),
+        Err(err) => {
+            warn!("Could not save puzzle: {}", err);
+            controller.notify("Could not save puzzle");
+        }
+    }
+}
+
+/// Copies a link opening a board's puzzle in f-puzzles, or says why not.
+#[cfg(feature = "gui")]
+fn copy_fpuzzles_link(gameboard: &Gameboard) -> Result<(), String> {
+    if let Some(variant) = fpuzzles::unwritable(&gameboard.rules) {
+        let kind = variant.kind().name();
+        return Err(format!("{} puzzles cannot be opened in f-puzzles", kind));
+    }
+    let mut fpuzzle = FPuzzle::new(gameboard.puzzle());
+    fpuzzle.rules = gameboard.rules.clone();
+    clipboard::write(&fpuzzle.url()).map_err(|err| {
+        warn!("Could not copy link: {}", err);
+        format!("Could not copy: {}", err)
+    })
+}
+
+/// Writes an image of the board alone to the exports directory, with or
+/// without its pencil marks.
+#[cfg(feature = "gui")]
+fn export_board(
+    gl: &mut GlGraphics,
+    glyphs: &mut GlyphCache,
+    view: &GameboardView,
+    controller: &mut GameboardController,
+    size: u32,
+    notes: bool,
+) {
+    let viewport = Viewport {
+        rect: [0, 0, size as i32, size as i32],
+        draw_size: [size; 2],
+        window_size: [size as f64; 2],
+    };
+    let result = screenshot::render(EXPORTS_DIR, size, || {
+        gl.draw(viewport, |c, g| {
+            let r = &mut OpenGlRenderer::new(&c, g, glyphs);
+            view.draw_export(&controller.gameboard, size as f64, notes, r);
+        });
+    });
+    match result {
+        Ok(path) => controller.notify(&format!("Saved {}", file_name(path))),
+        Err(err) => {
+            warn!("Could not export board: {}", err);
+            controller.notify("Could not export board");
+        }
+    }
+}
+
+/// Writes the current puzzle, with its solution on a second page, to a PDF
+/// in the exports directory.
+#[cfg(feature = "gui")]
+fn print_puzzle(controller: &mut GameboardController) {
+    let board = &controller.gameboard;
+    if let Some(variant) = board.rules.variants.first() {
+        let kind = variant.kind().name();
+        controller.notify(&format!("{} puzzles cannot be printed", kind));
+        return;
+    }
+    if board.size() != SIZE {
+        controller.notify(&format!("Only {}x{} puzzles can be printed", SIZE, SIZE));
+        return;
+    }
+    let printed = PrintedPuzzle {
+        cells: board.puzzle(),
+        solution: board.solution.clone(),
+        label: format!("Sudoku {} - {}", board.seed, board.difficulty.name()),
+    };
+    let path = std::path::Path::new(EXPORTS_DIR).join(format!("sudoku-{}.pdf", board.seed));
+    match pdf::save(&path, &[printed], 1, true) {
+        Ok(()) => controller.notify(&format!("Saved {}", file_name(path))),
+        Err(err) => {
+            warn!("Could not print puzzle: {}", err);
+            controller.notify("Could not print puzzle");
+        }
+    }
+}
+
+/// Whether the game on the board can be swapped for another, which it
+/// cannot while shared with other players.
+#[cfg(feature = "gui")]
+fn can_replace_game(session: &Option<Session>) -> Result<(), String> {
+    match session {
+        Some(_) => Err("a game with other players is under way".to_string()),
+        None => Ok(()),
+    }
+}
+
+/// Starts a game on a puzzle pasted from the clipboard.
+#[cfg(feature = "gui")]
+fn paste_game(controller: &mut GameboardController, config: &Config, session: &Option<Session>) {
+    let result = can_replace_game(session)
+        .and_then(|()| clipboard::read().map_err(|err| err.to_string()))
+        .and_then(|text| puzzle::parse_unique(&text));
+    match result {
+        Ok((cells, solution)) => {
+            *controller = start_game(Gameboard::from_puzzle(cells, solution), config);
+            controller.notify("Pasted puzzle");
+        }
+        Err(err) => {
+            warn!("Could not paste puzzle: {}", err);
+            controller.notify(&format!("Could not paste: {}", err));
+        }
+    }
+}
+
+/// Records a finished game in the puzzle database, if one is open, and
+/// announces any achievements it unlocked.
+fn record_result(database: &Option<Database>, controller: &mut GameboardController) {
+    let database = match database {
+        // A game watched is another player's to record.
+        Some(database) if !controller.spectating => database,
+        _ => return,
+    };
+    if let Err(err) = database.record_result(controller) {
+        warn!("Could not record result: {}", err);
+        return;
+    }
+    if let Some(day) = controller
+        .daily
+        .filter(|_| controller.gameboard.is_solved())
+    {
+        let difficulty = controller.gameboard.difficulty;
+        if let Err(err) = database.record_daily(day, difficulty, controller.elapsed()) {
+            warn!("Could not record daily puzzle: {}", err);
+        }
+    }
+    match achievements::check(database, controller) {
+        Ok(unlocked) if !unlocked.is_empty() => {
+            let names: Vec<&str> = unlocked
+                .iter()
+                .map(|achievement| achievement.name())
+                .collect();
+            controller.notify(&format!("Achievement unlocked: {}", names.join(", ")));
+        }
+        Ok(_) => {}
+        Err(err) => warn!("Could not check achievements: {}", err),
+    }
+}
+
+/// Looks up the last attempt at the game's puzzle, to race against.
+#[cfg(feature = "gui")]
+fn load_ghost(database: &Option<Database>, config: &Config, controller: &mut GameboardController) {
+    controller.ghost_loaded = true;
+    let database = match database {
+        Some(database) => database,
+        None => return,
+    };
+    match database.last_replay(&controller.gameboard.puzzle()) {
+        Ok(ghost) => controller.ghost = ghost,
+        Err(err) => warn!("Could not load last attempt: {}", err),
+    }
+    if config.ghost_replay && controller.ghost.is_some() && !controller.is_over() {
+        controller.notify("Racing your last attempt");
+    }
+}
+
+/// Reads the achievements for the gallery, returning whether they could be.
+#[cfg(feature = "gui")]
+fn show_achievements(
+    database: &Option<Database>,
+    gallery: &mut Vec<(Achievement, Option<String>)>,
+    controller: &mut GameboardController,
+) -> bool {
+    let result = match database {
+        Some(database) => achievements::gallery(database),
+        None => Err("No puzzle database".to_string()),
+    };
+    match result {
+        Ok(loaded) => {
+            *gallery = loaded;
+            true
+        }
+        Err(err) => {
+            warn!("Could not read achievements: {}", err);
+            controller.notify("Could not read achievements");
+            false
+        }
+    }
+}
+
+/// Reads the daily puzzles solved this month, showing none when they could
+/// not be read, since today's puzzles can be played all the same.
+#[cfg(feature = "gui")]
+fn load_calendar(database: &Option<Database>, today: i64) -> Calendar {
+    let loaded = match database {
+        Some(database) => Calendar::load(database, today),
+        None => return Calendar::new(today),
+    };
+    loaded.unwrap_or_else(|err| {
+        warn!("Could not read daily puzzles: {}", err);
+        Calendar::new(today)
+    })
+}
+
+/// Looks up whether the shown puzzle of a collection was solved before,
+/// unless it already has been.
+#[cfg(feature = "gui")]
+fn check_solved_before(database: &Option<Database>, collection: &mut Collection) {
+    let database = match database {
+        Some(database) => database,
+        None => return,
+    };
+    if collection.solved_before.contains_key(&collection.current) {
+        return;
+    }
+    match database.has_solved(&collection.current_entry().cells) {
+        Ok(solved) => {
+            collection.solved_before.insert(collection.current, solved);
+        }
+        Err(err) => warn!("Could not look up puzzle: {}", err),
+    }
+}
+
+/// Reads the statistics for the dashboard, returning whether there are any
+/// to show.
+#[cfg(feature = "gui")]
+fn show_stats(
+    database: &Option<Database>,
+    dashboard: &mut Option<Dashboard>,
+    controller: &mut GameboardController,
+) -> bool {
+    let result = match database {
+        Some(database) => Dashboard::load(database),
+        None => Err("No puzzle database".to_string()),
+    };
+    match result {
+        Ok(loaded) => {
+            *dashboard = Some(loaded);
+            true
+        }
+        Err(err) => {
+            warn!("Could not read statistics: {}", err);
+            controller.notify("Could not read statistics");
+            false
+        }
+    }
+}
+
+/// Writes the move log to the exports directory.
+#[cfg(feature = "gui")]
+fn export_move_log(controller: &mut GameboardController) {
+    match movelog::export(EXPORTS_DIR, &controller.gameboard, &controller.move_log) {
+        Ok(path) => controller.notify(&format!("Saved {}", file_name(path))),
+        Err(err) => {
+            warn!("Could not export moves: {}", err);
+            controller.notify("Could not export moves");
+        }
+    }
+}
+
+/// Writes the game history to the exports directory as CSV.
+#[cfg(feature = "gui")]
+fn export_history(database: &Option<Database>, controller: &mut GameboardController) {
+    let path = std::path::Path::new(EXPORTS_DIR).join(stats::HISTORY_FILE);
+    let result = match database {
+        Some(database) => stats::export_csv(database, &path),
+        None => Err("No puzzle database".to_string()),
+    };
+    match result {
+        Ok(games) => controller.notify(&format!("Saved {} games to {}", games, file_name(path))),
+        Err(err) => {
+            warn!("Could not export statistics: {}", err);
+            controller.notify("Could not export statistics");
+        }
+    }
+}
+
+/// Imports the puzzles of a collection into the puzzle database, if one is
+/// open.
+#[cfg(feature = "gui")]
+fn import_collection(database: &Option<Database>, collection: &Collection) {
+    if let Some(database) = database {
+        if let Err(err) = database.add_collection(collection) {
+            warn!("Could not import {}: {}", collection.path.display(), err);
+        }
+    }
+}
+
+/// Starts a game on a stored puzzle of a difficulty that has not been
+/// played yet.
+#[cfg(feature = "gui")]
+fn unplayed_game(
+    database: &Option<Database>,
+    difficulty: Difficulty,
+    controller: &mut GameboardController,
+    config: &Config,
+) -> bool {
+    let found = match database {
+        Some(database) => database.search(&Search {
+            difficulty: Some(difficulty),
+            unplayed: true,
+            limit: 1,
+        }),
+        None => Err("No puzzle database".to_string()),
+    };
+    let stored = match found {
+        Ok(mut stored) if !stored.is_empty() => stored.remove(0),
+        Ok(_) => {
+            controller.notify(&format!("No unplayed {} puzzles", difficulty.name()));
+            return false;
+        }
+        Err(err) => {
+            warn!("Could not search puzzles: {}", err);
+            controller.notify("Could not search puzzles");
+            return false;
+        }
+    };
+    // Imported puzzles are stored without their solutions.
+    let solution = match stored.solution {
+        Some(solution) => solution,
+        None => match puzzle::solve(stored.cells.clone()) {
+            Ok((_, solution)) => solution,
+            Err(err) => {
+                controller.notify(&format!("Could not play stored puzzle: {}", err));
+                return false;
+            }
+        },
+    };
+    let mut gameboard = Gameboard::from_puzzle(stored.cells, solution);
+    gameboard.difficulty = stored.difficulty;
+    *controller = start_game(gameboard, config);
+    controller.notify(&format!(
+        "Playing {} puzzle from {}",
+        difficulty.name(),
+        stored.source
+    ));
+    true
+}
+
+/// Starts a game on the first puzzle of the configured feed that has not
+/// been played yet, playing from the cached feed when it cannot be reached.
+#[cfg(feature = "gui")]
+fn feed_game(
+    database: &Option<Database>,
+    cache: &std::path::Path,
+    controller: &mut GameboardController,
+    config: &Config,
+) -> bool {
+    let url = match &config.puzzle_feed {
+        Some(url) => url,
+        None => return false,
+    };
+    let (puzzles, cached) = match feed::fetch_cached(url, cache)
+        .and_then(|(text, cached)| Ok((feed::parse(&text)?, cached)))
+    {
+        Ok(found) => found,
+        Err(err) => {
+            warn!("Could not read puzzle feed {}: {}", url, err);
+            controller.notify("Could not read the puzzle feed");
+            return false;
         }
+    };
+    let played = |cells: &Grid| match database {
+        Some(database) => database.has_played(cells).unwrap_or_else(|err| {
+            warn!("Could not look up puzzle: {}", err);
+            false
+        }),
+        None => false,
+    };
+    for found in puzzles.into_iter().filter(|found| !played(&found.cells)) {
+        let solution = match puzzle::solve_variant(found.cells.clone(), &found.rules) {
+            Ok((_, solution)) => solution,
+            Err(err) => {
+                warn!("Skipping feed puzzle: {}", err);
+                continue;
+            }
+        };
+        let mut gameboard = Gameboard::from_puzzle(found.cells, solution);
+        gameboard.rules = found.rules;
+        if let Some(difficulty) = found.difficulty {
+            gameboard.difficulty = difficulty;
+        }
+        let name = found
+            .title
+            .unwrap_or_else(|| format!("{} puzzle", gameboard.difficulty.name()));
+        *controller = start_game(gameboard, config);
+        if cached {
+            controller.notify(&format!("Offline: playing {} from the cached feed", name));
+        } else {
+            controller.notify(&format!("Playing {} from the feed", name));
+        }
+        return true;
+    }
+    controller.notify("No unplayed puzzles in the feed");
+    false
+}
+
+fn main() {
+    let args = cli::Args::parse();
+    logging::init(LOGS_DIR, args.verbose);
+    match args.command {
+        Some(command) => std::process::exit(cli::run(command)),
+        #[cfg(feature = "gui")]
+        None => open_window(args),
+        #[cfg(not(feature = "gui"))]
+        None => {
+            use clap::CommandFactory;
+            cli::Args::command()
+                .error(
+                    clap::error::ErrorKind::MissingSubcommand,
+                    "this build has no window, so a command is needed",
+                )
+                .exit()
+        }
+    }
+}
+
+/// Opens the game's window, playing as the profile given on the command
+/// line, or the last one, and switching profiles as the player asks.
+#[cfg(feature = "gui")]
+fn open_window(args: cli::Args) {
+    // A profile named on the command line is played as, and otherwise the
+    // player picks one at startup once there is more than the default.
+    let named = args.profile.as_deref().map(Profile::new);
+    let (role, race) = (args.role(), args.race);
+    let (mut profile, mut ask_profile) = match named {
+        Some(profile) => (profile, false),
+        None => (Profile::last(), Profile::list().len() > 1),
+    };
+    // Co-op players go by the name of the profile they start with.
+    let mut session = role.map(|role| {
+        let started = match &role {
+            Role::Host(address) => Session::host(address, &profile.name, race),
+            Role::Join(address) => Session::join(address, &profile.name, false),
+            Role::Watch(address) => Session::join(address, &profile.name, true),
+        };
+        started.unwrap_or_else(|err| {
+            error!("Could not start co-op game: {}", err);
+            std::process::exit(1);
+        })
+    });
+    let config = Config::load(profile.config_path());
+
+    let mut settings = EventSettings::new();
+    settings.set_lazy(!config.show_timer);
+    settings.swap_buffers(true);
+    settings.max_fps(30);
+    settings.ups(30);
+    let mut events = Events::new(settings);
+    let opengl = OpenGL::V3_2;
+    let settings = WindowSettings::new("Sudoku", config.window_size)
+        .fullscreen(config.fullscreen)
+        .exit_on_esc(true)
+        .graphics_api(opengl);
+    let mut window: GlutinWindow = settings.build().expect("Could not create window");
+    if let (Some(position), false) = (config.window_position, config.fullscreen) {
+        window.set_position(position);
+    }
+    let mut gl = GlGraphics::new(opengl);
+
+    loop {
+        if let Err(err) = profile.create().and_then(|()| profile.remember()) {
+            warn!("Could not set up profile {}: {}", profile.name, err);
+        }
+        match play(
+            &mut window,
+            &mut gl,
+            &mut events,
+            &profile,
+            ask_profile,
+            &mut session,
+        ) {
+            Some(next) => {
+                profile = next;
+                ask_profile = false;
+            }
+            None => break,
+        }
+    }
+}
+
+/// A profile's play in the window: the game, each screen's controller and
+/// what it shows, and where each screen returns to.
+#[cfg(feature = "gui")]
+struct App<'a> {
+    profile: &'a Profile,
+    session: &'a mut Option<Session>,
+    config_path: std::path::PathBuf,
+    config: Config,
+    autosave_path: std::path::PathBuf,
+    saves_dir: std::path::PathBuf,
+    progress_path: std::path::PathBuf,
+    feed_cache_path: std::path::PathBuf,
+    database: Option<Database>,
+    /// The game left unfinished last time, if there was one.
+    restorable: Option<SavedGame>,
+    gameboard_controller: GameboardController,
+    themes: Vec<Theme>,
+    theme: Theme,
+    views: Views,
+    sounds: Sounds,
+    settings_controller: SettingsController,
+    menu_controller: MenuController,
+    win_controller: WinController,
+    toolbar_controller: ToolbarController,
+    keypad_controller: KeypadController,
+    prompt_controller: PromptController,
+    slots_controller: SlotsController,
+    slots: Vec<save::Slot>,
+    files_controller: FilesController,
+    puzzle_files: Vec<std::path::PathBuf>,
+    library_controller: FilesController,
+    packs: Vec<Collection>,
+    collection_controller: CollectionController,
+    collection: Option<Collection>,
+    shared: Option<Shared>,
+    dashboard: Option<Dashboard>,
+    gallery: Vec<(Achievement, Option<String>)>,
+    daily_controller: DailyController,
+    calendar: Calendar,
+    lobby_controller: LobbyController,
+    /// Looks for games on the local network while the lobby is open.
+    browser: Option<Browser>,
+    playback: Option<Playback>,
+    editor_controller: EditorController,
+    /// Until answered, the game from last time is kept rather than saved
+    /// over.
+    restore_pending: bool,
+    screen: Screen,
+    profiles: Vec<Profile>,
+    profiles_controller: ProfilesController,
+    /// Where closing the profile picker returns to.
+    profiles_return: Screen,
+    /// The profile to play as next, once the player switches.
+    switch_to: Option<Profile>,
+    /// Where closing the settings screen returns to.
+    settings_return: Screen,
+    /// Where closing the saved games screen returns to.
+    slots_return: Screen,
+    /// Where closing the open puzzle screen returns to.
+    open_return: Screen,
+    /// Where closing the share screen returns to.
+    share_return: Screen,
+    /// Where closing the library returns to.
+    library_return: Screen,
+    /// Where closing the statistics returns to.
+    stats_return: Screen,
+    /// Where closing the achievements returns to.
+    achievements_return: Screen,
+    fonts: Vec<String>,
+    scripts: Vec<Script>,
+    glyphs: GlyphCache<'static>,
+    loaded_font: Option<String>,
+}
+
+#[cfg(feature = "gui")]
+impl<'a> App<'a> {
+    fn new(
+        window: &mut GlutinWindow,
+        profile: &'a Profile,
+        ask_profile: bool,
+        session: &'a mut Option<Session>,
+    ) -> App<'a> {
+        let config_path = profile.config_path();
+        let config = Config::load(&config_path);
+        // A profile switched to keeps its own window mode.
+        let monitor = window.ctx.window().get_current_monitor();
+        window
+            .ctx
+            .window()
+            .set_fullscreen(Some(monitor).filter(|_| config.fullscreen));
+
+        let autosave_path = profile.path(AUTOSAVE_FILE);
+        let database = Database::open(profile.path(DATABASE_FILE))
+            .map_err(|err| warn!("Could not open puzzle database: {}", err))
+            .ok();
+        // A game left unfinished last time is resumed, or offered for
+        // restoring, before a fresh one is generated.
+        let restorable = match SavedGame::load(&autosave_path) {
+            Ok(saved) => Some(saved),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => {
+                warn!("Ignoring autosave: {}", err);
+                None
+            }
+        };
+        let gameboard_controller = match &restorable {
+            Some(saved) => saved.restore(),
+            None => new_game(Difficulty::Medium, SIZE, &database, &config),
+        };
+        let themes = theme::load_themes(THEMES_DIR);
+        let theme = theme::select(&themes, &config.theme, config.high_contrast);
+        let mut views = Views::new(&theme);
+        views.apply_appearance(&theme, &config, window.size().into());
+        let restore_pending = restorable.is_some() && !config.continue_last_game;
+        let mut screen = if restore_pending {
+            Screen::Restore
+        } else {
+            Screen::Game
+        };
+        let profiles = Profile::list();
+        let mut profiles_controller = ProfilesController::new();
+        let profiles_return = screen;
+        if ask_profile {
+            profiles_controller.list.selected = profiles
+                .iter()
+                .position(|other| other == profile)
+                .map_or(0, |index| index + 1);
+            screen = Screen::Profiles;
+        }
+        let (glyphs, font_error) = font::load_glyphs(config.font.as_deref());
+        if let Some(err) = font_error {
+            warn!("{}", err);
+        }
+        App {
+            profile,
+            session,
+            config_path,
+            autosave_path,
+            saves_dir: profile.path(SAVES_DIR),
+            progress_path: profile.path(PROGRESS_FILE),
+            feed_cache_path: profile.path(FEED_CACHE_FILE),
+            database,
+            restorable,
+            gameboard_controller,
+            themes,
+            theme,
+            views,
+            sounds: Sounds::new(),
+            settings_controller: SettingsController::new(),
+            menu_controller: MenuController::new(),
+            win_controller: WinController::new(),
+            toolbar_controller: ToolbarController::new(),
+            keypad_controller: KeypadController::new(),
+            prompt_controller: PromptController::new(),
+            slots_controller: SlotsController::new(),
+            slots: Vec::new(),
+            files_controller: FilesController::new(),
+            puzzle_files: Vec::new(),
+            library_controller: FilesController::new(),
+            packs: Vec::new(),
+            collection_controller: CollectionController::new(),
+            collection: None,
+            shared: None,
+            dashboard: None,
+            gallery: Vec::new(),
+            daily_controller: DailyController::new(),
+            calendar: Calendar::new(daily::today()),
+            lobby_controller: LobbyController::new(),
+            browser: None,
+            playback: None,
+            editor_controller: EditorController::new(),
+            restore_pending,
+            screen,
+            profiles,
+            profiles_controller,
+            profiles_return,
+            switch_to: None,
+            settings_return: Screen::Game,
+            slots_return: Screen::Game,
+            open_return: Screen::Game,
+            share_return: Screen::Game,
+            library_return: Screen::Game,
+            stats_return: Screen::Game,
+            achievements_return: Screen::Game,
+            fonts: font::list_fonts(FONTS_DIR),
+            scripts: script::load_scripts(SCRIPTS_DIR),
+            glyphs,
+            loaded_font: config.font.clone(),
+            config,
+        }
+    }
+
+    /// The main menu's choices for the game as it stands.
+    fn menu_items(&self) -> Vec<MenuAction> {
+        menu::items(
+            !self.gameboard_controller.is_over(),
+            self.config.puzzle_feed.is_some(),
+        )
+    }
+
+    /// Maps an event to what the player did on the current screen, if
+    /// anything, the way the board maps keys and clicks to actions.
+    fn screen_action(
+        &mut self,
+        e: &piston::input::Event,
+        modifiers: ModifierKey,
+    ) -> Option<ScreenAction> {
+        use piston::input::{Button, Key, PressEvent};
+
+        match self.screen {
+            Screen::Menu => {
+                let items = self.menu_items();
+                let menu = &self.views.menu.settings;
+                self.menu_controller
+                    .event(menu.position, menu.size, menu.row_height, &items, e)
+                    .map(ScreenAction::Menu)
+            }
+            // The board watched follows its players, and Tab moves on to the
+            // next player's board in a race.
+            Screen::Game if self.gameboard_controller.spectating => {
+                self.views.gameboard.event(e);
+                match e.press_args() {
+                    Some(Button::Keyboard(Key::Tab)) => Some(ScreenAction::WatchNext),
+                    _ => None,
+                }
+            }
+            Screen::Game => {
+                let toolbar = &self.views.toolbar.settings;
+                let mut actions: Vec<Action> = self
+                    .toolbar_controller
+                    .event(toolbar.position, toolbar.size, toolbar.gap, e)
+                    .map(Action::Command)
+                    .into_iter()
+                    .collect();
+                let keypad = &self.views.keypad.settings;
+                let digit = self.keypad_controller.event(
+                    keypad.position,
+                    keypad.size,
+                    self.gameboard_controller.gameboard.digits(),
+                    e,
+                );
+                actions.extend(
+                    digit.and_then(|val| self.gameboard_controller.digit_action(val, &self.config)),
+                );
+                self.views.gameboard.event(e);
+                let board = &self.views.gameboard.settings;
+                actions.extend(self.gameboard_controller.event(
+                    board.position,
+                    board.size,
+                    board.zoom,
+                    board.pan,
+                    modifiers,
+                    &self.config,
+                    e,
+                ));
+                if actions.is_empty() {
+                    None
+                } else {
+                    Some(ScreenAction::Board(actions))
+                }
+            }
+            Screen::Settings => {
+                let volume = (self.config.sound, self.config.volume_percent);
+                let settings = &self.views.settings.settings;
+                let changed = self.settings_controller.event(
+                    settings.position,
+                    settings.size,
+                    settings.row_height,
+                    &mut self.config,
+                    &Choices {
+                        themes: &self.themes,
+                        fonts: &self.fonts,
+                        scripts: &self.scripts,
+                    },
+                    e,
+                );
+                if changed {
+                    let sound = (self.config.sound, self.config.volume_percent) != volume;
+                    Some(ScreenAction::Configured { sound })
+                } else {
+                    None
+                }
+            }
+            Screen::GameOver if self.views.gameboard.is_celebrating() => {
+                e.press_args().map(|_| ScreenAction::SkipCelebration)
+            }
+            Screen::GameOver => {
+                let board = &self.views.gameboard.settings;
+                self.win_controller
+                    .event(board.position, board.size, e)
+                    .map(ScreenAction::Win)
+            }
+            Screen::Review => e.press_args().map(|_| ScreenAction::Show(Screen::GameOver)),
+            Screen::Editor => {
+                let board = &self.views.gameboard.settings;
+                self.editor_controller
+                    .event(board.position, board.size, modifiers, e)
+                    .map(ScreenAction::Editor)
+            }
+            Screen::Replay => {
+                let closed = self
+                    .playback
+                    .as_mut()
+                    .is_none_or(|playback| playback.event(e));
+                if closed {
+                    Some(ScreenAction::CloseReplay)
+                } else {
+                    None
+                }
+            }
+            Screen::Share => e
+                .press_args()
+                .map(|_| ScreenAction::Show(self.share_return)),
+            Screen::Stats => match e.press_args() {
+                Some(Button::Keyboard(Key::E)) => Some(ScreenAction::ExportHistory),
+                Some(_) => Some(ScreenAction::Show(self.stats_return)),
+                None => None,
+            },
+            Screen::Achievements => e
+                .press_args()
+                .map(|_| ScreenAction::Show(self.achievements_return)),
+            Screen::Daily => match self.daily_controller.event(e) {
+                Some(Some(difficulty)) => Some(ScreenAction::Daily(difficulty)),
+                Some(None) => Some(ScreenAction::Show(Screen::Menu)),
+                None => None,
+            },
+            Screen::Lobby => {
+                let games = self
+                    .browser
+                    .as_ref()
+                    .map_or(&[][..], |browser| &browser.games[..]);
+                self.lobby_controller
+                    .event(self.session.is_some(), games, e)
+                    .map(ScreenAction::Lobby)
+            }
+            Screen::Restore => {
+                let board = &self.views.gameboard.settings;
+                self.prompt_controller
+                    .event(board.position, board.size, e)
+                    .map(ScreenAction::Restore)
+            }
+            Screen::Slots => {
+                let slots = &self.views.slots.settings;
+                self.slots_controller
+                    .event(slots.position, slots.size, slots.row_height, &self.slots, e)
+                    .map(ScreenAction::Slot)
+            }
+            Screen::Open => {
+                let files = &self.views.files.settings;
+                self.files_controller
+                    .event(
+                        files.position,
+                        files.size,
+                        files.row_height,
+                        self.puzzle_files.len(),
+                        e,
+                    )
+                    .map(ScreenAction::OpenFile)
+            }
+            Screen::Profiles => {
+                let files = &self.views.files.settings;
+                self.profiles_controller
+                    .event(
+                        files.position,
+                        files.size,
+                        files.row_height,
+                        &self.profiles,
+                        e,
+                    )
+                    .map(ScreenAction::Profile)
+            }
+            Screen::Library => {
+                let files = &self.views.files.settings;
+                self.library_controller
+                    .event(
+                        files.position,
+                        files.size,
+                        files.row_height,
+                        self.packs.len(),
+                        e,
+                    )
+                    .map(ScreenAction::OpenPack)
+            }
+            Screen::Collection => {
+                let collection = self.collection.as_mut()?;
+                let play = self.collection_controller.event(collection, e);
+                check_solved_before(&self.database, collection);
+                if play {
+                    Some(ScreenAction::PlayCollection)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Carries out what the player did on a screen, the one way screens
+    /// change the game and move between each other.
+    fn reduce(&mut self, action: ScreenAction, window_size: [f64; 2]) {
+        match action {
+            ScreenAction::Board(actions) => {
+                let controller = &mut self.gameboard_controller;
+                let (moves, mistakes) = (controller.moves_since_save, controller.mistakes);
+                for action in actions {
+                    controller.dispatch(action);
+                }
+                // The loudest news of a move is heard.
+                let cue = if controller.moves_since_save <= moves {
+                    None
+                } else if controller.gameboard.is_solved() {
+                    Some(Cue::Solved)
+                } else if controller.is_lost() {
+                    Some(Cue::Lost)
+                } else if controller.mistakes > mistakes {
+                    Some(Cue::Mistake)
+                } else if !controller.completed_houses.is_empty() {
+                    Some(Cue::House)
+                } else {
+                    Some(Cue::Place)
+                };
+                if let Some(cue) = cue {
+                    self.sounds.play(cue, &self.config);
+                }
+            }
+            ScreenAction::WatchNext => {
+                if let Some(shared) = &mut *self.session {
+                    if let Some(name) = shared.watch_next() {
+                        self.gameboard_controller
+                            .notify(&format!("Watching {}", name));
+                    }
+                }
+            }
+            ScreenAction::Show(screen) => self.screen = screen,
+            ScreenAction::Menu(action) => self.choose_from_menu(action),
+            ScreenAction::Configured { sound } => {
+                if let Err(err) = self.config.save(&self.config_path) {
+                    warn!("Could not save config: {}", err);
+                }
+                // Changes to the sound are heard at once.
+                if sound {
+                    self.sounds.play(Cue::Place, &self.config);
+                }
+                if self.config.font != self.loaded_font {
+                    let (glyphs, font_error) = font::load_glyphs(self.config.font.as_deref());
+                    if let Some(err) = font_error {
+                        warn!("{}", err);
+                    }
+                    self.glyphs = glyphs;
+                    self.loaded_font = self.config.font.clone();
+                }
+                self.theme =
+                    theme::select(&self.themes, &self.config.theme, self.config.high_contrast);
+                self.views
+                    .apply_appearance(&self.theme, &self.config, window_size);
+            }
+            ScreenAction::SkipCelebration => self.views.gameboard.skip_celebration(),
+            ScreenAction::Win(WinAction::NewGame) => {
+                let board = &self.gameboard_controller.gameboard;
+                self.gameboard_controller = new_game(
+                    board.difficulty,
+                    board.digits(),
+                    &self.database,
+                    &self.config,
+                );
+                self.screen = Screen::Game;
+            }
+            ScreenAction::Win(WinAction::Review) => self.screen = Screen::Review,
+            ScreenAction::Win(WinAction::Replay) => {
+                self.playback = Some(Playback::new(self.gameboard_controller.replay.clone()));
+                self.screen = Screen::Replay;
+            }
+            ScreenAction::Win(WinAction::MainMenu) => {
+                self.menu_controller.selected = 0;
+                self.screen = Screen::Menu;
+            }
+            ScreenAction::Editor(EditorAction::Play) => {
+                self.gameboard_controller = start_game(self.editor_controller.play(), &self.config);
+                self.screen = Screen::Game;
+            }
+            ScreenAction::Editor(EditorAction::Save(format)) => {
+                let editor = &mut self.editor_controller;
+                editor.message = Some(match puzzle::save(PUZZLES_DIR, &editor.gameboard, format) {
+                    Ok(path) => format!("Saved {}", file_name(path)),
+                    Err(err) => format!("Could not save puzzle: {}", err),
+                });
+            }
+            ScreenAction::Editor(EditorAction::CopyLink) => {
+                let editor = &mut self.editor_controller;
+                editor.message = Some(match copy_fpuzzles_link(&editor.gameboard) {
+                    Ok(()) => "Copied f-puzzles link".to_string(),
+                    Err(err) => err,
+                });
+            }
+            ScreenAction::CloseReplay => {
+                self.playback = None;
+                self.screen = Screen::GameOver;
+            }
+            ScreenAction::ExportHistory => {
+                export_history(&self.database, &mut self.gameboard_controller)
+            }
+            ScreenAction::Daily(difficulty) => {
+                let today = daily::today();
+                self.gameboard_controller =
+                    start_game(daily::puzzle(today, difficulty), &self.config);
+                self.gameboard_controller.daily = Some(today);
+                self.gameboard_controller
+                    .notify(&format!("Daily puzzle for {}", daily::date_name(today)));
+                self.screen = Screen::Game;
+            }
+            ScreenAction::Lobby(action) => self.act_in_lobby(action),
+            ScreenAction::Restore(answer) => {
+                if !answer {
+                    self.gameboard_controller =
+                        new_game(Difficulty::Medium, SIZE, &self.database, &self.config);
+                }
+                save::autosave(&self.gameboard_controller, &self.autosave_path);
+                self.restore_pending = false;
+                self.screen = Screen::Game;
+            }
+            ScreenAction::Slot(SlotAction::Load(index)) => {
+                let slot = &self.slots[index];
+                self.gameboard_controller = slot.game.restore();
+                self.gameboard_controller
+                    .notify(&format!("Loaded {}", slot.name));
+                self.screen = Screen::Game;
+            }
+            ScreenAction::Slot(SlotAction::Save(name)) => {
+                let controller = &mut self.gameboard_controller;
+                if controller.is_over() {
+                    controller.notify("Finished games cannot be saved");
+                } else {
+                    let game = SavedGame::new(controller);
+                    match save::save_slot(&self.saves_dir, &name, &game) {
+                        Ok(()) => controller.notify(&format!("Saved as {}", name)),
+                        Err(err) => {
+                            warn!("Could not save {}: {}", name, err);
+                            controller.notify("Could not save game");
+                        }
+                    }
+                }
+                self.screen = self.slots_return;
+            }
+            ScreenAction::Slot(SlotAction::Delete(index)) => {
+                let name = &self.slots[index].name;
+                if let Err(err) = save::delete_slot(&self.saves_dir, name) {
+                    warn!("Could not delete {}: {}", name, err);
+                }
+                self.slots = save::list_slots(&self.saves_dir);
+            }
+            ScreenAction::OpenFile(index) => {
+                let path = self.puzzle_files[index].clone();
+                let result = can_replace_game(self.session).and_then(|()| {
+                    if puzzle::is_collection(&path) {
+                        Collection::open(&path, &self.progress_path).map(|opened| {
+                            import_collection(&self.database, &opened);
+                            self.collection = Some(opened);
+                            self.screen = Screen::Collection;
+                        })
+                    } else {
+                        open_game(&path, &self.config).map(|controller| {
+                            self.gameboard_controller = controller;
+                            self.screen = Screen::Game;
+                        })
+                    }
+                });
+                if let Err(err) = result {
+                    warn!("Could not open {}: {}", path.display(), err);
+                    self.gameboard_controller.notify(&format!(
+                        "Could not open {}: {}",
+                        file_name(&path),
+                        err
+                    ));
+                }
+            }
+            ScreenAction::Profile(ProfileAction::Choose(index)) => {
+                if self.profiles[index] == *self.profile {
+                    self.screen = self.profiles_return;
+                } else {
+                    self.switch_to = Some(self.profiles[index].clone());
+                }
+            }
+            ScreenAction::Profile(ProfileAction::Create(name)) => {
+                let created = Profile::new(&name);
+                if self.profiles.contains(&created) {
+                    self.gameboard_controller
+                        .notify(&format!("Profile {} already exists", name));
+                } else {
+                    self.switch_to = Some(created);
+                }
+            }
+            ScreenAction::OpenPack(index) => {
+                // Reopened so it starts from the first puzzle each time.
+                let path = &self.packs[index].path;
+                match Collection::open(path, &self.progress_path) {
+                    Ok(opened) => {
+                        import_collection(&self.database, &opened);
+                        self.collection = Some(opened);
+                        self.screen = Screen::Collection;
+                    }
+                    Err(err) => {
+                        warn!("Could not open {}: {}", path.display(), err);
+                        self.gameboard_controller
+                            .notify(&format!("Could not open {}", file_name(path)));
+                    }
+                }
+            }
+            ScreenAction::PlayCollection => {
+                if let Some(collection) = &self.collection {
+                    match collection_game(collection, &self.config) {
+                        Ok(controller) => {
+                            self.gameboard_controller = controller;
+                            self.screen = Screen::Game;
+                        }
+                        Err(err) => self.gameboard_controller.notify(&err),
+                    }
+                }
+            }
+        }
+    }
+
+    fn choose_from_menu(&mut self, action: MenuAction) {
+        match action {
+            MenuAction::Resume => self.screen = Screen::Game,
+            MenuAction::GiveUp => {
+                self.gameboard_controller.give_up();
+                record_result(&self.database, &mut self.gameboard_controller);
+                self.screen = Screen::GameOver;
+            }
+            MenuAction::NewGame(difficulty) => {
+                self.gameboard_controller = new_game(
+                    difficulty,
+                    difficulty.board_size(),
+                    &self.database,
+                    &self.config,
+                );
+                self.screen = Screen::Game;
+            }
+            MenuAction::NewSized(size) => {
+                self.gameboard_controller =
+                    new_game(Difficulty::Medium, size, &self.database, &self.config);
+                self.screen = Screen::Game;
+            }
+            MenuAction::Unplayed => {
+                let difficulty = self.gameboard_controller.gameboard.difficulty;
+                let controller = &mut self.gameboard_controller;
+                if unplayed_game(&self.database, difficulty, controller, &self.config) {
+                    self.screen = Screen::Game;
+                }
+            }
+            MenuAction::Daily => {
+                self.calendar = load_calendar(&self.database, daily::today());
+                self.screen = Screen::Daily;
+            }
+            MenuAction::Multiplayer => {
+                if self.browser.is_none() {
+                    self.browser = Browser::new()
+                        .map_err(|err| warn!("Could not look for games on the network: {}", err))
+                        .ok();
+                }
+                self.screen = Screen::Lobby;
+            }
+            MenuAction::Feed => {
+                let started = feed_game(
+                    &self.database,
+                    &self.feed_cache_path,
+                    &mut self.gameboard_controller,
+                    &self.config,
+                );
+                if started {
+                    self.screen = Screen::Game;
+                }
+            }
+            MenuAction::OpenPuzzle => {
+                self.puzzle_files = puzzle::list_files(PUZZLES_DIR);
+                self.open_return = Screen::Menu;
+                self.screen = Screen::Open;
+            }
+            MenuAction::Library => {
+                self.packs = Collection::open_all(PACKS_DIR, &self.progress_path);
+                self.library_return = Screen::Menu;
+                self.screen = Screen::Library;
+            }
+            MenuAction::Editor => self.screen = Screen::Editor,
+            MenuAction::SavePuzzle(format) => save_puzzle(&mut self.gameboard_controller, format),
+            MenuAction::PrintPuzzle => print_puzzle(&mut self.gameboard_controller),
+            MenuAction::Stats => {
+                let controller = &mut self.gameboard_controller;
+                if show_stats(&self.database, &mut self.dashboard, controller) {
+                    self.stats_return = Screen::Menu;
+                    self.screen = Screen::Stats;
+                }
+            }
+            MenuAction::Achievements => {
+                let controller = &mut self.gameboard_controller;
+                if show_achievements(&self.database, &mut self.gallery, controller) {
+                    self.achievements_return = Screen::Menu;
+                    self.screen = Screen::Achievements;
+                }
+            }
+            MenuAction::Profiles => {
+                self.profiles = Profile::list();
+                self.profiles_return = Screen::Menu;
+                self.screen = Screen::Profiles;
+            }
+            MenuAction::Settings => {
+                self.settings_return = Screen::Menu;
+                self.screen = Screen::Settings;
+            }
+        }
+    }
+
+    fn act_in_lobby(&mut self, action: LobbyAction) {
+        match action {
+            LobbyAction::Host { race } => {
+                match Session::host(COOP_ADDRESS, &self.profile.name, race) {
+                    Ok(hosted) => *self.session = Some(hosted.hold()),
+                    Err(err) => self
+                        .gameboard_controller
+                        .notify(&format!("Could not host: {}", err)),
+                }
+            }
+            LobbyAction::Join { address, watching } => {
+                let games = self
+                    .browser
+                    .as_ref()
+                    .map_or(&[][..], |browser| &browser.games[..]);
+                let joined = lobby::address(&address, games).and_then(|address| {
+                    Session::join(&address, &self.profile.name, watching)
+                        .map_err(|err| err.to_string())
+                });
+                match joined {
+                    Ok(joined) => *self.session = Some(joined),
+                    Err(err) => self
+                        .gameboard_controller
+                        .notify(&format!("Could not join: {}", err)),
+                }
+            }
+            LobbyAction::Start => {
+                if let Some(shared) = &mut *self.session {
+                    if !shared.started() && shared.is_host() {
+                        let difficulty = self.gameboard_controller.gameboard.difficulty;
+                        self.gameboard_controller = new_game(
+                            difficulty,
+                            difficulty.board_size(),
+                            &self.database,
+                            &self.config,
+                        );
+                        shared.start(&mut self.gameboard_controller);
+                    }
+                    if shared.started() {
+                        self.screen = Screen::Game;
+                    }
+                }
+            }
+            LobbyAction::Leave => {
+                self.gameboard_controller.partners.clear();
+                self.gameboard_controller.spectating = false;
+                *self.session = None;
+            }
+            LobbyAction::Back => {
+                self.browser = None;
+                self.screen = Screen::Menu;
+            }
+        }
+    }
+
+    /// Shows what changed on the board since the last event, saves the game
+    /// every few moves, and ends it once it is over. A board only watched
+    /// just shows the houses its players completed.
+    fn follow_board(&mut self) {
+        let controller = &mut self.gameboard_controller;
+        let board = &controller.gameboard;
+        for house in &controller.completed_houses {
+            self.views
+                .gameboard
+                .flash(&house.cells(board.size(), board.rules.layout()));
+        }
+        controller.completed_houses.clear();
+        if controller.spectating {
+            return;
+        }
+        for cell in controller.clashing_entries.drain(..) {
+            if self.config.auto_check {
+                self.views.gameboard.shake(cell);
+            }
+        }
+        if controller.gameboard.is_solved() {
+            self.views.gameboard.celebrate();
+        }
+        if controller.moves_since_save >= AUTOSAVE_MOVES || controller.is_over() {
+            save::autosave(controller, &self.autosave_path);
+            controller.moves_since_save = 0;
+        }
+        if controller.is_over() {
+            record_result(&self.database, controller);
+            if controller.gameboard.is_solved() {
+                if let Some(collection) = &mut self.collection {
+                    collection.complete(&controller.gameboard.puzzle());
+                }
+            }
+            self.screen = Screen::GameOver;
+        }
+    }
+}
+
+/// Runs the game as a profile until the window closes, or until the player
+/// switches profile, returning the profile to switch to.
+#[cfg(feature = "gui")]
+fn play(
+    window: &mut GlutinWindow,
+    gl: &mut GlGraphics,
+    events: &mut Events,
+    profile: &Profile,
+    ask_profile: bool,
+    session: &mut Option<Session>,
+) -> Option<Profile> {
+    let mut app = App::new(window, profile, ask_profile, session);
+    let mut debug_controller = DebugController::new();
+    let mut show_move_log = false;
+    let mut chat_controller = ChatController::new();
+    // The player's Discord status, while they allow it.
+    let mut presence = Presence::new();
+    // Whether to capture the next frame drawn.
+    let mut screenshot_requested = false;
+    // Modifier keys held, for shortcuts.
+    let mut modifiers = ModifierKey::NO_MODIFIER;
+    while let Some(e) = events.next(window) {
+        use piston::input::{Button, Event, FileDrag, Input, Key, PressEvent, TextEvent};
+
+        debug_controller.event(&e);
+        modifiers.event(&e);
+        if let Some(shared) = &mut *app.session {
+            match shared.sync(&mut app.gameboard_controller) {
+                Ok(true) => {
+                    app.restore_pending = false;
+                    app.screen = Screen::Game;
+                }
+                Ok(false) => {}
+                Err(reason) => {
+                    app.gameboard_controller.notify(&reason);
+                    app.gameboard_controller.partners.clear();
+                    app.gameboard_controller.spectating = false;
+                    *app.session = None;
+                }
+            }
+        }
+        if let (Screen::Lobby, None, Some(browser)) = (app.screen, &app.session, &mut app.browser) {
+            browser.poll();
+        }
+        let mode = app.session.as_ref().map(|shared| {
+            if shared.is_race() {
+                "a race"
+            } else {
+                "a co-op game"
+            }
+        });
+        presence.update(
+            &app.config,
+            &presence::activity(&app.gameboard_controller, mode),
+        );
+        if !app.gameboard_controller.ghost_loaded {
+            load_ghost(&app.database, &app.config, &mut app.gameboard_controller);
+        }
+        // A puzzle file dropped on the window starts right away, and a
+        // collection from its current puzzle, imported as if opened.
+        if let Event::Input(Input::FileDrag(FileDrag::Drop(path)), _) = &e {
+            let name = file_name(path);
+            let result = can_replace_game(app.session).and_then(|()| {
+                if puzzle::is_collection(path) {
+                    Collection::open(path, &app.progress_path).and_then(|opened| {
+                        import_collection(&app.database, &opened);
+                        let started = collection_game(&opened, &app.config);
+                        app.collection = Some(opened);
+                        started
+                    })
+                } else {
+                    open_game(path, &app.config)
+                }
+            });
+            match result {
+                Ok(controller) => {
+                    app.gameboard_controller = controller;
+                    app.screen = Screen::Game;
+                }
+                Err(err) => {
+                    warn!("Could not open dropped {}: {}", path.display(), err);
+                    app.gameboard_controller
+                        .notify(&format!("Could not open {}: {}", name, err));
+                }
+            }
+            continue;
+        }
+        match e.press_args() {
+            Some(Button::Keyboard(Key::O)) if modifiers.contains(ModifierKey::CTRL) => {
+                if app.screen == Screen::Open {
+                    app.screen = app.open_return;
+                } else {
+                    app.puzzle_files = puzzle::list_files(PUZZLES_DIR);
+                    app.open_return = app.screen;
+                    app.screen = Screen::Open;
+                }
+                continue;
+            }
+            Some(Button::Keyboard(Key::L)) if modifiers.contains(ModifierKey::CTRL) => {
+                if app.screen == Screen::Library {
+                    app.screen = app.library_return;
+                } else {
+                    app.packs = Collection::open_all(PACKS_DIR, &app.progress_path);
+                    app.library_return = app.screen;
+                    app.screen = Screen::Library;
+                }
+                continue;
+            }
+            // The editor saves and links its own puzzle instead.
+            Some(Button::Keyboard(Key::S))
+                if modifiers.contains(ModifierKey::CTRL) && app.screen != Screen::Editor =>
+            {
+                let format = if modifiers.contains(ModifierKey::ALT) {
+                    SaveFormat::Json
+                } else if modifiers.contains(ModifierKey::SHIFT) {
+                    SaveFormat::Csv
+                } else {
+                    SaveFormat::Sdk
+                };
+                save_puzzle(&mut app.gameboard_controller, format);
+                continue;
+            }
+            Some(Button::Keyboard(Key::V)) if modifiers.contains(ModifierKey::CTRL) => {
+                if app.screen == Screen::Game || app.screen == Screen::GameOver {
+                    paste_game(&mut app.gameboard_controller, &app.config, app.session);
+                    app.screen = Screen::Game;
+                }
+                continue;
+            }
+            // Ctrl+C copies the puzzle, with Shift the progress so far, and
+            // with Alt the solution.
+            Some(Button::Keyboard(Key::C)) if modifiers.contains(ModifierKey::CTRL) => {
+                let board = &app.gameboard_controller.gameboard;
+                let (grid, what) = if modifiers.contains(ModifierKey::SHIFT) {
+                    (board.cells.clone(), "progress")
+                } else if modifiers.contains(ModifierKey::ALT) {
+                    (board.solution.clone(), "solution")
+                } else {
+                    (board.puzzle(), "puzzle")
+                };
+                match clipboard::write(&puzzle::to_line(&grid)) {
+                    Ok(()) => app.gameboard_controller.notify(&format!("Copied {}", what)),
+                    Err(err) => {
+                        warn!("Could not copy {}: {}", what, err);
+                        app.gameboard_controller
+                            .notify(&format!("Could not copy: {}", err));
+                    }
+                }
+                continue;
+            }
+            Some(Button::Keyboard(Key::U))
+                if modifiers.contains(ModifierKey::CTRL) && app.screen != Screen::Editor =>
+            {
+                match copy_fpuzzles_link(&app.gameboard_controller.gameboard) {
+                    Ok(()) => app.gameboard_controller.notify("Copied f-puzzles link"),
+                    Err(err) => app.gameboard_controller.notify(&err),
+                }
+                continue;
+            }
+            // Ctrl+E exports an image of the board, with Alt as SVG rather
+            // than PNG, and with Shift leaving out the pencil marks.
+            Some(Button::Keyboard(Key::E))
+                if modifiers.contains(ModifierKey::CTRL | ModifierKey::ALT) =>
+            {
+                let notes = !modifiers.contains(ModifierKey::SHIFT);
+                match svg::save(
+                    EXPORTS_DIR,
+                    &app.gameboard_controller.gameboard,
+                    &app.theme,
+                    notes,
+                ) {
+                    Ok(path) => app
+                        .gameboard_controller
+                        .notify(&format!("Saved {}", file_name(path))),
+                    Err(err) => {
+                        warn!("Could not export board: {}", err);
+                        app.gameboard_controller.notify("Could not export board");
+                    }
+                }
+                continue;
+            }
+            Some(Button::Keyboard(Key::E)) if modifiers.contains(ModifierKey::CTRL) => {
+                export_board(
+                    gl,
+                    &mut app.glyphs,
+                    &app.views.gameboard,
+                    &mut app.gameboard_controller,
+                    app.config.export_size,
+                    !modifiers.contains(ModifierKey::SHIFT),
+                );
+                continue;
+            }
+            // Ctrl+Q shows the puzzle as a QR code, to scan with a phone.
+            Some(Button::Keyboard(Key::Q)) if modifiers.contains(ModifierKey::CTRL) => {
+                if app.screen == Screen::Share {
+                    app.screen = app.share_return;
+                    continue;
+                }
+                let text = puzzle::to_line(&app.gameboard_controller.gameboard.puzzle());
+                match Shared::new(text) {
+                    Ok(share) => {
+                        app.shared = Some(share);
+                        app.share_return = app.screen;
+                        app.screen = Screen::Share;
+                    }
+                    Err(err) => app
+                        .gameboard_controller
+                        .notify(&format!("Cannot share: {}", err)),
+                }
+                continue;
+            }
+            Some(Button::Keyboard(Key::F7)) => {
+                if app.screen == Screen::Stats {
+                    app.screen = app.stats_return;
+                } else if show_stats(
+                    &app.database,
+                    &mut app.dashboard,
+                    &mut app.gameboard_controller,
+                ) {
+                    app.stats_return = app.screen;
+                    app.screen = Screen::Stats;
+                }
+                continue;
+            }
+            Some(Button::Keyboard(Key::F8)) => {
+                if app.screen == Screen::Achievements {
+                    app.screen = app.achievements_return;
+                } else if show_achievements(
+                    &app.database,
+                    &mut app.gallery,
+                    &mut app.gameboard_controller,
+                ) {
+                    app.achievements_return = app.screen;
+                    app.screen = Screen::Achievements;
+                }
+                continue;
+            }
+            Some(Button::Keyboard(Key::F9)) => {
+                show_move_log = !show_move_log;
+                continue;
+            }
+            // Shift+F10 mutes the chat of a game with other players, or
+            // unmutes it.
+            Some(Button::Keyboard(Key::F10)) if modifiers.contains(ModifierKey::SHIFT) => {
+                match &mut *app.session {
+                    Some(shared) => {
+                        let muted = !shared.is_muted();
+                        shared.set_muted(muted);
+                        app.gameboard_controller.notify(if muted {
+                            "Chat muted"
+                        } else {
+                            "Chat unmuted"
+                        });
+                    }
+                    None => app
+                        .gameboard_controller
+                        .notify("Chat is for games with other players"),
+                }
+                continue;
+            }
+            Some(Button::Keyboard(Key::F10)) => {
+                if app.session.is_some() {
+                    chat_controller.visible = !chat_controller.visible;
+                } else {
+                    app.gameboard_controller
+                        .notify("Chat is for games with other players");
+                }
+                continue;
+            }
+            // Ctrl+R runs the script tool chosen in the settings.
+            Some(Button::Keyboard(Key::R))
+                if modifiers.contains(ModifierKey::CTRL) && app.screen == Screen::Game =>
+            {
+                run_script_tool(&app.config, &mut app.gameboard_controller);
+                continue;
+            }
+            // Ctrl+M writes the move log to a text file.
+            Some(Button::Keyboard(Key::M)) if modifiers.contains(ModifierKey::CTRL) => {
+                export_move_log(&mut app.gameboard_controller);
+                continue;
+            }
+            Some(Button::Keyboard(Key::F3)) => {
+                debug_controller.visible = !debug_controller.visible;
+                continue;
+            }
+            Some(Button::Keyboard(Key::F12)) => {
+                screenshot_requested = true;
+                continue;
+            }
+            Some(Button::Keyboard(Key::F2)) => {
+                if app.screen == Screen::Settings {
+                    app.screen = app.settings_return;
+                } else {
+                    // Scripts added since are offered too.
+                    app.scripts = script::load_scripts(SCRIPTS_DIR);
+                    app.settings_return = app.screen;
+                    app.screen = Screen::Settings;
+                }
+                continue;
+            }
+            Some(Button::Keyboard(Key::F5)) => {
+                if app.screen == Screen::Slots {
+                    app.screen = app.slots_return;
+                } else {
+                    app.slots = save::list_slots(&app.saves_dir);
+                    app.slots_controller.editing = None;
+                    app.slots_return = app.screen;
+                    app.screen = Screen::Slots;
+                }
+                continue;
+            }
+            Some(Button::Keyboard(Key::F6)) if app.collection.is_some() => {
+                app.screen = if app.screen == Screen::Collection {
+                    Screen::Game
+                } else {
+                    Screen::Collection
+                };
+                continue;
+            }
+            Some(Button::Keyboard(Key::F1)) if app.screen != Screen::Menu => {
+                app.screen = Screen::Menu;
+                continue;
+            }
+            Some(Button::Keyboard(Key::F11)) => {
+                app.config.fullscreen = !app.config.fullscreen;
+                let monitor = window.ctx.window().get_current_monitor();
+                window
+                    .ctx
+                    .window()
+                    .set_fullscreen(Some(monitor).filter(|_| app.config.fullscreen));
+                continue;
+            }
+            Some(Button::Keyboard(Key::F4)) => {
+                app.config.toggle_dark_mode(DARK_THEME);
+                if let Err(err) = app.config.save(&app.config_path) {
+                    warn!("Could not save config: {}", err);
+                }
+                app.theme = theme::select(&app.themes, &app.config.theme, app.config.high_contrast);
+                app.views
+                    .apply_appearance(&app.theme, &app.config, window.size().into());
+                continue;
+            }
+            _ => {}
+        }
+        // The chat, while open, takes typing from the screen under it.
+        let chatting = chat_controller.visible
+            && matches!(app.screen, Screen::Game | Screen::GameOver | Screen::Lobby);
+        if let (true, Some(shared)) = (chatting, &mut *app.session) {
+            if let Some(text) = chat_controller.event(&e) {
+                shared.say(&text);
+            }
+            if e.text_args().is_some() || matches!(e.press_args(), Some(Button::Keyboard(_))) {
+                continue;
+            }
+        }
+        let on_board = app.screen == Screen::Game;
+        if let Some(action) = app.screen_action(&e, modifiers) {
+            app.reduce(action, window.size().into());
+            if app.switch_to.is_some() {
+                break;
+            }
+        }
+        if on_board {
+            app.follow_board();
+        }
+        if let Some(args) = e.resize_args() {
+            app.views.gameboard.settings.layout(args.window_size);
+            if !app.config.fullscreen {
+                app.config.window_size = [args.window_size[0] as u32, args.window_size[1] as u32];
+            }
+        }
+        if let Some(args) = e.update_args() {
+            app.views.gameboard.update(args.dt);
+            if let Some(playback) = &mut app.playback {
+                playback.update(args.dt);
+            }
+        }
+        // Only redraw on input unless something changes on its own. Mouse
+        // moves count as input, which keeps the hover highlight current.
+        events.set_lazy(
+            !app.config.show_timer
+                && !app.views.gameboard.is_animating()
+                && app.gameboard_controller.message().is_none()
+                && !app.gameboard_controller.is_peeking()
+                && !app.playback.as_ref().is_some_and(|playback| playback.playing)
+                && (!app.config.ghost_replay || app.gameboard_controller.ghost.is_none())
+                // Other players' moves and games found arrive on their own.
+                && app.session.is_none()
+                && app.screen != Screen::Lobby,
+        );
+        if let Some(args) = e.render_args() {
+            let menu_items = app.menu_items();
+            gl.draw(args.viewport(), |c, g| {
+                use graphics::clear;
+                clear(app.theme.background, g);
+                match app.screen {
+                    Screen::Menu => {
+                        app.views.menu.settings.layout(
+                            app.views.gameboard.settings.position,
+                            [app.views.gameboard.settings.size; 2],
+                            menu_items.len(),
+                        );
+                        app.views.menu.draw(
+                            &app.menu_controller,
+                            &menu_items,
+                            &mut app.glyphs,
+                            &c,
+                            g,
+                        );
+                    }
+                    Screen::Game => {
+                        app.views.gameboard.draw(
+                            &app.gameboard_controller,
+                            &app.config,
+                            &mut OpenGlRenderer::new(&c, g, &mut app.glyphs),
+                        );
+                        app.views.toolbar.settings.layout(
+                            app.views.gameboard.settings.position,
+                            app.views.gameboard.settings.size,
+                        );
+                        app.views.toolbar.draw(
+                            &app.toolbar_controller,
+                            app.gameboard_controller.notes_mode,
+                            &mut app.glyphs,
+                            &c,
+                            g,
+                        );
+                        app.views.keypad.settings.layout(
+                            app.views.gameboard.settings.position,
+                            app.views.gameboard.settings.size,
+                        );
+                        app.views.keypad.draw(
+                            &app.gameboard_controller.gameboard,
+                            app.gameboard_controller.active_digit,
+                            &mut app.glyphs,
+                            &c,
+                            g,
+                        );
+                    }
+                    Screen::Settings => {
+                        app.views.settings.settings.layout(
+                            app.views.gameboard.settings.position,
+                            [app.views.gameboard.settings.size; 2],
+                        );
+                        app.views.settings.draw(
+                            &app.settings_controller,
+                            &app.config,
+                            &mut app.glyphs,
+                            &c,
+                            g,
+                        );
+                    }
+                    Screen::GameOver if app.views.gameboard.is_celebrating() => {
+                        app.views.gameboard.draw(
+                            &app.gameboard_controller,
+                            &app.config,
+                            &mut OpenGlRenderer::new(&c, g, &mut app.glyphs),
+                        )
+                    }
+                    Screen::Review => app.views.gameboard.draw_review(
+                        &app.gameboard_controller,
+                        &mut OpenGlRenderer::new(&c, g, &mut app.glyphs),
+                    ),
+                    Screen::Editor => app.views.gameboard.draw_editor(
+                        &app.editor_controller,
+                        &mut OpenGlRenderer::new(&c, g, &mut app.glyphs),
+                    ),
+                    Screen::Replay => {
+                        if let Some(playback) = &app.playback {
+                            app.views.gameboard.draw(
+                                &playback.controller,
+                                &app.config,
+                                &mut OpenGlRenderer::new(&c, g, &mut app.glyphs),
+                            );
+                        }
+                    }
+                    Screen::Restore => {
+                        let details = app.restorable.as_ref().map_or_else(String::new, |saved| {
+                            format!(
+                                "{}, {} played",
+                                saved.difficulty.name(),
+                                format_duration(Duration::from_secs(saved.elapsed))
+                            )
+                        });
+                        app.views.prompt.draw(
+                            &["Restore the game left", "unfinished last time?", &details],
+                            app.views.gameboard.settings.position,
+                            app.views.gameboard.settings.size,
+                            &mut app.glyphs,
+                            &c,
+                            g,
+                        );
+                    }
+                    Screen::Slots => {
+                        app.views.slots.settings.layout(
+                            app.views.gameboard.settings.position,
+                            [app.views.gameboard.settings.size; 2],
+                        );
+                        app.views.slots.draw(
+                            &app.slots_controller,
+                            &app.slots,
+                            &mut app.glyphs,
+                            &c,
+                            g,
+                        );
+                    }
+                    Screen::Open => {
+                        app.views.files.settings.position = app.views.gameboard.settings.position;
+                        app.views.files.settings.size = [app.views.gameboard.settings.size; 2];
+                        let names: Vec<String> = app.puzzle_files.iter().map(file_name).collect();
+                        app.views.files.draw(
+                            &app.files_controller,
+                            "Open puzzle (Ctrl+O to close)",
+                            &names,
+                            &mut app.glyphs,
+                            &c,
+                            g,
+                        );
+                    }
+                    Screen::Profiles => {
+                        app.views.files.settings.position = app.views.gameboard.settings.position;
+                        app.views.files.settings.size = [app.views.gameboard.settings.size; 2];
+                        app.views.files.draw(
+                            &app.profiles_controller.list,
+                            &format!("Who is playing? (now {})", profile.name),
+                            &app.profiles_controller.rows(&app.profiles),
+                            &mut app.glyphs,
+                            &c,
+                            g,
+                        );
+                    }
+                    Screen::Library => {
+                        app.views.files.settings.position = app.views.gameboard.settings.position;
+                        app.views.files.settings.size = [app.views.gameboard.settings.size; 2];
+                        let summaries: Vec<String> =
+                            app.packs.iter().map(Collection::summary).collect();
+                        app.views.files.draw(
+                            &app.library_controller,
+                            "Puzzle packs (Ctrl+L to close)",
+                            &summaries,
+                            &mut app.glyphs,
+                            &c,
+                            g,
+                        );
+                    }
+                    Screen::Collection => {
+                        if let Some(collection) = &app.collection {
+                            app.views.collection.draw(
+                                collection,
+                                app.views.gameboard.settings.position,
+                                app.views.gameboard.settings.size,
+                                &mut app.glyphs,
+                                &c,
+                                g,
+                            );
+                        }
+                    }
+                    Screen::Achievements => {
+                        app.views.achievements.draw(
+                            &app.gallery,
+                            app.views.gameboard.settings.position,
+                            app.views.gameboard.settings.size,
+                            &mut app.glyphs,
+                            &c,
+                            g,
+                        );
+                    }
+                    Screen::Lobby => {
+                        let games = app
+                            .browser
+                            .as_ref()
+                            .map_or(&[][..], |browser| &browser.games[..]);
+                        app.views.lobby.draw(
+                            &app.lobby_controller,
+                            app.session.as_ref(),
+                            games,
+                            app.gameboard_controller.message(),
+                            app.views.gameboard.settings.position,
+                            app.views.gameboard.settings.size,
+                            &mut app.glyphs,
+                            &c,
+                            g,
+                        );
+                    }
+                    Screen::Daily => {
+                        app.views.daily.draw(
+                            &app.daily_controller,
+                            &app.calendar,
+                            app.views.gameboard.settings.position,
+                            app.views.gameboard.settings.size,
+                            &mut app.glyphs,
+                            &c,
+                            g,
+                        );
+                    }
+                    Screen::Stats => {
+                        if let Some(dashboard) = &app.dashboard {
+                            app.views.stats.draw(
+                                dashboard,
+                                app.views.gameboard.settings.position,
+                                app.views.gameboard.settings.size,
+                                &mut app.glyphs,
+                                &c,
+                                g,
+                            );
+                        }
+                    }
+                    Screen::Share => {
+                        if let Some(shared) = &app.shared {
+                            app.views.share.draw(
+                                shared,
+                                app.views.gameboard.settings.position,
+                                app.views.gameboard.settings.size,
+                                &mut app.glyphs,
+                                &c,
+                                g,
+                            );
+                        }
+                    }
+                    Screen::GameOver => {
+                        app.views.gameboard.draw(
+                            &app.gameboard_controller,
+                            &app.config,
+                            &mut OpenGlRenderer::new(&c, g, &mut app.glyphs),
+                        );
+                        app.views.win.draw(
+                            &app.gameboard_controller,
+                            app.views.gameboard.settings.position,
+                            app.views.gameboard.settings.size,
+                            &mut app.glyphs,
+                            &c,
+                            g,
+                        );
+                    }
+                }
+                if show_move_log && (app.screen == Screen::Game || app.screen == Screen::GameOver) {
+                    app.views.move_log.draw(
+                        &app.gameboard_controller.move_log,
+                        &mut app.glyphs,
+                        &c,
+                        g,
+                    );
+                }
+                if let (true, Some(shared)) = (chatting, &*app.session) {
+                    app.views.chat.draw(
+                        &chat_controller,
+                        shared.chat(),
+                        shared.is_muted(),
+                        &mut app.glyphs,
+                        &c,
+                        g,
+                    );
+                }
+                if debug_controller.visible {
+                    app.views.debug.draw(
+                        &debug_controller,
+                        &app.gameboard_controller.gameboard,
+                        &mut app.glyphs,
+                        &c,
+                        g,
+                    );
+                }
+            });
+            if screenshot_requested {
+                screenshot_requested = false;
+                match screenshot::capture(SCREENSHOTS_DIR, args.draw_size) {
+                    Ok(path) => {
+                        let name = path.file_name().unwrap_or_default().to_string_lossy();
+                        app.gameboard_controller.notify(&format!("Saved {}", name));
+                    }
+                    Err(err) => {
+                        warn!("Could not save screenshot: {}", err);
+                        app.gameboard_controller.notify("Could not save screenshot");
+                    }
+                }
+            }
+        }
+    }
+    if !app.config.fullscreen {
+        if let Some(position) = window.get_position() {
+            app.config.window_position = Some(position.into());
+        }
+    }
+    if let Err(err) = app.config.save(&app.config_path) {
+        warn!("Could not save config: {}", err);
+    }
+    // Keep an unfinished game for next time, unless the one from last time
+    // is still waiting to be restored.
+    if !app.restore_pending {
+        save::autosave(&app.gameboard_controller, &app.autosave_path);
+    }
+    app.switch_to
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "gui")]
+    use piston::input::Key;
+    use sudoku::grid::Grid;
+
+    fn controller(size: usize) -> GameboardController {
+        let gameboard = Gameboard::from_puzzle(Grid::new(size), Grid::new(size));
+        let mut controller = GameboardController::new(gameboard);
+        controller.selected_cell = Some([0, 0]);
+        controller
+    }
+
+    #[test]
+    fn counts_mistakes_until_solved() {
+        let solution = digits::parse(
+            "534678912672195348198342567859761423426853791713924856961537284287419635345286179",
+        )
+        .unwrap();
+        let mut cells = solution.clone();
+        cells[0][0] = 0;
+        let mut controller =
+            start_game(Gameboard::from_puzzle(cells, solution), &Config::default());
+        controller.dispatch(Action::Enter([0, 0], 4));
+        assert_eq!(controller.mistakes, 1);
+        assert!(!controller.is_over());
+        controller.dispatch(Action::Enter([0, 0], 5));
+        assert!(controller.gameboard.is_solved() && controller.is_over());
+        // A finished game takes no more moves.
+        controller.dispatch(Action::Command(GameAction::Undo));
+        assert!(controller.gameboard.is_solved());
+    }
+
+    #[test]
+    fn limits_mistakes() {
+        let mut controller = controller(4);
+        controller.gameboard.solution[0][0] = 1;
+        controller.mistake_limit = Some(2);
+        controller.dispatch(Action::Enter([0, 0], 2));
+        assert!(!controller.is_lost());
+        controller.dispatch(Action::Enter([0, 0], 3));
+        assert!(controller.is_lost() && controller.is_over());
+        controller.dispatch(Action::Enter([0, 0], 1));
+        assert_eq!(controller.gameboard.cells[0][0], 3);
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn letter_shortcuts_on_a_classic_board() {
+        let controller = controller(9);
+        let config = Config::default();
+        assert_eq!(
+            controller.key_actions(Key::C, ModifierKey::NO_MODIFIER, &config),
+            vec![Action::Command(GameAction::Check)]
+        );
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn letter_digits_take_alt_for_shortcuts() {
+        let config = Config::default();
+        let cases = [
+            (16, Key::C, 12, GameAction::Check),
+            (25, Key::C, 12, GameAction::Check),
+            (25, Key::H, 17, GameAction::Hint),
+            (25, Key::N, 23, GameAction::Notes),
+            (25, Key::P, 25, GameAction::Peek),
+        ];
+        for (size, key, val, action) in cases {
+            let controller = controller(size);
+            assert_eq!(
+                controller.key_actions(key, ModifierKey::NO_MODIFIER, &config),
+                vec![Action::Enter([0, 0], val)]
+            );
+            assert_eq!(
+                controller.key_actions(key, ModifierKey::ALT, &config),
+                vec![Action::Command(action)]
+            );
+        }
+    }
+
+    /// Keeps what a view draws that tests look at.
+    #[cfg(feature = "gui")]
+    #[derive(Default)]
+    struct Recorder {
+        chars: Vec<char>,
+        text: Vec<String>,
+        clips: Vec<Option<[f64; 4]>>,
+    }
+
+    #[cfg(feature = "gui")]
+    impl Renderer for Recorder {
+        fn rect(&mut self, _: [f64; 4], _: sudoku::render::Color) {}
+        fn border(&mut self, _: [f64; 4], _: sudoku::render::Color, _: f64) {}
+        fn line(&mut self, _: [f64; 4], _: sudoku::render::Color, _: f64) {}
+        fn ellipse(&mut self, _: [f64; 4], _: sudoku::render::Color) {}
+        fn text(&mut self, text: &str, _: u32, _: sudoku::render::Color, _: [f64; 2]) {
+            self.text.push(text.to_string());
+        }
+        fn text_width(&mut self, text: &str, size: u32) -> f64 {
+            (text.len() as u32 * size) as f64 / 2.0
+        }
+        fn centered(&mut self, ch: char, _: f64, _: sudoku::render::Color, _: [f64; 2]) {
+            self.chars.push(ch);
+        }
+        fn clip(&mut self, rect: Option<[f64; 4]>) {
+            self.clips.push(rect);
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn draws_the_board_clipped_to_its_area() {
+        let mut controller = controller(4);
+        controller.gameboard.cells[0][0] = 3;
+        controller.gameboard.givens[0][0] = true;
+        let mut view = GameboardView::new(GameboardViewSettings::new(&Theme::default()));
+        view.settings.layout([400.0, 500.0]);
+        let mut recorder = Recorder::default();
+        view.draw(&controller, &Config::default(), &mut recorder);
+        assert!(recorder.chars.contains(&'3'));
+        let [x, y] = view.settings.position;
+        assert_eq!(
+            recorder.clips,
+            vec![Some([x, y, view.settings.size, view.settings.size]), None]
+        );
+        assert!(!recorder.text.is_empty());
     }
 }
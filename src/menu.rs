@@ -0,0 +1,269 @@
+use crate::puzzle::SaveFormat;
+use crate::text::draw_text;
+use crate::theme::Theme;
+use crate::Difficulty;
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{Context, Graphics};
+use piston::input::GenericEvent;
+
+/// What a main menu entry does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuAction {
+    Resume,
+    /// Ends the current game and shows its summary.
+    GiveUp,
+    NewGame(Difficulty),
+    /// Starts a medium game on a board of a size other than the classic one.
+    NewSized(usize),
+    /// Plays a stored puzzle of the current difficulty not played before.
+    Unplayed,
+    /// Picks today's puzzle, the same for every player, at a difficulty.
+    Daily,
+    /// Plays the next unplayed puzzle of the configured puzzle feed.
+    Feed,
+    /// Hosts or joins a game with other players on the network.
+    Multiplayer,
+    /// Picks a puzzle file to play.
+    OpenPuzzle,
+    /// Browses the puzzle packs.
+    Library,
+    /// Sets a puzzle by hand in the editor.
+    Editor,
+    /// Writes the current puzzle to a file.
+    SavePuzzle(SaveFormat),
+    /// Writes the current puzzle and its solution to a PDF.
+    PrintPuzzle,
+    /// Shows the statistics of the games played.
+    Stats,
+    /// Shows the achievements unlocked and still to unlock.
+    Achievements,
+    /// Picks who is playing.
+    Profiles,
+    Settings,
+}
+
+impl MenuAction {
+    pub fn label(self) -> String {
+        match self {
+            MenuAction::Resume => "Resume".to_string(),
+            MenuAction::GiveUp => "Give up".to_string(),
+            MenuAction::NewGame(difficulty) => format!("New game: {}", difficulty.name()),
+            MenuAction::NewSized(size) => format!("Quick game: {}x{}", size, size),
+            MenuAction::Unplayed => "Unplayed puzzle".to_string(),
+            MenuAction::Daily => "Daily puzzle...".to_string(),
+            MenuAction::Multiplayer => "Multiplayer...".to_string(),
+            MenuAction::Feed => "Puzzle feed".to_string(),
+            MenuAction::OpenPuzzle => "Open puzzle...".to_string(),
+            MenuAction::Library => "Puzzle packs...".to_string(),
+            MenuAction::Editor => "Set a puzzle...".to_string(),
+            MenuAction::SavePuzzle(format) => format!("Save puzzle as .{}", format.extension()),
+            MenuAction::PrintPuzzle => "Print puzzle to PDF".to_string(),
+            MenuAction::Stats => "Statistics".to_string(),
+            MenuAction::Achievements => "Achievements".to_string(),
+            MenuAction::Profiles => "Switch profile...".to_string(),
+            MenuAction::Settings => "Settings".to_string(),
+        }
+    }
+}
+
+/// Main menu entries, in display order.
+pub fn items(can_resume: bool, has_feed: bool) -> Vec<MenuAction> {
+    let mut items = Vec::new();
+    if can_resume {
+        items.push(MenuAction::Resume);
+        items.push(MenuAction::GiveUp);
+    }
+    items.extend(Difficulty::ALL.iter().map(|&d| MenuAction::NewGame(d)));
+    items.push(MenuAction::NewGame(Difficulty::Kids));
+    items.push(MenuAction::NewSized(6));
+    items.push(MenuAction::NewSized(16));
+    items.push(MenuAction::NewSized(25));
+    items.push(MenuAction::Unplayed);
+    items.push(MenuAction::Daily);
+    if has_feed {
+        items.push(MenuAction::Feed);
+    }
+    items.push(MenuAction::Multiplayer);
+    items.push(MenuAction::OpenPuzzle);
+    items.push(MenuAction::Library);
+    items.push(MenuAction::Editor);
+    items.push(MenuAction::SavePuzzle(SaveFormat::Sdk));
+    items.push(MenuAction::SavePuzzle(SaveFormat::Csv));
+    items.push(MenuAction::SavePuzzle(SaveFormat::Json));
+    items.push(MenuAction::PrintPuzzle);
+    items.push(MenuAction::Stats);
+    items.push(MenuAction::Achievements);
+    items.push(MenuAction::Profiles);
+    items.push(MenuAction::Settings);
+    items
+}
+
+pub struct MenuController {
+    pub selected: usize,
+    cursor_pos: [f64; 2],
+}
+
+impl Default for MenuController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MenuController {
+    pub fn new() -> MenuController {
+        MenuController {
+            selected: 0,
+            cursor_pos: [0.0; 2],
+        }
+    }
+
+    /// Handles events, returning the chosen action.
+    pub fn event<E: GenericEvent>(
+        &mut self,
+        pos: [f64; 2],
+        size: [f64; 2],
+        row_height: f64,
+        items: &[MenuAction],
+        e: &E,
+    ) -> Option<MenuAction> {
+        use piston::input::{Button, Key, MouseButton};
+
+        self.selected = self.selected.min(items.len() - 1);
+        if let Some(pos) = e.mouse_cursor_args() {
+            self.cursor_pos = pos;
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
+            let x = self.cursor_pos[0] - pos[0];
+            // The first row holds the title.
+            let y = self.cursor_pos[1] - pos[1] - row_height;
+            if x >= 0.0 && x <= size[0] && y >= 0.0 && y <= size[1] - row_height {
+                let row = (y / row_height) as usize;
+                if row < items.len() {
+                    self.selected = row;
+                    return Some(items[row]);
+                }
+            }
+        }
+        if let Some(Button::Keyboard(key)) = e.press_args() {
+            match key {
+                Key::Up => self.selected = (self.selected + items.len() - 1) % items.len(),
+                Key::Down => self.selected = (self.selected + 1) % items.len(),
+                Key::Return | Key::Space => return Some(items[self.selected]),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+pub struct MenuViewSettings {
+    pub position: [f64; 2],
+    pub size: [f64; 2],
+    pub row_height: f64,
+    pub font_size: u32,
+    pub ui_scale: f64,
+    pub background_color: Color,
+    pub selected_background_color: Color,
+    pub text_color: Color,
+}
+
+impl MenuViewSettings {
+    pub fn new(theme: &Theme) -> MenuViewSettings {
+        MenuViewSettings {
+            position: [10.0; 2],
+            size: [400.0; 2],
+            row_height: 40.0,
+            font_size: 20,
+            ui_scale: 1.0,
+            background_color: theme.panel_background,
+            selected_background_color: theme.panel_selected_background,
+            text_color: theme.text,
+        }
+    }
+
+    /// Scales rows and text.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.ui_scale = scale;
+        self.row_height = 40.0 * scale;
+        self.font_size = (20.0 * scale).round() as u32;
+    }
+
+    /// Places the menu, shrinking rows so every entry fits below the title.
+    pub fn layout(&mut self, position: [f64; 2], size: [f64; 2], entries: usize) {
+        self.position = position;
+        self.size = size;
+        self.row_height = (40.0 * self.ui_scale).min(size[1] / (entries + 1) as f64);
+        self.font_size = (self.row_height / 2.0).round() as u32;
+    }
+
+    /// Switches to a theme's colors, keeping the layout.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.background_color = theme.panel_background;
+        self.selected_background_color = theme.panel_selected_background;
+        self.text_color = theme.text;
+    }
+}
+
+pub struct MenuView {
+    pub settings: MenuViewSettings,
+}
+
+impl MenuView {
+    pub fn new(settings: MenuViewSettings) -> MenuView {
+        MenuView { settings }
+    }
+
+    pub fn draw<G: Graphics, C>(
+        &self,
+        controller: &MenuController,
+        items: &[MenuAction],
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::Rectangle;
+
+        let settings = &self.settings;
+        let [x, y] = settings.position;
+        Rectangle::new(settings.background_color).draw(
+            [x, y, settings.size[0], settings.size[1]],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+
+        let baseline = (settings.row_height + settings.font_size as f64) / 2.0 - 2.0;
+        draw_text(
+            "Sudoku",
+            settings.font_size,
+            settings.text_color,
+            [x + 10.0, y + baseline],
+            glyphs,
+            c,
+            g,
+        );
+        for (row, item) in items.iter().enumerate() {
+            let row_y = y + (row + 1) as f64 * settings.row_height;
+            if row == controller.selected {
+                Rectangle::new(settings.selected_background_color).draw(
+                    [x, row_y, settings.size[0], settings.row_height],
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+            }
+            draw_text(
+                &item.label(),
+                settings.font_size,
+                settings.text_color,
+                [x + 10.0, row_y + baseline],
+                glyphs,
+                c,
+                g,
+            );
+        }
+    }
+}
@@ -0,0 +1,192 @@
+#[cfg(feature = "gui")]
+use crate::text::draw_text;
+#[cfg(feature = "gui")]
+use crate::theme::Theme;
+use crate::{puzzle, Gameboard};
+#[cfg(feature = "gui")]
+use graphics::character::CharacterCache;
+#[cfg(feature = "gui")]
+use graphics::types::Color;
+#[cfg(feature = "gui")]
+use graphics::{Context, Graphics};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Something the player did to the board, as kept in the move log.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogEntry {
+    Place {
+        cell: [usize; 2],
+        value: u8,
+    },
+    /// A value filled in by a hint.
+    Hint {
+        cell: [usize; 2],
+        value: u8,
+    },
+    Erase {
+        cell: [usize; 2],
+    },
+    /// A pencil mark added or removed.
+    Note {
+        cell: [usize; 2],
+        value: u8,
+        added: bool,
+    },
+    Undo,
+    Redo,
+    /// A value entered, or erased with 0, by another player of a co-op game,
+    /// numbered from 0 for the host.
+    Partner {
+        player: u8,
+        cell: [usize; 2],
+        value: u8,
+    },
+}
+
+/// Writes a cell as `r4c7`, counting rows and columns from 1.
+fn cell_name([i, j]: [usize; 2]) -> String {
+    format!("r{}c{}", j + 1, i + 1)
+}
+
+impl fmt::Display for LogEntry {
+    /// Writes the entry in the usual notation, like `r4c7=5` for placing a 5
+    /// or `r4c7 -5 (note)` for removing a pencil mark.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LogEntry::Place { cell, value } => write!(f, "{}={}", cell_name(cell), value),
+            LogEntry::Hint { cell, value } => write!(f, "{}={} (hint)", cell_name(cell), value),
+            LogEntry::Erase { cell } => write!(f, "{} erase", cell_name(cell)),
+            LogEntry::Note { cell, value, added } => write!(
+                f,
+                "{} {}{} (note)",
+                cell_name(cell),
+                if added { '+' } else { '-' },
+                value
+            ),
+            LogEntry::Undo => write!(f, "undo"),
+            LogEntry::Redo => write!(f, "redo"),
+            LogEntry::Partner {
+                player,
+                cell,
+                value: 0,
+            } => write!(f, "{} erase (player {})", cell_name(cell), player + 1),
+            LogEntry::Partner {
+                player,
+                cell,
+                value,
+            } => write!(f, "{}={} (player {})", cell_name(cell), value, player + 1),
+        }
+    }
+}
+
+/// Writes the move log to a text file in a directory, one numbered move per
+/// line after the puzzle, returning the path.
+pub fn export<P: AsRef<Path>>(
+    dir: P,
+    gameboard: &Gameboard,
+    log: &[LogEntry],
+) -> Result<PathBuf, String> {
+    let mut text = format!("# {}\n", puzzle::to_line(&gameboard.puzzle()));
+    for (k, entry) in log.iter().enumerate() {
+        text += &format!("{}. {}\n", k + 1, entry);
+    }
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    let path = dir.as_ref().join(format!("moves-{}.txt", gameboard.seed));
+    fs::write(&path, text).map_err(|err| err.to_string())?;
+    Ok(path)
+}
+
+#[cfg(feature = "gui")]
+pub struct MoveLogViewSettings {
+    pub font_size: u32,
+    /// Width of the panel along the window's right edge.
+    pub width: f64,
+    pub background_color: Color,
+    pub text_color: Color,
+}
+
+#[cfg(feature = "gui")]
+impl MoveLogViewSettings {
+    pub fn new(theme: &Theme) -> MoveLogViewSettings {
+        let mut background_color = theme.panel_background;
+        background_color[3] *= 0.9;
+        MoveLogViewSettings {
+            font_size: 14,
+            width: 160.0,
+            background_color,
+            text_color: theme.text,
+        }
+    }
+
+    /// Scales text and the panel.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.font_size = (14.0 * scale).round() as u32;
+        self.width = 160.0 * scale;
+    }
+
+    /// Switches to a theme's colors.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        *self = MoveLogViewSettings {
+            font_size: self.font_size,
+            width: self.width,
+            ..MoveLogViewSettings::new(theme)
+        };
+    }
+}
+
+#[cfg(feature = "gui")]
+pub struct MoveLogView {
+    pub settings: MoveLogViewSettings,
+}
+
+#[cfg(feature = "gui")]
+impl MoveLogView {
+    pub fn new(settings: MoveLogViewSettings) -> MoveLogView {
+        MoveLogView { settings }
+    }
+
+    /// Draws a panel down the window's right edge listing the latest moves
+    /// that fit, numbered from the first.
+    pub fn draw<G: Graphics, C>(&self, log: &[LogEntry], glyphs: &mut C, c: &Context, g: &mut G)
+    where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::Rectangle;
+
+        let settings = &self.settings;
+        let [width, height] = c.get_view_size();
+        let x = width - settings.width;
+        Rectangle::new(settings.background_color).draw(
+            [x, 0.0, settings.width, height],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+        let line_height = settings.font_size as f64 * 1.4;
+        draw_text(
+            "Moves (F9)",
+            settings.font_size,
+            settings.text_color,
+            [x + 8.0, line_height],
+            glyphs,
+            c,
+            g,
+        );
+        let rows = ((height / line_height) as usize).saturating_sub(2);
+        let first = log.len().saturating_sub(rows);
+        for (row, (k, entry)) in log.iter().enumerate().skip(first).enumerate() {
+            draw_text(
+                &format!("{}. {}", k + 1, entry),
+                settings.font_size,
+                settings.text_color,
+                [x + 8.0, (row + 2) as f64 * line_height + line_height / 2.0],
+                glyphs,
+                c,
+                g,
+            );
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::text;
+use graphics::character::CharacterCache;
+use graphics::{Context, Ellipse, Graphics, Line, Rectangle};
+use sudoku::render::{Color, Renderer};
+
+/// Draws boards with piston's graphics, on the window's OpenGL context or
+/// into an exported image.
+pub struct OpenGlRenderer<'a, G, C> {
+    c: Context,
+    /// The context as given, to go back to when clipping ends.
+    unclipped: Context,
+    g: &'a mut G,
+    glyphs: &'a mut C,
+}
+
+impl<'a, G, C> OpenGlRenderer<'a, G, C> {
+    pub fn new(c: &Context, g: &'a mut G, glyphs: &'a mut C) -> Self {
+        OpenGlRenderer {
+            c: *c,
+            unclipped: *c,
+            g,
+            glyphs,
+        }
+    }
+}
+
+impl<G: Graphics, C> Renderer for OpenGlRenderer<'_, G, C>
+where
+    C: CharacterCache<Texture = G::Texture>,
+{
+    fn rect(&mut self, rect: [f64; 4], color: Color) {
+        let c = &self.c;
+        Rectangle::new(color).draw(rect, &c.draw_state, c.transform, self.g);
+    }
+
+    fn border(&mut self, rect: [f64; 4], color: Color, radius: f64) {
+        let c = &self.c;
+        Rectangle::new_border(color, radius).draw(rect, &c.draw_state, c.transform, self.g);
+    }
+
+    fn line(&mut self, line: [f64; 4], color: Color, radius: f64) {
+        let c = &self.c;
+        Line::new(color, radius).draw(line, &c.draw_state, c.transform, self.g);
+    }
+
+    fn round_line(&mut self, line: [f64; 4], color: Color, radius: f64) {
+        let c = &self.c;
+        Line::new_round(color, radius).draw(line, &c.draw_state, c.transform, self.g);
+    }
+
+    fn ellipse(&mut self, rect: [f64; 4], color: Color) {
+        let c = &self.c;
+        Ellipse::new(color).draw(rect, &c.draw_state, c.transform, self.g);
+    }
+
+    fn text(&mut self, text: &str, size: u32, color: Color, pos: [f64; 2]) {
+        text::draw_text(text, size, color, pos, self.glyphs, &self.c, self.g);
+    }
+
+    fn text_width(&mut self, text: &str, size: u32) -> f64 {
+        text::text_width(text, size, self.glyphs, &self.c)
+    }
+
+    fn centered(&mut self, ch: char, size: f64, color: Color, center: [f64; 2]) {
+        text::draw_centered(ch, size, color, center, self.glyphs, &self.c, self.g);
+    }
+
+    fn clip(&mut self, rect: Option<[f64; 4]>) {
+        self.c = match rect {
+            // The scissor is in framebuffer pixels.
+            Some([x, y, width, height]) => {
+                let scale = text::dpi_scale(&self.unclipped);
+                let clip = [
+                    (x * scale).max(0.0) as u32,
+                    (y * scale).max(0.0) as u32,
+                    (width * scale).round() as u32,
+                    (height * scale).round() as u32,
+                ];
+                Context {
+                    draw_state: self.unclipped.draw_state.scissor(clip),
+                    ..self.unclipped
+                }
+            }
+            None => self.unclipped,
+        };
+    }
+}
@@ -0,0 +1,78 @@
+use crate::collection::Entry;
+use crate::puzzle;
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+
+fn attribute(attributes: &[OwnedAttribute], name: &str) -> Option<String> {
+    attributes
+        .iter()
+        .find(|attribute| attribute.name.local_name == name)
+        .map(|attribute| attribute.value.clone())
+}
+
+/// Reads the puzzles of an OpenSudoku `.opensudoku` export.
+///
+/// Older exports hold `game` elements with a document-wide `level`, while
+/// newer ones group them in named `folder` elements. Either becomes the
+/// group of the puzzles it holds.
+pub fn parse(text: &str) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    let mut group = None;
+    let mut in_level = false;
+    for event in EventReader::from_str(text) {
+        match event.map_err(|err| err.to_string())? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => match name.local_name.as_str() {
+                "folder" => group = attribute(&attributes, "name"),
+                "level" => in_level = true,
+                "game" => {
+                    if let Some(data) = attribute(&attributes, "data") {
+                        entries.push(Entry {
+                            cells: puzzle::parse(&data)?,
+                            group: group.clone(),
+                        });
+                    }
+                }
+                _ => {}
+            },
+            XmlEvent::Characters(level) if in_level => group = Some(level.trim().to_string()),
+            XmlEvent::EndElement { name } => match name.local_name.as_str() {
+                "folder" => group = None,
+                "level" => in_level = false,
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &str =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+
+    #[test]
+    fn files_puzzles_under_their_folder_or_level() {
+        let old = format!(
+            "<opensudoku><name>Old</name><level>Easy</level><game data=\"{}\"/></opensudoku>",
+            DATA
+        );
+        let entries = parse(&old).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].group.as_deref(), Some("Easy"));
+        assert_eq!(entries[0].cells[0][..], [5, 3, 0, 0, 7, 0, 0, 0, 0]);
+        let new = format!(
+            "<opensudoku version=\"2\"><folder name=\"Hard\"><game data=\"{0}\"/></folder>\
+             <game data=\"{0}\"/></opensudoku>",
+            DATA
+        );
+        let groups: Vec<_> = parse(&new).unwrap().into_iter().map(|e| e.group).collect();
+        assert_eq!(groups, [Some("Hard".to_string()), None]);
+        assert!(parse("<opensudoku><game data=\"123\"/></opensudoku>").is_err());
+        assert!(parse("<opensudoku>").is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use crate::grid::Grid;
+use crate::SIZE;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A4, in points.
+const PAGE_SIZE: [f64; 2] = [595.0, 842.0];
+const MARGIN: f64 = 50.0;
+/// Space between puzzles on a page.
+const GAP: f64 = 30.0;
+const LABEL_SIZE: f64 = 12.0;
+/// Columns and rows of solutions on a page, which are printed small.
+const SOLUTION_LAYOUT: [usize; 2] = [3, 4];
+
+/// Puzzles per page that can be printed.
+pub const PER_PAGE: [usize; 4] = [1, 2, 4, 6];
+
+/// A puzzle to print, with the solution and the line printed above it.
+pub struct PrintedPuzzle {
+    pub cells: Grid,
+    pub solution: Grid,
+    pub label: String,
+}
+
+/// Columns and rows for a number of puzzles per page.
+fn layout(per_page: usize) -> [usize; 2] {
+    match per_page {
+        1 => [1, 1],
+        2 => [1, 2],
+        4 => [2, 2],
+        _ => [2, 3],
+    }
+}
+
+/// Escapes text for a PDF string literal.
+fn escape(text: &str) -> String {
+    text.chars()
+        .filter(char::is_ascii)
+        .flat_map(|ch| match ch {
+            '(' | ')' | '\\' => vec!['\\', ch],
+            _ => vec![ch],
+        })
+        .collect()
+}
+
+/// Appends drawing commands for a grid with its label, inside the box whose
+/// top left corner is `top_left` in page coordinates, which run up from the
+/// bottom of the page.
+fn draw_grid(
+    content: &mut String,
+    grid: &Grid,
+    label: &str,
+    top_left: [f64; 2],
+    box_size: [f64; 2],
+) {
+    let label_size = LABEL_SIZE.min(box_size[1] / 12.0);
+    let side = box_size[0].min(box_size[1] - 2.0 * label_size);
+    let left = top_left[0] + (box_size[0] - side) / 2.0;
+    let top = top_left[1] - 2.0 * label_size;
+    let bottom = top - side;
+    let cell = side / SIZE as f64;
+
+    // Writing to a String cannot fail.
+    let _ = writeln!(
+        content,
+        "BT /F1 {} Tf {:.2} {:.2} Td ({}) Tj ET",
+        label_size,
+        left,
+        top + label_size / 2.0,
+        escape(label)
+    );
+    for k in 1..SIZE {
+        let width = if k % 3 == 0 { 1.5 } else { 0.5 };
+        let offset = k as f64 * cell;
+        let _ = writeln!(
+            content,
+            "{} w {:.2} {:.2} m {:.2} {:.2} l S {:.2} {:.2} m {:.2} {:.2} l S",
+            width,
+            left + offset,
+            bottom,
+            left + offset,
+            top,
+            left,
+            bottom + offset,
+            left + side,
+            bottom + offset
+        );
+    }
+    let _ = writeln!(
+        content,
+        "2 w {:.2} {:.2} {:.2} {:.2} re S",
+        left, bottom, side, side
+    );
+
+    // Helvetica digits are 0.556 em wide and about 0.7 em tall.
+    let digit_size = cell * 0.65;
+    for (j, row) in grid.iter().enumerate() {
+        for (i, &val) in row.iter().enumerate() {
+            if val == 0 {
+                continue;
+            }
+            let _ = writeln!(
+                content,
+                "BT /F1 {:.2} Tf {:.2} {:.2} Td ({}) Tj ET",
+                digit_size,
+                left + (i as f64 + 0.5) * cell - 0.278 * digit_size,
+                top - (j as f64 + 0.5) * cell - 0.35 * digit_size,
+                val
+            );
+        }
+    }
+}
+
+/// Lays out grids on pages of `columns` by `rows`, returning each page's
+/// content stream.
+fn pages<'a, I>(grids: I, [columns, rows]: [usize; 2]) -> Vec<String>
+where
+    I: Iterator<Item = (&'a Grid, String)>,
+{
+    let box_size = [
+        (PAGE_SIZE[0] - 2.0 * MARGIN - (columns - 1) as f64 * GAP) / columns as f64,
+        (PAGE_SIZE[1] - 2.0 * MARGIN - (rows - 1) as f64 * GAP) / rows as f64,
+    ];
+    let mut pages: Vec<String> = Vec::new();
+    for (k, (grid, label)) in grids.enumerate() {
+        let slot = k % (columns * rows);
+        if slot == 0 {
+            pages.push(String::new());
+        }
+        let top_left = [
+            MARGIN + (slot % columns) as f64 * (box_size[0] + GAP),
+            PAGE_SIZE[1] - MARGIN - (slot / columns) as f64 * (box_size[1] + GAP),
+        ];
+        if let Some(page) = pages.last_mut() {
+            draw_grid(page, grid, &label, top_left, box_size);
+        }
+    }
+    pages
+}
+
+/// Writes a PDF of puzzles, `per_page` to an A4 page, followed by their
+/// solutions if `solutions` is set.
+pub fn document(puzzles: &[PrintedPuzzle], per_page: usize, solutions: bool) -> Vec<u8> {
+    let mut contents = pages(
+        puzzles
+            .iter()
+            .map(|puzzle| (&puzzle.cells, puzzle.label.clone())),
+        layout(per_page),
+    );
+    if solutions {
+        contents.extend(pages(
+            puzzles
+                .iter()
+                .map(|puzzle| (&puzzle.solution, format!("Solution: {}", puzzle.label))),
+            SOLUTION_LAYOUT,
+        ));
+    }
+
+    // Objects 1 to 3 are the catalog, the page tree, and the font, followed
+    // by each page and its content stream.
+    let page_ids: Vec<usize> = (0..contents.len()).map(|k| 4 + 2 * k).collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids
+                .iter()
+                .map(|id| format!("{} 0 R", id))
+                .collect::<Vec<_>>()
+                .join(" "),
+            page_ids.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+    ];
+    for (content, id) in contents.iter().zip(&page_ids) {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            PAGE_SIZE[0],
+            PAGE_SIZE[1],
+            id + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut pdf = "%PDF-1.4\n".to_string();
+    let mut offsets = Vec::new();
+    for (k, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        let _ = write!(pdf, "{} 0 obj\n{}\nendobj\n", k + 1, object);
+    }
+    let xref = pdf.len();
+    let _ = write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(pdf, "{:010} 00000 n ", offset);
+    }
+    let _ = write!(
+        pdf,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    );
+    pdf.into_bytes()
+}
+
+/// Writes a PDF of puzzles to a file.
+pub fn save<P: AsRef<Path>>(
+    path: P,
+    puzzles: &[PrintedPuzzle],
+    per_page: usize,
+    solutions: bool,
+) -> io::Result<()> {
+    if let Some(dir) = path.as_ref().parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, document(puzzles, per_page, solutions))
+}
@@ -0,0 +1,286 @@
+use crate::config::Config;
+use crate::{format_duration, GameboardController};
+use serde::Serialize;
+use serde_json::json;
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Opcodes of the frames Discord's local connection trades.
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+const OP_CLOSE: u32 = 2;
+
+/// Largest frame read from Discord.
+const MAX_FRAME: usize = 1 << 16;
+
+/// How long to wait before looking for Discord again.
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Least time between activities sent, as Discord takes only five every
+/// twenty seconds.
+const SEND_INTERVAL: Duration = Duration::from_secs(4);
+
+/// What the player is doing, as shown on their Discord status.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Activity {
+    pub details: String,
+    pub state: String,
+    /// When the game started, in seconds since 1970, for Discord to count
+    /// the time elapsed itself.
+    #[serde(
+        rename = "timestamps",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "timestamps"
+    )]
+    pub start: Option<u64>,
+}
+
+/// Writes the start of an activity as Discord takes it.
+fn timestamps<S: serde::Serializer>(start: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+    json!({ "start": start }).serialize(serializer)
+}
+
+impl Activity {
+    /// Whether the activity shows the same as another, letting the start
+    /// drift by the second it is rounded to.
+    fn same(&self, other: &Activity) -> bool {
+        let drift = match (self.start, other.start) {
+            (Some(a), Some(b)) => a.max(b) - a.min(b),
+            (None, None) => 0,
+            _ => u64::MAX,
+        };
+        self.details == other.details && self.state == other.state && drift <= 2
+    }
+}
+
+/// The activity of a game: its puzzle, whether it is played with others,
+/// and how many cells are left, counting the time from its start.
+pub fn activity(controller: &GameboardController, multiplayer: Option<&str>) -> Activity {
+    let board = &controller.gameboard;
+    let difficulty = board.difficulty.name();
+    let article = if "AEIOU".contains(&difficulty[..1]) {
+        "an"
+    } else {
+        "a"
+    };
+    let puzzle = format!("{} {} puzzle", article, difficulty);
+    let details = match multiplayer {
+        Some(mode) if controller.spectating => format!("Watching {}", mode),
+        _ if board.is_solved() => format!("Solved {}", puzzle),
+        _ if controller.is_over() => format!("Played {}", puzzle),
+        Some(mode) => format!("Solving {} in {}", puzzle, mode),
+        None => format!("Solving {}", puzzle),
+    };
+    let left = board
+        .cells
+        .positions()
+        .filter(|&[i, j]| board.in_play([i, j]) && board.cells[j][i] == 0)
+        .count();
+    let state = match left {
+        _ if board.is_solved() => format!("Solved in {}", format_duration(controller.elapsed())),
+        1 => "1 cell left".to_string(),
+        left => format!("{} cells left", left),
+    };
+    let start = Some(controller)
+        .filter(|controller| !controller.is_over())
+        .and_then(|controller| {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+            Some(now.checked_sub(controller.elapsed())?.as_secs())
+        });
+    Activity {
+        details,
+        state,
+        start,
+    }
+}
+
+/// Writes a frame: its opcode and length, then its JSON.
+fn write_frame(writer: &mut dyn Write, op: u32, payload: &serde_json::Value) -> io::Result<()> {
+    let json = payload.to_string();
+    let mut frame = Vec::with_capacity(8 + json.len());
+    frame.extend_from_slice(&op.to_le_bytes());
+    frame.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    frame.extend_from_slice(json.as_bytes());
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+/// Reads a frame, returning its opcode and JSON.
+fn read_frame(reader: &mut dyn Read) -> io::Result<(u32, String)> {
+    let mut header = [0; 8];
+    reader.read_exact(&mut header)?;
+    let op = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+    let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    if len > MAX_FRAME {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "frame too large",
+        ));
+    }
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload)?;
+    Ok((op, String::from_utf8_lossy(&payload).into_owned()))
+}
+
+/// Where Discord listens, from the directories it may use for runtime
+/// files, including those of its Flatpak and Snap packages.
+#[cfg(unix)]
+fn socket_paths() -> Vec<std::path::PathBuf> {
+    use std::path::PathBuf;
+
+    let mut dirs: Vec<PathBuf> = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .map(PathBuf::from)
+        .collect();
+    dirs.push(PathBuf::from("/tmp"));
+    let mut paths = Vec::new();
+    for dir in &dirs {
+        for sub in &["", "app/com.discordapp.Discord", "snap.discord"] {
+            for n in 0..10 {
+                paths.push(dir.join(sub).join(format!("discord-ipc-{}", n)));
+            }
+        }
+    }
+    paths
+}
+
+/// Connects to the Discord app running, returning a stream to read and one
+/// to write.
+#[cfg(unix)]
+fn open() -> io::Result<(Box<dyn Read + Send>, Box<dyn Write + Send>)> {
+    use std::os::unix::net::UnixStream;
+
+    for path in socket_paths() {
+        if let Ok(stream) = UnixStream::connect(&path) {
+            return Ok((Box::new(stream.try_clone()?), Box::new(stream)));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "Discord is not running",
+    ))
+}
+
+#[cfg(not(unix))]
+fn open() -> io::Result<(Box<dyn Read + Send>, Box<dyn Write + Send>)> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "Discord status is only shown on Unix",
+    ))
+}
+
+/// The player's status on Discord, kept to the game while they allow it.
+///
+/// Discord is looked for every so often while it is not running, and
+/// clears the status itself once the connection closes.
+pub struct Presence {
+    writer: Option<Box<dyn Write + Send>>,
+    /// Set by the reading thread once Discord closes the connection.
+    closed: Arc<AtomicBool>,
+    /// The application connected as.
+    application_id: String,
+    sent: Option<Activity>,
+    sent_at: Option<Instant>,
+    next_try: Option<Instant>,
+    nonce: u64,
+}
+
+impl Default for Presence {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Presence {
+    pub fn new() -> Presence {
+        Presence {
+            writer: None,
+            closed: Arc::new(AtomicBool::new(false)),
+            application_id: String::new(),
+            sent: None,
+            sent_at: None,
+            next_try: None,
+            nonce: 0,
+        }
+    }
+
+    /// Connects as an application and says hello, reading what Discord
+    /// says back on a thread of its own.
+    fn connect(&mut self, application_id: &str) -> io::Result<()> {
+        let (mut reader, mut writer) = open()?;
+        let hello = json!({ "v": 1, "client_id": application_id });
+        write_frame(&mut writer, OP_HANDSHAKE, &hello)?;
+        let closed = Arc::new(AtomicBool::new(false));
+        let reading = Arc::clone(&closed);
+        thread::spawn(move || {
+            while let Ok((op, payload)) = read_frame(&mut reader) {
+                if op == OP_CLOSE {
+                    warn!("Discord refused the status: {}", payload);
+                    break;
+                }
+            }
+            reading.store(true, Ordering::Relaxed);
+        });
+        self.writer = Some(writer);
+        self.closed = closed;
+        self.application_id = application_id.to_string();
+        self.sent = None;
+        Ok(())
+    }
+
+    fn disconnect(&mut self) {
+        self.writer = None;
+        self.sent = None;
+    }
+
+    /// Shows an activity as the player's status, if they allow it and
+    /// Discord is running, and clears it once they no longer allow it.
+    pub fn update(&mut self, config: &Config, activity: &Activity) {
+        let application_id = match &config.discord_application_id {
+            Some(id) if config.discord_presence => id.as_str(),
+            _ => return self.disconnect(),
+        };
+        if self.closed.load(Ordering::Relaxed) || self.application_id != application_id {
+            self.disconnect();
+        }
+        let now = Instant::now();
+        if self.writer.is_none() {
+            if self.next_try.is_some_and(|next| now < next) {
+                return;
+            }
+            self.next_try = Some(now + RETRY_INTERVAL);
+            if self.connect(application_id).is_err() {
+                return;
+            }
+        }
+        let unchanged = self.sent.as_ref().is_some_and(|sent| sent.same(activity));
+        let recent = self
+            .sent_at
+            .is_some_and(|at| now.duration_since(at) < SEND_INTERVAL);
+        if unchanged || recent {
+            return;
+        }
+        self.nonce += 1;
+        let command = json!({
+            "cmd": "SET_ACTIVITY",
+            "args": { "pid": std::process::id(), "activity": activity },
+            "nonce": self.nonce.to_string(),
+        });
+        let written = match &mut self.writer {
+            Some(writer) => write_frame(writer, OP_FRAME, &command),
+            None => return,
+        };
+        match written {
+            Ok(()) => {
+                self.sent = Some(activity.clone());
+                self.sent_at = Some(now);
+            }
+            Err(_) => self.disconnect(),
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::config::{self, CONFIG_FILE};
+#[cfg(feature = "gui")]
+use crate::files::FilesController;
+#[cfg(feature = "gui")]
+use crate::save::is_name_char;
+#[cfg(feature = "gui")]
+use piston::input::GenericEvent;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where named profiles keep their files, one directory each.
+pub const PROFILES_DIR: &str = "profiles";
+/// Records the profile played last, inside the profiles directory.
+const LAST_PROFILE_FILE: &str = "last";
+/// Name of the profile whose files sit where they did before profiles.
+pub const DEFAULT_PROFILE: &str = "Default";
+
+/// Longest profile name that can be typed.
+const MAX_NAME_LEN: usize = 24;
+
+/// A player, with their own settings, saves, statistics, and achievements.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Profile {
+        Profile {
+            name: name.to_string(),
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        self.name == DEFAULT_PROFILE
+    }
+
+    /// Where a file of this profile is kept.
+    pub fn path<P: AsRef<Path>>(&self, file: P) -> PathBuf {
+        if self.is_default() {
+            file.as_ref().to_path_buf()
+        } else {
+            Path::new(PROFILES_DIR).join(&self.name).join(file)
+        }
+    }
+
+    /// Where this profile's settings are kept.
+    pub fn config_path(&self) -> PathBuf {
+        if self.is_default() {
+            config::config_path()
+        } else {
+            self.path(CONFIG_FILE)
+        }
+    }
+
+    /// Makes the directory the profile's files go in.
+    pub fn create(&self) -> io::Result<()> {
+        fs::create_dir_all(self.path(""))
+    }
+
+    /// Every profile, the default first and the rest by name.
+    pub fn list() -> Vec<Profile> {
+        let mut names: Vec<String> = fs::read_dir(PROFILES_DIR)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.path().is_dir())
+                    .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                    .filter(|name| name != DEFAULT_PROFILE)
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        std::iter::once(DEFAULT_PROFILE.to_string())
+            .chain(names)
+            .map(|name| Profile { name })
+            .collect()
+    }
+
+    /// The profile played last, or the default one.
+    pub fn last() -> Profile {
+        fs::read_to_string(Path::new(PROFILES_DIR).join(LAST_PROFILE_FILE))
+            .ok()
+            .map(|name| Profile::new(name.trim()))
+            .filter(|profile| Profile::list().contains(profile))
+            .unwrap_or_else(|| Profile::new(DEFAULT_PROFILE))
+    }
+
+    /// Records this as the profile played last.
+    pub fn remember(&self) -> io::Result<()> {
+        fs::create_dir_all(PROFILES_DIR)?;
+        fs::write(Path::new(PROFILES_DIR).join(LAST_PROFILE_FILE), &self.name)
+    }
+}
+
+/// What the profile picker asks for.
+#[cfg(feature = "gui")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProfileAction {
+    /// Play as the profile at an index.
+    Choose(usize),
+    /// Make a profile with a name.
+    Create(String),
+}
+
+/// Picks a profile from a list whose first row makes a new one.
+#[cfg(feature = "gui")]
+#[derive(Default)]
+pub struct ProfilesController {
+    pub list: FilesController,
+    /// Name being typed for a new profile.
+    pub editing: Option<String>,
+}
+
+#[cfg(feature = "gui")]
+impl ProfilesController {
+    pub fn new() -> ProfilesController {
+        ProfilesController::default()
+    }
+
+    /// Rows to show: the new profile row, then each profile's name.
+    pub fn rows(&self, profiles: &[Profile]) -> Vec<String> {
+        let first = match &self.editing {
+            Some(name) => format!("Name: {}_", name),
+            None => "New profile...".to_string(),
+        };
+        std::iter::once(first)
+            .chain(profiles.iter().map(|profile| profile.name.clone()))
+            .collect()
+    }
+
+    /// Handles events, returning the chosen action.
+    ///
+    /// Return on the first row starts naming a new profile, and Return again
+    /// creates it.
+    pub fn event<E: GenericEvent>(
+        &mut self,
+        pos: [f64; 2],
+        size: [f64; 2],
+        row_height: f64,
+        profiles: &[Profile],
+        e: &E,
+    ) -> Option<ProfileAction> {
+        use piston::input::{Button, Key};
+
+        if let Some(name) = &mut self.editing {
+            if let Some(text) = e.text_args() {
+                name.extend(text.chars().filter(|&ch| is_name_char(ch)));
+                name.truncate(MAX_NAME_LEN);
+            }
+            match e.press_args() {
+                Some(Button::Keyboard(Key::Backspace)) => {
+                    name.pop();
+                }
+                Some(Button::Keyboard(Key::Return)) => {
+                    let name = self.editing.take().unwrap_or_default();
+                    let name = name.trim();
+                    if !name.is_empty() {
+                        return Some(ProfileAction::Create(name.to_string()));
+                    }
+                }
+                _ => {}
+            }
+            return None;
+        }
+
+        match self
+            .list
+            .event(pos, size, row_height, profiles.len() + 1, e)?
+        {
+            0 => {
+                self.editing = Some(String::new());
+                None
+            }
+            row => Some(ProfileAction::Choose(row - 1)),
+        }
+    }
+}
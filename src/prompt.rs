@@ -0,0 +1,165 @@
+use crate::text::{draw_text, text_width};
+use crate::theme::Theme;
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{Context, Graphics};
+use piston::input::GenericEvent;
+
+const BUTTONS: [(bool, &str); 2] = [(true, "Yes"), (false, "No")];
+
+/// Button rectangles for a prompt covering the given area.
+fn button_rects(pos: [f64; 2], size: f64) -> [[f64; 4]; 2] {
+    let width = size * 0.3;
+    let height = size * 0.1;
+    let y = pos[1] + size * 0.55;
+    [
+        [pos[0] + size * 0.15, y, width, height],
+        [pos[0] + size * 0.55, y, width, height],
+    ]
+}
+
+fn inside(rect: [f64; 4], pos: [f64; 2]) -> bool {
+    pos[0] >= rect[0]
+        && pos[0] <= rect[0] + rect[2]
+        && pos[1] >= rect[1]
+        && pos[1] <= rect[1] + rect[3]
+}
+
+/// Asks a yes or no question.
+pub struct PromptController {
+    cursor_pos: [f64; 2],
+}
+
+impl Default for PromptController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PromptController {
+    pub fn new() -> PromptController {
+        PromptController {
+            cursor_pos: [0.0; 2],
+        }
+    }
+
+    /// Handles events, returning the answer once given.
+    pub fn event<E: GenericEvent>(&mut self, pos: [f64; 2], size: f64, e: &E) -> Option<bool> {
+        use piston::input::{Button, Key, MouseButton};
+
+        if let Some(pos) = e.mouse_cursor_args() {
+            self.cursor_pos = pos;
+        }
+        match e.press_args() {
+            Some(Button::Mouse(MouseButton::Left)) => button_rects(pos, size)
+                .iter()
+                .zip(BUTTONS.iter())
+                .find(|(rect, _)| inside(**rect, self.cursor_pos))
+                .map(|(_, &(answer, _))| answer),
+            Some(Button::Keyboard(Key::Y)) | Some(Button::Keyboard(Key::Return)) => Some(true),
+            Some(Button::Keyboard(Key::N)) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+pub struct PromptViewSettings {
+    pub font_size: u32,
+    pub background_color: Color,
+    pub button_color: Color,
+    pub text_color: Color,
+}
+
+impl PromptViewSettings {
+    pub fn new(theme: &Theme) -> PromptViewSettings {
+        PromptViewSettings {
+            font_size: 20,
+            background_color: theme.panel_background,
+            button_color: theme.panel_selected_background,
+            text_color: theme.text,
+        }
+    }
+
+    /// Scales text.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.font_size = (20.0 * scale).round() as u32;
+    }
+
+    /// Switches to a theme's colors.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        *self = PromptViewSettings {
+            font_size: self.font_size,
+            ..PromptViewSettings::new(theme)
+        };
+    }
+}
+
+pub struct PromptView {
+    pub settings: PromptViewSettings,
+}
+
+impl PromptView {
+    pub fn new(settings: PromptViewSettings) -> PromptView {
+        PromptView { settings }
+    }
+
+    /// Draws the question, one line per entry, with Yes and No buttons over
+    /// the area at `pos` with side length `size`.
+    pub fn draw<G: Graphics, C>(
+        &self,
+        lines: &[&str],
+        pos: [f64; 2],
+        size: f64,
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::Rectangle;
+
+        let settings = &self.settings;
+        Rectangle::new(settings.background_color).draw(
+            [pos[0], pos[1], size, size],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+        for (row, line) in lines.iter().enumerate() {
+            draw_text(
+                line,
+                settings.font_size,
+                settings.text_color,
+                [
+                    pos[0] + size * 0.1,
+                    pos[1] + size * 0.2 + row as f64 * size * 0.1,
+                ],
+                glyphs,
+                c,
+                g,
+            );
+        }
+
+        for (rect, &(_, label)) in button_rects(pos, size).iter().zip(BUTTONS.iter()) {
+            Rectangle::new(settings.button_color)
+                .border(graphics::rectangle::Border {
+                    color: settings.text_color,
+                    radius: 1.0,
+                })
+                .draw(*rect, &c.draw_state, c.transform, g);
+            let width = text_width(label, settings.font_size, glyphs, c);
+            draw_text(
+                label,
+                settings.font_size,
+                settings.text_color,
+                [
+                    rect[0] + (rect[2] - width) / 2.0,
+                    rect[1] + (rect[3] + settings.font_size as f64) / 2.0 - 2.0,
+                ],
+                glyphs,
+                c,
+                g,
+            );
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use crate::csv;
+pub use crate::digits::{parse, to_line};
+use crate::fpuzzles::{self, FPuzzle};
+use crate::grid::Grid;
+use crate::qr;
+use crate::rules::RuleSet;
+use crate::sdk::SdkPuzzle;
+use crate::solver;
+use crate::{Difficulty, Gameboard, SIZE};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where puzzle files are opened from and saved to.
+pub const PUZZLES_DIR: &str = "puzzles";
+
+/// Extensions of the puzzle files that can be opened.
+const EXTENSIONS: [&str; 11] = [
+    "sdk",
+    "txt",
+    "sdm",
+    "opensudoku",
+    "json",
+    "csv",
+    "png",
+    "jpg",
+    "jpeg",
+    "gif",
+    "bmp",
+];
+
+/// Formats a puzzle can be saved in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveFormat {
+    Sdk,
+    Csv,
+    /// An f-puzzles file, which also keeps the variants and constraints.
+    Json,
+}
+
+impl SaveFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            SaveFormat::Sdk => "sdk",
+            SaveFormat::Csv => "csv",
+            SaveFormat::Json => "json",
+        }
+    }
+}
+
+/// A puzzle opened from a file, with its solution.
+pub struct OpenedPuzzle {
+    pub cells: Grid,
+    pub solution: Grid,
+    /// Description from the file, if it has one.
+    pub title: Option<String>,
+    pub rules: RuleSet,
+}
+
+/// Solves a puzzle, rejecting puzzles without exactly one solution.
+pub fn solve(grid: Grid) -> Result<(Grid, Grid), String> {
+    solve_variant(grid, &RuleSet::default())
+}
+
+/// Solves a puzzle under a set of rules, rejecting puzzles without exactly
+/// one solution.
+pub fn solve_variant(grid: Grid, rules: &RuleSet) -> Result<(Grid, Grid), String> {
+    match solver::unique_solution(&grid, rules) {
+        Some(solution) => Ok((grid, solution)),
+        None => Err("Puzzle does not have a unique solution".to_string()),
+    }
+}
+
+/// Reads a puzzle and solves it, rejecting puzzles without exactly one
+/// solution.
+pub fn parse_unique(text: &str) -> Result<(Grid, Grid), String> {
+    solve(parse(text)?)
+}
+
+/// Puzzle files in a directory that can be opened, sorted by name.
+pub fn list_files<P: AsRef<Path>>(dir: P) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|ext| EXTENSIONS.iter().any(|&known| ext == known))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// Whether a file holds a collection of puzzles rather than one.
+pub fn is_collection<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().extension().is_some_and(|ext| {
+        ext.eq_ignore_ascii_case("sdm") || ext.eq_ignore_ascii_case("opensudoku")
+    })
+}
+
+/// Reads a puzzle file without solving it, choosing the format by its
+/// extension. Its solution is left empty.
+pub fn read<P: AsRef<Path>>(path: P) -> Result<OpenedPuzzle, String> {
+    let path = path.as_ref();
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    let opened = |cells: Grid, title, rules| OpenedPuzzle {
+        solution: Grid::new(cells.size()),
+        cells,
+        title,
+        rules,
+    };
+    // Images are read as QR codes holding a puzzle line.
+    if let Some("png" | "jpg" | "jpeg" | "gif" | "bmp") = ext.as_deref() {
+        let cells = parse(&qr::read_image(path)?)?;
+        return Ok(opened(cells, None, RuleSet::default()));
+    }
+    let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    match ext.as_deref() {
+        Some("sdk") => {
+            let sdk = SdkPuzzle::parse(&text)?;
+            let title = sdk.header('D').map(str::to_string);
+            Ok(opened(sdk.cells, title, RuleSet::default()))
+        }
+        Some("json") => {
+            let fpuzzle = FPuzzle::parse(&text)?;
+            let title = match (fpuzzle.title, fpuzzle.author) {
+                (Some(title), Some(author)) => Some(format!("{} by {}", title, author)),
+                (title, _) => title,
+            };
+            Ok(opened(fpuzzle.cells, title, fpuzzle.rules))
+        }
+        Some("csv") => Ok(opened(csv::parse(&text)?, None, RuleSet::default())),
+        Some("txt") => Ok(opened(parse(&text)?, None, RuleSet::default())),
+        _ => Err(format!("Unknown puzzle format: {}", path.display())),
+    }
+}
+
+/// Opens a puzzle file, choosing the format by its extension, and solves
+/// it, rejecting puzzles without exactly one solution.
+pub fn open<P: AsRef<Path>>(path: P) -> Result<OpenedPuzzle, String> {
+    let mut opened = read(path)?;
+    let (_, solution) = solve_variant(opened.cells.clone(), &opened.rules)?;
+    opened.solution = solution;
+    Ok(opened)
+}
+
+/// Writes a puzzle under a set of rules in a format, rated at a difficulty
+/// where the format keeps one.
+///
+/// Neither sdk nor csv has a way to write variants or added constraints,
+/// so only classic puzzles can be written in them. Json keeps every rule
+/// f-puzzles can draw.
+pub fn write(
+    cells: &Grid,
+    rules: &RuleSet,
+    difficulty: Difficulty,
+    format: SaveFormat,
+) -> Result<String, String> {
+    let unsaved = if format == SaveFormat::Json {
+        fpuzzles::unwritable(rules).map(|variant| variant.kind().name())
+    } else {
+        rules.names().first().copied()
+    };
+    if let Some(name) = unsaved {
+        return Err(format!(
+            "{} puzzles cannot be saved as {}",
+            name,
+            format.extension()
+        ));
+    }
+    if cells.size() != SIZE {
+        return Err(format!("Only {}x{} puzzles can be saved", SIZE, SIZE));
+    }
+    Ok(match format {
+        SaveFormat::Sdk => {
+            let mut sdk = SdkPuzzle::new(cells.clone());
+            sdk.headers.push(('L', difficulty.name().to_string()));
+            sdk.write()
+        }
+        SaveFormat::Csv => csv::write(cells),
+        SaveFormat::Json => {
+            let mut fpuzzle = FPuzzle::new(cells.clone());
+            fpuzzle.rules = rules.clone();
+            fpuzzle.to_json()
+        }
+    })
+}
+
+/// Saves a board's puzzle to a file named after its seed.
+pub fn save<P: AsRef<Path>>(
+    dir: P,
+    gameboard: &Gameboard,
+    format: SaveFormat,
+) -> io::Result<PathBuf> {
+    let puzzle = gameboard.puzzle();
+    let text = write(&puzzle, &gameboard.rules, gameboard.difficulty, format)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    fs::create_dir_all(&dir)?;
+    let name = format!("sudoku-{}.{}", gameboard.seed, format.extension());
+    let path = dir.as_ref().join(name);
+    fs::write(&path, text)?;
+    Ok(path)
+}
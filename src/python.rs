@@ -0,0 +1,155 @@
+//! A Python module over the parser, solver, generator, and rater, built
+//! with `maturin build` as configured in `pyproject.toml`.
+//!
+//! Puzzles are given as lines of 81 digits with `0` or `.` for empty cells,
+//! as `sudoku solve` reads them, or as 9 rows of 9 ints. The engine lets go
+//! of the interpreter while it works, so corpora can be spread over threads.
+
+// PyO3's macros expand to a conversion of errors to themselves, and to a
+// check of a feature it no longer has.
+#![allow(clippy::useless_conversion, unexpected_cfgs)]
+
+use crate::digits::{self, to_line};
+use crate::grid::Grid;
+use crate::rules::RuleSet;
+use crate::technique::Registry;
+use crate::{solver, Difficulty, Gameboard, SIZE};
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+create_exception!(
+    sudoku,
+    SudokuError,
+    PyValueError,
+    "A puzzle that could not be read, or has no unique solution."
+);
+
+/// A puzzle as Python passes it.
+#[derive(FromPyObject)]
+enum Puzzle {
+    Line(String),
+    Rows(Vec<Vec<u8>>),
+}
+
+impl Puzzle {
+    fn cells(self) -> PyResult<Grid> {
+        let rows = match self {
+            Puzzle::Line(text) => return digits::parse(&text).map_err(SudokuError::new_err),
+            Puzzle::Rows(rows) => rows,
+        };
+        if rows.len() != SIZE || rows.iter().any(|row| row.len() != SIZE) {
+            return Err(SudokuError::new_err(format!(
+                "Expected {} rows of {} cells",
+                SIZE, SIZE
+            )));
+        }
+        let mut grid = Grid::new(SIZE);
+        for (j, row) in rows.iter().enumerate() {
+            for (i, &val) in row.iter().enumerate() {
+                if val as usize > SIZE {
+                    return Err(SudokuError::new_err(format!("Not a digit: {}", val)));
+                }
+                grid[j][i] = val;
+            }
+        }
+        Ok(grid)
+    }
+}
+
+/// The puzzle's solution, or why it has none.
+fn unique_solution(cells: &Grid) -> PyResult<Grid> {
+    let rules = RuleSet::default();
+    solver::unique_solution(cells, &rules).ok_or_else(|| {
+        match solver::count_solutions(cells, &rules, 2, usize::MAX) {
+            Some(0) => SudokuError::new_err("no solution"),
+            _ => SudokuError::new_err("multiple solutions"),
+        }
+    })
+}
+
+/// Reads a puzzle written as digits, in a line or a drawn grid, as a list
+/// of 9 rows of 9 ints, with 0 for empty cells.
+#[pyfunction]
+fn parse(text: &str) -> PyResult<Vec<Vec<u8>>> {
+    let cells = digits::parse(text).map_err(SudokuError::new_err)?;
+    Ok(cells.iter().map(|row| row.to_vec()).collect())
+}
+
+/// The solution of a puzzle with exactly one, as a line of 81 digits.
+#[pyfunction]
+fn solve(py: Python<'_>, puzzle: Puzzle) -> PyResult<String> {
+    let cells = puzzle.cells()?;
+    py.allow_threads(|| unique_solution(&cells).map(|solution| to_line(&solution)))
+}
+
+/// A new puzzle with a unique solution, as a line with `.` for empty cells,
+/// at a difficulty from easy to expert. The same seed always gives the same
+/// puzzle.
+#[pyfunction]
+#[pyo3(signature = (difficulty = "medium", seed = None))]
+fn generate(py: Python<'_>, difficulty: &str, seed: Option<u64>) -> PyResult<String> {
+    let difficulty = Difficulty::from_name(difficulty)
+        .ok_or_else(|| SudokuError::new_err(format!("Unknown difficulty: {}", difficulty)))?;
+    let seed = seed.unwrap_or_else(rand::random);
+    Ok(py.allow_threads(|| {
+        let mut gameboard = Gameboard::with_seed(seed);
+        gameboard.generate_rules(difficulty, RuleSet::default(), SIZE);
+        to_line(&gameboard.puzzle())
+    }))
+}
+
+/// The difficulty of a puzzle with exactly one solution, by the techniques
+/// that solve it, as `sudoku rate` gives it: easy, medium, hard, or expert.
+#[pyfunction]
+fn rate(py: Python<'_>, puzzle: Puzzle) -> PyResult<String> {
+    let cells = puzzle.cells()?;
+    py.allow_threads(|| {
+        unique_solution(&cells)?;
+        let rated = Registry::default().rate(&cells, &RuleSet::default());
+        Ok(rated.name().to_lowercase())
+    })
+}
+
+#[pymodule]
+fn sudoku(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("SudokuError", m.py().get_type_bound::<SudokuError>())?;
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(solve, m)?)?;
+    m.add_function(wrap_pyfunction!(generate, m)?)?;
+    m.add_function(wrap_pyfunction!(rate, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &str =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+
+    #[test]
+    fn solves_and_rates_lines_and_rows() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let rows = parse(PUZZLE).unwrap();
+            assert_eq!(rows[0], vec![5, 3, 0, 0, 7, 0, 0, 0, 0]);
+            let solved = solve(py, Puzzle::Line(PUZZLE.to_string())).unwrap();
+            assert_eq!(solve(py, Puzzle::Rows(rows.clone())).unwrap(), solved);
+            assert!(solved.starts_with("534678912"));
+            assert_eq!(rate(py, Puzzle::Rows(rows)).unwrap(), "easy");
+        });
+    }
+
+    #[test]
+    fn refuses_puzzles_without_one_solution() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let empty = Puzzle::Line(".".repeat(81));
+            let err = solve(py, empty).unwrap_err();
+            assert!(err.is_instance_of::<SudokuError>(py));
+            assert!(solve(py, Puzzle::Rows(vec![vec![0; 9]; 8])).is_err());
+            assert!(generate(py, "impossible", None).is_err());
+        });
+    }
+}
@@ -0,0 +1,949 @@
+use std::path::Path;
+
+/// Largest version handled. Later versions also carry version information,
+/// and the 81 cells of a puzzle fit in version 4.
+const MAX_VERSION: usize = 6;
+
+/// How much of a code can be damaged and still be read, from least to most.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EcLevel {
+    Low,
+    Medium,
+    Quartile,
+    High,
+}
+
+impl EcLevel {
+    const ALL: [EcLevel; 4] = [
+        EcLevel::Low,
+        EcLevel::Medium,
+        EcLevel::Quartile,
+        EcLevel::High,
+    ];
+
+    /// The level's two bits in the format information.
+    fn format_bits(self) -> u32 {
+        match self {
+            EcLevel::Low => 1,
+            EcLevel::Medium => 0,
+            EcLevel::Quartile => 3,
+            EcLevel::High => 2,
+        }
+    }
+}
+
+/// Error correction codewords per block and the data codewords of each
+/// block, by version and then level.
+const BLOCKS: [[(usize, &[usize]); 4]; MAX_VERSION] = [
+    [(7, &[19]), (10, &[16]), (13, &[13]), (17, &[9])],
+    [(10, &[34]), (16, &[28]), (22, &[22]), (28, &[16])],
+    [(15, &[55]), (26, &[44]), (18, &[17, 17]), (22, &[13, 13])],
+    [
+        (20, &[80]),
+        (18, &[32, 32]),
+        (26, &[24, 24]),
+        (16, &[9, 9, 9, 9]),
+    ],
+    [
+        (26, &[108]),
+        (24, &[43, 43]),
+        (18, &[15, 15, 16, 16]),
+        (22, &[11, 11, 12, 12]),
+    ],
+    [
+        (18, &[68, 68]),
+        (16, &[27, 27, 27, 27]),
+        (24, &[19, 19, 19, 19]),
+        (28, &[15, 15, 15, 15]),
+    ],
+];
+
+fn blocks(version: usize, level: EcLevel) -> (usize, &'static [usize]) {
+    BLOCKS[version - 1][level as usize]
+}
+
+const ALPHANUMERIC: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+const MODE_NUMERIC: u32 = 1;
+const MODE_ALPHANUMERIC: u32 = 2;
+const MODE_BYTE: u32 = 4;
+
+/// Multiplies in GF(256) modulo the QR polynomial x^8 + x^4 + x^3 + x^2 + 1.
+fn mul(x: u8, y: u8) -> u8 {
+    let mut z: u8 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x1d);
+        z ^= ((y >> i) & 1) * x;
+    }
+    z
+}
+
+fn pow(x: u8, n: usize) -> u8 {
+    (0..n).fold(1, |acc, _| mul(acc, x))
+}
+
+fn inverse(x: u8) -> u8 {
+    pow(x, 254)
+}
+
+/// Generator polynomial with roots 2^0 to 2^(degree - 1), highest power
+/// first and its leading 1 left out.
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0; degree];
+    result[degree - 1] = 1;
+    let mut root = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = mul(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = mul(root, 2);
+    }
+    result
+}
+
+/// Error correction codewords for a block of data.
+fn rs_remainder(data: &[u8], degree: usize) -> Vec<u8> {
+    let divisor = rs_divisor(degree);
+    let mut result = vec![0; degree];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (x, &y) in result.iter_mut().zip(&divisor) {
+            *x ^= mul(y, factor);
+        }
+    }
+    result
+}
+
+/// Corrects a block of data followed by `degree` error correction codewords
+/// in place, failing if it has more errors than can be fixed.
+fn rs_correct(block: &mut [u8], degree: usize) -> Result<(), String> {
+    let syndromes = |block: &[u8]| -> Vec<u8> {
+        (0..degree)
+            .map(|j| {
+                let x = pow(2, j);
+                block.iter().fold(0, |acc, &byte| mul(acc, x) ^ byte)
+            })
+            .collect()
+    };
+    let s = syndromes(block);
+    if s.iter().all(|&s| s == 0) {
+        return Ok(());
+    }
+
+    // Berlekamp-Massey finds the error locator, lowest power first.
+    let mut locator = vec![1u8];
+    let mut previous = vec![1u8];
+    let mut errors = 0;
+    let mut shift = 1;
+    let mut last_discrepancy = 1u8;
+    for n in 0..degree {
+        let discrepancy = (1..=errors).fold(s[n], |acc, i| {
+            acc ^ mul(*locator.get(i).unwrap_or(&0), s[n - i])
+        });
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+        let scale = mul(discrepancy, inverse(last_discrepancy));
+        let mut next = locator.clone();
+        next.resize(next.len().max(previous.len() + shift), 0);
+        for (i, &coef) in previous.iter().enumerate() {
+            next[i + shift] ^= mul(scale, coef);
+        }
+        if 2 * errors <= n {
+            previous = std::mem::replace(&mut locator, next);
+            errors = n + 1 - errors;
+            last_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            locator = next;
+            shift += 1;
+        }
+    }
+    if 2 * errors > degree {
+        return Err("Too many errors to correct".to_string());
+    }
+
+    let eval = |poly: &[u8], x: u8| poly.iter().rev().fold(0, |acc, &coef| mul(acc, x) ^ coef);
+    // Error evaluator, the product of the syndromes and the locator.
+    let mut evaluator = vec![0u8; degree];
+    for (i, &coef) in locator.iter().enumerate() {
+        for (j, &syndrome) in s.iter().enumerate() {
+            if i + j < degree {
+                evaluator[i + j] ^= mul(coef, syndrome);
+            }
+        }
+    }
+    // Formal derivative of the locator, which keeps the odd powers.
+    let derivative: Vec<u8> = locator
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, &coef)| if i % 2 == 1 { coef } else { 0 })
+        .collect();
+
+    // Chien search tries each position, counted from the end of the block.
+    let mut found = 0;
+    for position in 0..block.len() {
+        let x = pow(2, position);
+        let x_inv = inverse(x);
+        if eval(&locator, x_inv) != 0 {
+            continue;
+        }
+        let denominator = eval(&derivative, x_inv);
+        if denominator == 0 {
+            return Err("Too many errors to correct".to_string());
+        }
+        let magnitude = mul(x, mul(eval(&evaluator, x_inv), inverse(denominator)));
+        let k = block.len() - 1 - position;
+        block[k] ^= magnitude;
+        found += 1;
+    }
+    if found != errors || syndromes(block).iter().any(|&s| s != 0) {
+        return Err("Too many errors to correct".to_string());
+    }
+    Ok(())
+}
+
+/// Appends the low `len` bits of `value`, highest first.
+fn push_bits(bits: &mut Vec<bool>, value: u32, len: usize) {
+    bits.extend((0..len).rev().map(|i| (value >> i) & 1 == 1));
+}
+
+/// Encodes text as a single segment in the most compact mode that holds it.
+fn encode_segment(text: &str) -> Vec<bool> {
+    let mut bits = Vec::new();
+    if !text.is_empty() && text.chars().all(|ch| ch.is_ascii_digit()) {
+        push_bits(&mut bits, MODE_NUMERIC, 4);
+        push_bits(&mut bits, text.len() as u32, 10);
+        for group in text.as_bytes().chunks(3) {
+            let value = group.iter().fold(0, |acc, &b| acc * 10 + (b - b'0') as u32);
+            push_bits(&mut bits, value, group.len() * 3 + 1);
+        }
+    } else if text.chars().all(|ch| ALPHANUMERIC.contains(ch)) {
+        push_bits(&mut bits, MODE_ALPHANUMERIC, 4);
+        push_bits(&mut bits, text.len() as u32, 9);
+        let values: Vec<u32> = text
+            .chars()
+            .filter_map(|ch| ALPHANUMERIC.find(ch).map(|k| k as u32))
+            .collect();
+        for pair in values.chunks(2) {
+            match *pair {
+                [a, b] => push_bits(&mut bits, a * 45 + b, 11),
+                [a] => push_bits(&mut bits, a, 6),
+                _ => {}
+            }
+        }
+    } else {
+        push_bits(&mut bits, MODE_BYTE, 4);
+        push_bits(&mut bits, text.len() as u32, 8);
+        for &byte in text.as_bytes() {
+            push_bits(&mut bits, byte as u32, 8);
+        }
+    }
+    bits
+}
+
+/// Format information for a level and mask, with its error correction.
+fn format_bits(level: EcLevel, mask: usize) -> u32 {
+    let data = level.format_bits() << 3 | mask as u32;
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    (data << 10 | rem) ^ 0x5412
+}
+
+/// Where each of the 15 format bits goes, in each of the two copies, as
+/// `[x, y]` for a code of side `size`.
+fn format_positions(size: usize) -> [[[usize; 2]; 15]; 2] {
+    let mut first = [[0; 2]; 15];
+    let mut second = [[0; 2]; 15];
+    for i in 0..15 {
+        first[i] = match i {
+            0..=5 => [8, i],
+            6 => [8, 7],
+            7 => [8, 8],
+            8 => [7, 8],
+            _ => [14 - i, 8],
+        };
+        second[i] = if i < 8 {
+            [size - 1 - i, 8]
+        } else {
+            [8, size - 15 + i]
+        };
+    }
+    [first, second]
+}
+
+fn mask_applies(mask: usize, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (x / 3 + y / 2).is_multiple_of(2),
+        5 => x * y % 2 + x * y % 3 == 0,
+        6 => (x * y % 2 + x * y % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + x * y % 3).is_multiple_of(2),
+    }
+}
+
+/// A QR code, as a square of dark and light modules without the quiet
+/// zone around it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QrCode {
+    size: usize,
+    modules: Vec<Vec<bool>>,
+    /// Modules of the fixed patterns, which hold no data.
+    function: Vec<Vec<bool>>,
+}
+
+impl QrCode {
+    /// A code of a version with its fixed patterns drawn, and the format
+    /// area reserved.
+    fn blank(version: usize) -> QrCode {
+        let size = 17 + 4 * version;
+        let mut code = QrCode {
+            size,
+            modules: vec![vec![false; size]; size],
+            function: vec![vec![false; size]; size],
+        };
+        for i in 0..size {
+            code.set_function(6, i, i % 2 == 0);
+            code.set_function(i, 6, i % 2 == 0);
+        }
+        for &[cx, cy] in &[[3, 3], [size - 4, 3], [3, size - 4]] {
+            code.draw_square(cx, cy, 4, |dist| dist != 2 && dist != 4);
+        }
+        if version > 1 {
+            let center = 4 * version + 10;
+            code.draw_square(center, center, 2, |dist| dist != 1);
+        }
+        code.draw_format(0);
+        code
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y][x] = dark;
+        self.function[y][x] = true;
+    }
+
+    /// Draws a square pattern around a center, with each module dark by
+    /// its distance from the center.
+    fn draw_square<F: Fn(usize) -> bool>(&mut self, cx: usize, cy: usize, radius: usize, dark: F) {
+        let size = self.size as isize;
+        let radius = radius as isize;
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let (x, y) = (cx as isize + dx, cy as isize + dy);
+                if x >= 0 && x < size && y >= 0 && y < size {
+                    let dist = dx.abs().max(dy.abs()) as usize;
+                    self.set_function(x as usize, y as usize, dark(dist));
+                }
+            }
+        }
+    }
+
+    fn draw_format(&mut self, bits: u32) {
+        for positions in format_positions(self.size).iter() {
+            for (i, &[x, y]) in positions.iter().enumerate() {
+                self.set_function(x, y, (bits >> i) & 1 == 1);
+            }
+        }
+        let size = self.size;
+        self.set_function(8, size - 8, true);
+    }
+
+    /// Data module positions in the order codeword bits are placed: upwards
+    /// and downwards in two-column strips from the right, skipping the
+    /// vertical timing pattern.
+    fn data_positions(&self) -> Vec<[usize; 2]> {
+        let size = self.size;
+        let mut positions = Vec::new();
+        let mut right = size - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vert in 0..size {
+                let y = if upward { size - 1 - vert } else { vert };
+                for x in [right, right - 1] {
+                    if !self.function[y][x] {
+                        positions.push([x, y]);
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+        positions
+    }
+
+    fn apply_mask(&mut self, mask: usize) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if !self.function[y][x] && mask_applies(mask, x, y) {
+                    self.modules[y][x] = !self.modules[y][x];
+                }
+            }
+        }
+    }
+
+    /// How much a mask makes the code hard to read, by the standard's rules.
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let mut penalty = 0;
+        let lines: Vec<Vec<bool>> = (0..size)
+            .map(|y| self.modules[y].clone())
+            .chain((0..size).map(|x| (0..size).map(|y| self.modules[y][x]).collect()))
+            .collect();
+        for line in &lines {
+            // Long runs of one color.
+            let mut run = 1;
+            for k in 1..=size {
+                if k < size && line[k] == line[k - 1] {
+                    run += 1;
+                    continue;
+                }
+                if run >= 5 {
+                    penalty += run - 2;
+                }
+                run = 1;
+            }
+            // Patterns that look like finders.
+            let finder = [true, false, true, true, true, false, true];
+            for start in 0..size.saturating_sub(6) {
+                if line[start..start + 7] != finder {
+                    continue;
+                }
+                let light = |from: isize| {
+                    (from..from + 4).all(|k| k < 0 || k >= size as isize || !line[k as usize])
+                };
+                if light(start as isize - 4) || light(start as isize + 7) {
+                    penalty += 40;
+                }
+            }
+        }
+        // Blocks of one color.
+        for y in 1..size {
+            for x in 1..size {
+                let color = self.modules[y][x];
+                if self.modules[y - 1][x] == color
+                    && self.modules[y][x - 1] == color
+                    && self.modules[y - 1][x - 1] == color
+                {
+                    penalty += 3;
+                }
+            }
+        }
+        // An unbalanced share of dark modules.
+        let dark = self.modules.iter().flatten().filter(|&&dark| dark).count();
+        let percent = dark * 100 / (size * size);
+        penalty + 10 * (percent.max(50) - percent.min(50)) / 5
+    }
+
+    /// Encodes text at medium error correction in the smallest version that
+    /// holds it.
+    pub fn encode(text: &str) -> Result<QrCode, String> {
+        let level = EcLevel::Medium;
+        let bits = encode_segment(text).len();
+        let version = (1..=MAX_VERSION)
+            .find(|&version| bits <= blocks(version, level).1.iter().sum::<usize>() * 8)
+            .ok_or("Text is too long for a QR code")?;
+        QrCode::encode_at(text, version, level)
+    }
+
+    /// Encodes text in the given version and level.
+    fn encode_at(text: &str, version: usize, level: EcLevel) -> Result<QrCode, String> {
+        let mut bits = encode_segment(text);
+        let (ec, data_lens) = blocks(version, level);
+        let capacity = data_lens.iter().sum::<usize>() * 8;
+        if bits.len() > capacity {
+            return Err("Text is too long for a QR code".to_string());
+        }
+
+        // Terminator, padding to a whole byte, then alternating pad bytes.
+        let terminator = (capacity - bits.len()).min(4);
+        push_bits(&mut bits, 0, terminator);
+        let padding = (8 - bits.len() % 8) % 8;
+        push_bits(&mut bits, 0, padding);
+        for pad in [0xec, 0x11].iter().cycle() {
+            if bits.len() >= capacity {
+                break;
+            }
+            push_bits(&mut bits, *pad, 8);
+        }
+        let data: Vec<u8> = bits
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0, |acc, &bit| acc << 1 | bit as u8))
+            .collect();
+
+        // Split into blocks, then interleave their data and their error
+        // correction.
+        let mut blocks = Vec::new();
+        let mut start = 0;
+        for &len in data_lens {
+            let block = &data[start..start + len];
+            blocks.push((block.to_vec(), rs_remainder(block, ec)));
+            start += len;
+        }
+        let longest = data_lens.iter().max().copied().unwrap_or(0);
+        let mut codewords = Vec::new();
+        for i in 0..longest {
+            codewords.extend(blocks.iter().filter_map(|(data, _)| data.get(i)));
+        }
+        for i in 0..ec {
+            codewords.extend(blocks.iter().map(|(_, ec)| ec[i]));
+        }
+
+        let mut code = QrCode::blank(version);
+        let positions = code.data_positions();
+        for (k, &[x, y]) in positions.iter().enumerate() {
+            code.modules[y][x] = codewords
+                .get(k / 8)
+                .is_some_and(|byte| (byte >> (7 - k % 8)) & 1 == 1);
+        }
+        let best = (0..8)
+            .min_by_key(|&mask| {
+                let mut masked = code.clone();
+                masked.apply_mask(mask);
+                masked.draw_format(format_bits(level, mask));
+                masked.penalty()
+            })
+            .unwrap_or(0);
+        code.apply_mask(best);
+        code.draw_format(format_bits(level, best));
+        Ok(code)
+    }
+
+    /// Modules along a side.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y][x]
+    }
+}
+
+/// Reads `len` bits from `bits` starting at `*pos`, or None past the end.
+fn read_bits(bits: &[bool], pos: &mut usize, len: usize) -> Option<u32> {
+    let slice = bits.get(*pos..*pos + len)?;
+    *pos += len;
+    Some(slice.iter().fold(0, |acc, &bit| acc << 1 | bit as u32))
+}
+
+/// Reads the text of a QR code from its modules, `modules[y][x]` being dark.
+pub fn decode_modules(modules: &[Vec<bool>]) -> Result<String, String> {
+    let size = modules.len();
+    if size < 21 || !(size - 17).is_multiple_of(4) || (size - 17) / 4 > MAX_VERSION {
+        return Err(format!("Unsupported QR code size {}", size));
+    }
+    let version = (size - 17) / 4;
+
+    // Either copy of the format may be damaged, so take the valid format
+    // nearest to either.
+    let mut best = None;
+    for positions in format_positions(size).iter() {
+        let read = positions
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (i, &[x, y])| acc | (modules[y][x] as u32) << i);
+        for &level in &EcLevel::ALL {
+            for mask in 0..8 {
+                let distance = (read ^ format_bits(level, mask)).count_ones();
+                if best.is_none_or(|(best, _, _)| distance < best) {
+                    best = Some((distance, level, mask));
+                }
+            }
+        }
+    }
+    let (level, mask) = match best {
+        Some((distance, level, mask)) if distance <= 3 => (level, mask),
+        _ => return Err("Could not read the QR code's format".to_string()),
+    };
+
+    let code = QrCode::blank(version);
+    let mut codewords = Vec::new();
+    let mut byte = 0u8;
+    for (k, &[x, y]) in code.data_positions().iter().enumerate() {
+        byte = byte << 1 | (modules[y][x] ^ mask_applies(mask, x, y)) as u8;
+        if k % 8 == 7 {
+            codewords.push(byte);
+            byte = 0;
+        }
+    }
+
+    // Undo the interleaving, correct each block, and join their data.
+    let (ec, data_lens) = blocks(version, level);
+    let mut blocks: Vec<Vec<u8>> = data_lens
+        .iter()
+        .map(|&len| Vec::with_capacity(len + ec))
+        .collect();
+    let mut next = codewords.into_iter();
+    let longest = data_lens.iter().max().copied().unwrap_or(0);
+    for i in 0..longest + ec {
+        for (block, &len) in blocks.iter_mut().zip(data_lens) {
+            if i < len || i >= longest {
+                block.extend(next.next());
+            }
+        }
+    }
+    let mut data = Vec::new();
+    for (block, &len) in blocks.iter_mut().zip(data_lens) {
+        rs_correct(block, ec)?;
+        data.extend_from_slice(&block[..len]);
+    }
+
+    let bits: Vec<bool> = data
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1))
+        .collect();
+    let mut pos = 0;
+    let mut bytes = Vec::new();
+    while let Some(mode) = read_bits(&bits, &mut pos, 4) {
+        let truncated = || "QR code data is cut short".to_string();
+        match mode {
+            0 => break,
+            MODE_NUMERIC => {
+                let mut count = read_bits(&bits, &mut pos, 10).ok_or_else(truncated)? as usize;
+                while count > 0 {
+                    let digits = count.min(3);
+                    let value = read_bits(&bits, &mut pos, digits * 3 + 1).ok_or_else(truncated)?;
+                    bytes.extend(format!("{:0width$}", value, width = digits).bytes());
+                    count -= digits;
+                }
+            }
+            MODE_ALPHANUMERIC => {
+                let mut count = read_bits(&bits, &mut pos, 9).ok_or_else(truncated)? as usize;
+                let chars = ALPHANUMERIC.as_bytes();
+                while count > 0 {
+                    if count >= 2 {
+                        let value = read_bits(&bits, &mut pos, 11).ok_or_else(truncated)? as usize;
+                        bytes.push(*chars.get(value / 45).ok_or_else(truncated)?);
+                        bytes.push(chars[value % 45]);
+                        count -= 2;
+                    } else {
+                        let value = read_bits(&bits, &mut pos, 6).ok_or_else(truncated)? as usize;
+                        bytes.push(*chars.get(value).ok_or_else(truncated)?);
+                        count -= 1;
+                    }
+                }
+            }
+            MODE_BYTE => {
+                let count = read_bits(&bits, &mut pos, 8).ok_or_else(truncated)?;
+                for _ in 0..count {
+                    bytes.push(read_bits(&bits, &mut pos, 8).ok_or_else(truncated)? as u8);
+                }
+            }
+            _ => return Err(format!("Unsupported QR code mode {}", mode)),
+        }
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Dark and light pixels of an image.
+struct Bitmap {
+    width: usize,
+    height: usize,
+    dark: Vec<bool>,
+}
+
+impl Bitmap {
+    /// Whether a pixel is dark, treating everything outside as light.
+    fn get(&self, x: isize, y: isize) -> bool {
+        x >= 0
+            && y >= 0
+            && (x as usize) < self.width
+            && (y as usize) < self.height
+            && self.dark[y as usize * self.width + x as usize]
+    }
+
+    /// Pixels of one color in a row from `(x, y)` in the direction `step`.
+    fn run(&self, x: isize, y: isize, step: [isize; 2], dark: bool) -> usize {
+        let mut len = 0;
+        while len < self.width.max(self.height)
+            && self.get(x + step[0] * len as isize, y + step[1] * len as isize) == dark
+        {
+            len += 1;
+        }
+        len
+    }
+}
+
+/// Whether five runs have the 1:1:3:1:1 proportions across a finder
+/// pattern, giving the module size if so.
+fn finder_ratio(runs: [usize; 5]) -> Option<f64> {
+    let total: usize = runs.iter().sum();
+    let module = total as f64 / 7.0;
+    let expected = [1.0, 1.0, 3.0, 1.0, 1.0];
+    let fits = runs.iter().zip(expected.iter()).all(|(&run, &modules)| {
+        (run as f64 - modules * module).abs() < modules * module / 2.0 + 0.5
+    });
+    Some(module).filter(|_| total >= 7 && fits)
+}
+
+/// A possible finder pattern center, merged from every row that crosses it.
+struct Candidate {
+    center: [f64; 2],
+    module: f64,
+    count: usize,
+}
+
+/// Finds the centers and module sizes of finder patterns, most often seen
+/// first.
+fn find_finders(bitmap: &Bitmap) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = Vec::new();
+    for y in 0..bitmap.height {
+        // Runs of color along the row, as start and length.
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        let mut start = 0;
+        for x in 1..=bitmap.width {
+            if x == bitmap.width
+                || bitmap.get(x as isize, y as isize) != bitmap.get(start as isize, y as isize)
+            {
+                runs.push((start, x - start));
+                start = x;
+            }
+        }
+        for window in runs.windows(5) {
+            if !bitmap.get(window[0].0 as isize, y as isize) {
+                continue;
+            }
+            let lens = [
+                window[0].1,
+                window[1].1,
+                window[2].1,
+                window[3].1,
+                window[4].1,
+            ];
+            let module_x = match finder_ratio(lens) {
+                Some(module) => module,
+                None => continue,
+            };
+            let x = window[2].0 + window[2].1 / 2;
+            let (x, y) = (x as isize, y as isize);
+
+            // Confirm it vertically through the middle of the center run.
+            let up = bitmap.run(x, y, [0, -1], true);
+            let light_up = bitmap.run(x, y - up as isize, [0, -1], false);
+            let outer_up = bitmap.run(x, y - (up + light_up) as isize, [0, -1], true);
+            let down = bitmap.run(x, y + 1, [0, 1], true);
+            let light_down = bitmap.run(x, y + 1 + down as isize, [0, 1], false);
+            let outer_down = bitmap.run(x, y + 1 + (down + light_down) as isize, [0, 1], true);
+            let module_y =
+                match finder_ratio([outer_up, light_up, up + down, light_down, outer_down]) {
+                    Some(module) => module,
+                    None => continue,
+                };
+            let center = [
+                window[2].0 as f64 + window[2].1 as f64 / 2.0,
+                (y - up as isize + 1) as f64 + (up + down) as f64 / 2.0,
+            ];
+            let module = (module_x + module_y) / 2.0;
+
+            let near = candidates.iter_mut().find(|candidate| {
+                (candidate.center[0] - center[0]).abs() < 2.0 * module
+                    && (candidate.center[1] - center[1]).abs() < 2.0 * module
+            });
+            match near {
+                Some(candidate) => {
+                    let n = candidate.count as f64;
+                    for (merged, &seen) in candidate.center.iter_mut().zip(center.iter()) {
+                        *merged = (*merged * n + seen) / (n + 1.0);
+                    }
+                    candidate.module = (candidate.module * n + module) / (n + 1.0);
+                    candidate.count += 1;
+                }
+                None => candidates.push(Candidate {
+                    center,
+                    module,
+                    count: 1,
+                }),
+            }
+        }
+    }
+    candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.count));
+    candidates
+}
+
+/// The top left, top right, and bottom left finders, if three candidates
+/// could be the corners of one code, with how far they are from an exact
+/// fit.
+fn arrange(finders: [&Candidate; 3]) -> Option<(f64, [[f64; 2]; 3], f64)> {
+    let dist = |a: [f64; 2], b: [f64; 2]| (a[0] - b[0]).hypot(a[1] - b[1]);
+    let points = [finders[0].center, finders[1].center, finders[2].center];
+    let side = |k: usize| dist(points[(k + 1) % 3], points[(k + 2) % 3]);
+
+    // The top left finder is across from the longest side.
+    let corner = (0..3).max_by(|&a, &b| side(a).total_cmp(&side(b)))?;
+    let top_left = points[corner];
+    let (mut right, mut bottom) = (points[(corner + 1) % 3], points[(corner + 2) % 3]);
+    let cross = (right[0] - top_left[0]) * (bottom[1] - top_left[1])
+        - (right[1] - top_left[1]) * (bottom[0] - top_left[0]);
+    if cross < 0.0 {
+        std::mem::swap(&mut right, &mut bottom);
+    }
+
+    let modules = finders.iter().map(|finder| finder.module);
+    let smallest = modules.clone().fold(f64::MAX, f64::min);
+    let largest = modules.clone().fold(0.0, f64::max);
+    let module = modules.sum::<f64>() / 3.0;
+    let (across, down) = (dist(top_left, right), dist(top_left, bottom));
+    let mean = (across + down) / 2.0;
+    // Both sides about equal, meeting at a right angle, and at least as long
+    // as the smallest code.
+    let unequal = (across - down).abs() / mean;
+    let skew = (side(corner) / mean - 2f64.sqrt()).abs();
+    if largest > 1.5 * smallest || unequal > 0.2 || skew > 0.2 || mean < 14.0 * module {
+        return None;
+    }
+    Some((unequal + skew, [top_left, right, bottom], module))
+}
+
+/// Reads the modules of a code from the centers of its finders.
+fn sample(
+    bitmap: &Bitmap,
+    [top_left, right, bottom]: [[f64; 2]; 3],
+    module: f64,
+) -> Vec<Vec<bool>> {
+    let dist = |a: [f64; 2], b: [f64; 2]| (a[0] - b[0]).hypot(a[1] - b[1]);
+    let span = (dist(top_left, right) + dist(top_left, bottom)) / 2.0 / module;
+    let version = ((span + 7.0 - 17.0) / 4.0).round().max(1.0) as usize;
+    let size = 17 + 4 * version;
+    // Finder centers are 3.5 modules in from the edges, and so are module
+    // centers 3 modules from them.
+    let steps = (size - 7) as f64;
+    (0..size)
+        .map(|j| {
+            (0..size)
+                .map(|i| {
+                    let u = (i as f64 - 3.0) / steps;
+                    let v = (j as f64 - 3.0) / steps;
+                    let x =
+                        top_left[0] + u * (right[0] - top_left[0]) + v * (bottom[0] - top_left[0]);
+                    let y =
+                        top_left[1] + u * (right[1] - top_left[1]) + v * (bottom[1] - top_left[1]);
+                    bitmap.get(x.floor() as isize, y.floor() as isize)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Candidate finders considered when looking for a code's corners.
+const MAX_CANDIDATES: usize = 12;
+
+/// Reads the text of a QR code in an image file.
+///
+/// The code may be turned but should be seen straight on, as in a
+/// screenshot or a scan.
+pub fn read_image<P: AsRef<Path>>(path: P) -> Result<String, String> {
+    let image = image::open(path).map_err(|err| err.to_string())?.to_rgba();
+    let (width, height) = image.dimensions();
+    // Transparent pixels count as white.
+    let luma: Vec<f64> = image
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, a] = pixel.0;
+            let alpha = a as f64 / 255.0;
+            let gray = 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64;
+            gray * alpha + 255.0 * (1.0 - alpha)
+        })
+        .collect();
+    let darkest = luma.iter().copied().fold(255.0, f64::min);
+    let lightest = luma.iter().copied().fold(0.0, f64::max);
+    let threshold = (darkest + lightest) / 2.0;
+    let bitmap = Bitmap {
+        width: width as usize,
+        height: height as usize,
+        dark: luma.iter().map(|&gray| gray < threshold).collect(),
+    };
+
+    // Parts of the data can look like finders too, so try the likeliest
+    // corners until one reads.
+    let finders = find_finders(&bitmap);
+    let finders = &finders[..finders.len().min(MAX_CANDIDATES)];
+    let mut corners = Vec::new();
+    for a in 0..finders.len() {
+        for b in a + 1..finders.len() {
+            for c in b + 1..finders.len() {
+                corners.extend(arrange([&finders[a], &finders[b], &finders[c]]));
+            }
+        }
+    }
+    corners.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut error = "No QR code found".to_string();
+    for (_, points, module) in corners {
+        match decode_modules(&sample(&bitmap, points, module)) {
+            Ok(text) => return Ok(text),
+            Err(err) => error = err,
+        }
+    }
+    Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_every_version_and_level() {
+        for version in 1..=MAX_VERSION {
+            for &level in &EcLevel::ALL {
+                for text in &["8", "SUDOKU", "row 1"] {
+                    let code = QrCode::encode_at(text, version, level).unwrap();
+                    assert_eq!(code.size(), 17 + 4 * version);
+                    assert_eq!(decode_modules(&code.modules).as_deref(), Ok(*text));
+                }
+            }
+        }
+        let puzzle = "4".repeat(81);
+        let code = QrCode::encode(&puzzle).unwrap();
+        assert_eq!(decode_modules(&code.modules), Ok(puzzle));
+    }
+
+    #[test]
+    fn corrects_up_to_half_the_error_codewords() {
+        let data: Vec<u8> = (0..16).map(|i| i * 7 + 3).collect();
+        let ec = 10;
+        let mut block = data.clone();
+        block.extend(rs_remainder(&data, ec));
+        let clean = block.clone();
+
+        let mut damaged = clean.clone();
+        for k in 0..ec / 2 {
+            damaged[k * 5] ^= 0x5a;
+        }
+        assert_eq!(rs_correct(&mut damaged, ec), Ok(()));
+        assert_eq!(damaged, clean);
+
+        let mut damaged = clean.clone();
+        for k in 0..=ec / 2 {
+            damaged[k * 4] ^= 0x5a;
+        }
+        assert!(rs_correct(&mut damaged, ec).is_err());
+    }
+
+    #[test]
+    fn reads_damaged_format_bits() {
+        let code = QrCode::encode("SUDOKU").unwrap();
+        let mut modules = code.modules.clone();
+        let [first, second] = format_positions(code.size());
+        for &[x, y] in &first[..6] {
+            modules[y][x] = !modules[y][x];
+        }
+        for &[x, y] in &second[..2] {
+            modules[y][x] = !modules[y][x];
+        }
+        assert_eq!(decode_modules(&modules).as_deref(), Ok("SUDOKU"));
+    }
+}
@@ -0,0 +1,41 @@
+//! The shapes and text boards are drawn with, so a board can be drawn by
+//! any graphics library a front-end has.
+
+/// A color as red, green, blue, and alpha, each from 0 to 1.
+pub type Color = [f32; 4];
+
+/// Draws in window points, from the top left, as
+/// `[x, y, width, height]` rectangles and `[x1, y1, x2, y2]` lines.
+pub trait Renderer {
+    fn rect(&mut self, rect: [f64; 4], color: Color);
+
+    /// Outlines a rectangle with lines `radius` either side of its edges.
+    fn border(&mut self, rect: [f64; 4], color: Color, radius: f64);
+
+    /// Draws a line `radius` either side of its ends, with square ends.
+    fn line(&mut self, line: [f64; 4], color: Color, radius: f64);
+
+    /// Draws a line with round ends.
+    fn round_line(&mut self, line: [f64; 4], color: Color, radius: f64) {
+        self.line(line, color, radius);
+        for end in [[line[0], line[1]], [line[2], line[3]]] {
+            let rect = [end[0] - radius, end[1] - radius, 2.0 * radius, 2.0 * radius];
+            self.ellipse(rect, color);
+        }
+    }
+
+    /// Fills the ellipse inside a rectangle.
+    fn ellipse(&mut self, rect: [f64; 4], color: Color);
+
+    /// Draws text with its baseline starting at `pos`.
+    fn text(&mut self, text: &str, size: u32, color: Color, pos: [f64; 2]);
+
+    fn text_width(&mut self, text: &str, size: u32) -> f64;
+
+    /// Draws a character with its outline centered on `center`.
+    fn centered(&mut self, ch: char, size: f64, color: Color, center: [f64; 2]);
+
+    /// Leaves out what is drawn outside a rectangle, until clipping is set
+    /// to `None`.
+    fn clip(&mut self, rect: Option<[f64; 4]>);
+}
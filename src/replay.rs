@@ -0,0 +1,296 @@
+use crate::constraints::Constraints;
+use crate::grid::Grid;
+use crate::rules::RuleSet;
+use crate::variant::Variant;
+#[cfg(feature = "gui")]
+use crate::{Action, GameboardController};
+use crate::{Difficulty, Gameboard};
+#[cfg(feature = "gui")]
+use piston::input::GenericEvent;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Playback speeds, as multiples of the pace the game was played at.
+#[cfg(feature = "gui")]
+const SPEEDS: [f64; 6] = [0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+/// Index of real-time speed in `SPEEDS`.
+#[cfg(feature = "gui")]
+const NORMAL_SPEED: usize = 1;
+
+/// One change to a cell, as the player made it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Move {
+    /// Milliseconds into the game.
+    pub at: u64,
+    pub cell: [usize; 2],
+    pub value: u8,
+    pub notes: u32,
+    /// Mistakes made so far, counting this move.
+    pub mistakes: u32,
+}
+
+/// A board and every change made to it, with timing, to play back later.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub difficulty: Difficulty,
+    /// Missing from replays recorded before variants could be combined,
+    /// which kept them in the two fields below.
+    #[serde(default)]
+    pub rules: RuleSet,
+    /// The one variant of replays recorded before variants could be
+    /// combined, missing from those recorded before variants, which were
+    /// classic.
+    #[serde(default, skip_serializing)]
+    variant: Option<Variant>,
+    /// Constraints of replays recorded before variants could be combined.
+    #[serde(default, skip_serializing)]
+    constraints: Option<Constraints>,
+    pub givens: Grid<bool>,
+    pub solution: Grid,
+    /// The board when recording began, which has entries if the game was
+    /// resumed from a save made before replays were recorded.
+    pub cells: Grid,
+    pub notes: Grid<u32>,
+    /// Milliseconds played, and mistakes made, when recording began.
+    pub started: u64,
+    pub mistakes: u32,
+    pub moves: Vec<Move>,
+}
+
+impl Replay {
+    /// Starts recording from a board, after some time already played.
+    pub fn new(gameboard: &Gameboard, elapsed: Duration, mistakes: u32) -> Replay {
+        Replay {
+            difficulty: gameboard.difficulty,
+            rules: gameboard.rules.clone(),
+            variant: None,
+            constraints: None,
+            givens: gameboard.givens.clone(),
+            solution: gameboard.solution.clone(),
+            cells: gameboard.cells.clone(),
+            notes: gameboard.notes.clone(),
+            started: elapsed.as_millis() as u64,
+            mistakes,
+            moves: Vec::new(),
+        }
+    }
+
+    /// The rules the game was played under, however the replay was
+    /// recorded.
+    pub fn rules(&self) -> RuleSet {
+        match (&self.variant, self.constraints) {
+            (None, None) => self.rules.clone(),
+            (variant, constraints) => RuleSet::new(
+                variant.clone().unwrap_or_default(),
+                constraints.unwrap_or_default(),
+            ),
+        }
+    }
+
+    /// Records a cell changing to a value and notes.
+    pub fn record(
+        &mut self,
+        at: Duration,
+        cell: [usize; 2],
+        (value, notes): (u8, u32),
+        mistakes: u32,
+    ) {
+        self.moves.push(Move {
+            at: at.as_millis() as u64,
+            cell,
+            value,
+            notes,
+            mistakes,
+        });
+    }
+
+    /// The board as it was when recording began.
+    pub fn board(&self) -> Gameboard {
+        let mut gameboard = Gameboard::new();
+        gameboard.difficulty = self.difficulty;
+        gameboard.rules = self.rules();
+        gameboard.givens = self.givens.clone();
+        gameboard.solution = self.solution.clone();
+        gameboard.cells = self.cells.clone();
+        gameboard.notes = self.notes.clone();
+        gameboard.update_solved();
+        gameboard
+    }
+
+    /// The entries on the board at a time into the game.
+    pub fn cells_at(&self, time: Duration) -> Grid {
+        let at = time.as_millis() as u64;
+        let mut cells = self.cells.clone();
+        for next in self.moves.iter().take_while(|next| next.at <= at) {
+            cells[next.cell[1]][next.cell[0]] = next.value;
+        }
+        cells
+    }
+
+    /// Share of the non-given cells filled at a time into the game, from 0
+    /// to 1.
+    pub fn progress_at(&self, time: Duration) -> f64 {
+        let cells = self.cells_at(time);
+        let open = self
+            .givens
+            .iter()
+            .flatten()
+            .filter(|&&given| !given)
+            .count();
+        let filled = cells
+            .iter()
+            .flatten()
+            .zip(self.givens.iter().flatten())
+            .filter(|&(&val, &given)| !given && val != 0)
+            .count();
+        if open == 0 {
+            1.0
+        } else {
+            filled as f64 / open as f64
+        }
+    }
+
+    /// How long the recorded game took.
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.moves.last().map_or(self.started, |last| last.at))
+    }
+}
+
+/// Replays a game on a board of its own, move by move.
+#[cfg(feature = "gui")]
+pub struct Playback {
+    replay: Replay,
+    /// The board being played back, drawn like the game's.
+    pub controller: GameboardController,
+    /// Moves made on the board so far.
+    position: usize,
+    /// Milliseconds into the game being shown.
+    clock: f64,
+    pub playing: bool,
+    speed: usize,
+}
+
+#[cfg(feature = "gui")]
+impl Playback {
+    /// Starts playing a replay from the beginning.
+    pub fn new(replay: Replay) -> Playback {
+        let mut playback = Playback {
+            controller: GameboardController::new(replay.board()),
+            replay,
+            position: 0,
+            clock: 0.0,
+            playing: true,
+            speed: NORMAL_SPEED,
+        };
+        playback.seek(0);
+        playback
+    }
+
+    /// Sets the board up with the first `position` moves made.
+    fn seek(&mut self, position: usize) {
+        self.controller = GameboardController::new(self.replay.board());
+        self.controller.mistakes = self.replay.mistakes;
+        self.position = 0;
+        self.clock = self.replay.started as f64;
+        while self.position < position {
+            self.step();
+        }
+        self.show();
+    }
+
+    /// Makes the next move, bringing the clock up to when it was made.
+    fn step(&mut self) {
+        if let Some(&next) = self.replay.moves.get(self.position) {
+            self.controller.dispatch(Action::Replay {
+                cell: next.cell,
+                state: (next.value, next.notes),
+            });
+            self.controller.mistakes = next.mistakes;
+            self.clock = self.clock.max(next.at as f64);
+            self.position += 1;
+        }
+    }
+
+    /// Whether every move has been made.
+    pub fn is_finished(&self) -> bool {
+        self.position == self.replay.moves.len()
+    }
+
+    /// Runs the clock while playing, making the moves it reaches.
+    pub fn update(&mut self, dt: f64) {
+        if self.playing {
+            self.clock += dt * 1000.0 * SPEEDS[self.speed];
+            while self
+                .replay
+                .moves
+                .get(self.position)
+                .is_some_and(|next| next.at as f64 <= self.clock)
+            {
+                self.step();
+            }
+            if self.is_finished() {
+                self.playing = false;
+            }
+        }
+        self.show();
+    }
+
+    /// Puts the clock and the position in the moves on the status bar.
+    fn show(&mut self) {
+        let clock = Duration::from_millis(self.clock as u64).min(self.replay.duration());
+        self.controller.solve_time = Some(clock);
+        let control = if self.playing {
+            "Space to pause"
+        } else {
+            "Space to play"
+        };
+        self.controller.notify(&format!(
+            "Move {}/{} at {}x - {}",
+            self.position,
+            self.replay.moves.len(),
+            SPEEDS[self.speed],
+            control
+        ));
+    }
+
+    /// Handles events, returning whether playback was closed.
+    ///
+    /// Space plays and pauses, Left and Right step back and forward a move,
+    /// Up and Down change speed, Home goes back to the start, and Return or
+    /// Backspace closes.
+    pub fn event<E: GenericEvent>(&mut self, e: &E) -> bool {
+        use piston::input::{Button, Key};
+
+        match e.press_args() {
+            Some(Button::Keyboard(Key::Space)) => {
+                if self.is_finished() {
+                    self.seek(0);
+                    self.playing = true;
+                } else {
+                    self.playing = !self.playing;
+                }
+            }
+            Some(Button::Keyboard(Key::Right)) => {
+                self.playing = false;
+                self.step();
+            }
+            Some(Button::Keyboard(Key::Left)) => {
+                self.playing = false;
+                self.seek(self.position.saturating_sub(1));
+            }
+            Some(Button::Keyboard(Key::Up)) => {
+                self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+            }
+            Some(Button::Keyboard(Key::Down)) => {
+                self.speed = self.speed.saturating_sub(1);
+            }
+            Some(Button::Keyboard(Key::Home)) => self.seek(0),
+            Some(Button::Keyboard(Key::Return)) | Some(Button::Keyboard(Key::Backspace)) => {
+                return true;
+            }
+            _ => {}
+        }
+        self.show();
+        false
+    }
+}
@@ -0,0 +1,216 @@
+use crate::constraints::Constraints;
+use crate::grid::Grid;
+use crate::script::Script;
+use crate::variant::{digit_mask, Variant, VariantKind};
+use crate::House;
+use serde::{Deserialize, Serialize};
+
+/// Every rule a puzzle is played under: the variants it combines, such as a
+/// killer puzzle that is also an X-Sudoku, the constraints added on top,
+/// and any rules scripted by the player.
+/// Checking entries, solving, and drawing all go through it, so any
+/// combination is handled the same way as a single variant.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleSet {
+    /// Variants combined, at most one of each kind and never classic. A
+    /// classic puzzle has none.
+    pub variants: Vec<Variant>,
+    pub constraints: Constraints,
+    /// Scripts with rules of their own, from the scripts directory.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub scripts: Vec<Script>,
+}
+
+impl RuleSet {
+    /// The rules of a single variant with some constraints added.
+    pub fn new(variant: Variant, constraints: Constraints) -> RuleSet {
+        RuleSet::default()
+            .with(variant)
+            .with_constraints(constraints)
+    }
+
+    /// These rules with a variant added, replacing any of the same kind.
+    /// Adding a classic variant changes nothing. Variants are kept in the
+    /// order of their kinds, so the same rules always compare equal.
+    pub fn with(mut self, variant: Variant) -> RuleSet {
+        if variant != Variant::Classic {
+            self.variants.retain(|v| v.kind() != variant.kind());
+            self.variants.push(variant);
+            self.variants
+                .sort_by_key(|v| VariantKind::ALL.iter().position(|&kind| kind == v.kind()));
+        }
+        self
+    }
+
+    /// The variant of a kind other than classic among the rules, added
+    /// without any clues if it is missing, to add clues to.
+    pub fn entry(&mut self, kind: VariantKind) -> &mut Variant {
+        if self.get(kind).is_none() {
+            *self = std::mem::take(self).with(Variant::new(kind));
+        }
+        self.variants
+            .iter_mut()
+            .find(|variant| variant.kind() == kind)
+            .expect("Variant was just added")
+    }
+
+    /// These rules with more constraints turned on.
+    pub fn with_constraints(mut self, constraints: Constraints) -> RuleSet {
+        self.constraints = Constraints {
+            anti_knight: self.constraints.anti_knight || constraints.anti_knight,
+            anti_king: self.constraints.anti_king || constraints.anti_king,
+            non_consecutive: self.constraints.non_consecutive || constraints.non_consecutive,
+        };
+        self
+    }
+
+    /// Whether no variant, constraint, or script is added to the classic
+    /// rules.
+    pub fn is_classic(&self) -> bool {
+        self.variants.is_empty() && self.constraints.is_empty() && self.scripts.is_empty()
+    }
+
+    /// The variant of a kind among the rules, if there is one.
+    pub fn get(&self, kind: VariantKind) -> Option<&Variant> {
+        self.variants.iter().find(|variant| variant.kind() == kind)
+    }
+
+    /// Names of the variants, constraints, and scripts, for display.
+    pub fn names(&self) -> Vec<&str> {
+        self.variants
+            .iter()
+            .map(|variant| variant.kind().name())
+            .chain(self.constraints.names())
+            .chain(self.scripts.iter().map(|script| script.name.as_str()))
+            .collect()
+    }
+
+    /// Whether the variants can be played together: boards can only be laid
+    /// out one way, and some rules cannot share a board.
+    pub fn check(&self) -> Result<(), String> {
+        let layouts: Vec<&str> = self
+            .variants
+            .iter()
+            .filter(|variant| matches!(variant, Variant::Jigsaw(_) | Variant::Samurai))
+            .map(|variant| variant.kind().name())
+            .collect();
+        if layouts.len() > 1 {
+            return Err(format!("{} cannot be combined", layouts.join(" and ")));
+        }
+        // Diagonals and windows are laid out on a single grid.
+        if self.get(VariantKind::Samurai).is_some() {
+            for &kind in &[VariantKind::Diagonal, VariantKind::Windoku] {
+                if self.get(kind).is_some() {
+                    return Err(format!("Samurai and {} cannot be combined", kind.name()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The variant that lays out the board, such as jigsaw regions or the
+    /// samurai grids, or the classic layout if none does.
+    pub fn layout(&self) -> &Variant {
+        self.variants
+            .iter()
+            .find(|variant| matches!(variant, Variant::Jigsaw(_) | Variant::Samurai))
+            .unwrap_or(&Variant::Classic)
+    }
+
+    /// Houses a cell is in beyond its row, column, and box, on a board of a
+    /// size.
+    pub fn extra_houses(&self, size: usize, ind: [usize; 2]) -> impl Iterator<Item = House> + '_ {
+        self.variants
+            .iter()
+            .flat_map(move |variant| variant.extra_houses(size, ind))
+    }
+
+    /// Bit mask of the digits a cell can take under every rule other than
+    /// its houses, given the rest of the board.
+    pub fn allowed(&self, cells: &Grid, ind: [usize; 2]) -> u32 {
+        let digits = self.layout().digits(cells.size());
+        let mask = self
+            .variants
+            .iter()
+            .fold(digit_mask(digits), |mask, variant| {
+                mask & variant.allowed(cells, ind)
+            })
+            & self.constraints.allowed(cells, ind);
+        self.scripts.iter().fold(mask, |mask, script| {
+            mask & script.allowed(cells, digits, ind)
+        })
+    }
+
+    /// Names of the rules other than houses that keep any digit of a mask
+    /// out of a cell, given the rest of the board, such as a cage's sum or
+    /// anti-knight.
+    pub fn ruling_out(&self, cells: &Grid, ind: [usize; 2], mask: u32) -> Vec<&'static str> {
+        let variants = self
+            .variants
+            .iter()
+            .filter(|variant| !variant.allowed(cells, ind) & mask != 0)
+            .map(|variant| variant.kind().name());
+        let constraints = self
+            .constraints
+            .split()
+            .into_iter()
+            .filter(|rule| !rule.allowed(cells, ind) & mask != 0)
+            .flat_map(Constraints::names);
+        let digits = self.layout().digits(cells.size());
+        let scripts = self
+            .scripts
+            .iter()
+            .any(|script| !script.allowed(cells, digits, ind) & mask != 0)
+            .then_some("scripted");
+        variants.chain(constraints).chain(scripts).collect()
+    }
+
+    /// Whether a cell's value breaks any rule other than its houses.
+    pub fn breaks(&self, cells: &Grid, ind: [usize; 2]) -> bool {
+        self.variants
+            .iter()
+            .any(|variant| variant.breaks(cells, ind))
+            || self.constraints.breaks(cells, ind)
+            || {
+                let digits = self.layout().digits(cells.size());
+                self.scripts
+                    .iter()
+                    .any(|script| script.breaks(cells, digits, ind))
+            }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_rules_that_cannot_share_a_board() {
+        let samurai = RuleSet::default().with(Variant::Samurai);
+        assert_eq!(samurai.check(), Ok(()));
+        assert_eq!(
+            samurai.clone().with(Variant::Killer(Vec::new())).check(),
+            Ok(())
+        );
+        assert_eq!(
+            samurai.clone().with(Variant::Diagonal).check(),
+            Err("Samurai and X-Sudoku cannot be combined".to_string())
+        );
+        assert_eq!(
+            samurai.clone().with(Variant::Windoku).check(),
+            Err("Samurai and Windoku cannot be combined".to_string())
+        );
+        assert!(samurai
+            .with(Variant::new(VariantKind::Jigsaw))
+            .check()
+            .is_err());
+        assert_eq!(
+            RuleSet::default()
+                .with(Variant::Diagonal)
+                .with(Variant::Windoku)
+                .check(),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,395 @@
+use crate::grid::{Grid, SIZES};
+use crate::movelog::LogEntry;
+use crate::replay::Replay;
+use crate::rules::RuleSet;
+use crate::{Difficulty, Gameboard, GameboardController};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use toml::{Table, Value};
+use tracing::{debug, info, instrument, warn};
+
+/// Where the game in progress is saved, within a profile.
+pub const AUTOSAVE_FILE: &str = "autosave.toml";
+
+/// Board changes between autosaves.
+pub const AUTOSAVE_MOVES: u32 = 5;
+
+/// Where named save slots are kept within a profile, one file per slot.
+pub const SAVES_DIR: &str = "saves";
+
+/// Version of the save format written now. Saves made before versions were
+/// recorded count as version 0.
+pub const SAVE_VERSION: u32 = 3;
+
+/// Upgrades a save's fields from each version to the next, in order, the
+/// first taking version 0 to 1.
+const MIGRATIONS: [fn(&mut Table); SAVE_VERSION as usize] =
+    [to_version_1, to_version_2, to_version_3];
+
+/// Writes the seed as text, since TOML integers cannot hold every seed, and
+/// starts the move log missing from older saves.
+fn to_version_1(table: &mut Table) {
+    if let Some(&Value::Integer(seed)) = table.get("seed") {
+        table.insert("seed".to_string(), Value::String(seed.to_string()));
+    }
+    table
+        .entry("move_log")
+        .or_insert_with(|| Value::Array(Vec::new()));
+}
+
+/// Records the games saved before variants as classic ones.
+fn to_version_2(table: &mut Table) {
+    table
+        .entry("variant")
+        .or_insert_with(|| Value::String("classic".to_string()));
+}
+
+/// Gathers the single variant and the constraints into the rules, which
+/// can combine variants.
+fn to_version_3(table: &mut Table) {
+    let mut rules = Table::new();
+    let variants = match table.remove("variant") {
+        Some(Value::String(name)) if name == "classic" => Vec::new(),
+        Some(variant) => vec![variant],
+        None => Vec::new(),
+    };
+    rules.insert("variants".to_string(), Value::Array(variants));
+    if let Some(constraints) = table.remove("constraints") {
+        rules.insert("constraints".to_string(), constraints);
+    }
+    table.insert("rules".to_string(), Value::Table(rules));
+}
+
+/// Seeds are saved as text, as TOML integers are signed 64-bit numbers.
+mod seed_text {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(seed)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// A game in progress, as written to disk.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedGame {
+    /// Format the game was saved in, which is `SAVE_VERSION` once loaded.
+    pub version: u32,
+    pub difficulty: Difficulty,
+    pub rules: RuleSet,
+    #[serde(with = "seed_text")]
+    pub seed: u64,
+    pub cells: Grid,
+    pub solution: Grid,
+    pub givens: Grid<bool>,
+    pub notes: Grid<u32>,
+    /// Seconds played so far.
+    pub elapsed: u64,
+    pub mistakes: u32,
+    pub mistake_limit: Option<u32>,
+    pub hints: u32,
+    pub peeks: u32,
+    /// Moves made so far, missing from saves made before they were recorded.
+    pub replay: Option<Replay>,
+    pub move_log: Vec<LogEntry>,
+    /// The day of the daily puzzle this is, missing from other games.
+    pub daily: Option<i64>,
+}
+
+impl SavedGame {
+    pub fn new(controller: &GameboardController) -> SavedGame {
+        let board = &controller.gameboard;
+        SavedGame {
+            version: SAVE_VERSION,
+            difficulty: board.difficulty,
+            rules: board.rules.clone(),
+            seed: board.seed,
+            cells: board.cells.clone(),
+            solution: board.solution.clone(),
+            givens: board.givens.clone(),
+            notes: board.notes.clone(),
+            elapsed: controller.elapsed().as_secs(),
+            mistakes: controller.mistakes,
+            mistake_limit: controller.mistake_limit,
+            hints: controller.hints,
+            peeks: controller.peeks,
+            replay: Some(controller.replay.clone()),
+            move_log: controller.move_log.clone(),
+            daily: controller.daily,
+        }
+    }
+
+    /// Resumes the game, with its timer picking up where it left off.
+    pub fn restore(&self) -> GameboardController {
+        let mut gameboard = Gameboard::with_seed(self.seed);
+        gameboard.difficulty = self.difficulty;
+        gameboard.rules = self.rules.clone();
+        gameboard.cells = self.cells.clone();
+        gameboard.solution = self.solution.clone();
+        gameboard.givens = self.givens.clone();
+        gameboard.notes = self.notes.clone();
+        gameboard.update_solved();
+        let mut controller = GameboardController::new(gameboard);
+        let elapsed = Duration::from_secs(self.elapsed);
+        controller.start_time = Instant::now()
+            .checked_sub(elapsed)
+            .unwrap_or_else(Instant::now);
+        controller.mistakes = self.mistakes;
+        controller.mistake_limit = self.mistake_limit;
+        controller.hints = self.hints;
+        controller.peeks = self.peeks;
+        controller.move_log = self.move_log.clone();
+        controller.daily = self.daily;
+        controller.replay = match &self.replay {
+            Some(replay) => replay.clone(),
+            None => Replay::new(&controller.gameboard, elapsed, self.mistakes),
+        };
+        controller
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<SavedGame> {
+        let text = fs::read_to_string(path)?;
+        SavedGame::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Reads a save of this version or an older one, migrating it to this
+    /// version. Saves from newer versions are refused rather than read
+    /// partly, as are boards of sizes the game does not play.
+    pub fn parse(text: &str) -> Result<SavedGame, String> {
+        let mut table: Table = toml::from_str(text).map_err(|err| err.to_string())?;
+        let version = match table.get("version") {
+            None => 0,
+            Some(&Value::Integer(version)) if version >= 0 => version as u64,
+            Some(_) => return Err("Invalid save version".to_string()),
+        };
+        if version > u64::from(SAVE_VERSION) {
+            return Err(format!(
+                "Saved by a newer version of the game (format {}, newest known {})",
+                version, SAVE_VERSION
+            ));
+        }
+        if version < u64::from(SAVE_VERSION) {
+            debug!("Migrating save from format {} to {}", version, SAVE_VERSION);
+        }
+        for migrate in &MIGRATIONS[version as usize..] {
+            migrate(&mut table);
+        }
+        table.insert("version".to_string(), Value::Integer(SAVE_VERSION.into()));
+        let game: SavedGame = Value::Table(table)
+            .try_into()
+            .map_err(|err: toml::de::Error| err.to_string())?;
+        // The board is played as it is, so its grids must all fit one of
+        // the sizes the game plays on.
+        let size = game.cells.size();
+        if !SIZES
+            .iter()
+            .any(|&s| game.rules.layout().board_size(s) == size)
+        {
+            return Err(format!("Unsupported board size {}", size));
+        }
+        let sizes = [game.solution.size(), game.givens.size(), game.notes.size()];
+        if sizes.iter().any(|&other| other != size) {
+            return Err("The saved grids are of different sizes".to_string());
+        }
+        Ok(game)
+    }
+
+    /// Writes the game beside the save first and then moves it into place,
+    /// so a save cut short leaves the last one whole.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        let temp = path.as_ref().with_extension("tmp");
+        fs::write(&temp, &text)?;
+        fs::rename(&temp, &path)?;
+        debug!("Saved {} bytes to {}", text.len(), path.as_ref().display());
+        Ok(())
+    }
+}
+
+/// Saves an unfinished game, or removes the save once the game is over.
+/// Games only watched are left alone.
+pub fn autosave<P: AsRef<Path>>(controller: &GameboardController, path: P) {
+    if controller.spectating {
+        return;
+    }
+    let result = if controller.is_over() {
+        match fs::remove_file(path) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    } else {
+        SavedGame::new(controller).save(path)
+    };
+    if let Err(err) = result {
+        warn!("Could not autosave: {}", err);
+    }
+}
+
+/// A named saved game.
+pub struct Slot {
+    pub name: String,
+    pub game: SavedGame,
+}
+
+/// Whether a character may appear in a slot name, which is also its file name.
+pub fn is_name_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == ' ' || ch == '-' || ch == '_'
+}
+
+fn slot_path<P: AsRef<Path>>(dir: P, name: &str) -> PathBuf {
+    dir.as_ref().join(format!("{}.toml", name))
+}
+
+/// Loads every slot in a directory, sorted by name.
+///
+/// Unreadable files are reported and skipped.
+pub fn list_slots<P: AsRef<Path>>(dir: P) -> Vec<Slot> {
+    let mut slots = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            if path.extension().is_none_or(|ext| ext != "toml") {
+                continue;
+            }
+            let name = match path.file_stem() {
+                Some(stem) => stem.to_string_lossy().into_owned(),
+                None => continue,
+            };
+            match SavedGame::load(&path) {
+                Ok(game) => slots.push(Slot { name, game }),
+                Err(err) => warn!("Ignoring save {}: {}", path.display(), err),
+            }
+        }
+    }
+    slots.sort_by(|a, b| a.name.cmp(&b.name));
+    slots
+}
+
+/// Saves a game to a slot, replacing any game already there.
+#[instrument(skip(dir, game))]
+pub fn save_slot<P: AsRef<Path>>(dir: P, name: &str, game: &SavedGame) -> io::Result<()> {
+    fs::create_dir_all(&dir)?;
+    game.save(slot_path(dir, name))?;
+    info!("Saved game {}", name);
+    Ok(())
+}
+
+#[instrument(skip(dir))]
+pub fn delete_slot<P: AsRef<Path>>(dir: P, name: &str) -> io::Result<()> {
+    fs::remove_file(slot_path(dir, name))?;
+    info!("Deleted saved game {}", name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::Constraints;
+    use crate::variant::Variant;
+
+    /// A game just started on an empty 4x4 board.
+    fn saved() -> SavedGame {
+        let mut gameboard = Gameboard::from_puzzle(Grid::new(4), Grid::new(4));
+        gameboard.seed = u64::MAX;
+        SavedGame::new(&GameboardController::new(gameboard))
+    }
+
+    /// A game saved in the current format, as a table to make older ones of.
+    fn table(game: &SavedGame) -> Table {
+        Table::try_from(game).unwrap()
+    }
+
+    fn parse(table: Table) -> Result<SavedGame, String> {
+        SavedGame::parse(&toml::to_string(&table).unwrap())
+    }
+
+    #[test]
+    fn reads_saves_from_before_versions() {
+        let mut game = saved();
+        game.seed = 12;
+        let mut old = table(&game);
+        old.remove("version");
+        old.remove("rules");
+        old.remove("move_log");
+        old.remove("replay");
+        old.insert("seed".to_string(), Value::Integer(12));
+        game.replay = None;
+        assert_eq!(parse(old), Ok(game));
+    }
+
+    #[test]
+    fn reads_saves_from_before_variants() {
+        let game = saved();
+        let mut old = table(&game);
+        old.insert("version".to_string(), Value::Integer(1));
+        old.remove("rules");
+        assert_eq!(parse(old), Ok(game));
+    }
+
+    #[test]
+    fn reads_saves_with_a_single_variant() {
+        let mut game = saved();
+        let constraints = Constraints {
+            anti_knight: true,
+            ..Constraints::default()
+        };
+        let mut old = table(&game);
+        old.insert("version".to_string(), Value::Integer(2));
+        old.remove("rules");
+        old.insert("variant".to_string(), Value::String("diagonal".to_string()));
+        old.insert(
+            "constraints".to_string(),
+            Value::try_from(constraints).unwrap(),
+        );
+        game.rules = RuleSet::new(Variant::Diagonal, constraints);
+        assert_eq!(parse(old), Ok(game));
+    }
+
+    #[test]
+    fn refuses_grids_of_other_sizes() {
+        let game = saved();
+        assert_eq!(parse(table(&game)), Ok(game.clone()));
+
+        let mut odd = game.clone();
+        odd.cells = Grid::new(5);
+        assert!(parse(table(&odd)).is_err());
+
+        let mut mixed = game;
+        mixed.notes = Grid::new(9);
+        assert!(parse(table(&mixed)).is_err());
+    }
+
+    #[test]
+    fn replaces_a_save_whole() {
+        let dir = std::env::temp_dir().join(format!("sudoku-saves-{}", std::process::id()));
+        let game = saved();
+        save_slot(&dir, "first", &game).unwrap();
+        save_slot(&dir, "first", &game).unwrap();
+        let files: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        let slots = list_slots(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files, ["first.toml"]);
+        assert_eq!(slots.len(), 1);
+        assert_eq!(slots[0].game, game);
+    }
+
+    #[test]
+    fn refuses_saves_from_newer_versions() {
+        let mut new = table(&saved());
+        new.insert(
+            "version".to_string(),
+            Value::Integer(i64::from(SAVE_VERSION) + 1),
+        );
+        assert!(parse(new).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where screenshots are written.
+pub const SCREENSHOTS_DIR: &str = "screenshots";
+/// Where board images are exported to.
+pub const EXPORTS_DIR: &str = "exports";
+
+/// Reads the bound framebuffer, top row first.
+fn read_pixels(width: u32, height: u32) -> Vec<u8> {
+    let row = width as usize * 4;
+    let mut pixels = vec![0u8; row * height as usize];
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut _,
+        );
+    }
+    // OpenGL rows run bottom to top.
+    pixels.chunks(row).rev().flatten().copied().collect()
+}
+
+/// Writes pixels to a timestamped PNG in `dir`.
+fn write_png<P: AsRef<Path>>(
+    dir: P,
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<PathBuf, String> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    let path = dir.as_ref().join(format!("sudoku-{}.png", secs));
+    image::save_buffer(&path, pixels, width, height, image::ColorType::RGBA(8))
+        .map_err(|err| err.to_string())?;
+    Ok(path)
+}
+
+/// Writes the frame just drawn to a timestamped PNG, returning its path.
+///
+/// Must be called after drawing and before the buffers are swapped.
+pub fn capture<P: AsRef<Path>>(dir: P, draw_size: [u32; 2]) -> Result<PathBuf, String> {
+    let [width, height] = draw_size;
+    write_png(dir, &read_pixels(width, height), width, height)
+}
+
+/// Draws an image of `size` pixels square off-screen and writes it to a
+/// timestamped PNG, returning its path.
+///
+/// `draw` should draw with a viewport of that size. The window is left as
+/// it was.
+pub fn render<P: AsRef<Path>, F: FnOnce()>(dir: P, size: u32, draw: F) -> Result<PathBuf, String> {
+    let mut max_size = 0;
+    unsafe {
+        gl::GetIntegerv(gl::MAX_RENDERBUFFER_SIZE, &mut max_size);
+    }
+    if size == 0 || size as i32 > max_size {
+        return Err(format!("Cannot draw an image of {} pixels", size));
+    }
+
+    let (mut framebuffer, mut renderbuffer) = (0, 0);
+    let complete = unsafe {
+        gl::GenFramebuffers(1, &mut framebuffer);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+        gl::GenRenderbuffers(1, &mut renderbuffer);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, renderbuffer);
+        gl::RenderbufferStorage(gl::RENDERBUFFER, gl::RGBA8, size as i32, size as i32);
+        gl::FramebufferRenderbuffer(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::RENDERBUFFER,
+            renderbuffer,
+        );
+        gl::CheckFramebufferStatus(gl::FRAMEBUFFER) == gl::FRAMEBUFFER_COMPLETE
+    };
+    let pixels = if complete {
+        draw();
+        Some(read_pixels(size, size))
+    } else {
+        None
+    };
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        gl::DeleteRenderbuffers(1, &renderbuffer);
+        gl::DeleteFramebuffers(1, &framebuffer);
+    }
+    match pixels {
+        Some(pixels) => write_png(dir, &pixels, size, size),
+        None => Err("Could not set up off-screen drawing".to_string()),
+    }
+}
@@ -0,0 +1,490 @@
+//! Rhai scripts, read from the scripts directory as the game runs, for
+//! trying out rules and board tools without building the game again.
+//!
+//! A script defining `fn allowed(x, y, digit)` is a rule: it says whether
+//! a digit may go in an empty cell, given the rest of the board, and
+//! joins the rules puzzles are generated, checked, and solved under. A
+//! script with statements outside any function is a tool, run over the
+//! board in play as a bulk change. Cells are counted from 0 at the top
+//! left, and empty cells read as 0.
+//!
+//! ```text
+//! // Rule: the corners hold even digits.
+//! fn allowed(x, y, digit) {
+//!     let last = size() - 1;
+//!     if (x == 0 || x == last) && (y == 0 || y == last) {
+//!         return digit % 2 == 0;
+//!     }
+//!     true
+//! }
+//! ```
+//!
+//! Besides Rhai's own functions, scripts read the board with `size()`,
+//! `digits()`, and `get(x, y)`, and tools also have `given(x, y)`,
+//! `in_play(x, y)`, `note(x, y, digit)`, `candidates(x, y)`,
+//! `set(x, y, digit)`, and `set_note(x, y, digit, on)`.
+//!
+//! Rules travel with saved games and co-op games, so a script may come from
+//! anyone. Each run is cut short after a number of operations, and nesting,
+//! strings, and arrays are capped, so a script cannot hang the game or run
+//! it out of memory, and scripts cannot load modules or `eval` code.
+
+use crate::grid::Grid;
+use crate::technique;
+use crate::{Gameboard, SIZE};
+use rhai::{Array, CallFnOptions, Dynamic, Engine, EvalAltResult, NativeCallContext, Scope, AST};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info, warn};
+
+/// Where scripts are looked up, as `.rhai` files.
+pub const SCRIPTS_DIR: &str = "scripts";
+
+/// Operations a rule may take to check a digit, as the solver asks often.
+const RULE_OPERATIONS: u64 = 100_000;
+
+/// Operations a tool may take over the whole board.
+const TOOL_OPERATIONS: u64 = 10_000_000;
+
+/// Deepest expressions may nest, and functions may call each other.
+const MAX_DEPTH: usize = 64;
+
+/// Longest string a script may build, in bytes.
+const MAX_STRING: usize = 1 << 16;
+
+/// Most items an array or object map may hold.
+const MAX_ITEMS: usize = 1 << 16;
+
+type Result<T> = std::result::Result<T, Box<EvalAltResult>>;
+
+/// The board a script runs over, handed to the functions it calls: a rule
+/// reads a copy of the cells, and a tool changes the board in play.
+#[derive(Clone)]
+enum Target {
+    Rule { cells: Arc<Grid>, digits: usize },
+    Tool(Arc<Mutex<Gameboard>>),
+}
+
+fn target(context: &NativeCallContext) -> Target {
+    context
+        .tag()
+        .and_then(|tag| tag.read_lock::<Target>().map(|target| target.clone()))
+        .expect("scripts run over a board")
+}
+
+/// Reads the cells and highest digit of the board a script runs over.
+fn read<T>(context: &NativeCallContext, f: impl FnOnce(&Grid, usize) -> T) -> T {
+    match target(context) {
+        Target::Rule { cells, digits } => f(&cells, digits),
+        Target::Tool(board) => {
+            let board = board.lock().unwrap();
+            f(&board.cells, board.digits())
+        }
+    }
+}
+
+/// Changes the board a tool runs over. Rules cannot.
+fn change<T>(
+    context: &NativeCallContext,
+    f: impl FnOnce(&mut Gameboard) -> Result<T>,
+) -> Result<T> {
+    match target(context) {
+        Target::Tool(board) => f(&mut board.lock().unwrap()),
+        Target::Rule { .. } => Err(format!("rules cannot call {}", context.fn_name()).into()),
+    }
+}
+
+/// A cell named by its column and row.
+fn cell(size: usize, x: i64, y: i64) -> Result<[usize; 2]> {
+    let size = size as i64;
+    if x < 0 || y < 0 || x >= size || y >= size {
+        return Err(format!("({}, {}) is off the board", x, y).into());
+    }
+    Ok([x as usize, y as usize])
+}
+
+/// A digit the board can hold, or 0 for none when `empty` allows it.
+fn digit(digits: usize, digit: i64, empty: bool) -> Result<u8> {
+    let least = if empty { 0 } else { 1 };
+    if digit < least || digit > digits as i64 {
+        return Err(format!("{} is not a digit of this board", digit).into());
+    }
+    Ok(digit as u8)
+}
+
+/// A cell of a tool's board that it may change.
+fn changeable(board: &Gameboard, x: i64, y: i64) -> Result<[usize; 2]> {
+    let ind = cell(board.size(), x, y)?;
+    if board.is_given(ind) || !board.in_play(ind) {
+        return Err(format!("({}, {}) cannot be changed", x, y).into());
+    }
+    Ok(ind)
+}
+
+/// An engine for a script, with the board's functions and the limits every
+/// run is held to.
+fn engine(name: &str, operations: u64) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(operations)
+        .set_max_expr_depths(MAX_DEPTH, MAX_DEPTH)
+        .set_max_call_levels(MAX_DEPTH)
+        .set_max_string_size(MAX_STRING)
+        .set_max_array_size(MAX_ITEMS)
+        .set_max_map_size(MAX_ITEMS)
+        .set_max_modules(0)
+        .disable_symbol("eval");
+    let name = name.to_string();
+    engine.on_print(move |text| info!("{}: {}", name, text));
+    engine
+        .register_fn("size", |context: NativeCallContext| -> i64 {
+            read(&context, |cells, _| cells.size() as i64)
+        })
+        .register_fn("digits", |context: NativeCallContext| -> i64 {
+            read(&context, |_, digits| digits as i64)
+        })
+        .register_fn(
+            "get",
+            |context: NativeCallContext, x: i64, y: i64| -> Result<i64> {
+                read(&context, |cells, _| {
+                    let [x, y] = cell(cells.size(), x, y)?;
+                    Ok(i64::from(cells[y][x]))
+                })
+            },
+        )
+        .register_fn(
+            "given",
+            |context: NativeCallContext, x: i64, y: i64| -> Result<bool> {
+                change(&context, |board| {
+                    Ok(board.is_given(cell(board.size(), x, y)?))
+                })
+            },
+        )
+        .register_fn(
+            "in_play",
+            |context: NativeCallContext, x: i64, y: i64| -> Result<bool> {
+                change(&context, |board| {
+                    Ok(board.in_play(cell(board.size(), x, y)?))
+                })
+            },
+        )
+        .register_fn(
+            "note",
+            |context: NativeCallContext, x: i64, y: i64, val: i64| -> Result<bool> {
+                change(&context, |board| {
+                    let ind = cell(board.size(), x, y)?;
+                    Ok(board.has_note(ind, digit(board.digits(), val, false)?))
+                })
+            },
+        )
+        .register_fn(
+            "candidates",
+            |context: NativeCallContext, x: i64, y: i64| -> Result<Array> {
+                change(&context, |board| {
+                    let ind = cell(board.size(), x, y)?;
+                    let mask = technique::Board::new(&board.cells, &board.rules)
+                        .map_or(0, |board| board.candidates(ind));
+                    Ok((1..=board.digits() as i64)
+                        .filter(|&val| mask & (1 << val) != 0)
+                        .map(Dynamic::from)
+                        .collect::<Array>())
+                })
+            },
+        )
+        .register_fn(
+            "set",
+            |context: NativeCallContext, x: i64, y: i64, val: i64| -> Result<()> {
+                change(&context, |board| {
+                    let ind = changeable(board, x, y)?;
+                    let val = digit(board.digits(), val, true)?;
+                    board.set(ind, val);
+                    Ok(())
+                })
+            },
+        )
+        .register_fn(
+            "set_note",
+            |context: NativeCallContext, x: i64, y: i64, val: i64, on: bool| -> Result<()> {
+                change(&context, |board| {
+                    let [x, y] = changeable(board, x, y)?;
+                    let bit = 1 << digit(board.digits(), val, false)?;
+                    let notes = &mut board.notes[y][x];
+                    *notes = if on { *notes | bit } else { *notes & !bit };
+                    Ok(())
+                })
+            },
+        );
+    engine
+}
+
+/// A script as saved with the rules it is part of: its name and source.
+#[derive(Clone, Serialize, Deserialize)]
+struct ScriptSource {
+    name: String,
+    source: String,
+}
+
+/// A script read and compiled, ready to run.
+///
+/// Rules keep their scripts' source, so saved games and games shared with
+/// other players keep the rules they were played under.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(into = "ScriptSource", try_from = "ScriptSource")]
+pub struct Script {
+    pub name: String,
+    source: String,
+    ast: Arc<AST>,
+    /// The engine rules are checked with, kept as the solver asks often.
+    engine: Arc<Engine>,
+}
+
+impl fmt::Debug for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Script").field("name", &self.name).finish()
+    }
+}
+
+impl PartialEq for Script {
+    fn eq(&self, other: &Script) -> bool {
+        self.name == other.name && self.source == other.source
+    }
+}
+
+impl Eq for Script {}
+
+impl From<Script> for ScriptSource {
+    fn from(script: Script) -> ScriptSource {
+        ScriptSource {
+            name: script.name,
+            source: script.source,
+        }
+    }
+}
+
+impl TryFrom<ScriptSource> for Script {
+    type Error = String;
+
+    fn try_from(saved: ScriptSource) -> std::result::Result<Script, String> {
+        Script::parse(&saved.name, &saved.source)
+    }
+}
+
+impl Script {
+    /// Compiles a script, or says where it went wrong.
+    pub fn parse(name: &str, source: &str) -> std::result::Result<Script, String> {
+        let engine = engine(name, RULE_OPERATIONS);
+        let ast = engine.compile(source).map_err(|err| err.to_string())?;
+        if let Some(allowed) = ast.iter_functions().find(|f| f.name == "allowed") {
+            if allowed.params.len() != 3 {
+                return Err("allowed takes x, y, and digit".to_string());
+            }
+        }
+        let script = Script {
+            name: name.to_string(),
+            source: source.to_string(),
+            ast: Arc::new(ast),
+            engine: Arc::new(engine),
+        };
+        if !script.is_rule() && !script.is_tool() {
+            return Err("neither a rule nor a tool".to_string());
+        }
+        // Rules failing as they run allow every digit, so the plainest
+        // failures are caught here, on an empty classic board.
+        if script.is_rule() {
+            match script.call_allowed(&Arc::new(Grid::new(SIZE)), SIZE, [0, 0], 1) {
+                Ok(allowed) if allowed.is_bool() => {}
+                Ok(other) => {
+                    return Err(format!(
+                        "allowed gave {}, not true or false",
+                        other.type_name()
+                    ))
+                }
+                Err(err) => return Err(err.to_string()),
+            }
+        }
+        Ok(script)
+    }
+
+    /// Reads a script from a file, named after it.
+    pub fn load<P: AsRef<Path>>(path: P) -> std::result::Result<Script, String> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let name = path
+            .file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+        Script::parse(&name, &source)
+    }
+
+    /// Whether the script is a rule, deciding where digits may go.
+    pub fn is_rule(&self) -> bool {
+        self.ast.iter_functions().any(|f| f.name == "allowed")
+    }
+
+    /// Whether the script is a tool, run over the board in play.
+    pub fn is_tool(&self) -> bool {
+        !AsRef::<[_]>::as_ref(&*self.ast).is_empty()
+    }
+
+    /// Asks the rule whether a digit may go in a cell.
+    fn call_allowed(
+        &self,
+        cells: &Arc<Grid>,
+        digits: usize,
+        [x, y]: [usize; 2],
+        val: usize,
+    ) -> Result<Dynamic> {
+        let target = Target::Rule {
+            cells: Arc::clone(cells),
+            digits,
+        };
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .with_tag(Dynamic::from(target));
+        let args = (x as i64, y as i64, val as i64);
+        self.engine
+            .call_fn_with_options(options, &mut Scope::new(), &self.ast, "allowed", args)
+    }
+
+    /// Bit mask of the digits a rule lets an empty cell take, given the
+    /// rest of the board. A rule that fails allows every digit.
+    pub fn allowed(&self, cells: &Grid, digits: usize, [x, y]: [usize; 2]) -> u32 {
+        if !self.is_rule() {
+            return !0;
+        }
+        let cells = Arc::new(cells.clone());
+        let mut mask = 0;
+        for val in 1..=digits {
+            match self.call_allowed(&cells, digits, [x, y], val) {
+                Ok(allowed) => match allowed.as_bool() {
+                    Ok(false) => {}
+                    Ok(true) => mask |= 1 << val,
+                    Err(kind) => {
+                        debug!("{}: allowed gave {} for ({}, {})", self.name, kind, x, y);
+                        mask |= 1 << val;
+                    }
+                },
+                Err(err) => {
+                    debug!("{}: {}", self.name, err);
+                    mask |= 1 << val;
+                }
+            }
+        }
+        mask
+    }
+
+    /// Whether a cell's digit breaks the rule, given the rest of the board.
+    pub fn breaks(&self, cells: &Grid, digits: usize, [x, y]: [usize; 2]) -> bool {
+        let val = cells[y][x];
+        if val == 0 || !self.is_rule() {
+            return false;
+        }
+        let mut rest = cells.clone();
+        rest[y][x] = 0;
+        self.allowed(&rest, digits, [x, y]) & (1 << val) == 0
+    }
+
+    /// Runs a tool over a board, changing it, or says why it stopped.
+    ///
+    /// Changes made before a tool stops are kept.
+    pub fn run(&self, board: &mut Gameboard) -> std::result::Result<(), String> {
+        let shared = Arc::new(Mutex::new(std::mem::take(board)));
+        let mut engine = engine(&self.name, TOOL_OPERATIONS);
+        engine.set_default_tag(Dynamic::from(Target::Tool(Arc::clone(&shared))));
+        let result = engine.run_ast(&self.ast);
+        drop(engine);
+        let shared = Arc::try_unwrap(shared).unwrap_or_else(|_| unreachable!("the engine is gone"));
+        *board = shared.into_inner().unwrap();
+        result.map_err(|err| err.to_string())
+    }
+}
+
+/// Loads every `.rhai` script in a directory, sorted by name.
+///
+/// Scripts that cannot be read or compiled are reported and skipped.
+pub fn load_scripts<P: AsRef<Path>>(dir: P) -> Vec<Script> {
+    let mut scripts = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            if path.extension().is_none_or(|ext| ext != "rhai") {
+                continue;
+            }
+            match Script::load(&path) {
+                Ok(script) => scripts.push(script),
+                Err(err) => warn!("Ignoring script {}: {}", path.display(), err),
+            }
+        }
+    }
+    scripts.sort_by(|a, b| a.name.cmp(&b.name));
+    scripts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVEN_CORNERS: &str = "
+        fn allowed(x, y, digit) {
+            let last = size() - 1;
+            if (x == 0 || x == last) && (y == 0 || y == last) {
+                return digit % 2 == 0;
+            }
+            true
+        }
+    ";
+
+    #[test]
+    fn rules_decide_where_digits_go() {
+        let script = Script::parse("even corners", EVEN_CORNERS).unwrap();
+        assert!(script.is_rule() && !script.is_tool());
+        let mut cells = Grid::new(SIZE);
+        assert_eq!(script.allowed(&cells, SIZE, [0, 0]), 0b01_0101_0100);
+        assert_eq!(script.allowed(&cells, SIZE, [4, 4]), 0b11_1111_1110);
+        cells[8][8] = 3;
+        assert!(script.breaks(&cells, SIZE, [8, 8]));
+    }
+
+    #[test]
+    fn tools_change_the_board() {
+        let script = Script::parse("fill", "set(0, 0, digits()); set_note(1, 0, 2, true);");
+        let script = script.unwrap();
+        assert!(script.is_tool() && !script.is_rule());
+        let mut board = Gameboard::from_puzzle(Grid::new(SIZE), Grid::new(SIZE));
+        script.run(&mut board).unwrap();
+        assert_eq!(board.cells[0][0], 9);
+        assert!(board.has_note([1, 0], 2));
+    }
+
+    #[test]
+    fn loads_the_scripts_that_compile() {
+        let dir = std::env::temp_dir().join(format!("sudoku-scripts-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b corners.rhai"), EVEN_CORNERS).unwrap();
+        fs::write(dir.join("a fill.rhai"), "set(0, 0, 1);").unwrap();
+        fs::write(dir.join("broken.rhai"), "fn (").unwrap();
+        fs::write(dir.join("notes.txt"), "not a script").unwrap();
+        let names: Vec<String> = load_scripts(&dir).into_iter().map(|s| s.name).collect();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(names, ["a fill", "b corners"]);
+    }
+
+    #[test]
+    fn refuses_scripts_breaking_the_limits() {
+        let rule = Script::parse("rule", "fn allowed(x, y, digit) { get(x, y) }");
+        assert!(rule.unwrap_err().contains("not true or false"));
+        let endless = Script::parse("endless", "loop {}").unwrap();
+        let mut board = Gameboard::from_puzzle(Grid::new(SIZE), Grid::new(SIZE));
+        assert!(endless.run(&mut board).is_err());
+        let growing = Script::parse("growing", "let s = \"x\"; loop { s += s; }").unwrap();
+        assert!(growing.run(&mut board).is_err());
+        let nested = format!("let x = {}1{};", "(".repeat(200), ")".repeat(200));
+        assert!(Script::parse("nested", &nested).is_err());
+        let import = Script::parse("import", "import \"other\" as other;").unwrap();
+        assert!(import.run(&mut board).is_err());
+        let setter = "fn allowed(x, y, digit) { set(x, y, digit); true }";
+        assert!(Script::parse("setter", setter).is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use crate::grid::Grid;
+use crate::SIZE;
+
+/// A puzzle in a SadMan Sudoku `.sdk` file: `#` header lines followed by
+/// nine rows of nine cells, with `.` for empty cells.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SdkPuzzle {
+    /// Header lines by their tag letter, such as `A` for the author, `D` for
+    /// a description, `C` for a comment, `B` for the date, `S` for the
+    /// source, `L` for the level, and `U` for a URL.
+    pub headers: Vec<(char, String)>,
+    pub cells: Grid,
+}
+
+impl SdkPuzzle {
+    pub fn new(cells: Grid) -> SdkPuzzle {
+        SdkPuzzle {
+            headers: Vec::new(),
+            cells,
+        }
+    }
+
+    /// The value of the first header with a tag.
+    pub fn header(&self, tag: char) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn parse(text: &str) -> Result<SdkPuzzle, String> {
+        let mut headers = Vec::new();
+        let mut rows = Vec::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if let Some(header) = line.strip_prefix('#') {
+                let mut chars = header.chars();
+                if let Some(tag) = chars.next() {
+                    headers.push((tag, chars.as_str().trim().to_string()));
+                }
+                continue;
+            }
+            let row: Vec<u8> = line
+                .chars()
+                .map(|ch| match ch {
+                    '.' | '0' => Ok(0),
+                    '1'..='9' => Ok(ch as u8 - b'0'),
+                    _ => Err(format!("Unexpected '{}' in row {}", ch, rows.len() + 1)),
+                })
+                .collect::<Result<_, _>>()?;
+            if row.len() != SIZE {
+                return Err(format!(
+                    "Row {} has {} cells instead of {}",
+                    rows.len() + 1,
+                    row.len(),
+                    SIZE
+                ));
+            }
+            rows.push(row);
+        }
+        if rows.len() != SIZE {
+            return Err(format!("Expected {} rows but found {}", SIZE, rows.len()));
+        }
+        let mut cells = Grid::new(SIZE);
+        for (cells, row) in cells.iter_mut().zip(rows) {
+            cells.copy_from_slice(&row);
+        }
+        Ok(SdkPuzzle { headers, cells })
+    }
+
+    pub fn write(&self) -> String {
+        let mut text = String::new();
+        for (tag, value) in &self.headers {
+            text += &format!("#{}{}\n", tag, value);
+        }
+        for row in self.cells.iter() {
+            for &val in row {
+                text.push(match val {
+                    0 => '.',
+                    _ => (b'0' + val) as char,
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "#AJane\n#DA gentle start\n\
+        53..7....\n6..195...\n.98....6.\n8...6...3\n4..8.3..1\n\
+        7...2...6\n.6....28.\n...419..5\n....8..79\n";
+
+    #[test]
+    fn reads_and_writes_headers_and_rows() {
+        let puzzle = SdkPuzzle::parse(FILE).unwrap();
+        assert_eq!(puzzle.header('A'), Some("Jane"));
+        assert_eq!(puzzle.header('D'), Some("A gentle start"));
+        assert_eq!(puzzle.header('U'), None);
+        assert_eq!(puzzle.cells[8][..], [0, 0, 0, 0, 8, 0, 0, 7, 9]);
+        assert_eq!(puzzle.write(), FILE);
+    }
+
+    #[test]
+    fn refuses_misshapen_grids() {
+        assert!(SdkPuzzle::parse("53..7....\n").is_err());
+        let letter = FILE.replacen("53..7", "53x.7", 1);
+        assert!(SdkPuzzle::parse(&letter)
+            .unwrap_err()
+            .contains("'x' in row 1"));
+        let short = FILE.replacen("6..195...", "6..195..", 1);
+        assert!(SdkPuzzle::parse(&short).unwrap_err().contains("Row 2"));
+    }
+}
@@ -0,0 +1,412 @@
+use crate::config::Config;
+use crate::database::Database;
+use crate::theme::{self, THEMES_DIR};
+use crate::{
+    font, new_game, record_result, GameboardController, GameboardView, GameboardViewSettings,
+};
+use piston::input::keyboard::ModifierKey;
+use piston::input::{Button, ButtonArgs, ButtonState, Event, Input, Key, Motion, MouseButton};
+use sdl2::event::{Event as SdlEvent, WindowEvent};
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton as SdlMouseButton;
+use sdl2::pixels;
+use sdl2::rect::{FPoint, Rect};
+use sdl2::render::{BlendMode, TextureCreator, Vertex, VertexIndices, WindowCanvas};
+use sdl2::rwops::RWops;
+use sdl2::surface::Surface;
+use sdl2::ttf::{Font, Sdl2TtfContext};
+use sdl2::video::WindowContext;
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+use std::time::Instant;
+use sudoku::render::{Color, Renderer};
+use tracing::debug;
+
+/// Draws boards on an SDL2 window, in its pixels, which are finer than
+/// window points on HiDPI displays.
+struct SdlRenderer<'a> {
+    canvas: WindowCanvas,
+    textures: TextureCreator<WindowContext>,
+    ttf: &'a Sdl2TtfContext,
+    font: &'a [u8],
+    /// The font loaded at each pixel size drawn.
+    fonts: HashMap<u16, Font<'a, 'a>>,
+    /// Pixels per window point.
+    scale: f64,
+}
+
+impl<'a> SdlRenderer<'a> {
+    fn new(mut canvas: WindowCanvas, ttf: &'a Sdl2TtfContext, font: &'a [u8]) -> Self {
+        canvas.set_blend_mode(BlendMode::Blend);
+        SdlRenderer {
+            textures: canvas.texture_creator(),
+            canvas,
+            ttf,
+            font,
+            fonts: HashMap::new(),
+            scale: 1.0,
+        }
+    }
+
+    /// The window's size in points, catching up with a change of display.
+    fn window_size(&mut self) -> [f64; 2] {
+        let (width, height) = self.canvas.window().size();
+        let (pixels, _) = self.canvas.output_size().unwrap_or((width, height));
+        let scale = f64::from(pixels) / f64::from(width.max(1));
+        if scale != self.scale {
+            self.scale = scale;
+            self.fonts.clear();
+        }
+        [f64::from(width), f64::from(height)]
+    }
+
+    fn px(&self, points: f64) -> i32 {
+        (points * self.scale).round() as i32
+    }
+
+    fn px_rect(&self, [x, y, width, height]: [f64; 4]) -> Rect {
+        let (left, top) = (self.px(x), self.px(y));
+        let (right, bottom) = (self.px(x + width), self.px(y + height));
+        Rect::new(
+            left,
+            top,
+            (right - left).max(0) as u32,
+            (bottom - top).max(0) as u32,
+        )
+    }
+
+    /// The pixel size of a font size, with the font loaded at it.
+    fn load_font(&mut self, size: f64) -> Option<u16> {
+        let pixels = ((size * self.scale).round() as u16).max(1);
+        if !self.fonts.contains_key(&pixels) {
+            let font = RWops::from_bytes(self.font)
+                .and_then(|rwops| self.ttf.load_font_from_rwops(rwops, pixels));
+            match font {
+                Ok(font) => self.fonts.insert(pixels, font),
+                Err(err) => {
+                    debug!("Could not load font at {}px: {}", pixels, err);
+                    return None;
+                }
+            };
+        }
+        Some(pixels)
+    }
+
+    /// A point in pixels.
+    fn fpx(&self, [x, y]: [f64; 2]) -> FPoint {
+        FPoint::new((x * self.scale) as f32, (y * self.scale) as f32)
+    }
+
+    /// Fills triangles given by their corners in points.
+    fn triangles(&mut self, corners: &[[f64; 2]], color: Color) {
+        let color = sdl_color(color);
+        let vertices: Vec<Vertex> = corners
+            .iter()
+            .map(|&corner| Vertex {
+                position: self.fpx(corner),
+                color,
+                tex_coord: FPoint::new(0.0, 0.0),
+            })
+            .collect();
+        drawn(
+            self.canvas
+                .render_geometry(&vertices, None, VertexIndices::Sequential),
+        );
+    }
+
+    /// Copies rendered text to the window with its top left at a pixel.
+    fn blit(&mut self, surface: &Surface, x: i32, y: i32) {
+        let rect = Rect::new(x, y, surface.width(), surface.height());
+        match self.textures.create_texture_from_surface(surface) {
+            Ok(texture) => drawn(self.canvas.copy(&texture, None, rect)),
+            Err(err) => debug!("Could not draw text: {}", err),
+        }
+    }
+}
+
+/// Drawing errors leave a shape out of one frame, so they are only logged.
+fn drawn(result: Result<(), String>) {
+    if let Err(err) = result {
+        debug!("Could not draw: {}", err);
+    }
+}
+
+fn sdl_color(color: Color) -> pixels::Color {
+    let [r, g, b, a] = color.map(|part| (part.clamp(0.0, 1.0) * 255.0).round() as u8);
+    pixels::Color::RGBA(r, g, b, a)
+}
+
+impl Renderer for SdlRenderer<'_> {
+    fn rect(&mut self, rect: [f64; 4], color: Color) {
+        let rect = self.px_rect(rect);
+        self.canvas.set_draw_color(sdl_color(color));
+        drawn(self.canvas.fill_rect(rect));
+    }
+
+    fn border(&mut self, [x, y, width, height]: [f64; 4], color: Color, radius: f64) {
+        let d = 2.0 * radius;
+        self.rect([x - radius, y - radius, width + d, d], color);
+        self.rect([x - radius, y + height - radius, width + d, d], color);
+        self.rect([x - radius, y + radius, d, height - d], color);
+        self.rect([x + width - radius, y + radius, d, height - d], color);
+    }
+
+    fn line(&mut self, [x1, y1, x2, y2]: [f64; 4], color: Color, radius: f64) {
+        let length = (x2 - x1).hypot(y2 - y1);
+        if length == 0.0 {
+            return;
+        }
+        // The line is a quad reaching `radius` out from it either side.
+        let [nx, ny] = [(y1 - y2) / length * radius, (x2 - x1) / length * radius];
+        let (a, b) = ([x1 + nx, y1 + ny], [x1 - nx, y1 - ny]);
+        let (c, d) = ([x2 + nx, y2 + ny], [x2 - nx, y2 - ny]);
+        self.triangles(&[a, b, c, c, b, d], color);
+    }
+
+    fn ellipse(&mut self, [x, y, width, height]: [f64; 4], color: Color) {
+        // A fan of triangles, with more of them the larger the ellipse.
+        let [cx, cy, rx, ry] = [x + width / 2.0, y + height / 2.0, width / 2.0, height / 2.0];
+        let steps = ((rx.max(ry) * self.scale) as usize).clamp(12, 64);
+        let at = |k: usize| {
+            let angle = k as f64 / steps as f64 * TAU;
+            [cx + rx * angle.cos(), cy + ry * angle.sin()]
+        };
+        let corners: Vec<[f64; 2]> = (0..steps)
+            .flat_map(|k| [[cx, cy], at(k), at(k + 1)])
+            .collect();
+        self.triangles(&corners, color);
+    }
+
+    fn text(&mut self, text: &str, size: u32, color: Color, pos: [f64; 2]) {
+        // SDL_ttf refuses to render nothing.
+        if text.is_empty() {
+            return;
+        }
+        let pixels = match self.load_font(size as f64) {
+            Some(pixels) => pixels,
+            None => return,
+        };
+        let font = &self.fonts[&pixels];
+        let ascent = font.ascent();
+        match font.render(text).blended(sdl_color(color)) {
+            Ok(surface) => self.blit(&surface, self.px(pos[0]), self.px(pos[1]) - ascent),
+            Err(err) => debug!("Could not render {:?}: {}", text, err),
+        }
+    }
+
+    fn text_width(&mut self, text: &str, size: u32) -> f64 {
+        let pixels = match self.load_font(size as f64) {
+            Some(pixels) => pixels,
+            None => return 0.0,
+        };
+        self.fonts[&pixels]
+            .size_of(text)
+            .map_or(0.0, |(width, _)| f64::from(width) / self.scale)
+    }
+
+    fn centered(&mut self, ch: char, size: f64, color: Color, center: [f64; 2]) {
+        let pixels = match self.load_font(size) {
+            Some(pixels) => pixels,
+            None => return,
+        };
+        let font = &self.fonts[&pixels];
+        let ascent = font.ascent();
+        let rendered = font.render_char(ch).blended(sdl_color(color));
+        if let (Some(metrics), Ok(surface)) = (font.find_glyph_metrics(ch), rendered) {
+            // The outline runs from minx to maxx across, and from miny to
+            // maxy above the baseline, which is the ascent down the surface.
+            let x = self.px(center[0]) - (metrics.minx + metrics.maxx) / 2;
+            let y = self.px(center[1]) - ascent + (metrics.miny + metrics.maxy) / 2;
+            self.blit(&surface, x, y);
+        }
+    }
+
+    fn clip(&mut self, rect: Option<[f64; 4]>) {
+        let rect = rect.map(|rect| self.px_rect(rect));
+        self.canvas.set_clip_rect(rect);
+    }
+}
+
+/// The piston event an SDL event stands for, so the board takes input just
+/// as it does in the OpenGL window. Piston's keys are SDL's keycodes.
+fn piston_event(event: &SdlEvent) -> Option<Event> {
+    let button = |state, button, scancode| {
+        Input::Button(ButtonArgs {
+            state,
+            button,
+            scancode,
+        })
+    };
+    let mouse_button = |mouse_btn| match mouse_btn {
+        SdlMouseButton::Left => Some(Button::Mouse(MouseButton::Left)),
+        SdlMouseButton::Right => Some(Button::Mouse(MouseButton::Right)),
+        SdlMouseButton::Middle => Some(Button::Mouse(MouseButton::Middle)),
+        _ => None,
+    };
+    let input = match *event {
+        SdlEvent::MouseMotion { x, y, .. } => {
+            Input::Move(Motion::MouseCursor([f64::from(x), f64::from(y)]))
+        }
+        SdlEvent::MouseWheel {
+            precise_x,
+            precise_y,
+            ..
+        } => Input::Move(Motion::MouseScroll([
+            f64::from(precise_x),
+            f64::from(precise_y),
+        ])),
+        SdlEvent::MouseButtonDown { mouse_btn, .. } => {
+            button(ButtonState::Press, mouse_button(mouse_btn)?, None)
+        }
+        SdlEvent::MouseButtonUp { mouse_btn, .. } => {
+            button(ButtonState::Release, mouse_button(mouse_btn)?, None)
+        }
+        SdlEvent::KeyDown {
+            keycode: Some(keycode),
+            scancode,
+            ..
+        } => button(
+            ButtonState::Press,
+            Button::Keyboard(Key::from(keycode.into_i32() as u32)),
+            scancode.map(|scancode| scancode as i32),
+        ),
+        SdlEvent::KeyUp {
+            keycode: Some(keycode),
+            scancode,
+            ..
+        } => button(
+            ButtonState::Release,
+            Button::Keyboard(Key::from(keycode.into_i32() as u32)),
+            scancode.map(|scancode| scancode as i32),
+        ),
+        SdlEvent::Window {
+            win_event: WindowEvent::Leave,
+            ..
+        } => Input::Cursor(false),
+        _ => return None,
+    };
+    Some(Event::Input(input, None))
+}
+
+/// Plays games on a window drawn with SDL2 until it is closed, starting new
+/// games of the same difficulty and size once one is over.
+///
+/// This is for systems where the OpenGL window cannot open. Only the board
+/// is shown, with the window's mouse and keys, and finished games are
+/// recorded in the puzzle database as usual.
+pub fn play(
+    mut controller: GameboardController,
+    database: &Option<Database>,
+    config: &Config,
+) -> Result<(), String> {
+    let context = sdl2::init()?;
+    let video = context.video()?;
+    let ttf = sdl2::ttf::init().map_err(|err| err.to_string())?;
+    let [width, height] = config.window_size;
+    let window = video
+        .window("Sudoku", width, height)
+        .position_centered()
+        .resizable()
+        .allow_highdpi()
+        .build()
+        .map_err(|err| err.to_string())?;
+    let canvas = window
+        .into_canvas()
+        .present_vsync()
+        .build()
+        .map_err(|err| err.to_string())?;
+    let font = font::load_bytes(config.font.as_deref());
+    let mut renderer = SdlRenderer::new(canvas, &ttf, &font);
+    let mut events = context.event_pump()?;
+
+    let themes = theme::load_themes(THEMES_DIR);
+    let theme = theme::select(&themes, &config.theme, config.high_contrast);
+    let mut view = GameboardView::new(GameboardViewSettings::new(&theme));
+    view.settings
+        .set_scale(config.ui_scale_percent as f64 / 100.0);
+    let mut modifiers = ModifierKey::NO_MODIFIER;
+    let mut recorded = false;
+    let mut last_frame = Instant::now();
+    loop {
+        view.settings.layout(renderer.window_size());
+        for event in events.poll_iter() {
+            match event {
+                SdlEvent::Quit { .. }
+                | SdlEvent::KeyDown {
+                    keycode: Some(Keycode::ESCAPE),
+                    ..
+                } => return Ok(()),
+                SdlEvent::KeyDown {
+                    keycode: Some(Keycode::RETURN),
+                    ..
+                } if controller.is_over() => {
+                    let difficulty = controller.gameboard.difficulty;
+                    let size = controller.gameboard.size();
+                    controller = new_game(difficulty, size, database, config);
+                    recorded = false;
+                    drawn(
+                        renderer
+                            .canvas
+                            .window_mut()
+                            .set_title("Sudoku")
+                            .map_err(|err| err.to_string()),
+                    );
+                    continue;
+                }
+                _ => {}
+            }
+            if let Some(e) = piston_event(&event) {
+                modifiers.event(&e);
+                view.event(&e);
+                let actions = controller.event(
+                    view.settings.position,
+                    view.settings.size,
+                    view.settings.zoom,
+                    view.settings.pan,
+                    modifiers,
+                    config,
+                    &e,
+                );
+                for action in actions {
+                    controller.dispatch(action);
+                }
+            }
+        }
+
+        let board = &controller.gameboard;
+        for house in &controller.completed_houses {
+            view.flash(&house.cells(board.size(), board.rules.layout()));
+        }
+        controller.completed_houses.clear();
+        for cell in controller.clashing_entries.drain(..) {
+            if config.auto_check {
+                view.shake(cell);
+            }
+        }
+        if controller.is_over() && !recorded {
+            record_result(database, &mut controller);
+            recorded = true;
+            let title = if controller.gameboard.is_solved() {
+                view.celebrate();
+                "Sudoku - Solved! Enter for a new game"
+            } else {
+                "Sudoku - Game over. Enter for a new game"
+            };
+            drawn(
+                renderer
+                    .canvas
+                    .window_mut()
+                    .set_title(title)
+                    .map_err(|err| err.to_string()),
+            );
+        }
+
+        let now = Instant::now();
+        view.update(now.duration_since(last_frame).as_secs_f64());
+        last_frame = now;
+        renderer.canvas.set_draw_color(sdl_color(theme.background));
+        renderer.canvas.clear();
+        view.draw(&controller, config, &mut renderer);
+        renderer.canvas.present();
+    }
+}
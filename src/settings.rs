@@ -0,0 +1,441 @@
+use crate::config::{Config, InputMode, EXPORT_SIZES, UI_SCALES, VOLUMES};
+use crate::font::font_name;
+use crate::script::Script;
+use crate::text::draw_text;
+use crate::theme::Theme;
+use crate::variant::VariantKind;
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{Context, Graphics};
+use piston::input::GenericEvent;
+
+/// Values offered by settings that depend on files found at startup.
+pub struct Choices<'a> {
+    pub themes: &'a [Theme],
+    /// Font file paths.
+    pub fonts: &'a [String],
+    /// Scripts in the scripts directory.
+    pub scripts: &'a [Script],
+}
+
+/// An option shown on the settings screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingsItem {
+    Theme,
+    HighContrast,
+    UiScale,
+    Font,
+    HighlightSameDigit,
+    HighlightHouses,
+    AutoCheck,
+    InputMode,
+    StrictDigits,
+    ShowTimer,
+    Sound,
+    Volume,
+    GhostReplay,
+    Variant,
+    AntiKnight,
+    AntiKing,
+    NonConsecutive,
+    RuleScript,
+    ToolScript,
+    CoordinateLabels,
+    MistakeLimit,
+    ContinueLastGame,
+    ExportSize,
+    DiscordPresence,
+}
+
+/// Settings screen rows, in display order.
+pub const ITEMS: [SettingsItem; 24] = [
+    SettingsItem::Theme,
+    SettingsItem::HighContrast,
+    SettingsItem::UiScale,
+    SettingsItem::Font,
+    SettingsItem::HighlightSameDigit,
+    SettingsItem::HighlightHouses,
+    SettingsItem::AutoCheck,
+    SettingsItem::InputMode,
+    SettingsItem::StrictDigits,
+    SettingsItem::ShowTimer,
+    SettingsItem::Sound,
+    SettingsItem::Volume,
+    SettingsItem::GhostReplay,
+    SettingsItem::Variant,
+    SettingsItem::AntiKnight,
+    SettingsItem::AntiKing,
+    SettingsItem::NonConsecutive,
+    SettingsItem::RuleScript,
+    SettingsItem::ToolScript,
+    SettingsItem::CoordinateLabels,
+    SettingsItem::MistakeLimit,
+    SettingsItem::ContinueLastGame,
+    SettingsItem::ExportSize,
+    SettingsItem::DiscordPresence,
+];
+
+/// The script after the one chosen among those of a kind, cycling through
+/// none and then each of them.
+fn next_script(
+    chosen: &Option<String>,
+    choices: &Choices,
+    kind: fn(&Script) -> bool,
+) -> Option<String> {
+    let names: Vec<&String> = choices
+        .scripts
+        .iter()
+        .filter(|script| kind(script))
+        .map(|script| &script.name)
+        .collect();
+    let next = match chosen {
+        None => 0,
+        Some(name) => names
+            .iter()
+            .position(|&each| each == name)
+            .map_or(0, |k| k + 1),
+    };
+    names.get(next).map(|name| name.to_string())
+}
+
+fn on_off(value: bool) -> String {
+    if value { "On" } else { "Off" }.to_string()
+}
+
+impl SettingsItem {
+    pub fn label(self) -> &'static str {
+        match self {
+            SettingsItem::Theme => "Theme",
+            SettingsItem::HighContrast => "High contrast",
+            SettingsItem::UiScale => "UI scale",
+            SettingsItem::Font => "Font",
+            SettingsItem::HighlightSameDigit => "Highlight same digit",
+            SettingsItem::HighlightHouses => "Highlight row/column/box",
+            SettingsItem::AutoCheck => "Auto-check",
+            SettingsItem::InputMode => "Input mode",
+            SettingsItem::StrictDigits => "Block finished digits",
+            SettingsItem::ShowTimer => "Show timer",
+            SettingsItem::Sound => "Sound",
+            SettingsItem::Volume => "Volume",
+            SettingsItem::GhostReplay => "Race last attempt",
+            SettingsItem::Variant => "Variant",
+            SettingsItem::AntiKnight => "Anti-knight",
+            SettingsItem::AntiKing => "Anti-king",
+            SettingsItem::NonConsecutive => "Non-consecutive",
+            SettingsItem::RuleScript => "Scripted rule",
+            SettingsItem::ToolScript => "Script tool (Ctrl+R)",
+            SettingsItem::CoordinateLabels => "Coordinate labels",
+            SettingsItem::MistakeLimit => "Mistake limit",
+            SettingsItem::ContinueLastGame => "Continue last game",
+            SettingsItem::ExportSize => "Board image size",
+            SettingsItem::DiscordPresence => "Status on Discord",
+        }
+    }
+
+    pub fn value(self, config: &Config) -> String {
+        match self {
+            SettingsItem::Theme => config.theme.clone(),
+            SettingsItem::HighContrast => on_off(config.high_contrast),
+            SettingsItem::UiScale => format!("{}%", config.ui_scale_percent),
+            SettingsItem::Font => config
+                .font
+                .as_deref()
+                .map_or_else(|| "Default".to_string(), font_name),
+            SettingsItem::HighlightSameDigit => on_off(config.highlight_same_digit),
+            SettingsItem::HighlightHouses => on_off(config.highlight_houses),
+            SettingsItem::AutoCheck => on_off(config.auto_check),
+            SettingsItem::InputMode => config.input_mode.name().to_string(),
+            SettingsItem::StrictDigits => on_off(config.strict_digits),
+            SettingsItem::ShowTimer => on_off(config.show_timer),
+            SettingsItem::Sound => on_off(config.sound),
+            SettingsItem::Volume => format!("{}%", config.volume_percent),
+            SettingsItem::GhostReplay => on_off(config.ghost_replay),
+            SettingsItem::Variant => config.variant.name().to_string(),
+            SettingsItem::AntiKnight => on_off(config.constraints.anti_knight),
+            SettingsItem::AntiKing => on_off(config.constraints.anti_king),
+            SettingsItem::NonConsecutive => on_off(config.constraints.non_consecutive),
+            SettingsItem::RuleScript => config.rule_script.as_deref().unwrap_or("Off").to_string(),
+            SettingsItem::ToolScript => config.tool_script.as_deref().unwrap_or("Off").to_string(),
+            SettingsItem::CoordinateLabels => on_off(config.coordinate_labels),
+            SettingsItem::MistakeLimit => match config.mistake_limit {
+                Some(limit) => limit.to_string(),
+                None => "Off".to_string(),
+            },
+            SettingsItem::ContinueLastGame => if config.continue_last_game {
+                "Always"
+            } else {
+                "Ask"
+            }
+            .to_string(),
+            SettingsItem::ExportSize => format!("{} px", config.export_size),
+            SettingsItem::DiscordPresence => on_off(config.discord_presence),
+        }
+    }
+
+    /// Advances the option to its next value.
+    pub fn toggle(self, config: &mut Config, choices: &Choices) {
+        match self {
+            SettingsItem::Theme => {
+                let themes = choices.themes;
+                let current = themes.iter().position(|theme| theme.name == config.theme);
+                let next = current.map_or(0, |k| (k + 1) % themes.len());
+                config.theme = themes[next].name.clone();
+            }
+            SettingsItem::Font => {
+                // Cycles through the default font and then each available font.
+                let fonts = choices.fonts;
+                let next = match &config.font {
+                    None => 0,
+                    Some(font) => fonts.iter().position(|f| f == font).map_or(0, |k| k + 1),
+                };
+                config.font = fonts.get(next).cloned();
+            }
+            SettingsItem::HighContrast => config.high_contrast = !config.high_contrast,
+            SettingsItem::UiScale => {
+                let current = UI_SCALES
+                    .iter()
+                    .position(|&scale| scale == config.ui_scale_percent);
+                config.ui_scale_percent =
+                    UI_SCALES[current.map_or(1, |k| (k + 1) % UI_SCALES.len())];
+            }
+            SettingsItem::HighlightSameDigit => {
+                config.highlight_same_digit = !config.highlight_same_digit
+            }
+            SettingsItem::HighlightHouses => config.highlight_houses = !config.highlight_houses,
+            SettingsItem::AutoCheck => config.auto_check = !config.auto_check,
+            SettingsItem::InputMode => {
+                config.input_mode = match config.input_mode {
+                    InputMode::CellFirst => InputMode::DigitFirst,
+                    InputMode::DigitFirst => InputMode::CellFirst,
+                }
+            }
+            SettingsItem::StrictDigits => config.strict_digits = !config.strict_digits,
+            SettingsItem::ShowTimer => config.show_timer = !config.show_timer,
+            SettingsItem::Sound => config.sound = !config.sound,
+            SettingsItem::Volume => {
+                let current = VOLUMES
+                    .iter()
+                    .position(|&volume| volume == config.volume_percent);
+                config.volume_percent = VOLUMES[current.map_or(1, |k| (k + 1) % VOLUMES.len())];
+            }
+            SettingsItem::GhostReplay => config.ghost_replay = !config.ghost_replay,
+            SettingsItem::Variant => {
+                let kinds = VariantKind::ALL;
+                let current = kinds.iter().position(|&kind| kind == config.variant);
+                config.variant = kinds[current.map_or(0, |k| (k + 1) % kinds.len())];
+            }
+            SettingsItem::AntiKnight => {
+                config.constraints.anti_knight = !config.constraints.anti_knight
+            }
+            SettingsItem::AntiKing => config.constraints.anti_king = !config.constraints.anti_king,
+            SettingsItem::NonConsecutive => {
+                config.constraints.non_consecutive = !config.constraints.non_consecutive
+            }
+            SettingsItem::RuleScript => {
+                config.rule_script = next_script(&config.rule_script, choices, Script::is_rule)
+            }
+            SettingsItem::ToolScript => {
+                config.tool_script = next_script(&config.tool_script, choices, Script::is_tool)
+            }
+            SettingsItem::CoordinateLabels => config.coordinate_labels = !config.coordinate_labels,
+            SettingsItem::MistakeLimit => {
+                config.mistake_limit = match config.mistake_limit {
+                    None => Some(3),
+                    Some(_) => None,
+                }
+            }
+            SettingsItem::ContinueLastGame => {
+                config.continue_last_game = !config.continue_last_game
+            }
+            SettingsItem::ExportSize => {
+                let current = EXPORT_SIZES
+                    .iter()
+                    .position(|&size| size == config.export_size);
+                config.export_size =
+                    EXPORT_SIZES[current.map_or(1, |k| (k + 1) % EXPORT_SIZES.len())];
+            }
+            SettingsItem::DiscordPresence => config.discord_presence = !config.discord_presence,
+        }
+    }
+}
+
+pub struct SettingsController {
+    pub selected: usize,
+    cursor_pos: [f64; 2],
+}
+
+impl Default for SettingsController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SettingsController {
+    pub fn new() -> SettingsController {
+        SettingsController {
+            selected: 0,
+            cursor_pos: [0.0; 2],
+        }
+    }
+
+    /// Handles events, returning true when the config was changed.
+    pub fn event<E: GenericEvent>(
+        &mut self,
+        pos: [f64; 2],
+        size: [f64; 2],
+        row_height: f64,
+        config: &mut Config,
+        choices: &Choices,
+        e: &E,
+    ) -> bool {
+        use piston::input::{Button, Key, MouseButton};
+
+        if let Some(pos) = e.mouse_cursor_args() {
+            self.cursor_pos = pos;
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
+            let x = self.cursor_pos[0] - pos[0];
+            // The first row holds the title.
+            let y = self.cursor_pos[1] - pos[1] - row_height;
+            if x >= 0.0 && x <= size[0] && y >= 0.0 && y <= size[1] - row_height {
+                let row = (y / row_height) as usize;
+                if row < ITEMS.len() {
+                    self.selected = row;
+                    ITEMS[row].toggle(config, choices);
+                    return true;
+                }
+            }
+        }
+        if let Some(Button::Keyboard(key)) = e.press_args() {
+            match key {
+                Key::Up => self.selected = (self.selected + ITEMS.len() - 1) % ITEMS.len(),
+                Key::Down => self.selected = (self.selected + 1) % ITEMS.len(),
+                Key::Return | Key::Space | Key::Left | Key::Right => {
+                    ITEMS[self.selected].toggle(config, choices);
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+}
+
+pub struct SettingsViewSettings {
+    pub position: [f64; 2],
+    pub size: [f64; 2],
+    pub row_height: f64,
+    pub font_size: u32,
+    pub ui_scale: f64,
+    pub background_color: Color,
+    pub selected_background_color: Color,
+    pub text_color: Color,
+}
+
+impl SettingsViewSettings {
+    pub fn new(theme: &Theme) -> SettingsViewSettings {
+        SettingsViewSettings {
+            position: [10.0; 2],
+            size: [400.0; 2],
+            row_height: 40.0,
+            font_size: 20,
+            ui_scale: 1.0,
+            background_color: theme.panel_background,
+            selected_background_color: theme.panel_selected_background,
+            text_color: theme.text,
+        }
+    }
+
+    /// Scales rows and text.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.ui_scale = scale;
+        self.layout(self.position, self.size);
+    }
+
+    /// Places the screen, shrinking rows so every option fits.
+    pub fn layout(&mut self, position: [f64; 2], size: [f64; 2]) {
+        self.position = position;
+        self.size = size;
+        self.row_height = (40.0 * self.ui_scale).min(size[1] / (ITEMS.len() + 1) as f64);
+        self.font_size = (self.row_height / 2.0).round() as u32;
+    }
+
+    /// Switches to a theme's colors, keeping the layout.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.background_color = theme.panel_background;
+        self.selected_background_color = theme.panel_selected_background;
+        self.text_color = theme.text;
+    }
+}
+
+pub struct SettingsView {
+    pub settings: SettingsViewSettings,
+}
+
+impl SettingsView {
+    pub fn new(settings: SettingsViewSettings) -> SettingsView {
+        SettingsView { settings }
+    }
+
+    pub fn draw<G: Graphics, C>(
+        &self,
+        controller: &SettingsController,
+        config: &Config,
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::Rectangle;
+
+        let settings = &self.settings;
+        let [x, y] = settings.position;
+        Rectangle::new(settings.background_color).draw(
+            [x, y, settings.size[0], settings.size[1]],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+
+        let baseline = (settings.row_height + settings.font_size as f64) / 2.0 - 2.0;
+        draw_text(
+            "Settings (F2 to close)",
+            settings.font_size,
+            settings.text_color,
+            [x + 10.0, y + baseline],
+            glyphs,
+            c,
+            g,
+        );
+        for (row, item) in ITEMS.iter().enumerate() {
+            let row_y = y + (row + 1) as f64 * settings.row_height;
+            if row == controller.selected {
+                Rectangle::new(settings.selected_background_color).draw(
+                    [x, row_y, settings.size[0], settings.row_height],
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+            }
+            draw_text(
+                item.label(),
+                settings.font_size,
+                settings.text_color,
+                [x + 10.0, row_y + baseline],
+                glyphs,
+                c,
+                g,
+            );
+            draw_text(
+                &item.value(config),
+                settings.font_size,
+                settings.text_color,
+                [x + settings.size[0] * 0.7, row_y + baseline],
+                glyphs,
+                c,
+                g,
+            );
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use crate::qr::QrCode;
+use crate::text::{draw_text, text_width};
+use crate::theme::Theme;
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{Context, Graphics};
+
+/// Light modules kept around a code so scanners can find its edge.
+const QUIET_ZONE: usize = 4;
+
+/// A puzzle line and the QR code holding it.
+pub struct Shared {
+    pub code: QrCode,
+    pub text: String,
+}
+
+impl Shared {
+    pub fn new(text: String) -> Result<Shared, String> {
+        Ok(Shared {
+            code: QrCode::encode(&text)?,
+            text,
+        })
+    }
+}
+
+pub struct ShareViewSettings {
+    pub font_size: u32,
+    pub background_color: Color,
+    pub text_color: Color,
+    /// Codes are always drawn dark on light, which every scanner reads.
+    pub light_color: Color,
+    pub dark_color: Color,
+}
+
+impl ShareViewSettings {
+    pub fn new(theme: &Theme) -> ShareViewSettings {
+        ShareViewSettings {
+            font_size: 16,
+            background_color: theme.panel_background,
+            text_color: theme.text,
+            light_color: [1.0; 4],
+            dark_color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+
+    /// Scales text.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.font_size = (16.0 * scale).round() as u32;
+    }
+
+    /// Switches to a theme's colors.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        *self = ShareViewSettings {
+            font_size: self.font_size,
+            ..ShareViewSettings::new(theme)
+        };
+    }
+}
+
+pub struct ShareView {
+    pub settings: ShareViewSettings,
+}
+
+impl ShareView {
+    pub fn new(settings: ShareViewSettings) -> ShareView {
+        ShareView { settings }
+    }
+
+    /// Draws a QR code of a puzzle, with the text it holds below, over the
+    /// area at `pos` with side length `size`.
+    pub fn draw<G: Graphics, C>(
+        &self,
+        shared: &Shared,
+        pos: [f64; 2],
+        size: f64,
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::Rectangle;
+
+        let settings = &self.settings;
+        let (code, text) = (&shared.code, &shared.text);
+        Rectangle::new(settings.background_color).draw(
+            [pos[0], pos[1], size, size],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+
+        // Whole pixels per module keep the edges sharp for cameras.
+        let line_height = settings.font_size as f64 * 1.4;
+        let side = size - 4.0 * line_height;
+        let modules = code.size() + 2 * QUIET_ZONE;
+        let module = (side / modules as f64).floor().max(1.0);
+        let side = module * modules as f64;
+        let left = (pos[0] + (size - side) / 2.0).round();
+        let top = (pos[1] + line_height).round();
+        Rectangle::new(settings.light_color).draw(
+            [left, top, side, side],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+        for y in 0..code.size() {
+            for x in 0..code.size() {
+                if !code.is_dark(x, y) {
+                    continue;
+                }
+                Rectangle::new(settings.dark_color).draw(
+                    [
+                        left + (x + QUIET_ZONE) as f64 * module,
+                        top + (y + QUIET_ZONE) as f64 * module,
+                        module,
+                        module,
+                    ],
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+            }
+        }
+
+        // The 81 characters are split across two lines to fit.
+        let split = text.len().div_ceil(2);
+        let lines = [&text[..split], &text[split..], "Press any key to go back"];
+        for (row, line) in lines.iter().enumerate() {
+            let width = text_width(line, settings.font_size, glyphs, c);
+            draw_text(
+                line,
+                settings.font_size,
+                settings.text_color,
+                [
+                    pos[0] + (size - width) / 2.0,
+                    top + side + (row as f64 + 0.8) * line_height,
+                ],
+                glyphs,
+                c,
+                g,
+            );
+        }
+    }
+}
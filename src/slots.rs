@@ -0,0 +1,329 @@
+use crate::format_duration;
+use crate::save::{is_name_char, Slot};
+use crate::text::draw_text;
+use crate::theme::Theme;
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{Context, Graphics};
+use piston::input::GenericEvent;
+use std::time::Duration;
+
+/// What the slot picker asks for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SlotAction {
+    /// Resume the game in a slot.
+    Load(usize),
+    /// Save the current game under a name.
+    Save(String),
+    Delete(usize),
+}
+
+/// Longest slot name that can be typed.
+const MAX_NAME_LEN: usize = 24;
+
+/// Lists saved games. The first row starts a new slot and the rest are the
+/// slots in order.
+pub struct SlotsController {
+    pub selected: usize,
+    /// Name being typed for a new slot.
+    pub editing: Option<String>,
+    cursor_pos: [f64; 2],
+}
+
+impl Default for SlotsController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SlotsController {
+    pub fn new() -> SlotsController {
+        SlotsController {
+            selected: 0,
+            editing: None,
+            cursor_pos: [0.0; 2],
+        }
+    }
+
+    /// Picks a name for a new slot that is not taken yet.
+    fn new_name(slots: &[Slot]) -> String {
+        (1..)
+            .map(|k| format!("Game {}", k))
+            .find(|name| slots.iter().all(|slot| &slot.name != name))
+            .unwrap_or_default()
+    }
+
+    /// Handles events, returning the chosen action.
+    ///
+    /// Return on the first row starts naming a new slot, and Return again
+    /// saves to it. On a slot, Return loads it, S saves over it, and Delete
+    /// removes it.
+    pub fn event<E: GenericEvent>(
+        &mut self,
+        pos: [f64; 2],
+        size: [f64; 2],
+        row_height: f64,
+        slots: &[Slot],
+        e: &E,
+    ) -> Option<SlotAction> {
+        use piston::input::{Button, Key, MouseButton};
+
+        let rows = slots.len() + 1;
+        self.selected = self.selected.min(rows - 1);
+        if let Some(pos) = e.mouse_cursor_args() {
+            self.cursor_pos = pos;
+        }
+
+        if let Some(name) = &mut self.editing {
+            if let Some(text) = e.text_args() {
+                name.extend(text.chars().filter(|&ch| is_name_char(ch)));
+                name.truncate(MAX_NAME_LEN);
+            }
+            match e.press_args() {
+                Some(Button::Keyboard(Key::Backspace)) => {
+                    name.pop();
+                }
+                Some(Button::Keyboard(Key::Return)) => {
+                    let name = self.editing.take().unwrap_or_default();
+                    let name = name.trim();
+                    if !name.is_empty() {
+                        return Some(SlotAction::Save(name.to_string()));
+                    }
+                }
+                _ => {}
+            }
+            return None;
+        }
+
+        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
+            let x = self.cursor_pos[0] - pos[0];
+            // The first row holds the title.
+            let y = self.cursor_pos[1] - pos[1] - row_height;
+            if x >= 0.0 && x <= size[0] && y >= 0.0 && y <= size[1] - row_height {
+                let row = (y / row_height) as usize;
+                if row < rows {
+                    self.selected = row;
+                    return self.choose(slots);
+                }
+            }
+        }
+        if let Some(Button::Keyboard(key)) = e.press_args() {
+            match key {
+                Key::Up => self.selected = (self.selected + rows - 1) % rows,
+                Key::Down => self.selected = (self.selected + 1) % rows,
+                Key::Return => return self.choose(slots),
+                Key::S if self.selected > 0 => {
+                    return Some(SlotAction::Save(slots[self.selected - 1].name.clone()))
+                }
+                Key::Delete if self.selected > 0 => {
+                    return Some(SlotAction::Delete(self.selected - 1))
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Acts on the selected row.
+    fn choose(&mut self, slots: &[Slot]) -> Option<SlotAction> {
+        if self.selected == 0 {
+            self.editing = Some(Self::new_name(slots));
+            None
+        } else {
+            Some(SlotAction::Load(self.selected - 1))
+        }
+    }
+}
+
+pub struct SlotsViewSettings {
+    pub position: [f64; 2],
+    pub size: [f64; 2],
+    pub row_height: f64,
+    pub font_size: u32,
+    pub ui_scale: f64,
+    pub background_color: Color,
+    pub selected_background_color: Color,
+    pub text_color: Color,
+    /// Givens in slot thumbnails.
+    pub given_color: Color,
+    /// Player entries in slot thumbnails.
+    pub entry_color: Color,
+}
+
+impl SlotsViewSettings {
+    pub fn new(theme: &Theme) -> SlotsViewSettings {
+        SlotsViewSettings {
+            position: [10.0; 2],
+            size: [400.0; 2],
+            row_height: 40.0,
+            font_size: 20,
+            ui_scale: 1.0,
+            background_color: theme.panel_background,
+            selected_background_color: theme.panel_selected_background,
+            text_color: theme.text,
+            given_color: theme.text,
+            entry_color: theme.entry_text,
+        }
+    }
+
+    /// Scales rows and text.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.ui_scale = scale;
+        self.layout(self.position, self.size);
+    }
+
+    pub fn layout(&mut self, position: [f64; 2], size: [f64; 2]) {
+        self.position = position;
+        self.size = size;
+        self.row_height = 40.0 * self.ui_scale;
+        self.font_size = (self.row_height / 2.0).round() as u32;
+    }
+
+    /// Switches to a theme's colors, keeping the layout.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.background_color = theme.panel_background;
+        self.selected_background_color = theme.panel_selected_background;
+        self.text_color = theme.text;
+        self.given_color = theme.text;
+        self.entry_color = theme.entry_text;
+    }
+}
+
+pub struct SlotsView {
+    pub settings: SlotsViewSettings,
+}
+
+impl SlotsView {
+    pub fn new(settings: SlotsViewSettings) -> SlotsView {
+        SlotsView { settings }
+    }
+
+    pub fn draw<G: Graphics, C>(
+        &self,
+        controller: &SlotsController,
+        slots: &[Slot],
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::Rectangle;
+
+        let settings = &self.settings;
+        let [x, y] = settings.position;
+        Rectangle::new(settings.background_color).draw(
+            [x, y, settings.size[0], settings.size[1]],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+
+        let baseline = (settings.row_height + settings.font_size as f64) / 2.0 - 2.0;
+        draw_text(
+            "Saved games (F5 to close)",
+            settings.font_size,
+            settings.text_color,
+            [x + 10.0, y + baseline],
+            glyphs,
+            c,
+            g,
+        );
+        let new_row = match &controller.editing {
+            Some(name) => format!("Name: {}_", name),
+            None => "Save current game...".to_string(),
+        };
+        let rows = (settings.size[1] / settings.row_height) as usize;
+        for row in 0..(slots.len() + 1).min(rows.saturating_sub(1)) {
+            let row_y = y + (row + 1) as f64 * settings.row_height;
+            if row == controller.selected {
+                Rectangle::new(settings.selected_background_color).draw(
+                    [x, row_y, settings.size[0], settings.row_height],
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+            }
+            if row == 0 {
+                draw_text(
+                    &new_row,
+                    settings.font_size,
+                    settings.text_color,
+                    [x + 10.0, row_y + baseline],
+                    glyphs,
+                    c,
+                    g,
+                );
+                continue;
+            }
+
+            let slot = &slots[row - 1];
+            let thumb = settings.row_height - 8.0 * settings.ui_scale;
+            self.draw_thumbnail(
+                slot,
+                [x + 10.0, row_y + 4.0 * settings.ui_scale],
+                thumb,
+                c,
+                g,
+            );
+            let details = format!(
+                "{} - {} - {}",
+                slot.name,
+                slot.game.difficulty.name(),
+                format_duration(Duration::from_secs(slot.game.elapsed))
+            );
+            draw_text(
+                &details,
+                settings.font_size,
+                settings.text_color,
+                [x + 20.0 + thumb, row_y + baseline],
+                glyphs,
+                c,
+                g,
+            );
+        }
+    }
+
+    /// Draws a slot's board in miniature, a dot per filled cell.
+    fn draw_thumbnail<G: Graphics>(
+        &self,
+        slot: &Slot,
+        pos: [f64; 2],
+        size: f64,
+        c: &Context,
+        g: &mut G,
+    ) {
+        use graphics::Rectangle;
+
+        let settings = &self.settings;
+        Rectangle::new_border(settings.text_color, 0.5).draw(
+            [pos[0], pos[1], size, size],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+        let cells = &slot.game.cells;
+        let cell = size / cells.size() as f64;
+        for [i, j] in cells.positions() {
+            if cells[j][i] == 0 {
+                continue;
+            }
+            let color = if slot.game.givens[j][i] {
+                settings.given_color
+            } else {
+                settings.entry_color
+            };
+            Rectangle::new(color).draw(
+                [
+                    pos[0] + (i as f64 + 0.2) * cell,
+                    pos[1] + (j as f64 + 0.2) * cell,
+                    cell * 0.6,
+                    cell * 0.6,
+                ],
+                &c.draw_state,
+                c.transform,
+                g,
+            );
+        }
+    }
+}
@@ -0,0 +1,608 @@
+use crate::grid::{digit_char, Grid};
+use crate::rules::RuleSet;
+use crate::variant::{digit_mask, Variant};
+use crate::House;
+use rand::Rng;
+use tracing::{debug, instrument};
+
+/// The rules of a puzzle, with the houses of each cell worked out once for
+/// the whole search.
+pub(crate) struct Rules<'a> {
+    pub(crate) rule_set: &'a RuleSet,
+    pub(crate) size: usize,
+    pub(crate) digits: usize,
+    /// Which house each is, for explaining deductions.
+    pub(crate) ids: Vec<House>,
+    /// Cells of each house, by index in reading order.
+    pub(crate) houses: Vec<Vec<usize>>,
+    /// Houses each cell is in, by cell in reading order. Cells in no house
+    /// are not played.
+    pub(crate) cell_houses: Vec<Vec<usize>>,
+}
+
+/// A grid being solved, with a bit mask of the digits placed in each house
+/// kept up to date as cells are filled and cleared.
+pub(crate) struct Board {
+    pub(crate) cells: Grid,
+    pub(crate) used: Vec<u32>,
+}
+
+/// What the search should do next.
+enum Step {
+    Solved,
+    /// Some cell or digit has nowhere left to go.
+    Stuck,
+    /// Guess each digit of a mask in a cell.
+    Try(usize, u32),
+}
+
+impl<'a> Rules<'a> {
+    pub(crate) fn new(size: usize, rule_set: &'a RuleSet) -> Rules<'a> {
+        let variant = rule_set.layout();
+        let mut found: Vec<House> = Vec::new();
+        let cell_houses = (0..size * size)
+            .map(|k| {
+                let ind = [k % size, k / size];
+                House::containing(ind, size, variant)
+                    .into_iter()
+                    .chain(rule_set.extra_houses(size, ind))
+                    .map(|house| {
+                        found.iter().position(|&h| h == house).unwrap_or_else(|| {
+                            found.push(house);
+                            found.len() - 1
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+        let houses = found
+            .iter()
+            .map(|house| {
+                house
+                    .cells(size, variant)
+                    .into_iter()
+                    .map(|[i, j]| j * size + i)
+                    .collect()
+            })
+            .collect();
+        Rules {
+            rule_set,
+            size,
+            digits: variant.digits(size),
+            ids: found,
+            houses,
+            cell_houses,
+        }
+    }
+
+    /// Sets up a board from a grid, or `None` if a digit appears twice in a
+    /// house or breaks another rule.
+    pub(crate) fn board(&self, cells: &Grid) -> Option<Board> {
+        let mut board = Board {
+            cells: Grid::new(self.size),
+            used: vec![0; self.houses.len()],
+        };
+        for [x, y] in cells.positions() {
+            let val = cells[y][x];
+            if val == 0 {
+                continue;
+            }
+            let k = y * self.size + x;
+            if self.candidates(&board, k) & (1 << val) == 0 {
+                return None;
+            }
+            self.place(&mut board, k, val);
+        }
+        // Rules such as cage sums only hold once every given is in.
+        for [x, y] in cells.positions() {
+            if self.rule_set.breaks(&board.cells, [x, y]) {
+                return None;
+            }
+        }
+        Some(board)
+    }
+
+    pub(crate) fn place(&self, board: &mut Board, k: usize, val: u8) {
+        board.cells[k / self.size][k % self.size] = val;
+        for &h in &self.cell_houses[k] {
+            board.used[h] |= 1 << val;
+        }
+    }
+
+    fn clear(&self, board: &mut Board, k: usize, val: u8) {
+        board.cells[k / self.size][k % self.size] = 0;
+        for &h in &self.cell_houses[k] {
+            board.used[h] &= !(1 << val);
+        }
+    }
+
+    /// Bit mask of the digits still allowed in a cell.
+    pub(crate) fn candidates(&self, board: &Board, k: usize) -> u32 {
+        let used = self.cell_houses[k]
+            .iter()
+            .fold(0, |used, &h| used | board.used[h]);
+        let ind = [k % self.size, k / self.size];
+        !used & self.rule_set.allowed(&board.cells, ind)
+    }
+
+    /// Names of the rules beyond its row, column, and box that keep any
+    /// digit of a mask out of a cell, such as X-Sudoku or a cage's sum.
+    pub(crate) fn ruled_out_by(&self, board: &Board, k: usize, mask: u32) -> Vec<&'static str> {
+        let basic = self.cell_houses[k]
+            .iter()
+            .filter(|&&h| self.ids[h].variant().is_none())
+            .fold(0, |used, &h| used | board.used[h]);
+        let mask = mask & !basic;
+        let houses = self.cell_houses[k].iter().filter_map(|&h| {
+            let kind = self.ids[h].variant()?;
+            Some(kind.name()).filter(|_| board.used[h] & mask != 0)
+        });
+        let ind = [k % self.size, k / self.size];
+        let mut names = Vec::new();
+        for name in houses.chain(self.rule_set.ruling_out(&board.cells, ind, mask)) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// Places a digit if a cell can still take it, recording the move.
+    fn force(&self, board: &mut Board, k: usize, bit: u32, placed: &mut Vec<usize>) -> bool {
+        if self.candidates(board, k) & bit == 0 {
+            return false;
+        }
+        self.place(board, k, bit.trailing_zeros() as u8);
+        placed.push(k);
+        true
+    }
+
+    /// Fills every cell with one candidate and every digit with one place
+    /// left in a house, until none are left, then picks the empty cell with
+    /// the fewest candidates to guess at.
+    ///
+    /// The cells filled are added to `placed`, to be cleared afterwards.
+    fn propagate(&self, board: &mut Board, placed: &mut Vec<usize>) -> Step {
+        let all = digit_mask(self.digits);
+        let mut masks = vec![0; self.size * self.size];
+        loop {
+            let mut best: Option<(usize, u32)> = None;
+            let mut progress = false;
+            for (k, mask) in masks.iter_mut().enumerate() {
+                *mask = 0;
+                if board.cells[k / self.size][k % self.size] != 0 || self.cell_houses[k].is_empty()
+                {
+                    continue;
+                }
+                let candidates = self.candidates(board, k);
+                match candidates.count_ones() {
+                    0 => return Step::Stuck,
+                    1 => {
+                        self.place(board, k, candidates.trailing_zeros() as u8);
+                        placed.push(k);
+                        progress = true;
+                    }
+                    n => {
+                        *mask = candidates;
+                        if best.is_none_or(|(_, m)| n < m.count_ones()) {
+                            best = Some((k, candidates));
+                        }
+                    }
+                }
+            }
+            if progress {
+                continue;
+            }
+            let best = match best {
+                Some(best) => best,
+                None => return Step::Solved,
+            };
+            for (h, house) in self.houses.iter().enumerate() {
+                let (mut once, mut twice) = (0u32, 0u32);
+                for &k in house {
+                    twice |= once & masks[k];
+                    once |= masks[k];
+                }
+                if all & !board.used[h] & !once != 0 {
+                    return Step::Stuck;
+                }
+                let mut single = once & !twice;
+                while single != 0 {
+                    let bit = single & single.wrapping_neg();
+                    single &= !bit;
+                    if board.used[h] & bit != 0 {
+                        continue;
+                    }
+                    let k = house.iter().copied().find(|&k| masks[k] & bit != 0);
+                    match k {
+                        Some(k) if self.force(board, k, bit, placed) => {
+                            masks[k] = 0;
+                            progress = true;
+                        }
+                        _ => return Step::Stuck,
+                    }
+                }
+            }
+            if !progress {
+                return Step::Try(best.0, best.1);
+            }
+        }
+    }
+
+    /// Clears the cells filled by `propagate`.
+    fn undo(&self, board: &mut Board, placed: Vec<usize>) {
+        for k in placed.into_iter().rev() {
+            let val = board.cells[k / self.size][k % self.size];
+            self.clear(board, k, val);
+        }
+    }
+
+    /// Counts solutions up to `limit`, keeping the first one found, and
+    /// stops early once `budget` guesses have been tried. Forced moves are
+    /// not guesses.
+    fn search(
+        &self,
+        board: &mut Board,
+        limit: usize,
+        budget: &mut usize,
+        found: &mut usize,
+        first: &mut Option<Grid>,
+    ) {
+        let mut placed = Vec::new();
+        match self.propagate(board, &mut placed) {
+            Step::Solved => {
+                *found += 1;
+                first.get_or_insert_with(|| board.cells.clone());
+            }
+            Step::Stuck => {}
+            Step::Try(k, mask) => {
+                for val in 1..=self.digits as u8 {
+                    if mask & (1 << val) == 0 {
+                        continue;
+                    }
+                    if *budget == 0 || *found >= limit {
+                        break;
+                    }
+                    *budget -= 1;
+                    self.place(board, k, val);
+                    self.search(board, limit, budget, found, first);
+                    self.clear(board, k, val);
+                }
+            }
+        }
+        self.undo(board, placed);
+    }
+
+    /// Fills the empty cells with the first solution found trying digits in
+    /// a random order, giving up once `budget` guesses have been tried.
+    fn fill<R: Rng>(&self, board: &mut Board, rng: &mut R, budget: &mut usize) -> bool {
+        let mut placed = Vec::new();
+        let (k, mask) = match self.propagate(board, &mut placed) {
+            Step::Solved => return true,
+            Step::Stuck => {
+                self.undo(board, placed);
+                return false;
+            }
+            Step::Try(k, mask) => (k, mask),
+        };
+        let mut vals: Vec<u8> = (1..=self.digits as u8)
+            .filter(|val| mask & (1 << val) != 0)
+            .collect();
+        for k in (1..vals.len()).rev() {
+            vals.swap(k, rng.gen_range(0, k + 1));
+        }
+        for val in vals {
+            if *budget == 0 {
+                break;
+            }
+            *budget -= 1;
+            self.place(board, k, val);
+            if self.fill(board, rng, budget) {
+                return true;
+            }
+            self.clear(board, k, val);
+        }
+        self.undo(board, placed);
+        false
+    }
+}
+
+/// Whether a cell's digit is the only one it can hold in any solution of
+/// the rest of the puzzle, settled within `budget` guesses.
+///
+/// If the puzzle has a unique solution, its clue in the cell can then be
+/// dug out without making another. Cells that take longer to settle count
+/// as not forced, which keeps digging clues out of large boards quick.
+pub fn is_forced_within(
+    cells: &Grid,
+    rule_set: &RuleSet,
+    [x, y]: [usize; 2],
+    budget: usize,
+) -> bool {
+    let rules = Rules::new(cells.size(), rule_set);
+    let mut rest = cells.clone();
+    rest[y][x] = 0;
+    let mut board = match rules.board(&rest) {
+        Some(board) => board,
+        None => return false,
+    };
+    let k = y * rules.size + x;
+    let others = rules.candidates(&board, k) & !(1 << cells[y][x]);
+    let mut budget = budget;
+    for val in 1..=rules.digits as u8 {
+        if others & (1 << val) == 0 {
+            continue;
+        }
+        let mut found = 0;
+        rules.place(&mut board, k, val);
+        rules.search(&mut board, 1, &mut budget, &mut found, &mut None);
+        rules.clear(&mut board, k, val);
+        if found > 0 || budget == 0 {
+            return false;
+        }
+    }
+    true
+}
+
+/// Solves a puzzle under a set of rules, if it has exactly one solution.
+#[instrument(level = "debug", skip_all)]
+pub fn unique_solution(cells: &Grid, rule_set: &RuleSet) -> Option<Grid> {
+    let rules = Rules::new(cells.size(), rule_set);
+    let mut board = rules.board(cells)?;
+    let (mut budget, mut found, mut first) = (usize::MAX, 0, None);
+    rules.search(&mut board, 2, &mut budget, &mut found, &mut first);
+    debug!(
+        "Solved a {}x{} puzzle in {} guesses: {}",
+        cells.size(),
+        cells.size(),
+        usize::MAX - budget,
+        match found {
+            0 => "no solution",
+            1 => "unique",
+            _ => "multiple solutions",
+        }
+    );
+    first.filter(|_| found == 1)
+}
+
+/// Solves a puzzle under a set of rules like `unique_solution`, or `None`
+/// if telling whether it has exactly one solution takes more than `budget`
+/// guesses. Otherwise gives the number of solutions, up to two, and the
+/// solution when there is just one.
+pub fn unique_solution_within(
+    cells: &Grid,
+    rule_set: &RuleSet,
+    budget: usize,
+) -> Option<(usize, Option<Grid>)> {
+    let rules = Rules::new(cells.size(), rule_set);
+    let mut board = match rules.board(cells) {
+        Some(board) => board,
+        None => return Some((0, None)),
+    };
+    let (mut budget, mut found, mut first) = (budget, 0, None);
+    rules.search(&mut board, 2, &mut budget, &mut found, &mut first);
+    if found < 2 && budget == 0 {
+        debug!("Gave up solving after {} found", found);
+        return None;
+    }
+    Some((found, first.filter(|_| found == 1)))
+}
+
+/// Counts a puzzle's solutions under a set of rules, stopping at `limit`,
+/// or `None` if counting takes more than `budget` guesses.
+pub fn count_solutions(
+    cells: &Grid,
+    rule_set: &RuleSet,
+    limit: usize,
+    budget: usize,
+) -> Option<usize> {
+    let rules = Rules::new(cells.size(), rule_set);
+    let mut board = match rules.board(cells) {
+        Some(board) => board,
+        None => return Some(0),
+    };
+    let (mut budget, mut found) = (budget, 0);
+    rules.search(&mut board, limit, &mut budget, &mut found, &mut None);
+    if found < limit && budget == 0 {
+        debug!("Gave up counting solutions after {} found", found);
+    }
+    Some(found).filter(|&found| found >= limit || budget > 0)
+}
+
+/// A random full grid of a size obeying a set of rules, for puzzles the
+/// classic shifted pattern does not suit.
+///
+/// Gives up after `budget` guesses on rules, such as some jigsaw regions,
+/// that take too long to fill or cannot be filled at all.
+pub fn random_solution<R: Rng>(
+    size: usize,
+    rule_set: &RuleSet,
+    budget: usize,
+    rng: &mut R,
+) -> Option<Grid> {
+    let rules = Rules::new(size, rule_set);
+    let mut board = rules.board(&Grid::new(size))?;
+    let mut budget = budget;
+    if rules.fill(&mut board, rng, &mut budget) {
+        Some(board.cells)
+    } else {
+        None
+    }
+}
+
+/// Why a digit must go in a cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// No other digit fits the cell.
+    OnlyDigit,
+    /// The digit fits nowhere else in a house.
+    OnlyPlace(House),
+}
+
+/// A digit that follows from the rest of a puzzle in one step, without
+/// guessing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deduction {
+    pub cell: [usize; 2],
+    pub val: u8,
+    pub reason: Reason,
+    /// Names of the rules beyond rows, columns, and boxes it relies on,
+    /// such as cage sums, X-Sudoku, or anti-knight.
+    pub rules: Vec<&'static str>,
+}
+
+impl Deduction {
+    /// Says why the digit goes in its cell, with boxes named as the layout
+    /// has them.
+    pub fn describe(&self, layout: &Variant) -> String {
+        let val = digit_char(self.val);
+        let found = match self.reason {
+            Reason::OnlyDigit => format!("Only {} fits here", val),
+            Reason::OnlyPlace(house) => {
+                format!("{} can only go here in {}", val, house.name(layout))
+            }
+        };
+        if self.rules.is_empty() {
+            found
+        } else {
+            format!("{}, by the {} rules", found, self.rules.join(" and "))
+        }
+    }
+}
+
+/// Every digit that follows from a partly filled puzzle in one step: the
+/// only digit left for a cell, or the only place left for a digit in a
+/// house. Houses include those variants add, such as diagonals and windows,
+/// and the digits left respect every other rule, such as cage sums and
+/// anti-knight.
+///
+/// Cells with a digit left alone come first, in reading order.
+pub fn deductions(cells: &Grid, rule_set: &RuleSet) -> Vec<Deduction> {
+    let rules = Rules::new(cells.size(), rule_set);
+    let board = match rules.board(cells) {
+        Some(board) => board,
+        None => return Vec::new(),
+    };
+    let cell = |k: usize| [k % rules.size, k / rules.size];
+    let masks: Vec<u32> = (0..rules.size * rules.size)
+        .map(|k| {
+            let [x, y] = cell(k);
+            if board.cells[y][x] != 0 || rules.cell_houses[k].is_empty() {
+                0
+            } else {
+                rules.candidates(&board, k)
+            }
+        })
+        .collect();
+    let mut found: Vec<Deduction> = masks
+        .iter()
+        .enumerate()
+        .filter(|(_, mask)| mask.count_ones() == 1)
+        .map(|(k, &mask)| Deduction {
+            cell: cell(k),
+            val: mask.trailing_zeros() as u8,
+            reason: Reason::OnlyDigit,
+            rules: rules.ruled_out_by(&board, k, digit_mask(rules.digits) & !mask),
+        })
+        .collect();
+    for (h, house) in rules.houses.iter().enumerate() {
+        for val in 1..=rules.digits as u8 {
+            let bit = 1 << val;
+            if board.used[h] & bit != 0 {
+                continue;
+            }
+            let k = match house
+                .iter()
+                .filter(|&&k| masks[k] & bit != 0)
+                .collect::<Vec<_>>()[..]
+            {
+                [&k] => k,
+                _ => continue,
+            };
+            if found.iter().any(|deduction| deduction.cell == cell(k)) {
+                continue;
+            }
+            let mut names = Vec::new();
+            for &other in house {
+                let [x, y] = cell(other);
+                if other == k || board.cells[y][x] != 0 {
+                    continue;
+                }
+                for name in rules.ruled_out_by(&board, other, bit) {
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+            }
+            found.push(Deduction {
+                cell: cell(k),
+                val,
+                reason: Reason::OnlyPlace(rules.ids[h]),
+                rules: names,
+            });
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::Constraints;
+    use crate::digits::parse;
+    use crate::variant::VariantKind;
+    use crate::Gameboard;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const PUZZLE: &str =
+        "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+
+    #[test]
+    fn solves_puzzles_with_one_solution() {
+        let rules = RuleSet::default();
+        let cells = parse(PUZZLE).unwrap();
+        let solution = unique_solution(&cells, &rules).unwrap();
+        assert_eq!(solution[0][..], [5, 3, 4, 6, 7, 8, 9, 1, 2]);
+        assert_eq!(
+            unique_solution_within(&cells, &rules, usize::MAX),
+            Some((1, Some(solution)))
+        );
+        let empty = Grid::new(9);
+        assert_eq!(unique_solution(&empty, &rules), None);
+        assert_eq!(count_solutions(&empty, &rules, 5, usize::MAX), Some(5));
+        let mut clash = cells.clone();
+        clash[0][2] = 5;
+        assert_eq!(count_solutions(&clash, &rules, 2, usize::MAX), Some(0));
+    }
+
+    #[test]
+    fn fills_grids_obeying_variants() {
+        let rules = RuleSet::new(Variant::new(VariantKind::Diagonal), Constraints::default());
+        let solution = random_solution(9, &rules, 100_000, &mut StdRng::seed_from_u64(7)).unwrap();
+        let mut board = Gameboard::from_puzzle(solution.clone(), solution);
+        board.rules = rules;
+        assert!(board.solved());
+        let diagonal: Vec<u8> = (0..9).map(|k| board.cells[k][k]).collect();
+        assert!((1..=9).all(|val| diagonal.contains(&val)));
+    }
+
+    #[test]
+    fn finds_digits_without_guessing() {
+        let rules = RuleSet::default();
+        let cells = parse(PUZZLE).unwrap();
+        let solution = unique_solution(&cells, &rules).unwrap();
+        let found = deductions(&cells, &rules);
+        for deduction in &found {
+            let [x, y] = deduction.cell;
+            assert_eq!(deduction.val, solution[y][x]);
+        }
+        // The centre sees every digit but 5 in its row, column, and box.
+        let only = found
+            .iter()
+            .find(|deduction| deduction.cell == [4, 4])
+            .unwrap();
+        assert_eq!((only.val, only.reason), (5, Reason::OnlyDigit));
+        assert_eq!(only.describe(rules.layout()), "Only 5 fits here");
+    }
+}
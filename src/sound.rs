@@ -0,0 +1,68 @@
+use crate::config::Config;
+use rodio::source::{self, SineWave, Source};
+use rodio::{OutputStream, OutputStreamHandle};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Loudness of a cue at full volume, as tones at full scale are harsh.
+const LOUDNESS: f32 = 0.25;
+
+/// A sound the game makes as it is played.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cue {
+    /// A digit or note went in.
+    Place,
+    /// A row, column, or box was completed.
+    House,
+    /// A wrong digit went in.
+    Mistake,
+    Solved,
+    Lost,
+}
+
+impl Cue {
+    /// The tones played, as a frequency in hertz and a length in
+    /// milliseconds.
+    fn tones(self) -> &'static [(f32, u64)] {
+        match self {
+            Cue::Place => &[(880.0, 40)],
+            Cue::House => &[(659.3, 70), (987.8, 110)],
+            Cue::Mistake => &[(196.0, 90), (185.0, 160)],
+            Cue::Solved => &[(523.3, 110), (659.3, 110), (784.0, 110), (1046.5, 320)],
+            Cue::Lost => &[(392.0, 170), (311.1, 170), (261.6, 380)],
+        }
+    }
+}
+
+/// Plays cues on the default output device, staying silent when there is
+/// none or sound is turned off.
+pub struct Sounds {
+    /// The stream must be kept for the handle to play on it.
+    output: Option<(OutputStream, OutputStreamHandle)>,
+}
+
+impl Sounds {
+    pub fn new() -> Sounds {
+        let output = OutputStream::try_default()
+            .map_err(|err| warn!("Playing without sound: {}", err))
+            .ok();
+        Sounds { output }
+    }
+
+    pub fn play(&self, cue: Cue, config: &Config) {
+        let handle = match &self.output {
+            Some((_, handle)) if config.sound => handle,
+            _ => return,
+        };
+        let volume = LOUDNESS * config.volume_percent.min(100) as f32 / 100.0;
+        let tones = cue.tones().iter().map(move |&(freq, millis)| {
+            SineWave::new(freq)
+                .take_duration(Duration::from_millis(millis))
+                .fade_in(Duration::from_millis(5))
+                .amplify(volume)
+        });
+        if let Err(err) = handle.play_raw(source::from_iter(tones)) {
+            debug!("Could not play {:?}: {}", cue, err);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use crate::csv;
+use crate::database::{Database, RecentGame, Stats};
+#[cfg(feature = "gui")]
+use crate::format_duration;
+#[cfg(feature = "gui")]
+use crate::text::{draw_text, text_width};
+#[cfg(feature = "gui")]
+use crate::theme::Theme;
+use crate::Difficulty;
+#[cfg(feature = "gui")]
+use graphics::character::CharacterCache;
+#[cfg(feature = "gui")]
+use graphics::types::Color;
+#[cfg(feature = "gui")]
+use graphics::{Context, Graphics};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+#[cfg(feature = "gui")]
+use std::time::Duration;
+
+/// Games shown in the chart of recent times.
+pub const RECENT_GAMES: usize = 30;
+
+/// File name of the exported game history.
+pub const HISTORY_FILE: &str = "statistics.csv";
+
+/// Writes every finished game to a CSV file with a header row, returning
+/// how many games there were.
+pub fn export_csv<P: AsRef<Path>>(database: &Database, path: P) -> Result<usize, String> {
+    let history = database.history()?;
+    let mut text = "finished,difficulty,seconds,mistakes,hints,solved,puzzle,source\n".to_string();
+    for entry in &history {
+        // Writing to a String cannot fail.
+        let _ = writeln!(
+            text,
+            "{},{},{},{},{},{},{},{}",
+            entry.finished,
+            entry.difficulty,
+            entry.seconds,
+            entry.mistakes,
+            entry.hints,
+            entry.solved,
+            entry.givens,
+            csv::field(&entry.source)
+        );
+    }
+    if let Some(dir) = path.as_ref().parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    fs::write(path, text).map_err(|err| err.to_string())?;
+    Ok(history.len())
+}
+
+/// Statistics read from the database for the dashboard.
+pub struct Dashboard {
+    pub stats: Vec<(Difficulty, Stats)>,
+    /// The last games played, oldest first.
+    pub recent: Vec<RecentGame>,
+}
+
+impl Dashboard {
+    pub fn load(database: &Database) -> Result<Dashboard, String> {
+        let stats = Difficulty::ALL
+            .iter()
+            .map(|&difficulty| Ok((difficulty, database.stats(difficulty)?)))
+            .collect::<Result<_, String>>()?;
+        Ok(Dashboard {
+            stats,
+            recent: database.recent(RECENT_GAMES)?,
+        })
+    }
+}
+
+#[cfg(feature = "gui")]
+pub struct StatsViewSettings {
+    pub font_size: u32,
+    pub background_color: Color,
+    pub text_color: Color,
+    pub axis_color: Color,
+    /// Bars of games won and of games lost or given up.
+    pub won_color: Color,
+    pub lost_color: Color,
+}
+
+#[cfg(feature = "gui")]
+impl StatsViewSettings {
+    pub fn new(theme: &Theme) -> StatsViewSettings {
+        StatsViewSettings {
+            font_size: 16,
+            background_color: theme.panel_background,
+            text_color: theme.text,
+            axis_color: theme.section_edge,
+            won_color: theme.correct_text,
+            lost_color: theme.conflict_text,
+        }
+    }
+
+    /// Scales text.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.font_size = (16.0 * scale).round() as u32;
+    }
+
+    /// Switches to a theme's colors.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        *self = StatsViewSettings {
+            font_size: self.font_size,
+            ..StatsViewSettings::new(theme)
+        };
+    }
+}
+
+#[cfg(feature = "gui")]
+pub struct StatsView {
+    pub settings: StatsViewSettings,
+}
+
+#[cfg(feature = "gui")]
+impl StatsView {
+    pub fn new(settings: StatsViewSettings) -> StatsView {
+        StatsView { settings }
+    }
+
+    /// Draws a table of each difficulty's statistics above a bar chart of
+    /// recent game times, over the area at `pos` with side length `size`.
+    pub fn draw<G: Graphics, C>(
+        &self,
+        dashboard: &Dashboard,
+        pos: [f64; 2],
+        size: f64,
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::{Line, Rectangle};
+
+        let settings = &self.settings;
+        Rectangle::new(settings.background_color).draw(
+            [pos[0], pos[1], size, size],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+        let line_height = settings.font_size as f64 * 1.4;
+        let text = |line: &str, x: f64, row: f64, glyphs: &mut C, g: &mut G| {
+            draw_text(
+                line,
+                settings.font_size,
+                settings.text_color,
+                [pos[0] + x, pos[1] + row * line_height],
+                glyphs,
+                c,
+                g,
+            );
+        };
+        text("Statistics", 10.0, 1.0, glyphs, g);
+
+        // Columns are spread evenly after the difficulty names.
+        let time = |time: Option<Duration>| time.map_or_else(|| "-".to_string(), format_duration);
+        let headers = ["Won", "Best", "Average", "Streak", "Longest"];
+        let column = (size - 20.0) / (headers.len() + 1) as f64;
+        for (k, header) in headers.iter().enumerate() {
+            text(header, 10.0 + (k + 1) as f64 * column, 2.5, glyphs, g);
+        }
+        for (row, (difficulty, stats)) in dashboard.stats.iter().enumerate() {
+            let cells = [
+                format!("{}/{}", stats.won, stats.played),
+                time(stats.best_time),
+                time(stats.average_time),
+                stats.current_streak.to_string(),
+                stats.longest_streak.to_string(),
+            ];
+            let row = row as f64 + 3.5;
+            text(difficulty.name(), 10.0, row, glyphs, g);
+            for (k, cell) in cells.iter().enumerate() {
+                text(cell, 10.0 + (k + 1) as f64 * column, row, glyphs, g);
+            }
+        }
+
+        // Bars are scaled to the slowest game shown.
+        let chart_row = dashboard.stats.len() as f64 + 5.0;
+        text(
+            &format!("Last {} games", dashboard.recent.len()),
+            10.0,
+            chart_row,
+            glyphs,
+            g,
+        );
+        let left = pos[0] + 10.0;
+        let top = pos[1] + (chart_row + 0.5) * line_height;
+        let bottom = pos[1] + size - 2.0 * line_height;
+        let width = size - 20.0;
+        let slowest = dashboard
+            .recent
+            .iter()
+            .map(|game| game.time)
+            .max()
+            .unwrap_or_default();
+        if slowest > Duration::ZERO && bottom > top {
+            let label = format_duration(slowest);
+            let label_width = text_width(&label, settings.font_size, glyphs, c);
+            text(&label, size - 10.0 - label_width, chart_row, glyphs, g);
+            let slot = width / RECENT_GAMES as f64;
+            for (k, game) in dashboard.recent.iter().enumerate() {
+                let height = (bottom - top) * game.time.as_secs_f64() / slowest.as_secs_f64();
+                let color = if game.solved {
+                    settings.won_color
+                } else {
+                    settings.lost_color
+                };
+                Rectangle::new(color).draw(
+                    [
+                        left + k as f64 * slot + slot * 0.15,
+                        bottom - height,
+                        slot * 0.7,
+                        height,
+                    ],
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+            }
+        }
+        Line::new(settings.axis_color, 1.0).draw(
+            [left, bottom, left + width, bottom],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+        text(
+            "E to export as CSV, any other key to go back",
+            10.0,
+            size / line_height - 0.5,
+            glyphs,
+            g,
+        );
+    }
+}
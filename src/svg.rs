@@ -0,0 +1,370 @@
+use crate::grid::{box_shape, digit_char};
+use crate::theme::Theme;
+use crate::variant::{
+    samurai_cell, sign_strokes, DotKind, Parity, Variant, VariantKind, SAMURAI_GRIDS,
+};
+use crate::{Gameboard, SIZE};
+use graphics::types::Color;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Side of a cell, in SVG user units.
+const CELL: f64 = 50.0;
+/// Space around the board.
+const MARGIN: f64 = 10.0;
+
+/// SVG attributes for a color, with its alpha as a separate opacity.
+fn paint(attribute: &str, color: Color) -> String {
+    let [r, g, b, a] = color.map(|k| (k.clamp(0.0, 1.0) * 255.0).round() as u8);
+    let mut text = format!("{}=\"#{:02x}{:02x}{:02x}\"", attribute, r, g, b);
+    if a < 255 {
+        text += &format!(" {}-opacity=\"{:.3}\"", attribute, color[3]);
+    }
+    text
+}
+
+/// Draws a board as an SVG document in a theme's colors: the grid, givens,
+/// entries, and, if `notes` is set, pencil marks.
+///
+/// Text is left as text, so the digits can be edited afterwards.
+pub fn board(gameboard: &Gameboard, theme: &Theme, notes: bool) -> String {
+    let size = gameboard.size();
+    let digits = gameboard.digits();
+    let [width, height] = box_shape(digits);
+    let side = size as f64 * CELL;
+    let full = side + 2.0 * MARGIN;
+    let mut svg = String::new();
+    // Writing to a String cannot fail.
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" \
+         viewBox=\"0 0 {0} {0}\">",
+        full
+    );
+    let _ = writeln!(
+        svg,
+        "<rect x=\"{0}\" y=\"{0}\" width=\"{1}\" height=\"{1}\" {2}/>",
+        MARGIN,
+        side,
+        paint("fill", theme.board_background)
+    );
+    // Cells in the houses the variants add are shaded.
+    let mut shade = theme.section_edge;
+    shade[3] = 0.12;
+    for [i, j] in gameboard.cells.positions() {
+        if gameboard.rules.extra_houses(size, [i, j]).next().is_some() {
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" {3}/>",
+                MARGIN + i as f64 * CELL,
+                MARGIN + j as f64 * CELL,
+                CELL,
+                paint("fill", shade)
+            );
+        }
+    }
+
+    // Thermometers, and even and odd marks, are grey shapes under the
+    // digits.
+    let shape = [0, 1, 2].map(|k| theme.board_background[k] * 0.7 + theme.section_edge[k] * 0.3);
+    let shape = [shape[0], shape[1], shape[2], 1.0];
+    if let Some(Variant::EvenOdd(marks)) = gameboard.rules.get(VariantKind::EvenOdd) {
+        for mark in marks {
+            let [i, j] = mark.cell;
+            let (x, y) = (MARGIN + i as f64 * CELL, MARGIN + j as f64 * CELL);
+            let _ = match mark.parity {
+                Parity::Even => writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" {3}/>",
+                    x + CELL * 0.12,
+                    y + CELL * 0.12,
+                    CELL * 0.76,
+                    paint("fill", shape)
+                ),
+                Parity::Odd => writeln!(
+                    svg,
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {}/>",
+                    x + CELL / 2.0,
+                    y + CELL / 2.0,
+                    CELL * 0.38,
+                    paint("fill", shape)
+                ),
+            };
+        }
+    }
+    // A thermometer is a round bulb on a thick shaft.
+    if let Some(Variant::Thermo(thermos)) = gameboard.rules.get(VariantKind::Thermo) {
+        for cells in thermos.iter().map(|thermo| &thermo.cells) {
+            let points: Vec<String> = cells
+                .iter()
+                .map(|&[i, j]| {
+                    format!(
+                        "{},{}",
+                        MARGIN + (i as f64 + 0.5) * CELL,
+                        MARGIN + (j as f64 + 0.5) * CELL
+                    )
+                })
+                .collect();
+            let _ = writeln!(
+                svg,
+                "<polyline points=\"{}\" fill=\"none\" {} stroke-width=\"{}\" \
+                 stroke-linecap=\"round\" stroke-linejoin=\"round\"/>",
+                points.join(" "),
+                paint("stroke", shape),
+                CELL * 0.3
+            );
+            if let Some(&[i, j]) = cells.first() {
+                let _ = writeln!(
+                    svg,
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {}/>",
+                    MARGIN + (i as f64 + 0.5) * CELL,
+                    MARGIN + (j as f64 + 0.5) * CELL,
+                    CELL * 0.35,
+                    paint("fill", shape)
+                );
+            }
+        }
+    }
+    // German whispers are thin green lines.
+    if let Some(Variant::Whispers(whispers)) = gameboard.rules.get(VariantKind::Whispers) {
+        for whisper in whispers {
+            let points: Vec<String> = whisper
+                .cells
+                .iter()
+                .map(|&[i, j]| {
+                    format!(
+                        "{},{}",
+                        MARGIN + (i as f64 + 0.5) * CELL,
+                        MARGIN + (j as f64 + 0.5) * CELL
+                    )
+                })
+                .collect();
+            let _ = writeln!(
+                svg,
+                "<polyline points=\"{}\" fill=\"none\" {} stroke-width=\"{}\" \
+                 stroke-linecap=\"round\" stroke-linejoin=\"round\"/>",
+                points.join(" "),
+                paint("stroke", [0.404, 0.941, 0.404, 1.0]),
+                CELL * 0.2
+            );
+        }
+    }
+
+    let _ = writeln!(
+        svg,
+        "<g font-family=\"sans-serif\" text-anchor=\"middle\" dominant-baseline=\"central\">"
+    );
+    for (j, row) in gameboard.cells.iter().enumerate() {
+        for (i, &val) in row.iter().enumerate() {
+            let x = MARGIN + i as f64 * CELL;
+            let y = MARGIN + j as f64 * CELL;
+            if val != 0 {
+                let color = if gameboard.givens[j][i] {
+                    theme.text
+                } else {
+                    theme.entry_text
+                };
+                let _ = writeln!(
+                    svg,
+                    "<text x=\"{}\" y=\"{}\" font-size=\"{}\" {}>{}</text>",
+                    x + CELL / 2.0,
+                    y + CELL / 2.0,
+                    CELL * 0.7,
+                    paint("fill", color),
+                    digit_char(val)
+                );
+            } else if notes {
+                // Pencil marks sit in a grid shaped like a box inside the
+                // cell.
+                for val in 1..=digits as u8 {
+                    if !gameboard.has_note([i, j], val) {
+                        continue;
+                    }
+                    let k = (val - 1) as usize;
+                    let _ = writeln!(
+                        svg,
+                        "<text x=\"{}\" y=\"{}\" font-size=\"{}\" {}>{}</text>",
+                        x + ((k % width) as f64 + 0.5) * CELL / width as f64,
+                        y + ((k / width) as f64 + 0.5) * CELL / height as f64,
+                        CELL * 0.75 / width.max(height) as f64,
+                        paint("fill", theme.entry_text),
+                        digit_char(val)
+                    );
+                }
+            }
+        }
+    }
+    let _ = writeln!(svg, "</g>");
+
+    // Killer cages are dashed just inside their cells, with their sums in
+    // the corner.
+    if let Some(Variant::Killer(cages)) = gameboard.rules.get(VariantKind::Killer) {
+        let inset = 0.08;
+        for cage in cages {
+            for [x1, y1, x2, y2] in cage.outline(inset) {
+                let _ = writeln!(
+                    svg,
+                    "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {} stroke-width=\"1\" \
+                     stroke-dasharray=\"{}\"/>",
+                    MARGIN + x1 * CELL,
+                    MARGIN + y1 * CELL,
+                    MARGIN + x2 * CELL,
+                    MARGIN + y2 * CELL,
+                    paint("stroke", theme.text),
+                    CELL / 10.0
+                );
+            }
+            let [i, j] = cage.label_cell();
+            let _ = writeln!(
+                svg,
+                "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"{}\" {}>{}</text>",
+                MARGIN + (i as f64 + inset * 1.5) * CELL,
+                MARGIN + (j as f64 + inset * 1.5) * CELL + CELL * 0.18,
+                CELL * 0.22,
+                paint("fill", theme.text),
+                cage.sum
+            );
+        }
+    }
+
+    // Cell edges first so the thicker box edges cover them. Jigsaw regions
+    // have their edges traced cell by cell instead.
+    let jigsaw = matches!(gameboard.rules.layout(), Variant::Jigsaw(_));
+    for k in 1..size {
+        let offset = MARGIN + k as f64 * CELL;
+        for (x1, y1, x2, y2, boxes) in [
+            (offset, MARGIN, offset, MARGIN + side, width),
+            (MARGIN, offset, MARGIN + side, offset, height),
+        ] {
+            let (color, stroke) = if k % boxes == 0 && !jigsaw {
+                (theme.section_edge, 4.0)
+            } else {
+                (theme.cell_edge, 2.0)
+            };
+            let _ = writeln!(
+                svg,
+                "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {} stroke-width=\"{}\"/>",
+                x1,
+                y1,
+                x2,
+                y2,
+                paint("stroke", color),
+                stroke
+            );
+        }
+    }
+    if jigsaw {
+        let variant = gameboard.rules.layout();
+        for [i, j] in gameboard.cells.positions() {
+            let x = MARGIN + (i + 1) as f64 * CELL;
+            let y = MARGIN + (j + 1) as f64 * CELL;
+            let box_of = |ind| variant.box_of(size, ind);
+            let mut edges = Vec::new();
+            if i + 1 < size && box_of([i, j]) != box_of([i + 1, j]) {
+                edges.push((x, y - CELL, x, y));
+            }
+            if j + 1 < size && box_of([i, j]) != box_of([i, j + 1]) {
+                edges.push((x - CELL, y, x, y));
+            }
+            for (x1, y1, x2, y2) in edges {
+                let _ = writeln!(
+                    svg,
+                    "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {} stroke-width=\"4\" \
+                     stroke-linecap=\"square\"/>",
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    paint("stroke", theme.section_edge)
+                );
+            }
+        }
+    }
+    // The gaps between samurai grids are covered up, and each grid gets its
+    // own edge.
+    let mut edges = vec![([0, 0], size)];
+    if *gameboard.rules.layout() == Variant::Samurai {
+        for [i, j] in gameboard.cells.positions() {
+            if !gameboard.in_play([i, j]) {
+                let _ = writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" {3}/>",
+                    MARGIN + i as f64 * CELL - 1.0,
+                    MARGIN + j as f64 * CELL - 1.0,
+                    CELL + 2.0,
+                    paint("fill", theme.background)
+                );
+            }
+        }
+        edges = (0..SAMURAI_GRIDS)
+            .map(|grid| (samurai_cell(grid, [0, 0]), SIZE))
+            .collect();
+    }
+    for ([i, j], cells) in edges {
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"none\" {3} \
+             stroke-width=\"6\"/>",
+            MARGIN + i as f64 * CELL,
+            MARGIN + j as f64 * CELL,
+            cells as f64 * CELL,
+            paint("stroke", theme.board_edge)
+        );
+    }
+
+    // Greater-than signs sit over the edges between their cells.
+    if let Some(Variant::GreaterThan(signs)) = gameboard.rules.get(VariantKind::GreaterThan) {
+        for sign in signs {
+            let [[ax, ay, tx, ty], [bx, by, _, _]] = sign_strokes(sign);
+            let _ = writeln!(
+                svg,
+                "<polyline points=\"{},{} {},{} {},{}\" fill=\"none\" {} stroke-width=\"2\" \
+                 stroke-linecap=\"round\" stroke-linejoin=\"round\"/>",
+                MARGIN + ax * CELL,
+                MARGIN + ay * CELL,
+                MARGIN + tx * CELL,
+                MARGIN + ty * CELL,
+                MARGIN + bx * CELL,
+                MARGIN + by * CELL,
+                paint("stroke", theme.text)
+            );
+        }
+    }
+
+    // Kropki dots sit over the edges between their cells.
+    if let Some(Variant::Kropki(kropki)) = gameboard.rules.get(VariantKind::Kropki) {
+        for dot in &kropki.dots {
+            let [[i1, j1], [i2, j2]] = dot.cells;
+            let fill = match dot.kind {
+                DotKind::White => theme.board_background,
+                DotKind::Black => theme.text,
+            };
+            let _ = writeln!(
+                svg,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {} {} stroke-width=\"1\"/>",
+                MARGIN + ((i1 + i2) as f64 / 2.0 + 0.5) * CELL,
+                MARGIN + ((j1 + j2) as f64 / 2.0 + 0.5) * CELL,
+                CELL * 0.12,
+                paint("fill", fill),
+                paint("stroke", theme.text)
+            );
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Writes a board's SVG to a file named after its seed.
+pub fn save<P: AsRef<Path>>(
+    dir: P,
+    gameboard: &Gameboard,
+    theme: &Theme,
+    notes: bool,
+) -> io::Result<PathBuf> {
+    fs::create_dir_all(&dir)?;
+    let path = dir.as_ref().join(format!("sudoku-{}.svg", gameboard.seed));
+    fs::write(&path, board(gameboard, theme, notes))?;
+    Ok(path)
+}
@@ -0,0 +1,320 @@
+//! Human solving techniques, each finding one step through a puzzle the way
+//! a player would, and a registry trying them from the easiest on.
+//!
+//! A technique only reads a `Board`: its digits, the candidates left in
+//! each cell, and its houses. New ones can be written in a module or crate
+//! of their own and registered alongside the built-in singles, without
+//! changing the solver.
+
+use crate::grid::Grid;
+use crate::rules::RuleSet;
+use crate::solver::{self, Deduction, Reason, Rules};
+use crate::variant::{digit_mask, Variant};
+use crate::{Difficulty, House};
+
+/// A step found by a technique: digits placed, candidates ruled out, or
+/// both.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolveStep {
+    /// Name of the technique that found it.
+    pub technique: &'static str,
+    /// Digits placed, by cell.
+    pub placed: Vec<([usize; 2], u8)>,
+    /// Candidates ruled out, as a mask of digits by cell.
+    pub eliminated: Vec<([usize; 2], u32)>,
+    /// Says what was found and why, as a hint would.
+    pub description: String,
+}
+
+/// A way of finding the next step through a puzzle.
+pub trait Technique: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// How hard the technique is to spot, from 1 for a naked single up.
+    /// Easier techniques are tried first.
+    fn weight(&self) -> u32;
+
+    /// The first step the technique finds on a board, if any.
+    fn find(&self, board: &Board) -> Option<SolveStep>;
+}
+
+/// A puzzle partly solved, as techniques see it.
+pub struct Board<'a> {
+    rules: Rules<'a>,
+    board: solver::Board,
+    /// Candidates ruled out by steps taken, by cell in reading order.
+    eliminated: Vec<u32>,
+}
+
+impl<'a> Board<'a> {
+    /// Sets up a board from a grid, or `None` if its digits break a rule.
+    pub fn new(cells: &Grid, rule_set: &'a RuleSet) -> Option<Board<'a>> {
+        let rules = Rules::new(cells.size(), rule_set);
+        let board = rules.board(cells)?;
+        let eliminated = vec![0; rules.size * rules.size];
+        Some(Board {
+            rules,
+            board,
+            eliminated,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.rules.size
+    }
+
+    /// Digits each house holds, which is the size but on some variants.
+    pub fn digits(&self) -> usize {
+        self.rules.digits
+    }
+
+    pub fn cells(&self) -> &Grid {
+        &self.board.cells
+    }
+
+    pub fn layout(&self) -> &Variant {
+        self.rules.rule_set.layout()
+    }
+
+    /// Whether a cell is played, which every cell is but on some variants.
+    pub fn in_play(&self, [x, y]: [usize; 2]) -> bool {
+        !self.rules.cell_houses[y * self.size() + x].is_empty()
+    }
+
+    /// Bit mask of the digits still allowed in an empty cell, by every rule
+    /// and every step taken, or 0 for a filled cell.
+    pub fn candidates(&self, [x, y]: [usize; 2]) -> u32 {
+        let k = y * self.size() + x;
+        if self.board.cells[y][x] != 0 || !self.in_play([x, y]) {
+            return 0;
+        }
+        self.rules.candidates(&self.board, k) & !self.eliminated[k]
+    }
+
+    /// Every house, such as rows, boxes, and those variants add, with its
+    /// cells.
+    pub fn houses(&self) -> impl Iterator<Item = (House, Vec<[usize; 2]>)> + '_ {
+        let size = self.size();
+        self.rules
+            .ids
+            .iter()
+            .zip(&self.rules.houses)
+            .map(move |(&id, house)| {
+                let cells = house.iter().map(|&k| [k % size, k / size]).collect();
+                (id, cells)
+            })
+    }
+
+    /// Names of the rules beyond rows, columns, and boxes that keep any
+    /// digit of a mask out of a cell, such as X-Sudoku or a cage's sum.
+    pub fn ruled_out_by(&self, [x, y]: [usize; 2], mask: u32) -> Vec<&'static str> {
+        self.rules
+            .ruled_out_by(&self.board, y * self.size() + x, mask)
+    }
+
+    /// Whether every cell in play is filled.
+    pub fn is_solved(&self) -> bool {
+        self.board
+            .cells
+            .positions()
+            .all(|[x, y]| self.board.cells[y][x] != 0 || !self.in_play([x, y]))
+    }
+
+    /// Takes a step, returning whether it changed the board. Digits the
+    /// cells no longer allow are not placed.
+    pub fn apply(&mut self, step: &SolveStep) -> bool {
+        let mut changed = false;
+        for &([x, y], val) in &step.placed {
+            if self.candidates([x, y]) & (1 << val) != 0 {
+                let k = y * self.size() + x;
+                self.rules.place(&mut self.board, k, val);
+                changed = true;
+            }
+        }
+        for &([x, y], mask) in &step.eliminated {
+            let k = y * self.size() + x;
+            changed |= self.candidates([x, y]) & mask != 0;
+            self.eliminated[k] |= mask;
+        }
+        changed
+    }
+}
+
+/// The only digit left for a cell.
+pub struct NakedSingle;
+
+impl Technique for NakedSingle {
+    fn name(&self) -> &'static str {
+        "naked single"
+    }
+
+    fn weight(&self) -> u32 {
+        1
+    }
+
+    fn find(&self, board: &Board) -> Option<SolveStep> {
+        let all = digit_mask(board.digits());
+        let (cell, mask) = board
+            .cells()
+            .positions()
+            .map(|cell| (cell, board.candidates(cell)))
+            .find(|(_, mask)| mask.count_ones() == 1)?;
+        let deduction = Deduction {
+            cell,
+            val: mask.trailing_zeros() as u8,
+            reason: Reason::OnlyDigit,
+            rules: board.ruled_out_by(cell, all & !mask),
+        };
+        Some(placing(self.name(), deduction, board.layout()))
+    }
+}
+
+/// The only place left for a digit in a house.
+pub struct HiddenSingle;
+
+impl Technique for HiddenSingle {
+    fn name(&self) -> &'static str {
+        "hidden single"
+    }
+
+    fn weight(&self) -> u32 {
+        2
+    }
+
+    fn find(&self, board: &Board) -> Option<SolveStep> {
+        for (house, cells) in board.houses() {
+            for val in 1..=board.digits() as u8 {
+                let bit = 1 << val;
+                if cells.iter().any(|&[x, y]| board.cells()[y][x] == val) {
+                    continue;
+                }
+                let cell = match cells
+                    .iter()
+                    .filter(|&&cell| board.candidates(cell) & bit != 0)
+                    .collect::<Vec<_>>()[..]
+                {
+                    [&cell] => cell,
+                    _ => continue,
+                };
+                let mut names = Vec::new();
+                for &other in &cells {
+                    if other == cell || board.cells()[other[1]][other[0]] != 0 {
+                        continue;
+                    }
+                    for name in board.ruled_out_by(other, bit) {
+                        if !names.contains(&name) {
+                            names.push(name);
+                        }
+                    }
+                }
+                let deduction = Deduction {
+                    cell,
+                    val,
+                    reason: Reason::OnlyPlace(house),
+                    rules: names,
+                };
+                return Some(placing(self.name(), deduction, board.layout()));
+            }
+        }
+        None
+    }
+}
+
+/// The step placing a deduction's digit.
+fn placing(technique: &'static str, deduction: Deduction, layout: &Variant) -> SolveStep {
+    SolveStep {
+        technique,
+        placed: vec![(deduction.cell, deduction.val)],
+        eliminated: Vec::new(),
+        description: deduction.describe(layout),
+    }
+}
+
+/// The techniques known, tried from the lightest on.
+pub struct Registry {
+    techniques: Vec<Box<dyn Technique>>,
+}
+
+impl Default for Registry {
+    /// The built-in techniques.
+    fn default() -> Self {
+        let mut registry = Registry::new();
+        registry.register(Box::new(NakedSingle));
+        registry.register(Box::new(HiddenSingle));
+        registry
+    }
+}
+
+impl Registry {
+    /// A registry with no techniques at all.
+    pub fn new() -> Registry {
+        Registry {
+            techniques: Vec::new(),
+        }
+    }
+
+    /// Adds a technique, tried after those no heavier than it.
+    pub fn register(&mut self, technique: Box<dyn Technique>) {
+        let at = self
+            .techniques
+            .iter()
+            .position(|known| known.weight() > technique.weight())
+            .unwrap_or(self.techniques.len());
+        self.techniques.insert(at, technique);
+    }
+
+    pub fn techniques(&self) -> impl Iterator<Item = &dyn Technique> {
+        self.techniques.iter().map(|technique| technique.as_ref())
+    }
+
+    /// The step the easiest technique finds on a board.
+    pub fn step(&self, board: &Board) -> Option<SolveStep> {
+        self.techniques
+            .iter()
+            .find_map(|technique| technique.find(board))
+    }
+
+    /// The steps the techniques take through a puzzle, in order, and
+    /// whether they fill it. A puzzle breaking its rules takes none.
+    pub fn solve(&self, cells: &Grid, rule_set: &RuleSet) -> (Vec<SolveStep>, bool) {
+        let mut board = match Board::new(cells, rule_set) {
+            Some(board) => board,
+            None => return (Vec::new(), false),
+        };
+        let mut steps = Vec::new();
+        while let Some(step) = self.step(&board) {
+            // A step changing nothing would be found again forever.
+            if !board.apply(&step) {
+                break;
+            }
+            steps.push(step);
+        }
+        (steps, board.is_solved())
+    }
+
+    /// How hard a puzzle is by the techniques solving it: easy with naked
+    /// singles alone, medium with hidden singles too, hard with anything
+    /// heavier, and expert when the techniques cannot finish it.
+    pub fn rate(&self, cells: &Grid, rule_set: &RuleSet) -> Difficulty {
+        let (steps, solved) = self.solve(cells, rule_set);
+        self.rating(&steps, solved)
+    }
+
+    /// The rating of a puzzle from the steps the techniques took through
+    /// it and whether they filled it.
+    pub fn rating(&self, steps: &[SolveStep], solved: bool) -> Difficulty {
+        match self.hardest(steps).map_or(0, |hardest| hardest.weight()) {
+            _ if !solved => Difficulty::Expert,
+            weight if weight <= NakedSingle.weight() => Difficulty::Easy,
+            weight if weight <= HiddenSingle.weight() => Difficulty::Medium,
+            _ => Difficulty::Hard,
+        }
+    }
+
+    /// The hardest technique among those that took steps.
+    pub fn hardest(&self, steps: &[SolveStep]) -> Option<&dyn Technique> {
+        self.techniques()
+            .filter(|technique| steps.iter().any(|step| step.technique == technique.name()))
+            .max_by_key(|technique| technique.weight())
+    }
+}
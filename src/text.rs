@@ -0,0 +1,83 @@
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{Context, Graphics, Image, Text, Transformed};
+
+/// Ratio of framebuffer pixels to window points, e.g. 2.0 on most HiDPI displays.
+pub fn dpi_scale(c: &Context) -> f64 {
+    c.viewport
+        .map_or(1.0, |v| f64::from(v.draw_size[0]) / v.window_size[0])
+}
+
+/// Glyph size in pixels for a font size in points.
+pub fn pixel_size(font_size: f64, c: &Context) -> u32 {
+    (font_size * dpi_scale(c)).round() as u32
+}
+
+/// Draws text with its baseline starting at `pos`, rasterized at native resolution.
+pub fn draw_text<G: Graphics, C>(
+    text: &str,
+    font_size: u32,
+    color: Color,
+    pos: [f64; 2],
+    glyphs: &mut C,
+    c: &Context,
+    g: &mut G,
+) where
+    C: CharacterCache<Texture = G::Texture>,
+{
+    let scale = dpi_scale(c);
+    let _ = Text::new_color(color, pixel_size(font_size as f64, c)).draw(
+        text,
+        glyphs,
+        &c.draw_state,
+        c.transform.trans(pos[0], pos[1]).zoom(1.0 / scale),
+        g,
+    );
+}
+
+/// Width of text in window points.
+pub fn text_width<C: CharacterCache>(
+    text: &str,
+    font_size: u32,
+    glyphs: &mut C,
+    c: &Context,
+) -> f64 {
+    glyphs
+        .width(pixel_size(font_size as f64, c), text)
+        .map_or(0.0, |width| width / dpi_scale(c))
+}
+
+/// Draws a single character with its visible outline centered on `center`.
+///
+/// Centering uses the rasterized glyph's own bounds, so it holds for any font
+/// and size.
+pub fn draw_centered<G: Graphics, C>(
+    ch: char,
+    font_size: f64,
+    color: Color,
+    center: [f64; 2],
+    glyphs: &mut C,
+    c: &Context,
+    g: &mut G,
+) where
+    C: CharacterCache<Texture = G::Texture>,
+{
+    let scale = dpi_scale(c);
+    if let Ok(character) = glyphs.character(pixel_size(font_size, c), ch) {
+        let [width, height] = character.atlas_size;
+        let image = Image::new_color(color).src_rect([
+            character.atlas_offset[0],
+            character.atlas_offset[1],
+            width,
+            height,
+        ]);
+        let x = center[0] - width / scale / 2.0;
+        let y = center[1] - height / scale / 2.0;
+        image.draw(
+            character.texture,
+            &c.draw_state,
+            c.transform.trans(x, y).zoom(1.0 / scale),
+            g,
+        );
+    }
+}
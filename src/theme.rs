@@ -0,0 +1,160 @@
+use graphics::types::Color;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use tracing::warn;
+
+/// Where theme files are looked up.
+pub const THEMES_DIR: &str = "assets/themes";
+
+/// Name of the theme the dark mode toggle switches to.
+pub const DARK_THEME: &str = "dark";
+
+/// A named set of colors used by every view.
+///
+/// Colors missing from a theme file are taken from the light theme.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub name: String,
+    /// Color the window is cleared to.
+    pub background: Color,
+    pub board_background: Color,
+    pub board_edge: Color,
+    pub section_edge: Color,
+    pub cell_edge: Color,
+    pub selected_cell_background: Color,
+    pub selected_cell_border: Color,
+    /// Ring around the cell moved to with the arrow keys.
+    pub focus_ring: Color,
+    /// Soft highlight under the mouse pointer.
+    pub hover_cell_background: Color,
+    pub house_background: Color,
+    pub text: Color,
+    pub entry_text: Color,
+    pub conflict_text: Color,
+    /// Entries that match the solution on the review screen.
+    pub correct_text: Color,
+    pub flash: Color,
+    pub confetti: Vec<Color>,
+    /// Cursors of the players of a co-op game, by player number.
+    pub partners: Vec<Color>,
+    /// Background of menus, settings, and overlays.
+    pub panel_background: Color,
+    pub panel_selected_background: Color,
+}
+
+impl Default for Theme {
+    /// The light theme, used when no theme files can be read.
+    fn default() -> Theme {
+        Theme {
+            name: "light".to_string(),
+            background: [1.0; 4],
+            board_background: [0.8, 0.8, 1.0, 1.0],
+            board_edge: [0.0, 0.0, 0.2, 1.0],
+            section_edge: [0.0, 0.0, 0.2, 1.0],
+            cell_edge: [0.0, 0.0, 0.2, 1.0],
+            selected_cell_background: [0.9, 0.9, 1.0, 1.0],
+            selected_cell_border: [1.0, 0.0, 0.0, 1.0],
+            focus_ring: [0.1, 0.5, 0.1, 1.0],
+            hover_cell_background: [1.0, 1.0, 1.0, 0.3],
+            house_background: [0.85, 0.85, 1.0, 1.0],
+            text: [0.0, 0.0, 0.1, 1.0],
+            entry_text: [0.1, 0.2, 0.6, 1.0],
+            conflict_text: [0.8, 0.0, 0.0, 1.0],
+            correct_text: [0.1, 0.55, 0.1, 1.0],
+            flash: [1.0, 1.0, 0.6, 0.8],
+            confetti: vec![
+                [0.9, 0.2, 0.2, 1.0],
+                [0.2, 0.7, 0.2, 1.0],
+                [0.2, 0.4, 0.9, 1.0],
+                [1.0, 0.8, 0.1, 1.0],
+                [0.8, 0.3, 0.8, 1.0],
+            ],
+            partners: vec![
+                [0.9, 0.4, 0.0, 1.0],
+                [0.0, 0.6, 0.6, 1.0],
+                [0.6, 0.2, 0.8, 1.0],
+                [0.2, 0.6, 0.1, 1.0],
+            ],
+            panel_background: [0.8, 0.8, 1.0, 1.0],
+            panel_selected_background: [0.9, 0.9, 1.0, 1.0],
+        }
+    }
+}
+
+/// Loads every `.toml` theme in a directory, sorted by name.
+///
+/// Unreadable files are reported and skipped. The built-in light theme is
+/// used if nothing loads.
+pub fn load_themes<P: AsRef<Path>>(dir: P) -> Vec<Theme> {
+    let mut themes = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            if path.extension().is_none_or(|ext| ext != "toml") {
+                continue;
+            }
+            let theme = fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|text| toml::from_str::<Theme>(&text).map_err(|err| err.to_string()));
+            match theme {
+                Ok(theme) => themes.push(theme),
+                Err(err) => warn!("Ignoring theme {}: {}", path.display(), err),
+            }
+        }
+    }
+    if themes.is_empty() {
+        themes.push(Theme::default());
+    }
+    themes.sort_by(|a, b| a.name.cmp(&b.name));
+    themes
+}
+
+/// Finds a theme by name, falling back to the first one.
+pub fn find<'a>(themes: &'a [Theme], name: &str) -> &'a Theme {
+    themes
+        .iter()
+        .find(|theme| theme.name == name)
+        .unwrap_or(&themes[0])
+}
+
+impl Theme {
+    /// Maximal-contrast colors for low-vision players.
+    pub fn high_contrast() -> Theme {
+        Theme {
+            name: "high contrast".to_string(),
+            background: [0.0, 0.0, 0.0, 1.0],
+            board_background: [0.0, 0.0, 0.0, 1.0],
+            board_edge: [1.0; 4],
+            section_edge: [1.0; 4],
+            cell_edge: [1.0; 4],
+            selected_cell_background: [0.0, 0.0, 0.6, 1.0],
+            selected_cell_border: [1.0, 1.0, 0.0, 1.0],
+            focus_ring: [0.0, 1.0, 0.0, 1.0],
+            hover_cell_background: [1.0, 1.0, 1.0, 0.25],
+            house_background: [0.2, 0.2, 0.2, 1.0],
+            text: [1.0; 4],
+            entry_text: [1.0, 1.0, 0.0, 1.0],
+            conflict_text: [1.0, 0.3, 0.3, 1.0],
+            correct_text: [0.0, 1.0, 0.0, 1.0],
+            flash: [0.0, 1.0, 1.0, 0.6],
+            confetti: vec![[1.0, 1.0, 0.0, 1.0], [0.0, 1.0, 1.0, 1.0], [1.0; 4]],
+            partners: vec![
+                [1.0, 0.5, 0.0, 1.0],
+                [0.0, 1.0, 1.0, 1.0],
+                [1.0, 0.0, 1.0, 1.0],
+            ],
+            panel_background: [0.0, 0.0, 0.0, 1.0],
+            panel_selected_background: [0.0, 0.0, 0.6, 1.0],
+        }
+    }
+}
+
+/// The theme to draw with: the named one, or high contrast when that mode is on.
+pub fn select(themes: &[Theme], name: &str, high_contrast: bool) -> Theme {
+    if high_contrast {
+        Theme::high_contrast()
+    } else {
+        find(themes, name).clone()
+    }
+}
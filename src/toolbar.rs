@@ -0,0 +1,205 @@
+use crate::text::{draw_text, text_width};
+use crate::theme::Theme;
+use crate::GameAction;
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{Context, Graphics};
+use piston::input::GenericEvent;
+
+/// Toolbar buttons, in display order.
+pub const BUTTONS: [GameAction; 6] = [
+    GameAction::Undo,
+    GameAction::Redo,
+    GameAction::Erase,
+    GameAction::Notes,
+    GameAction::Hint,
+    GameAction::Check,
+];
+
+/// Button rectangles for a toolbar at `pos` with the given size.
+fn button_rects(pos: [f64; 2], size: [f64; 2], gap: f64) -> Vec<[f64; 4]> {
+    let count = BUTTONS.len() as f64;
+    let width = (size[0] - gap * (count - 1.0)) / count;
+    (0..BUTTONS.len())
+        .map(|k| [pos[0] + k as f64 * (width + gap), pos[1], width, size[1]])
+        .collect()
+}
+
+fn inside(rect: [f64; 4], pos: [f64; 2]) -> bool {
+    pos[0] >= rect[0]
+        && pos[0] <= rect[0] + rect[2]
+        && pos[1] >= rect[1]
+        && pos[1] <= rect[1] + rect[3]
+}
+
+pub struct ToolbarController {
+    /// Button under the mouse pointer.
+    pub hovered: Option<usize>,
+    /// Button the mouse was pressed on and not yet released.
+    pub pressed: Option<usize>,
+}
+
+impl Default for ToolbarController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToolbarController {
+    pub fn new() -> ToolbarController {
+        ToolbarController {
+            hovered: None,
+            pressed: None,
+        }
+    }
+
+    /// Handles events, returning the action of a clicked button.
+    ///
+    /// A click counts when the mouse is pressed and released on the same button.
+    pub fn event<E: GenericEvent>(
+        &mut self,
+        pos: [f64; 2],
+        size: [f64; 2],
+        gap: f64,
+        e: &E,
+    ) -> Option<GameAction> {
+        use piston::input::{Button, MouseButton};
+
+        if let Some(cursor) = e.mouse_cursor_args() {
+            self.hovered = button_rects(pos, size, gap)
+                .iter()
+                .position(|&rect| inside(rect, cursor));
+        }
+        if e.cursor_args() == Some(false) {
+            self.hovered = None;
+            self.pressed = None;
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
+            self.pressed = self.hovered;
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = e.release_args() {
+            let pressed = self.pressed.take();
+            if pressed.is_some() && pressed == self.hovered {
+                return pressed.map(|k| BUTTONS[k]);
+            }
+        }
+        None
+    }
+}
+
+pub struct ToolbarViewSettings {
+    pub position: [f64; 2],
+    pub size: [f64; 2],
+    /// Space between neighboring buttons.
+    pub gap: f64,
+    pub font_size: u32,
+    pub ui_scale: f64,
+    pub button_color: Color,
+    /// Fill of a hovered button, or of a mode button that is on.
+    pub hover_color: Color,
+    /// Shade laid over a button while it is held down.
+    pub pressed_color: Color,
+    pub text_color: Color,
+}
+
+impl ToolbarViewSettings {
+    pub fn new(theme: &Theme) -> ToolbarViewSettings {
+        let mut pressed_color = theme.text;
+        pressed_color[3] *= 0.25;
+        ToolbarViewSettings {
+            position: [10.0; 2],
+            size: [400.0, 30.0],
+            gap: 4.0,
+            font_size: 16,
+            ui_scale: 1.0,
+            button_color: theme.panel_background,
+            hover_color: theme.panel_selected_background,
+            pressed_color,
+            text_color: theme.text,
+        }
+    }
+
+    /// Scales buttons and text.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.ui_scale = scale;
+        self.gap = 4.0 * scale;
+        self.font_size = (16.0 * scale).round() as u32;
+    }
+
+    /// Places the toolbar just above a board at `pos` with side length `size`.
+    pub fn layout(&mut self, pos: [f64; 2], size: f64) {
+        let height = 30.0 * self.ui_scale;
+        self.position = [pos[0], pos[1] - height - 8.0 * self.ui_scale];
+        self.size = [size, height];
+    }
+
+    /// Switches to a theme's colors, keeping the layout.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        *self = ToolbarViewSettings {
+            position: self.position,
+            size: self.size,
+            gap: self.gap,
+            font_size: self.font_size,
+            ui_scale: self.ui_scale,
+            ..ToolbarViewSettings::new(theme)
+        };
+    }
+}
+
+pub struct ToolbarView {
+    pub settings: ToolbarViewSettings,
+}
+
+impl ToolbarView {
+    pub fn new(settings: ToolbarViewSettings) -> ToolbarView {
+        ToolbarView { settings }
+    }
+
+    /// Draws the buttons, showing the notes button as on while in notes mode.
+    pub fn draw<G: Graphics, C>(
+        &self,
+        controller: &ToolbarController,
+        notes_mode: bool,
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::Rectangle;
+
+        let settings = &self.settings;
+        let rects = button_rects(settings.position, settings.size, settings.gap);
+        for (k, (rect, action)) in rects.iter().zip(BUTTONS.iter()).enumerate() {
+            let on = notes_mode && *action == GameAction::Notes;
+            let fill = if on || controller.hovered == Some(k) {
+                settings.hover_color
+            } else {
+                settings.button_color
+            };
+            Rectangle::new(fill)
+                .border(graphics::rectangle::Border {
+                    color: settings.text_color,
+                    radius: 0.5,
+                })
+                .draw(*rect, &c.draw_state, c.transform, g);
+            if controller.pressed == Some(k) && controller.hovered == Some(k) {
+                Rectangle::new(settings.pressed_color).draw(*rect, &c.draw_state, c.transform, g);
+            }
+            let label = action.label();
+            let width = text_width(label, settings.font_size, glyphs, c);
+            draw_text(
+                label,
+                settings.font_size,
+                settings.text_color,
+                [
+                    rect[0] + (rect[2] - width) / 2.0,
+                    rect[1] + (rect[3] + settings.font_size as f64) / 2.0 - 2.0,
+                ],
+                glyphs,
+                c,
+                g,
+            );
+        }
+    }
+}
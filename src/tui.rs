@@ -0,0 +1,316 @@
+use crate::config::{Config, InputMode};
+use crate::database::Database;
+use crate::grid::{char_digit, digit_char};
+use crate::{format_duration, new_game, record_result, Action, GameAction, GameboardController};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::Paragraph;
+use ratatui::{DefaultTerminal, Frame};
+use std::io::{self, IsTerminal};
+use std::time::Duration;
+
+/// How long to wait for a key before drawing again, so the timer keeps
+/// running.
+const TICK: Duration = Duration::from_millis(500);
+
+/// Keys to show at the foot of the screen.
+const HELP: &str = "Arrows move  Digits enter  Del erase  Tab notes  ? hint  \
+                    u undo  r redo  c check  p peek  q quit";
+
+/// Box drawing characters for the corner between four cells, indexed by
+/// which lines meet there: up, down, left, and right, from the lowest bit.
+const JUNCTIONS: [char; 16] = [
+    ' ', '╵', '╷', '│', '╴', '┘', '┐', '┤', '╶', '└', '┌', '├', '─', '┴', '┬', '┼',
+];
+
+/// The board and status lines, with the selected cell reversed.
+///
+/// Lines are drawn between boxes, or jigsaw regions, and around the cells
+/// in play. Cage sums and other clues cannot be drawn in text, so the rules
+/// in play are named above the board instead.
+fn text(controller: &GameboardController) -> Text<'static> {
+    let board = &controller.gameboard;
+    let size = board.size();
+    let layout = board.rules.layout();
+    // The box of a cell, or none beyond the board and in samurai gaps.
+    let box_at = |i: isize, j: isize| -> Option<usize> {
+        if i < 0 || j < 0 || i >= size as isize || j >= size as isize {
+            return None;
+        }
+        let ind = [i as usize, j as usize];
+        Some(layout.box_of(size, ind)).filter(|_| board.in_play(ind))
+    };
+    let across = |i: isize, j: isize| box_at(i - 1, j) != box_at(i, j);
+    let down = |i: isize, j: isize| box_at(i, j - 1) != box_at(i, j);
+    let selected = controller.selected_cell;
+    let selected_val = selected.map_or(0, |[i, j]| board.cells[j][i]);
+    let peeking = controller.is_peeking();
+
+    let mut lines = Vec::new();
+    let mut title = format!("Sudoku - {} {}x{}", board.difficulty.name(), size, size);
+    let names = board.rules.names();
+    if !names.is_empty() {
+        title.push_str(&format!(" - {}", names.join(", ")));
+    }
+    lines.push(Line::from(title.bold()));
+    lines.push(Line::default());
+    for j in 0..=size as isize {
+        let mut rule = String::new();
+        for i in 0..=size as isize {
+            let arms = [across(i, j - 1), across(i, j), down(i - 1, j), down(i, j)];
+            let index = arms
+                .iter()
+                .enumerate()
+                .fold(0, |index, (bit, &arm)| index | (arm as usize) << bit);
+            rule.push(JUNCTIONS[index]);
+            if i < size as isize {
+                let fill = if down(i, j) { "───" } else { "   " };
+                rule.push_str(fill);
+            }
+        }
+        // Rules crossed only by lines between boxes are left out.
+        if rule.contains('─') {
+            lines.push(Line::from(rule));
+        }
+        if j == size as isize {
+            break;
+        }
+        let mut row = Vec::new();
+        for i in 0..=size as isize {
+            row.push(Span::raw(if across(i, j) { "│" } else { " " }));
+            if i == size as isize {
+                break;
+            }
+            let ind = [i as usize, j as usize];
+            if !board.in_play(ind) {
+                row.push(Span::raw("   "));
+                continue;
+            }
+            let val = board.cells[ind[1]][ind[0]];
+            let dim = Style::new().add_modifier(Modifier::DIM);
+            let (text, mut style) = match val {
+                0 if peeking => (digit_char(board.solution[ind[1]][ind[0]]), dim),
+                0 if board.notes[ind[1]][ind[0]] != 0 => ('*', dim),
+                0 => ('·', dim),
+                _ if board.is_given(ind) => (digit_char(val), Style::new().bold()),
+                _ if controller.wrong_entries.contains(&ind) || board.conflicts(ind) => {
+                    (digit_char(val), Style::new().red())
+                }
+                _ => (digit_char(val), Style::new().cyan()),
+            };
+            if Some(ind) == selected {
+                style = style.reversed();
+            } else if val != 0 && val == selected_val {
+                style = style.underlined();
+            }
+            row.push(Span::styled(format!(" {} ", text), style));
+        }
+        lines.push(Line::from(row));
+    }
+    lines.push(Line::default());
+
+    let mut status = format!("Time {}", format_duration(controller.elapsed()));
+    match controller.mistake_limit {
+        Some(limit) => status.push_str(&format!("  Mistakes {}/{}", controller.mistakes, limit)),
+        None => status.push_str(&format!("  Mistakes {}", controller.mistakes)),
+    }
+    status.push_str(&format!(
+        "  Hints {}  {:.0}% done",
+        controller.hints,
+        board.progress() * 100.0
+    ));
+    if controller.notes_mode {
+        status.push_str("  [Notes]");
+    }
+    lines.push(Line::from(status));
+    let mut cell = String::new();
+    if let Some([i, j]) = selected {
+        cell = format!("R{}C{}", j + 1, i + 1);
+        let notes: Vec<String> = (1..=board.digits() as u8)
+            .filter(|&val| board.cells[j][i] == 0 && board.has_note([i, j], val))
+            .map(|val| digit_char(val).to_string())
+            .collect();
+        if !notes.is_empty() {
+            cell.push_str(&format!("  Notes: {}", notes.join(" ")));
+        }
+    }
+    lines.push(Line::from(cell));
+    let message = if board.is_solved() {
+        format!(
+            "Solved in {}! Enter for a new game, q to quit",
+            format_duration(controller.elapsed())
+        )
+    } else if controller.is_lost() {
+        "Out of mistakes. Enter for a new game, q to quit".to_string()
+    } else {
+        controller.message().unwrap_or("").to_string()
+    };
+    lines.push(Line::from(message));
+    let mut help = HELP.to_string();
+    if board.digits() > 9 {
+        help.push_str("  (letters with Alt)");
+    }
+    lines.push(Line::from(help.dim()));
+    Text::from(lines)
+}
+
+fn draw(frame: &mut Frame, controller: &GameboardController) {
+    frame.render_widget(Paragraph::new(text(controller)), frame.area());
+}
+
+/// Maps a key to the move it makes on the board, if any.
+///
+/// Letters that are digits on this board enter them, and take Alt for their
+/// shortcuts.
+fn key_action(controller: &GameboardController, key: KeyEvent, config: &Config) -> Option<Action> {
+    let size = controller.gameboard.size();
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+    if let (KeyCode::Char(ch), false, false) = (key.code, ctrl, alt) {
+        if let Some(val) =
+            char_digit(ch).filter(|&val| val as usize <= controller.gameboard.digits())
+        {
+            return controller.digit_action(val, config);
+        }
+    }
+    let command = match (key.code, ctrl) {
+        (KeyCode::Up, _) => return Some(controller.move_action([0, size - 1], config)),
+        (KeyCode::Down, _) => return Some(controller.move_action([0, 1], config)),
+        (KeyCode::Left, _) => return Some(controller.move_action([size - 1, 0], config)),
+        (KeyCode::Right, _) => return Some(controller.move_action([1, 0], config)),
+        (KeyCode::Delete | KeyCode::Backspace, _) | (KeyCode::Char('0' | '.'), false) => {
+            GameAction::Erase
+        }
+        (KeyCode::Tab, _) | (KeyCode::Char('n'), false) => GameAction::Notes,
+        (KeyCode::Char('?' | 'h'), false) => GameAction::Hint,
+        (KeyCode::Char('u'), false) | (KeyCode::Char('z'), true) => GameAction::Undo,
+        (KeyCode::Char('r'), false) | (KeyCode::Char('y'), true) => GameAction::Redo,
+        (KeyCode::Char('c'), false) => GameAction::Check,
+        (KeyCode::Char('p'), false) => GameAction::Peek,
+        _ => return None,
+    };
+    Some(Action::Command(command))
+}
+
+/// Plays a game in the terminal until the player quits, starting new games
+/// of the same difficulty and size once one is over.
+///
+/// Moves go through the same controller as the window's, so hints, checks,
+/// undo, and mistakes work alike, and finished games are recorded in the
+/// puzzle database. The terminal is put back as it was on the way out, even
+/// after a panic.
+pub fn play(
+    controller: GameboardController,
+    database: &Option<Database>,
+    config: &Config,
+) -> Result<(), String> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err("the terminal game needs a terminal".to_string());
+    }
+    let mut terminal = ratatui::try_init().map_err(|err| err.to_string())?;
+    let result = run(&mut terminal, controller, database, config).map_err(|err| err.to_string());
+    ratatui::restore();
+    result
+}
+
+fn run(
+    terminal: &mut DefaultTerminal,
+    mut controller: GameboardController,
+    database: &Option<Database>,
+    config: &Config,
+) -> io::Result<()> {
+    // Digits always go in the selected cell, which follows the arrows.
+    let config = Config {
+        input_mode: InputMode::CellFirst,
+        ..config.clone()
+    };
+    let size = controller.gameboard.size();
+    controller.selected_cell = Some([size / 2; 2]);
+    controller.focused_cell = controller.selected_cell;
+    let mut recorded = false;
+    loop {
+        terminal.draw(|frame| draw(frame, &controller))?;
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let key = match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => key,
+            _ => continue,
+        };
+        if let Some(action) = key_action(&controller, key, &config) {
+            controller.dispatch(action);
+        } else {
+            match (key.code, key.modifiers.contains(KeyModifiers::CONTROL)) {
+                (KeyCode::Enter, _) if controller.is_over() => {
+                    let difficulty = controller.gameboard.difficulty;
+                    let size = controller.gameboard.size();
+                    controller = new_game(difficulty, size, database, &config);
+                    controller.selected_cell = Some([size / 2; 2]);
+                    controller.focused_cell = controller.selected_cell;
+                    recorded = false;
+                }
+                (KeyCode::Char('q') | KeyCode::Esc, _) | (KeyCode::Char('c'), true) => {
+                    return Ok(())
+                }
+                _ => {}
+            }
+        }
+        if controller.is_over() && !recorded {
+            record_result(database, &mut controller);
+            recorded = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use sudoku::grid::Grid;
+    use sudoku::Gameboard;
+
+    fn controller(size: usize) -> GameboardController {
+        let gameboard = Gameboard::from_puzzle(Grid::new(size), Grid::new(size));
+        let mut controller = GameboardController::new(gameboard);
+        controller.selected_cell = Some([0, 0]);
+        controller
+    }
+
+    #[test]
+    fn letter_digits_take_alt_for_shortcuts() {
+        let config = Config::default();
+        let key = |modifiers| KeyEvent::new(KeyCode::Char('c'), modifiers);
+        let classic = controller(9);
+        assert_eq!(
+            key_action(&classic, key(KeyModifiers::NONE), &config),
+            Some(Action::Command(GameAction::Check))
+        );
+        let large = controller(16);
+        assert_eq!(
+            key_action(&large, key(KeyModifiers::NONE), &config),
+            Some(Action::Enter([0, 0], 12))
+        );
+        assert_eq!(
+            key_action(&large, key(KeyModifiers::ALT), &config),
+            Some(Action::Command(GameAction::Check))
+        );
+    }
+
+    #[test]
+    fn draws_the_board() {
+        let mut terminal = Terminal::new(TestBackend::new(60, 30)).unwrap();
+        let controller = controller(9);
+        terminal.draw(|frame| draw(frame, &controller)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let row = |y: u16| -> String {
+            (0..buffer.area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect()
+        };
+        assert!(row(0).starts_with("Sudoku - "));
+        assert!(row(2).starts_with("┌───────────┬"));
+        assert!(buffer[(2, 3)].modifier.contains(Modifier::REVERSED));
+    }
+}
@@ -0,0 +1,1342 @@
+use crate::grid::{box_shape, Grid};
+use crate::{House, SIZE};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Most cells a generated cage grows to.
+const MAX_CAGE_SIZE: usize = 4;
+
+/// Least difference between neighbors on a German whispers line.
+const WHISPER_DIFFERENCE: u8 = 5;
+
+/// Whispers lines made for a puzzle, and the most cells each runs to.
+const WHISPERS: usize = 6;
+const MAX_WHISPER_LENGTH: usize = 8;
+
+/// Cells given an even or odd mark in an even/odd puzzle.
+const PARITY_MARKS: usize = 16;
+
+/// Thermometers made for a thermo puzzle, and the most cells each runs to.
+const THERMOS: usize = 8;
+const MAX_THERMO_LENGTH: usize = 6;
+
+/// Cell swaps tried between neighboring regions when shaping jigsaw
+/// regions.
+const REGION_SWAPS: usize = 400;
+
+/// Rows and columns of a samurai board.
+pub const SAMURAI_SIZE: usize = 21;
+
+/// Grids on a samurai board.
+pub const SAMURAI_GRIDS: usize = 5;
+
+/// Top left cells of the grids of a samurai board: the four corners, then
+/// the middle, which shares a corner box with each of the others.
+const SAMURAI_CORNERS: [[usize; 2]; SAMURAI_GRIDS] = [[0, 0], [12, 0], [0, 12], [12, 12], [6, 6]];
+
+/// Bit mask of the digits 1 up to `digits`.
+pub fn digit_mask(digits: usize) -> u32 {
+    ((1u32 << digits) - 1) << 1
+}
+
+/// The samurai grids a cell is in, with its place inside each.
+pub fn samurai_grids([x, y]: [usize; 2]) -> impl Iterator<Item = (usize, [usize; 2])> {
+    SAMURAI_CORNERS
+        .iter()
+        .enumerate()
+        .filter(move |(_, &[left, top])| {
+            (left..left + SIZE).contains(&x) && (top..top + SIZE).contains(&y)
+        })
+        .map(move |(g, &[left, top])| (g, [x - left, y - top]))
+}
+
+/// The cell at a place inside a samurai grid.
+pub fn samurai_cell(g: usize, [i, j]: [usize; 2]) -> [usize; 2] {
+    let [left, top] = SAMURAI_CORNERS[g];
+    [left + i, top + j]
+}
+
+/// Windoku windows across and down a board of a size. Each is the shape
+/// of a box, with a line of cells between windows and around them.
+fn window_counts(size: usize) -> [usize; 2] {
+    let [width, height] = box_shape(size);
+    [(size - 1) / (width + 1), (size - 1) / (height + 1)]
+}
+
+/// The windoku window containing a cell on a board of a size, if any,
+/// numbered across then down.
+pub fn window_of(size: usize, [x, y]: [usize; 2]) -> Option<usize> {
+    let [width, height] = box_shape(size);
+    let [across, down] = window_counts(size);
+    let (x, y) = (x.checked_sub(1)?, y.checked_sub(1)?);
+    let (a, b) = (x / (width + 1), y / (height + 1));
+    if x % (width + 1) == width || y % (height + 1) == height || a >= across || b >= down {
+        return None;
+    }
+    Some(b * across + a)
+}
+
+/// The cells of a windoku window on a board of a size, in reading order.
+pub fn window_cells(size: usize, w: usize) -> Vec<[usize; 2]> {
+    let [width, height] = box_shape(size);
+    let [across, _] = window_counts(size);
+    let [left, top] = [1 + w % across * (width + 1), 1 + w / across * (height + 1)];
+    (0..size)
+        .map(|k| [left + k % width, top + k / width])
+        .collect()
+}
+
+/// Every windoku window on a board of a size.
+pub fn windows(size: usize) -> usize {
+    let [across, down] = window_counts(size);
+    across * down
+}
+
+/// Kinds of puzzle a new game can be.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VariantKind {
+    Classic,
+    Diagonal,
+    Killer,
+    Jigsaw,
+    Samurai,
+    Sandwich,
+    Thermo,
+    Kropki,
+    EvenOdd,
+    GreaterThan,
+    Whispers,
+    Windoku,
+}
+
+impl VariantKind {
+    pub const ALL: [VariantKind; 12] = [
+        VariantKind::Classic,
+        VariantKind::Diagonal,
+        VariantKind::Killer,
+        VariantKind::Jigsaw,
+        VariantKind::Samurai,
+        VariantKind::Sandwich,
+        VariantKind::Thermo,
+        VariantKind::Kropki,
+        VariantKind::EvenOdd,
+        VariantKind::GreaterThan,
+        VariantKind::Whispers,
+        VariantKind::Windoku,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            VariantKind::Classic => "Classic",
+            VariantKind::Diagonal => "X-Sudoku",
+            VariantKind::Killer => "Killer",
+            VariantKind::Jigsaw => "Jigsaw",
+            VariantKind::Samurai => "Samurai",
+            VariantKind::Sandwich => "Sandwich",
+            VariantKind::Thermo => "Thermo",
+            VariantKind::Kropki => "Kropki",
+            VariantKind::EvenOdd => "Even/Odd",
+            VariantKind::GreaterThan => "Greater-than",
+            VariantKind::Whispers => "German Whispers",
+            VariantKind::Windoku => "Windoku",
+        }
+    }
+
+    /// The kind with a name, ignoring case.
+    pub fn from_name(name: &str) -> Option<VariantKind> {
+        VariantKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Cells whose digits add up to a sum, without repeating.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cage {
+    pub sum: u32,
+    pub cells: Vec<[usize; 2]>,
+}
+
+impl Cage {
+    /// Bit mask of the digits a cell of the cage can take, given the digits
+    /// already in the cage's other cells.
+    fn allowed(&self, cells: &Grid, ind: [usize; 2]) -> u32 {
+        let size = cells.size() as u32;
+        let mut used = 0u32;
+        let mut total = 0;
+        let mut empty = 0;
+        for &[i, j] in self.cells.iter().filter(|&&cell| cell != ind) {
+            match cells[j][i] {
+                0 => empty += 1,
+                val => {
+                    used |= 1 << val;
+                    total += u32::from(val);
+                }
+            }
+        }
+        let free = digit_mask(cells.size()) & !used;
+        (1..=size as u8)
+            .filter(|&val| free & (1 << val) != 0)
+            .filter(|&val| {
+                // The other empty cells must be able to make up the rest
+                // with digits not yet used.
+                let rest = match self.sum.checked_sub(total + u32::from(val)) {
+                    Some(rest) => rest,
+                    None => return false,
+                };
+                let others: Vec<u32> = (1..=size)
+                    .filter(|&other| other != u32::from(val) && free & (1 << other) != 0)
+                    .collect();
+                if others.len() < empty {
+                    return false;
+                }
+                let least: u32 = others[..empty].iter().sum();
+                let most: u32 = others[others.len() - empty..].iter().sum();
+                (least..=most).contains(&rest)
+            })
+            .fold(0, |mask, val| mask | 1 << val)
+    }
+
+    /// Whether a digit repeats in the cage, or its digits add up to more
+    /// than the sum, or to anything else once every cell is filled.
+    fn broken(&self, cells: &Grid) -> bool {
+        let mut used = 0u32;
+        let mut total = 0;
+        let mut full = true;
+        for &[i, j] in &self.cells {
+            let val = cells[j][i];
+            if val == 0 {
+                full = false;
+                continue;
+            }
+            if used & (1 << val) != 0 {
+                return true;
+            }
+            used |= 1 << val;
+            total += u32::from(val);
+        }
+        total > self.sum || full && total != self.sum
+    }
+
+    /// Lines outlining the cage just inside its cells, as `[x1, y1, x2, y2]`
+    /// in cells from the board's top left, with an inset a fraction of a
+    /// cell.
+    ///
+    /// Sides run on to a cell's edge where the cage continues, so they meet
+    /// the next cell's.
+    pub fn outline(&self, inset: f64) -> Vec<[f64; 4]> {
+        let caged = |i: Option<usize>, j: Option<usize>| match (i, j) {
+            (Some(i), Some(j)) => self.cells.contains(&[i, j]),
+            _ => false,
+        };
+        let mut lines = Vec::new();
+        for &[i, j] in &self.cells {
+            let up = caged(Some(i), j.checked_sub(1));
+            let down = caged(Some(i), Some(j + 1));
+            let left = caged(i.checked_sub(1), Some(j));
+            let right = caged(Some(i + 1), Some(j));
+            let [x, y] = [i as f64, j as f64];
+            let [x2, y2] = [x + 1.0, y + 1.0];
+            let left_end = if left { x } else { x + inset };
+            let right_end = if right { x2 } else { x2 - inset };
+            let top_end = if up { y } else { y + inset };
+            let bottom_end = if down { y2 } else { y2 - inset };
+            if !up {
+                lines.push([left_end, y + inset, right_end, y + inset]);
+            }
+            if !down {
+                lines.push([left_end, y2 - inset, right_end, y2 - inset]);
+            }
+            if !left {
+                lines.push([x + inset, top_end, x + inset, bottom_end]);
+            }
+            if !right {
+                lines.push([x2 - inset, top_end, x2 - inset, bottom_end]);
+            }
+        }
+        lines
+    }
+
+    /// The cell whose corner shows the sum: the topmost, then leftmost.
+    pub fn label_cell(&self) -> [usize; 2] {
+        self.cells
+            .iter()
+            .copied()
+            .min_by_key(|&[i, j]| (j, i))
+            .unwrap_or([0, 0])
+    }
+}
+
+/// A line of cells whose digits strictly increase from the bulb, its first
+/// cell.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Thermo {
+    pub cells: Vec<[usize; 2]>,
+}
+
+impl Thermo {
+    /// Bit mask of the digits a cell on the thermometer can take: more than
+    /// the cells before it by at least their distance, and less than the
+    /// cells after it the same way.
+    fn allowed(&self, cells: &Grid, ind: [usize; 2]) -> u32 {
+        let digits = cells.size();
+        let p = match self.cells.iter().position(|&cell| cell == ind) {
+            Some(p) => p,
+            None => return digit_mask(digits),
+        };
+        let mut low = p + 1;
+        let mut high = digits.saturating_sub(self.cells.len() - 1 - p);
+        for (q, &[i, j]) in self.cells.iter().enumerate() {
+            let val = cells[j][i] as usize;
+            if val == 0 || q == p {
+                continue;
+            }
+            if q < p {
+                low = low.max(val + (p - q));
+            } else {
+                high = high.min(val.saturating_sub(q - p));
+            }
+        }
+        (low..=high).fold(0, |mask, val| mask | 1 << val)
+    }
+
+    /// Whether the filled cells of the thermometer fail to increase from the
+    /// bulb.
+    fn broken(&self, cells: &Grid) -> bool {
+        let filled: Vec<u8> = self
+            .cells
+            .iter()
+            .map(|&[i, j]| cells[j][i])
+            .filter(|&val| val != 0)
+            .collect();
+        filled.windows(2).any(|pair| pair[0] >= pair[1])
+    }
+}
+
+/// A German whispers line, along which neighboring digits differ by at
+/// least 5.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Whisper {
+    pub cells: Vec<[usize; 2]>,
+}
+
+impl Whisper {
+    /// The cells next to a cell along the line.
+    fn beside(&self, ind: [usize; 2]) -> impl Iterator<Item = [usize; 2]> + '_ {
+        let p = self.cells.iter().position(|&cell| cell == ind);
+        let before = p.and_then(|p| p.checked_sub(1));
+        let after = p.map(|p| p + 1).filter(|&q| q < self.cells.len());
+        IntoIterator::into_iter([before, after])
+            .flatten()
+            .map(move |q| self.cells[q])
+    }
+
+    /// Bit mask of the digits a cell on the line can take: far enough from
+    /// its filled neighbors on the line, and from some digit for the empty
+    /// ones.
+    fn allowed(&self, cells: &Grid, ind: [usize; 2]) -> u32 {
+        let digits = cells.size() as u8;
+        let far = |a: u8, b: u8| a.abs_diff(b) >= WHISPER_DIFFERENCE;
+        let mut mask = digit_mask(cells.size());
+        for [i, j] in self.beside(ind) {
+            for val in 1..=digits {
+                let fits = match cells[j][i] {
+                    0 => (1..=digits).any(|other| far(val, other)),
+                    other => far(val, other),
+                };
+                if !fits {
+                    mask &= !(1 << val);
+                }
+            }
+        }
+        mask
+    }
+
+    /// Whether a filled cell is too close to a filled neighbor on the line.
+    fn broken(&self, cells: &Grid, [x, y]: [usize; 2]) -> bool {
+        let val = cells[y][x];
+        val != 0
+            && self
+                .beside([x, y])
+                .any(|[i, j]| cells[j][i] != 0 && cells[j][i].abs_diff(val) < WHISPER_DIFFERENCE)
+    }
+}
+
+/// Lays whispers lines along paths of orthogonal neighbors whose digits
+/// differ by at least 5 in a solved grid, without sharing cells.
+fn make_whispers<R: Rng>(solution: &Grid, rng: &mut R) -> Vec<Whisper> {
+    let size = solution.size();
+    let mut used: Grid<bool> = Grid::new(size);
+    let mut order: Vec<[usize; 2]> = solution.positions().collect();
+    for k in (1..order.len()).rev() {
+        order.swap(k, rng.gen_range(0, k + 1));
+    }
+    let mut whispers = Vec::new();
+    for start in order {
+        if whispers.len() == WHISPERS {
+            break;
+        }
+        if used[start[1]][start[0]] {
+            continue;
+        }
+        let mut cells = vec![start];
+        while cells.len() < MAX_WHISPER_LENGTH {
+            let [x, y] = cells[cells.len() - 1];
+            let next: Vec<[usize; 2]> = neighbors(size, [x, y])
+                .filter(|&[i, j]| !used[j][i] && !cells.contains(&[i, j]))
+                .filter(|&[i, j]| solution[j][i].abs_diff(solution[y][x]) >= WHISPER_DIFFERENCE)
+                .collect();
+            if next.is_empty() {
+                break;
+            }
+            cells.push(next[rng.gen_range(0, next.len())]);
+        }
+        if cells.len() < 3 {
+            continue;
+        }
+        for &[i, j] in &cells {
+            used[j][i] = true;
+        }
+        whispers.push(Whisper { cells });
+    }
+    whispers
+}
+
+/// A sign between two neighboring cells saying which digit is greater.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inequality {
+    pub greater: [usize; 2],
+    pub less: [usize; 2],
+}
+
+/// Puts a sign on every edge between neighboring cells of the same box of
+/// a solved grid.
+fn make_inequalities(solution: &Grid) -> Vec<Inequality> {
+    let size = solution.size();
+    let mut signs = Vec::new();
+    for [x, y] in solution.positions() {
+        let box_of = |ind| Variant::Classic.box_of(size, ind);
+        for [i, j] in neighbors(size, [x, y]).filter(|&[i, j]| [i, j] > [x, y]) {
+            if box_of([i, j]) != box_of([x, y]) {
+                continue;
+            }
+            let (greater, less) = if solution[y][x] > solution[j][i] {
+                ([x, y], [i, j])
+            } else {
+                ([i, j], [x, y])
+            };
+            signs.push(Inequality { greater, less });
+        }
+    }
+    signs
+}
+
+/// The two strokes of a sign's chevron, as `[x1, y1, x2, y2]` in cells from
+/// the board's top left, meeting at a point towards the lesser cell.
+pub fn sign_strokes(sign: &Inequality) -> [[f64; 4]; 2] {
+    let [gx, gy] = sign.greater.map(|k| k as f64);
+    let [lx, ly] = sign.less.map(|k| k as f64);
+    let ([dx, dy], [px, py]) = ([lx - gx, ly - gy], [gy - ly, lx - gx]);
+    let [mx, my] = [(gx + lx) / 2.0 + 0.5, (gy + ly) / 2.0 + 0.5];
+    let (depth, width) = (0.07, 0.12);
+    let tip = [mx + dx * depth, my + dy * depth];
+    let arm = |side: f64| {
+        [
+            mx - dx * depth + px * width * side,
+            my - dy * depth + py * width * side,
+        ]
+    };
+    let ([ax, ay], [bx, by]) = (arm(1.0), arm(-1.0));
+    [[ax, ay, tip[0], tip[1]], [bx, by, tip[0], tip[1]]]
+}
+
+/// Bit mask of the digits a cell can take beside the signs it touches: more
+/// than a filled cell it is greater than, and at least 2, and the other way
+/// for cells it is less than.
+fn inequality_allowed(signs: &[Inequality], cells: &Grid, ind: [usize; 2]) -> u32 {
+    let digits = cells.size() as u8;
+    let (mut low, mut high) = (1, digits);
+    for sign in signs {
+        if sign.greater == ind {
+            let [i, j] = sign.less;
+            low = low.max(cells[j][i] + 1).max(2);
+        } else if sign.less == ind {
+            let [i, j] = sign.greater;
+            high = match cells[j][i] {
+                0 => high.min(digits - 1),
+                other => high.min(other - 1),
+            };
+        }
+    }
+    (low..=high).fold(0, |mask, val| mask | 1 << val)
+}
+
+/// Whether the signs a filled cell touches disagree with its filled
+/// neighbors.
+fn inequality_broken(signs: &[Inequality], cells: &Grid, ind: [usize; 2]) -> bool {
+    let value = |[i, j]: [usize; 2]| cells[j][i];
+    value(ind) != 0
+        && signs
+            .iter()
+            .filter(|sign| sign.greater == ind || sign.less == ind)
+            .any(|sign| {
+                let (greater, less) = (value(sign.greater), value(sign.less));
+                greater != 0 && less != 0 && greater <= less
+            })
+}
+
+/// Whether a digit is even or odd.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Parity {
+    Even,
+    Odd,
+}
+
+impl Parity {
+    pub fn of(val: u8) -> Parity {
+        if val.is_multiple_of(2) {
+            Parity::Even
+        } else {
+            Parity::Odd
+        }
+    }
+
+    /// Bit mask of the digits of this parity, from 1 up to `digits`.
+    fn mask(self, digits: usize) -> u32 {
+        let odd = 0xaaaa_aaaa & digit_mask(digits);
+        match self {
+            Parity::Even => digit_mask(digits) & !odd,
+            Parity::Odd => odd,
+        }
+    }
+}
+
+/// A cell marked to hold a digit of a parity.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParityMark {
+    pub parity: Parity,
+    pub cell: [usize; 2],
+}
+
+/// Marks the parity of some cells of a solved grid.
+fn make_parity_marks<R: Rng>(solution: &Grid, rng: &mut R) -> Vec<ParityMark> {
+    let mut order: Vec<[usize; 2]> = solution.positions().collect();
+    for k in (1..order.len()).rev() {
+        order.swap(k, rng.gen_range(0, k + 1));
+    }
+    order
+        .into_iter()
+        .take(PARITY_MARKS)
+        .map(|[i, j]| ParityMark {
+            parity: Parity::of(solution[j][i]),
+            cell: [i, j],
+        })
+        .collect()
+}
+
+/// Kinds of dot on the edge between two neighboring cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DotKind {
+    /// The digits are consecutive.
+    White,
+    /// One digit is double the other.
+    Black,
+}
+
+impl DotKind {
+    /// Whether two digits fit a dot of this kind.
+    fn fits(self, a: u8, b: u8) -> bool {
+        match self {
+            DotKind::White => a + 1 == b || b + 1 == a,
+            DotKind::Black => a == 2 * b || b == 2 * a,
+        }
+    }
+}
+
+/// A dot on the edge between two neighboring cells.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dot {
+    pub kind: DotKind,
+    pub cells: [[usize; 2]; 2],
+}
+
+/// The dots of a kropki puzzle. With `negative` set every dot is given, so
+/// neighbors without a dot between them fit neither kind.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Kropki {
+    pub dots: Vec<Dot>,
+    pub negative: bool,
+}
+
+impl Kropki {
+    /// The dots of a solved grid: one on every edge whose digits fit a
+    /// kind, picking either for the 1 and 2 that fit both.
+    fn new<R: Rng>(solution: &Grid, rng: &mut R) -> Kropki {
+        let mut dots = Vec::new();
+        for [x, y] in solution.positions() {
+            for [i, j] in neighbors(solution.size(), [x, y]).filter(|&[i, j]| [i, j] > [x, y]) {
+                let (a, b) = (solution[y][x], solution[j][i]);
+                let kinds: Vec<DotKind> = IntoIterator::into_iter([DotKind::White, DotKind::Black])
+                    .filter(|kind| kind.fits(a, b))
+                    .collect();
+                if !kinds.is_empty() {
+                    let kind = kinds[rng.gen_range(0, kinds.len())];
+                    dots.push(Dot {
+                        kind,
+                        cells: [[x, y], [i, j]],
+                    });
+                }
+            }
+        }
+        Kropki {
+            dots,
+            negative: true,
+        }
+    }
+
+    /// The kind of dot between two cells, if any.
+    fn dot(&self, a: [usize; 2], b: [usize; 2]) -> Option<DotKind> {
+        self.dots
+            .iter()
+            .find(|dot| dot.cells == [a, b] || dot.cells == [b, a])
+            .map(|dot| dot.kind)
+    }
+
+    /// Whether two neighboring digits agree with the edge between them.
+    fn fits(&self, edge: Option<DotKind>, a: u8, b: u8) -> bool {
+        match edge {
+            Some(kind) => kind.fits(a, b),
+            None => !self.negative || !DotKind::White.fits(a, b) && !DotKind::Black.fits(a, b),
+        }
+    }
+
+    /// Bit mask of the digits a cell can take beside its filled neighbors,
+    /// and, across a dot, beside some digit an empty neighbor could take.
+    fn allowed(&self, cells: &Grid, ind: [usize; 2]) -> u32 {
+        let digits = cells.size() as u8;
+        let mut mask = digit_mask(cells.size());
+        for [i, j] in neighbors(cells.size(), ind) {
+            let edge = self.dot(ind, [i, j]);
+            let other = cells[j][i];
+            for val in 1..=digits {
+                let fits = match other {
+                    0 => {
+                        edge.is_none() || (1..=digits).any(|b| b != val && self.fits(edge, val, b))
+                    }
+                    other => self.fits(edge, val, other),
+                };
+                if !fits {
+                    mask &= !(1 << val);
+                }
+            }
+        }
+        mask
+    }
+
+    /// Whether a cell's digit disagrees with a filled neighbor across the
+    /// edge between them.
+    fn broken(&self, cells: &Grid, [x, y]: [usize; 2]) -> bool {
+        let val = cells[y][x];
+        val != 0
+            && neighbors(cells.size(), [x, y]).any(|[i, j]| {
+                cells[j][i] != 0 && !self.fits(self.dot([x, y], [i, j]), val, cells[j][i])
+            })
+    }
+}
+
+/// Sums of the digits sandwiched between the 1 and the highest digit in each
+/// row and column, by row or column from the top left. Lines without a sum
+/// can have anything between.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sandwiches {
+    pub rows: Vec<Option<u32>>,
+    pub columns: Vec<Option<u32>>,
+}
+
+/// The sum of the digits between the 1 and the highest digit in a line, if
+/// both are in it.
+pub fn sandwich_sum(line: &[u8]) -> Option<u32> {
+    let high = line.len() as u8;
+    let a = line.iter().position(|&val| val == 1)?;
+    let b = line.iter().position(|&val| val == high)?;
+    Some(
+        line[a.min(b) + 1..a.max(b)]
+            .iter()
+            .map(|&val| u32::from(val))
+            .sum(),
+    )
+}
+
+/// Whether a partly filled line can still have its sandwich add up to a
+/// sum: the 1 and the highest digit have places to go with the empty cells
+/// between them able to make up the rest with digits not yet used.
+fn sandwich_possible(line: &[u8], sum: u32) -> bool {
+    let high = line.len() as u8;
+    let used = line.iter().fold(0u32, |used, &val| used | 1 << val);
+    let places = |digit: u8| {
+        line.iter()
+            .enumerate()
+            .filter(move |&(_, &val)| val == digit || val == 0 && used & (1 << digit) == 0)
+            .map(|(k, _)| k)
+    };
+    // The digits the empty cells between could take, smallest first.
+    let free: Vec<u32> = (2..high)
+        .filter(|&val| used & (1 << val) == 0)
+        .map(u32::from)
+        .collect();
+    places(1).any(|a| {
+        places(high).any(|b| {
+            if a == b {
+                return false;
+            }
+            let between = &line[a.min(b) + 1..a.max(b)];
+            let empty = between.iter().filter(|&&val| val == 0).count();
+            let total: u32 = between.iter().map(|&val| u32::from(val)).sum();
+            let rest = match sum.checked_sub(total) {
+                Some(rest) => rest,
+                None => return false,
+            };
+            if free.len() < empty {
+                return false;
+            }
+            let least: u32 = free[..empty].iter().sum();
+            let most: u32 = free[free.len() - empty..].iter().sum();
+            (least..=most).contains(&rest)
+        })
+    })
+}
+
+impl Sandwiches {
+    /// The sandwich sums of a solved grid.
+    fn new(solution: &Grid) -> Sandwiches {
+        let size = solution.size();
+        let columns = (0..size)
+            .map(|i| {
+                let column: Vec<u8> = (0..size).map(|j| solution[j][i]).collect();
+                sandwich_sum(&column)
+            })
+            .collect();
+        let rows = solution.iter().map(sandwich_sum).collect();
+        Sandwiches { rows, columns }
+    }
+
+    /// The row and column through a cell, each with the cell's place in it
+    /// and its sum, for the lines that have sums.
+    fn lines(&self, cells: &Grid, [x, y]: [usize; 2]) -> Vec<(Vec<u8>, usize, u32)> {
+        let size = cells.size();
+        let row = self.rows.get(y).copied().flatten();
+        let row = row.map(|sum| (cells[y].to_vec(), x, sum));
+        let column = self.columns.get(x).copied().flatten();
+        let column = column.map(|sum| ((0..size).map(|j| cells[j][x]).collect(), y, sum));
+        IntoIterator::into_iter([row, column]).flatten().collect()
+    }
+
+    /// Bit mask of the digits a cell can take and still leave its row and
+    /// column able to make their sums.
+    fn allowed(&self, cells: &Grid, ind: [usize; 2]) -> u32 {
+        let mut mask = digit_mask(cells.size());
+        for (mut line, k, sum) in self.lines(cells, ind) {
+            let used = line
+                .iter()
+                .enumerate()
+                .filter(|&(other, _)| other != k)
+                .fold(0u32, |used, (_, &val)| used | 1 << val);
+            for val in 1..=cells.size() as u8 {
+                if mask & (1 << val) == 0 {
+                    continue;
+                }
+                line[k] = val;
+                if used & (1 << val) != 0 || !sandwich_possible(&line, sum) {
+                    mask &= !(1 << val);
+                }
+            }
+        }
+        mask
+    }
+
+    /// Whether the digits between the 1 and the highest digit in the row or
+    /// column through a cell add up to more than its sum, or to anything
+    /// else once every cell between is filled.
+    fn broken(&self, cells: &Grid, ind: [usize; 2]) -> bool {
+        self.lines(cells, ind).iter().any(|(line, _, sum)| {
+            let high = line.len() as u8;
+            let a = line.iter().position(|&val| val == 1);
+            let b = line.iter().position(|&val| val == high);
+            let (a, b) = match (a, b) {
+                (Some(a), Some(b)) => (a.min(b), a.max(b)),
+                _ => return false,
+            };
+            let between = &line[a + 1..b];
+            let total: u32 = between.iter().map(|&val| u32::from(val)).sum();
+            total > *sum || total != *sum && !between.contains(&0)
+        })
+    }
+}
+
+/// Region of each cell when regions are the usual 3x3 boxes.
+fn classic_regions() -> Grid {
+    let mut regions = Grid::new(SIZE);
+    for (j, row) in regions.iter_mut().enumerate() {
+        for (i, region) in row.iter_mut().enumerate() {
+            *region = (j / 3 * 3 + i / 3) as u8;
+        }
+    }
+    regions
+}
+
+/// The cells next to a cell on a board of a size, above, left, below, and
+/// right.
+fn neighbors(size: usize, [i, j]: [usize; 2]) -> impl Iterator<Item = [usize; 2]> {
+    let up = j.checked_sub(1).map(|j| [i, j]);
+    let left = i.checked_sub(1).map(|i| [i, j]);
+    let down = Some([i, j + 1]).filter(|_| j + 1 < size);
+    let right = Some([i + 1, j]).filter(|_| i + 1 < size);
+    IntoIterator::into_iter([up, left, down, right]).flatten()
+}
+
+/// Whether the cells of a region are all joined through neighbors.
+fn is_connected(regions: &Grid, region: u8) -> bool {
+    let cells: Vec<[usize; 2]> = (0..SIZE * SIZE)
+        .map(|k| [k % SIZE, k / SIZE])
+        .filter(|&[i, j]| regions[j][i] == region)
+        .collect();
+    let mut reached = vec![cells[0]];
+    let mut k = 0;
+    while k < reached.len() {
+        for next in neighbors(SIZE, reached[k]) {
+            if regions[next[1]][next[0]] == region && !reached.contains(&next) {
+                reached.push(next);
+            }
+        }
+        k += 1;
+    }
+    reached.len() == cells.len()
+}
+
+/// Reshapes the 3x3 boxes into irregular regions of nine joined cells, by
+/// trading cells between neighboring regions.
+fn make_regions<R: Rng>(rng: &mut R) -> Grid {
+    let mut regions = classic_regions();
+    for _ in 0..REGION_SWAPS {
+        // A cell joins a neighboring region, which gives back one of its
+        // cells next to the first region.
+        let a = [rng.gen_range(0, SIZE), rng.gen_range(0, SIZE)];
+        let from = regions[a[1]][a[0]];
+        let others: Vec<u8> = neighbors(SIZE, a)
+            .map(|[i, j]| regions[j][i])
+            .filter(|&region| region != from)
+            .collect();
+        if others.is_empty() {
+            continue;
+        }
+        let to = others[rng.gen_range(0, others.len())];
+        let returns: Vec<[usize; 2]> = (0..SIZE * SIZE)
+            .map(|k| [k % SIZE, k / SIZE])
+            .filter(|&[i, j]| regions[j][i] == to)
+            .filter(|&b| neighbors(SIZE, b).any(|[i, j]| [i, j] != a && regions[j][i] == from))
+            .collect();
+        if returns.is_empty() {
+            continue;
+        }
+        let b = returns[rng.gen_range(0, returns.len())];
+        regions[a[1]][a[0]] = to;
+        regions[b[1]][b[0]] = from;
+        if !is_connected(&regions, from) || !is_connected(&regions, to) {
+            regions[a[1]][a[0]] = from;
+            regions[b[1]][b[0]] = to;
+        }
+    }
+    regions
+}
+
+/// Splits a solved grid into cages of orthogonally connected cells without
+/// repeated digits, summing each.
+fn make_cages<R: Rng>(solution: &Grid, rng: &mut R) -> Vec<Cage> {
+    let mut caged: Grid<bool> = Grid::new(solution.size());
+    let mut order: Vec<[usize; 2]> = solution.positions().collect();
+    for k in (1..order.len()).rev() {
+        order.swap(k, rng.gen_range(0, k + 1));
+    }
+    let mut cages = Vec::new();
+    for start in order {
+        if caged[start[1]][start[0]] {
+            continue;
+        }
+        caged[start[1]][start[0]] = true;
+        let mut cells = vec![start];
+        let size = rng.gen_range(2, MAX_CAGE_SIZE + 1);
+        while cells.len() < size {
+            let digits: Vec<u8> = cells.iter().map(|&[i, j]| solution[j][i]).collect();
+            let mut next: Vec<[usize; 2]> = cells
+                .iter()
+                .flat_map(|&cell| neighbors(solution.size(), cell))
+                .filter(|&[i, j]| !caged[j][i] && !digits.contains(&solution[j][i]))
+                .collect();
+            next.sort_unstable();
+            next.dedup();
+            if next.is_empty() {
+                break;
+            }
+            let [i, j] = next[rng.gen_range(0, next.len())];
+            caged[j][i] = true;
+            cells.push([i, j]);
+        }
+        let sum = cells.iter().map(|&[i, j]| u32::from(solution[j][i])).sum();
+        cages.push(Cage { sum, cells });
+    }
+    cages
+}
+
+/// Lays thermometers along paths of orthogonal neighbors whose digits
+/// increase in a solved grid, without sharing cells.
+fn make_thermos<R: Rng>(solution: &Grid, rng: &mut R) -> Vec<Thermo> {
+    let size = solution.size();
+    let mut used: Grid<bool> = Grid::new(size);
+    let mut order: Vec<[usize; 2]> = solution.positions().collect();
+    for k in (1..order.len()).rev() {
+        order.swap(k, rng.gen_range(0, k + 1));
+    }
+    let mut thermos = Vec::new();
+    // Bulbs on low digits leave room for longer thermometers.
+    for start in order
+        .into_iter()
+        .filter(|&[i, j]| solution[j][i] as usize <= size / 2)
+    {
+        if thermos.len() == THERMOS {
+            break;
+        }
+        if used[start[1]][start[0]] {
+            continue;
+        }
+        let mut cells = vec![start];
+        while cells.len() < MAX_THERMO_LENGTH {
+            let [x, y] = cells[cells.len() - 1];
+            // Stepping up as little as possible leaves the most room to
+            // grow.
+            let next = neighbors(size, [x, y])
+                .filter(|&[i, j]| !used[j][i] && !cells.contains(&[i, j]))
+                .filter(|&[i, j]| solution[j][i] > solution[y][x])
+                .min_by_key(|&[i, j]| solution[j][i]);
+            match next {
+                Some(next) => cells.push(next),
+                None => break,
+            }
+        }
+        if cells.len() < 3 {
+            continue;
+        }
+        for &[i, j] in &cells {
+            used[j][i] = true;
+        }
+        thermos.push(Thermo { cells });
+    }
+    thermos
+}
+
+/// The rules a puzzle is played under beyond its rows, columns, and boxes,
+/// with any clues they need.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Variant {
+    #[default]
+    Classic,
+    /// Both main diagonals also hold every digit once.
+    Diagonal,
+    /// Cages of cells add up to their sums without repeating a digit.
+    Killer(Vec<Cage>),
+    /// Irregular regions, numbered 0 to 8 in each cell, take the place of
+    /// the boxes.
+    Jigsaw(Grid),
+    /// Five 9x9 grids overlapping on a 21x21 board, the middle one sharing a
+    /// corner box with each of the others. Each grid has its own rows and
+    /// columns, and cells outside every grid are not played.
+    Samurai,
+    /// Sums outside the grid give the total of the digits between the 1 and
+    /// the highest digit in each row and column.
+    Sandwich(Sandwiches),
+    /// Digits strictly increase along each thermometer from its bulb.
+    Thermo(Vec<Thermo>),
+    /// White dots join consecutive digits and black dots join digits where
+    /// one is double the other.
+    Kropki(Kropki),
+    /// Marked cells hold even digits, drawn as squares, or odd digits,
+    /// drawn as circles.
+    EvenOdd(Vec<ParityMark>),
+    /// Signs between neighboring cells in the same box say which digit is
+    /// greater.
+    GreaterThan(Vec<Inequality>),
+    /// Neighboring digits along each line differ by at least 5.
+    Whispers(Vec<Whisper>),
+    /// Windows the shape of a box, one cell in from the edges and from each
+    /// other, also hold every digit once.
+    Windoku,
+}
+
+impl Variant {
+    /// A variant of a kind, before any clues are made for it.
+    pub fn new(kind: VariantKind) -> Variant {
+        match kind {
+            VariantKind::Classic => Variant::Classic,
+            VariantKind::Diagonal => Variant::Diagonal,
+            VariantKind::Killer => Variant::Killer(Vec::new()),
+            VariantKind::Jigsaw => Variant::Jigsaw(classic_regions()),
+            VariantKind::Samurai => Variant::Samurai,
+            VariantKind::Sandwich => Variant::Sandwich(Sandwiches::default()),
+            VariantKind::Thermo => Variant::Thermo(Vec::new()),
+            VariantKind::Kropki => Variant::Kropki(Kropki::default()),
+            VariantKind::EvenOdd => Variant::EvenOdd(Vec::new()),
+            VariantKind::GreaterThan => Variant::GreaterThan(Vec::new()),
+            VariantKind::Whispers => Variant::Whispers(Vec::new()),
+            VariantKind::Windoku => Variant::Windoku,
+        }
+    }
+
+    pub fn kind(&self) -> VariantKind {
+        match self {
+            Variant::Classic => VariantKind::Classic,
+            Variant::Diagonal => VariantKind::Diagonal,
+            Variant::Killer(_) => VariantKind::Killer,
+            Variant::Jigsaw(_) => VariantKind::Jigsaw,
+            Variant::Samurai => VariantKind::Samurai,
+            Variant::Sandwich(_) => VariantKind::Sandwich,
+            Variant::Thermo(_) => VariantKind::Thermo,
+            Variant::Kropki(_) => VariantKind::Kropki,
+            Variant::EvenOdd(_) => VariantKind::EvenOdd,
+            Variant::GreaterThan(_) => VariantKind::GreaterThan,
+            Variant::Whispers(_) => VariantKind::Whispers,
+            Variant::Windoku => VariantKind::Windoku,
+        }
+    }
+
+    /// Rows and columns of the board the variant is played on, for puzzles
+    /// of a size.
+    pub fn board_size(&self, size: usize) -> usize {
+        match self {
+            Variant::Samurai => SAMURAI_SIZE,
+            _ => size,
+        }
+    }
+
+    /// The highest digit on a board of a size, which is the size except on
+    /// boards of several grids.
+    pub fn digits(&self, size: usize) -> usize {
+        match self {
+            Variant::Samurai => SIZE,
+            _ => size,
+        }
+    }
+
+    /// Whether a cell is played, rather than in a gap between grids.
+    pub fn in_play(&self, ind: [usize; 2]) -> bool {
+        match self {
+            Variant::Samurai => samurai_grids(ind).next().is_some(),
+            _ => true,
+        }
+    }
+
+    /// Lays out what the variant needs before a solution can be made, such
+    /// as jigsaw regions.
+    pub fn make_layout<R: Rng>(&mut self, rng: &mut R) {
+        if let Variant::Jigsaw(regions) = self {
+            *regions = make_regions(rng);
+        }
+    }
+
+    /// The box, or jigsaw region, containing a cell on a board of a size.
+    pub fn box_of(&self, size: usize, [x, y]: [usize; 2]) -> usize {
+        match self {
+            Variant::Jigsaw(regions) => regions[y][x] as usize,
+            _ => {
+                let [width, height] = box_shape(self.digits(size));
+                y / height * (size / width) + x / width
+            }
+        }
+    }
+
+    /// The cells of a box, or jigsaw region, on a board of a size, in
+    /// reading order.
+    pub fn box_cells(&self, size: usize, b: usize) -> Vec<[usize; 2]> {
+        match self {
+            Variant::Jigsaw(regions) => regions
+                .positions()
+                .filter(|&[i, j]| regions[j][i] as usize == b)
+                .collect(),
+            _ => {
+                let digits = self.digits(size);
+                let [width, height] = box_shape(digits);
+                let per_row = size / width;
+                (0..digits)
+                    .map(|k| {
+                        [
+                            b % per_row * width + k % width,
+                            b / per_row * height + k / width,
+                        ]
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Makes the clues the variant needs, such as cages, to fit a solution.
+    pub fn make_clues<R: Rng>(&mut self, solution: &Grid, rng: &mut R) {
+        match self {
+            Variant::Killer(cages) => *cages = make_cages(solution, rng),
+            Variant::Sandwich(sandwiches) => *sandwiches = Sandwiches::new(solution),
+            Variant::Thermo(thermos) => *thermos = make_thermos(solution, rng),
+            Variant::Kropki(kropki) => *kropki = Kropki::new(solution, rng),
+            Variant::EvenOdd(marks) => *marks = make_parity_marks(solution, rng),
+            Variant::GreaterThan(signs) => *signs = make_inequalities(solution),
+            Variant::Whispers(whispers) => *whispers = make_whispers(solution, rng),
+            _ => {}
+        }
+    }
+
+    /// The cage a cell is in, if the variant has cages.
+    pub fn cage(&self, ind: [usize; 2]) -> Option<&Cage> {
+        match self {
+            Variant::Killer(cages) => cages.iter().find(|cage| cage.cells.contains(&ind)),
+            _ => None,
+        }
+    }
+
+    /// Bit mask of the digits a cell can take under rules other than its
+    /// houses, given the rest of the board.
+    pub fn allowed(&self, cells: &Grid, ind: [usize; 2]) -> u32 {
+        match self {
+            Variant::Sandwich(sandwiches) => sandwiches.allowed(cells, ind),
+            Variant::Kropki(kropki) => kropki.allowed(cells, ind),
+            Variant::GreaterThan(signs) => inequality_allowed(signs, cells, ind),
+            Variant::Whispers(whispers) => whispers
+                .iter()
+                .filter(|whisper| whisper.cells.contains(&ind))
+                .fold(digit_mask(cells.size()), |mask, whisper| {
+                    mask & whisper.allowed(cells, ind)
+                }),
+            Variant::EvenOdd(marks) => match marks.iter().find(|mark| mark.cell == ind) {
+                Some(mark) => mark.parity.mask(cells.size()),
+                None => digit_mask(cells.size()),
+            },
+            Variant::Thermo(_) => self
+                .thermos(ind)
+                .fold(digit_mask(self.digits(cells.size())), |mask, thermo| {
+                    mask & thermo.allowed(cells, ind)
+                }),
+            _ => match self.cage(ind) {
+                Some(cage) => cage.allowed(cells, ind),
+                None => digit_mask(self.digits(cells.size())),
+            },
+        }
+    }
+
+    /// Whether a cell's value breaks a rule other than its houses.
+    pub fn breaks(&self, cells: &Grid, ind: [usize; 2]) -> bool {
+        match self {
+            Variant::Sandwich(sandwiches) => {
+                cells[ind[1]][ind[0]] != 0 && sandwiches.broken(cells, ind)
+            }
+            Variant::Thermo(_) => self.thermos(ind).any(|thermo| thermo.broken(cells)),
+            Variant::Kropki(kropki) => kropki.broken(cells, ind),
+            Variant::GreaterThan(signs) => inequality_broken(signs, cells, ind),
+            Variant::Whispers(whispers) => {
+                whispers.iter().any(|whisper| whisper.broken(cells, ind))
+            }
+            Variant::EvenOdd(marks) => {
+                let val = cells[ind[1]][ind[0]];
+                val != 0
+                    && marks
+                        .iter()
+                        .any(|mark| mark.cell == ind && mark.parity != Parity::of(val))
+            }
+            _ => self.cage(ind).is_some_and(|cage| cage.broken(cells)),
+        }
+    }
+
+    /// The thermometers running through a cell, if the variant has them.
+    pub fn thermos(&self, ind: [usize; 2]) -> impl Iterator<Item = &Thermo> {
+        let thermos = match self {
+            Variant::Thermo(thermos) => &thermos[..],
+            _ => &[],
+        };
+        thermos
+            .iter()
+            .filter(move |thermo| thermo.cells.contains(&ind))
+    }
+
+    /// Houses a cell is in beyond its row, column, and box, on a board of a
+    /// size.
+    pub fn extra_houses(&self, size: usize, [x, y]: [usize; 2]) -> impl Iterator<Item = House> {
+        let diagonals = *self == Variant::Diagonal;
+        let window = window_of(size, [x, y]).filter(|_| *self == Variant::Windoku);
+        IntoIterator::into_iter([
+            (diagonals && x == y).then_some(House::Diagonal(0)),
+            (diagonals && x + y == size - 1).then_some(House::Diagonal(1)),
+            window.map(House::Window),
+        ])
+        .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::Constraints;
+
+    /// An empty 9x9 board with some digits filled in.
+    fn board(filled: &[([usize; 2], u8)]) -> Grid {
+        let mut cells: Grid = Grid::new(SIZE);
+        for &([i, j], val) in filled {
+            cells[j][i] = val;
+        }
+        cells
+    }
+
+    /// Whether a variant lets a digit into an empty cell, checking that its
+    /// allowed digits agree with whether the placed digit breaks a rule.
+    fn accepts(variant: &Variant, filled: &[([usize; 2], u8)], ind: [usize; 2], val: u8) -> bool {
+        let allowed = variant.allowed(&board(filled), ind) & (1 << val) != 0;
+        let mut cells = board(filled);
+        cells[ind[1]][ind[0]] = val;
+        assert_eq!(allowed, !variant.breaks(&cells, ind));
+        allowed
+    }
+
+    /// Whether added rules let a digit into an empty cell, the same way.
+    fn constraints_accept(
+        constraints: Constraints,
+        filled: &[([usize; 2], u8)],
+        ind: [usize; 2],
+        val: u8,
+    ) -> bool {
+        let allowed = constraints.allowed(&board(filled), ind) & (1 << val) != 0;
+        let mut cells = board(filled);
+        cells[ind[1]][ind[0]] = val;
+        assert_eq!(allowed, !constraints.breaks(&cells, ind));
+        allowed
+    }
+
+    #[test]
+    fn keeps_cages_to_their_sums() {
+        let killer = Variant::Killer(vec![Cage {
+            sum: 10,
+            cells: vec![[0, 0], [1, 0]],
+        }]);
+        assert!(accepts(&killer, &[([0, 0], 3)], [1, 0], 7));
+        assert!(!accepts(&killer, &[([0, 0], 3)], [1, 0], 6));
+    }
+
+    #[test]
+    fn keeps_sandwiches_to_their_sums() {
+        let sandwich = Variant::Sandwich(Sandwiches {
+            rows: vec![Some(5)],
+            columns: Vec::new(),
+        });
+        let filled = [([0, 0], 1), ([1, 0], 2), ([3, 0], 9)];
+        assert!(accepts(&sandwich, &filled, [2, 0], 3));
+        assert!(!accepts(&sandwich, &filled, [2, 0], 4));
+    }
+
+    #[test]
+    fn rises_along_thermometers() {
+        let thermo = Variant::Thermo(vec![Thermo {
+            cells: vec![[0, 0], [1, 0], [2, 0]],
+        }]);
+        let filled = [([0, 0], 2), ([2, 0], 5)];
+        assert!(accepts(&thermo, &filled, [1, 0], 3));
+        assert!(!accepts(&thermo, &filled, [1, 0], 5));
+    }
+
+    #[test]
+    fn fits_digits_to_kropki_dots() {
+        let kropki = Variant::Kropki(Kropki {
+            dots: vec![Dot {
+                kind: DotKind::White,
+                cells: [[0, 0], [1, 0]],
+            }],
+            negative: false,
+        });
+        assert!(accepts(&kropki, &[([0, 0], 4)], [1, 0], 5));
+        assert!(!accepts(&kropki, &[([0, 0], 4)], [1, 0], 7));
+    }
+
+    #[test]
+    fn keeps_marked_cells_to_their_parity() {
+        let even_odd = Variant::EvenOdd(vec![ParityMark {
+            parity: Parity::Even,
+            cell: [0, 0],
+        }]);
+        assert!(accepts(&even_odd, &[], [0, 0], 4));
+        assert!(!accepts(&even_odd, &[], [0, 0], 3));
+    }
+
+    #[test]
+    fn follows_greater_than_signs() {
+        let greater_than = Variant::GreaterThan(vec![Inequality {
+            greater: [0, 0],
+            less: [1, 0],
+        }]);
+        assert!(accepts(&greater_than, &[([1, 0], 5)], [0, 0], 7));
+        assert!(!accepts(&greater_than, &[([1, 0], 5)], [0, 0], 3));
+    }
+
+    #[test]
+    fn spreads_digits_along_whispers() {
+        let whispers = Variant::Whispers(vec![Whisper {
+            cells: vec![[0, 0], [1, 0]],
+        }]);
+        assert!(accepts(&whispers, &[([0, 0], 2)], [1, 0], 8));
+        assert!(!accepts(&whispers, &[([0, 0], 2)], [1, 0], 5));
+    }
+
+    #[test]
+    fn keeps_digits_a_knights_move_apart() {
+        let anti_knight = Constraints {
+            anti_knight: true,
+            ..Constraints::default()
+        };
+        assert!(constraints_accept(anti_knight, &[([0, 0], 5)], [1, 2], 6));
+        assert!(!constraints_accept(anti_knight, &[([0, 0], 5)], [1, 2], 5));
+    }
+
+    #[test]
+    fn keeps_digits_a_kings_move_apart() {
+        let anti_king = Constraints {
+            anti_king: true,
+            ..Constraints::default()
+        };
+        assert!(constraints_accept(anti_king, &[([0, 0], 5)], [1, 1], 6));
+        assert!(!constraints_accept(anti_king, &[([0, 0], 5)], [1, 1], 5));
+    }
+
+    #[test]
+    fn keeps_consecutive_digits_apart() {
+        let non_consecutive = Constraints {
+            non_consecutive: true,
+            ..Constraints::default()
+        };
+        assert!(constraints_accept(
+            non_consecutive,
+            &[([0, 0], 5)],
+            [1, 0],
+            7
+        ));
+        assert!(!constraints_accept(
+            non_consecutive,
+            &[([0, 0], 5)],
+            [1, 0],
+            6
+        ));
+    }
+
+    #[test]
+    fn finds_jigsaw_regions_broken_apart() {
+        let mut regions = classic_regions();
+        assert!((0..SIZE as u8).all(|region| is_connected(&regions, region)));
+        regions[0][0] = 4;
+        assert!(is_connected(&regions, 0));
+        assert!(!is_connected(&regions, 4));
+    }
+}
@@ -0,0 +1,364 @@
+//! A game for browsers, drawn on a canvas, built for WebAssembly with
+//! `wasm-pack build --target web -- --no-default-features --features web`
+//! for `web/index.html` to load.
+//!
+//! The page passes it clicks and keys, and has it draw after each.
+
+use crate::grid::{box_shape, char_digit, digit_char};
+use crate::render::{Color, Renderer};
+use crate::rules::RuleSet;
+use crate::{Difficulty, Gameboard, SIZE};
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+
+/// Space around the board, in canvas points.
+const MARGIN: f64 = 10.0;
+/// Height of the line under the board.
+const STATUS_HEIGHT: f64 = 30.0;
+
+const BACKGROUND: Color = [1.0; 4];
+const BOARD_BACKGROUND: Color = [0.8, 0.8, 1.0, 1.0];
+const SELECTED_BACKGROUND: Color = [0.9, 0.9, 1.0, 1.0];
+const SELECTED_BORDER: Color = [1.0, 0.0, 0.0, 1.0];
+const EDGE: Color = [0.0, 0.0, 0.2, 1.0];
+const TEXT: Color = [0.0, 0.0, 0.1, 1.0];
+const ENTRY_TEXT: Color = [0.1, 0.2, 0.6, 1.0];
+const CONFLICT_TEXT: Color = [0.8, 0.0, 0.0, 1.0];
+
+/// A classic game played in a browser.
+#[wasm_bindgen]
+pub struct WebGame {
+    board: Gameboard,
+    selected: Option<[usize; 2]>,
+    /// Whether digits pencil notes in rather than filling cells.
+    notes_mode: bool,
+}
+
+#[wasm_bindgen]
+impl WebGame {
+    /// Starts a game at a difficulty from easy to expert, the same puzzle
+    /// for the same seed.
+    #[wasm_bindgen(constructor)]
+    pub fn new(difficulty: &str, seed: Option<u64>) -> Result<WebGame, JsError> {
+        let difficulty = Difficulty::from_name(difficulty)
+            .ok_or_else(|| JsError::new(&format!("Unknown difficulty: {}", difficulty)))?;
+        Ok(WebGame::generate(difficulty, seed))
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn solved(&self) -> bool {
+        self.board.is_solved()
+    }
+
+    /// Selects the cell at a point of a canvas of a width and height.
+    pub fn click(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        let [left, top, side] = board_rect(width, height);
+        let size = self.board.size();
+        let cell = [(x - left) / side, (y - top) / side].map(|k| (k * size as f64).floor());
+        if cell.iter().all(|&k| k >= 0.0 && k < size as f64) {
+            self.selected = Some([cell[0] as usize, cell[1] as usize]);
+        }
+    }
+
+    /// Plays a key, named as `KeyboardEvent.key` names it, returning
+    /// whether it did anything.
+    pub fn key(&mut self, key: &str) -> bool {
+        let size = self.board.size();
+        let [i, j] = match self.selected {
+            Some(ind) => ind,
+            None if key.starts_with("Arrow") => {
+                self.selected = Some([0, 0]);
+                return true;
+            }
+            None => return false,
+        };
+        let mut chars = key.chars();
+        let digit = match (chars.next(), chars.next()) {
+            (Some(ch), None) => char_digit(ch).filter(|&val| val as usize <= self.board.digits()),
+            _ => None,
+        };
+        match (key, digit) {
+            ("ArrowUp", _) => self.selected = Some([i, (j + size - 1) % size]),
+            ("ArrowDown", _) => self.selected = Some([i, (j + 1) % size]),
+            ("ArrowLeft", _) => self.selected = Some([(i + size - 1) % size, j]),
+            ("ArrowRight", _) => self.selected = Some([(i + 1) % size, j]),
+            ("Backspace" | "Delete" | "0" | ".", _) => {
+                self.board.set([i, j], 0);
+                if !self.board.is_given([i, j]) {
+                    self.board.notes[j][i] = 0;
+                }
+            }
+            ("n" | "N", None) => self.notes_mode = !self.notes_mode,
+            (_, Some(val)) if self.notes_mode => {
+                if self.board.cells[j][i] == 0 {
+                    self.board.notes[j][i] ^= 1 << val;
+                }
+            }
+            (_, Some(val)) => self.board.set([i, j], val),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Starts another game at the same difficulty.
+    pub fn restart(&mut self, seed: Option<u64>) {
+        *self = WebGame::generate(self.board.difficulty, seed);
+    }
+
+    pub fn draw(&self, canvas: &HtmlCanvasElement) -> Result<(), JsValue> {
+        let context: CanvasRenderingContext2d = canvas
+            .get_context("2d")?
+            .ok_or("The canvas cannot draw in 2D")?
+            .dyn_into()?;
+        let size = [canvas.width() as f64, canvas.height() as f64];
+        self.draw_on(&mut Canvas(context), size);
+        Ok(())
+    }
+}
+
+impl WebGame {
+    fn generate(difficulty: Difficulty, seed: Option<u64>) -> WebGame {
+        let mut board = seed.map_or_else(Gameboard::new, Gameboard::with_seed);
+        board.generate_rules(difficulty, RuleSet::default(), SIZE);
+        WebGame {
+            board,
+            selected: None,
+            notes_mode: false,
+        }
+    }
+
+    fn draw_on<R: Renderer>(&self, r: &mut R, [width, height]: [f64; 2]) {
+        let board = &self.board;
+        let [left, top, side] = board_rect(width, height);
+        let size = board.size();
+        let cell_size = side / size as f64;
+        let cell_rect = |[i, j]: [usize; 2]| {
+            let [x, y] = [left + i as f64 * cell_size, top + j as f64 * cell_size];
+            [x, y, cell_size, cell_size]
+        };
+        r.rect([0.0, 0.0, width, height], BACKGROUND);
+        r.rect([left, top, side, side], BOARD_BACKGROUND);
+        if let Some(ind) = self.selected {
+            r.rect(cell_rect(ind), SELECTED_BACKGROUND);
+        }
+
+        let [note_width, note_height] = box_shape(board.digits());
+        for [i, j] in board.cells.positions() {
+            let [x, y, ..] = cell_rect([i, j]);
+            if let Some(ch) = board.char([i, j]) {
+                let color = if board.conflicts([i, j]) {
+                    CONFLICT_TEXT
+                } else if board.is_given([i, j]) {
+                    TEXT
+                } else {
+                    ENTRY_TEXT
+                };
+                let center = [x + cell_size / 2.0, y + cell_size / 2.0];
+                r.centered(ch, cell_size * 0.6, color, center);
+                continue;
+            }
+            for val in (1..=board.digits() as u8).filter(|&val| board.has_note([i, j], val)) {
+                let k = (val - 1) as usize;
+                let center = [
+                    x + ((k % note_width) as f64 + 0.5) / note_width as f64 * cell_size,
+                    y + ((k / note_width) as f64 + 0.5) / note_height as f64 * cell_size,
+                ];
+                let note_size = cell_size * 0.6 / note_width.max(note_height) as f64;
+                r.centered(digit_char(val), note_size, ENTRY_TEXT, center);
+            }
+        }
+
+        // Thin lines between cells, and thick ones between boxes.
+        let [box_width, box_height] = box_shape(size);
+        for k in 0..=size {
+            let offset = k as f64 * cell_size;
+            let radius = |edge: bool| if edge { 1.5 } else { 0.5 };
+            let vertical = [left + offset, top, left + offset, top + side];
+            r.line(vertical, EDGE, radius(k % box_width == 0));
+            let horizontal = [left, top + offset, left + side, top + offset];
+            r.line(horizontal, EDGE, radius(k % box_height == 0));
+        }
+        if let Some(ind) = self.selected {
+            r.border(cell_rect(ind), SELECTED_BORDER, 1.0);
+        }
+
+        let status = if board.is_solved() {
+            format!("Solved! {} puzzle", board.difficulty.name())
+        } else {
+            let notes = if self.notes_mode { "  [Notes]" } else { "" };
+            format!("{:.0}% done{}", board.progress() * 100.0, notes)
+        };
+        r.text(&status, 16, TEXT, [left, top + side + STATUS_HEIGHT * 0.7]);
+    }
+}
+
+/// The left, top, and side of the board on a canvas of a width and height.
+fn board_rect(width: f64, height: f64) -> [f64; 3] {
+    let side = (width - 2.0 * MARGIN).min(height - 2.0 * MARGIN - STATUS_HEIGHT);
+    [MARGIN, MARGIN, side.max(0.0)]
+}
+
+fn css(color: Color) -> String {
+    let [r, g, b, a] = color;
+    let byte = |c: f32| (c * 255.0).round() as u8;
+    format!("rgba({}, {}, {}, {})", byte(r), byte(g), byte(b), a)
+}
+
+/// Draws on a canvas with its 2D context.
+struct Canvas(CanvasRenderingContext2d);
+
+impl Canvas {
+    fn stroke(&self, [x1, y1, x2, y2]: [f64; 4], color: Color, radius: f64, cap: &str) {
+        let context = &self.0;
+        context.set_stroke_style_str(&css(color));
+        context.set_line_width(2.0 * radius);
+        context.set_line_cap(cap);
+        context.begin_path();
+        context.move_to(x1, y1);
+        context.line_to(x2, y2);
+        context.stroke();
+    }
+}
+
+impl Renderer for Canvas {
+    fn rect(&mut self, [x, y, width, height]: [f64; 4], color: Color) {
+        self.0.set_fill_style_str(&css(color));
+        self.0.fill_rect(x, y, width, height);
+    }
+
+    fn border(&mut self, [x, y, width, height]: [f64; 4], color: Color, radius: f64) {
+        self.0.set_stroke_style_str(&css(color));
+        self.0.set_line_width(2.0 * radius);
+        self.0.stroke_rect(x, y, width, height);
+    }
+
+    fn line(&mut self, line: [f64; 4], color: Color, radius: f64) {
+        self.stroke(line, color, radius, "square");
+    }
+
+    fn round_line(&mut self, line: [f64; 4], color: Color, radius: f64) {
+        self.stroke(line, color, radius, "round");
+    }
+
+    fn ellipse(&mut self, [x, y, width, height]: [f64; 4], color: Color) {
+        let context = &self.0;
+        context.set_fill_style_str(&css(color));
+        context.begin_path();
+        let (rx, ry) = (width / 2.0, height / 2.0);
+        let _ = context.ellipse(x + rx, y + ry, rx, ry, 0.0, 0.0, 2.0 * PI);
+        context.fill();
+    }
+
+    fn text(&mut self, text: &str, size: u32, color: Color, [x, y]: [f64; 2]) {
+        self.0.set_font(&format!("{}px sans-serif", size));
+        self.0.set_text_align("left");
+        self.0.set_text_baseline("alphabetic");
+        self.0.set_fill_style_str(&css(color));
+        let _ = self.0.fill_text(text, x, y);
+    }
+
+    fn text_width(&mut self, text: &str, size: u32) -> f64 {
+        self.0.set_font(&format!("{}px sans-serif", size));
+        self.0
+            .measure_text(text)
+            .map_or(0.0, |metrics| metrics.width())
+    }
+
+    fn centered(&mut self, ch: char, size: f64, color: Color, [x, y]: [f64; 2]) {
+        self.0.set_font(&format!("{}px sans-serif", size.round()));
+        self.0.set_text_align("center");
+        self.0.set_text_baseline("middle");
+        self.0.set_fill_style_str(&css(color));
+        let _ = self.0.fill_text(&ch.to_string(), x, y);
+    }
+
+    fn clip(&mut self, rect: Option<[f64; 4]>) {
+        // Clipping only narrows until the state saved before it is restored.
+        self.0.restore();
+        if let Some([x, y, width, height]) = rect {
+            self.0.save();
+            self.0.begin_path();
+            self.0.rect(x, y, width, height);
+            self.0.clip();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keeps the characters drawn, by where they were drawn.
+    #[derive(Default)]
+    struct Recorder {
+        chars: Vec<(char, [f64; 2])>,
+        text: Vec<String>,
+    }
+
+    impl Renderer for Recorder {
+        fn rect(&mut self, _: [f64; 4], _: Color) {}
+        fn border(&mut self, _: [f64; 4], _: Color, _: f64) {}
+        fn line(&mut self, _: [f64; 4], _: Color, _: f64) {}
+        fn ellipse(&mut self, _: [f64; 4], _: Color) {}
+        fn text(&mut self, text: &str, _: u32, _: Color, _: [f64; 2]) {
+            self.text.push(text.to_string());
+        }
+        fn text_width(&mut self, text: &str, size: u32) -> f64 {
+            (text.len() as u32 * size) as f64 / 2.0
+        }
+        fn centered(&mut self, ch: char, _: f64, _: Color, center: [f64; 2]) {
+            self.chars.push((ch, center));
+        }
+        fn clip(&mut self, _: Option<[f64; 4]>) {}
+    }
+
+    /// A game with only the top left cell empty.
+    fn game() -> WebGame {
+        let mut game = WebGame::generate(Difficulty::Easy, Some(1));
+        let solution = game.board.solution.clone();
+        game.board = Gameboard::from_puzzle(solution.clone(), solution);
+        game.board.cells[0][0] = 0;
+        game.board.givens[0][0] = false;
+        game
+    }
+
+    #[test]
+    fn plays_clicks_and_keys() {
+        let mut game = game();
+        assert!(!game.key("5"));
+        game.click(15.0, 15.0, 200.0, 240.0);
+        assert_eq!(game.selected, Some([0, 0]));
+        let answer = game.board.solution[0][0];
+        assert!(game.key("n") && game.key(&answer.to_string()));
+        assert!(game.board.has_note([0, 0], answer));
+        assert!(game.key("N") && game.key(&answer.to_string()));
+        assert!(game.solved());
+        assert!(game.key("ArrowLeft"));
+        assert_eq!(game.selected, Some([SIZE - 1, 0]));
+        // Givens stay as they are.
+        assert!(game.key("Backspace"));
+        assert_ne!(game.board.cells[0][SIZE - 1], 0);
+        assert!(!game.key("x"));
+        game.click(500.0, 15.0, 200.0, 240.0);
+        assert_eq!(game.selected, Some([SIZE - 1, 0]));
+    }
+
+    #[test]
+    fn draws_the_board_and_status() {
+        let mut game = game();
+        let mut recorder = Recorder::default();
+        game.draw_on(&mut recorder, [200.0, 240.0]);
+        assert_eq!(recorder.chars.len(), SIZE * SIZE - 1);
+        assert_eq!(recorder.text, ["0% done"]);
+        game.board.set([0, 0], game.board.solution[0][0]);
+        let mut recorder = Recorder::default();
+        game.draw_on(&mut recorder, [200.0, 240.0]);
+        let first = recorder
+            .chars
+            .iter()
+            .find(|(_, [x, y])| *x < 40.0 && *y < 40.0);
+        assert_eq!(first.map(|(ch, _)| *ch), game.board.char([0, 0]));
+        assert_eq!(recorder.text, ["Solved! Easy puzzle"]);
+    }
+}
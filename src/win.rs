@@ -0,0 +1,207 @@
+use crate::text::{draw_text, text_width};
+use crate::theme::Theme;
+use crate::{format_duration, GameboardController};
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{Context, Graphics};
+use piston::input::GenericEvent;
+
+/// What a win screen button does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WinAction {
+    NewGame,
+    /// Shows the board marked against the solution.
+    Review,
+    /// Plays the game back move by move.
+    Replay,
+    MainMenu,
+}
+
+const BUTTONS: [(WinAction, &str); 4] = [
+    (WinAction::NewGame, "New Game"),
+    (WinAction::Review, "Review"),
+    (WinAction::Replay, "Replay"),
+    (WinAction::MainMenu, "Main Menu"),
+];
+
+/// Button rectangles for a win screen covering the given area.
+fn button_rects(pos: [f64; 2], size: f64) -> [[f64; 4]; 4] {
+    let width = size * 0.21;
+    let height = size * 0.1;
+    let y = pos[1] + size * 0.75;
+    [
+        [pos[0] + size * 0.04, y, width, height],
+        [pos[0] + size * 0.28, y, width, height],
+        [pos[0] + size * 0.52, y, width, height],
+        [pos[0] + size * 0.76, y, width, height],
+    ]
+}
+
+fn inside(rect: [f64; 4], pos: [f64; 2]) -> bool {
+    pos[0] >= rect[0]
+        && pos[0] <= rect[0] + rect[2]
+        && pos[1] >= rect[1]
+        && pos[1] <= rect[1] + rect[3]
+}
+
+pub struct WinController {
+    cursor_pos: [f64; 2],
+}
+
+impl Default for WinController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WinController {
+    pub fn new() -> WinController {
+        WinController {
+            cursor_pos: [0.0; 2],
+        }
+    }
+
+    /// Handles events, returning the chosen action.
+    pub fn event<E: GenericEvent>(&mut self, pos: [f64; 2], size: f64, e: &E) -> Option<WinAction> {
+        use piston::input::{Button, Key, MouseButton};
+
+        if let Some(pos) = e.mouse_cursor_args() {
+            self.cursor_pos = pos;
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = e.press_args() {
+            for (rect, &(action, _)) in button_rects(pos, size).iter().zip(BUTTONS.iter()) {
+                if inside(*rect, self.cursor_pos) {
+                    return Some(action);
+                }
+            }
+        }
+        match e.press_args() {
+            Some(Button::Keyboard(Key::Return)) => Some(WinAction::NewGame),
+            Some(Button::Keyboard(Key::R)) => Some(WinAction::Review),
+            Some(Button::Keyboard(Key::P)) => Some(WinAction::Replay),
+            _ => None,
+        }
+    }
+}
+
+pub struct WinViewSettings {
+    pub font_size: u32,
+    pub overlay_color: Color,
+    pub button_color: Color,
+    pub text_color: Color,
+}
+
+impl WinViewSettings {
+    pub fn new(theme: &Theme) -> WinViewSettings {
+        let mut overlay_color = theme.panel_background;
+        overlay_color[3] *= 0.9;
+        WinViewSettings {
+            font_size: 20,
+            overlay_color,
+            button_color: theme.panel_selected_background,
+            text_color: theme.text,
+        }
+    }
+
+    /// Scales text.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.font_size = (20.0 * scale).round() as u32;
+    }
+
+    /// Switches to a theme's colors.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        *self = WinViewSettings {
+            font_size: self.font_size,
+            ..WinViewSettings::new(theme)
+        };
+    }
+}
+
+pub struct WinView {
+    pub settings: WinViewSettings,
+}
+
+impl WinView {
+    pub fn new(settings: WinViewSettings) -> WinView {
+        WinView { settings }
+    }
+
+    /// Draws the end-of-game summary over a board at `pos` with side length `size`.
+    pub fn draw<G: Graphics, C>(
+        &self,
+        controller: &GameboardController,
+        pos: [f64; 2],
+        size: f64,
+        glyphs: &mut C,
+        c: &Context,
+        g: &mut G,
+    ) where
+        C: CharacterCache<Texture = G::Texture>,
+    {
+        use graphics::Rectangle;
+
+        let settings = &self.settings;
+        Rectangle::new(settings.overlay_color).draw(
+            [pos[0], pos[1], size, size],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+
+        let lines = [
+            if controller.gave_up {
+                "Gave up".to_string()
+            } else if controller.is_lost() {
+                "Out of mistakes".to_string()
+            } else {
+                "Solved!".to_string()
+            },
+            format!("Time: {}", format_duration(controller.elapsed())),
+            format!("Difficulty: {}", controller.gameboard.difficulty.name()),
+            match controller.mistake_limit {
+                Some(limit) => format!("Mistakes: {}/{}", controller.mistakes, limit),
+                None => format!("Mistakes: {}", controller.mistakes),
+            },
+            format!("Hints used: {}", controller.hints),
+            format!("Solution peeks: {}", controller.peeks),
+        ];
+        for (row, line) in lines.iter().enumerate() {
+            draw_text(
+                line,
+                settings.font_size,
+                settings.text_color,
+                [
+                    pos[0] + size * 0.1,
+                    pos[1] + size * 0.15 + row as f64 * size * 0.1,
+                ],
+                glyphs,
+                c,
+                g,
+            );
+        }
+
+        // Labels are smaller than the summary to fit four buttons across.
+        let button_font = (settings.font_size as f64 * 0.8).round() as u32;
+        for (rect, &(_, label)) in button_rects(pos, size).iter().zip(BUTTONS.iter()) {
+            Rectangle::new(settings.button_color)
+                .border(graphics::rectangle::Border {
+                    color: settings.text_color,
+                    radius: 1.0,
+                })
+                .draw(*rect, &c.draw_state, c.transform, g);
+            let width = text_width(label, button_font, glyphs, c);
+            draw_text(
+                label,
+                button_font,
+                settings.text_color,
+                [
+                    rect[0] + (rect[2] - width) / 2.0,
+                    rect[1] + (rect[3] + button_font as f64) / 2.0 - 2.0,
+                ],
+                glyphs,
+                c,
+                g,
+            );
+        }
+    }
+}